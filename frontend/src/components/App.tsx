@@ -22,8 +22,10 @@ import {
   AiAnalysisResult,
   FenResult,
   GameRecord,
+  GameStateAtPointInTime,
   PgnResult,
   PlayerActionName,
+  PositionsResult,
 } from "@src/types/game";
 import { FormToShow } from "@src/types/sharedComponentTypes";
 import { API_ROUTE, WEBSOCKET_ENDPOINT } from "@src/constants";
@@ -86,6 +88,10 @@ export const App: React.FC = () => {
   const pendingFenGameIdRef = useRef<string | null>(null);
   const [pgnResults, setPgnResults] = useState<Record<string, string>>({});
   const pendingPgnGameIdRef = useRef<string | null>(null);
+  // Past positions by game ID and ply, as they're requested
+  const [pastPositions, setPastPositions] = useState<
+    Record<string, Record<number, GameStateAtPointInTime>>
+  >({});
   const pendingPlayAgainIndexRef = useRef<number | null>(null);
 
   const [formToShow, setFormToShow] = useState<FormToShow>(
@@ -102,7 +108,12 @@ export const App: React.FC = () => {
     (
       response:
         | ApiResponse<
-            GameRecord | AiAnalysisResult | FenResult | PgnResult | null
+            | GameRecord
+            | AiAnalysisResult
+            | FenResult
+            | PgnResult
+            | PositionsResult
+            | null
           >
         | ApiRunTimeError,
     ) => {
@@ -138,6 +149,11 @@ export const App: React.FC = () => {
         Object.keys(response.data).includes("pgn") &&
         Object.keys(response.data).length === 1;
 
+      const isPositionsResult =
+        response.data &&
+        Object.keys(response.data).includes("gameId") &&
+        Object.keys(response.data).includes("positions");
+
       const gameRecord = isGameRecord ? (response.data as GameRecord) : null;
 
       if (isAiAnalysis) {
@@ -172,6 +188,20 @@ export const App: React.FC = () => {
         }
       }
 
+      if (isPositionsResult) {
+        const { gameId, positions } = response.data as PositionsResult;
+
+        setPastPositions((old) => ({
+          ...old,
+          [gameId]: {
+            ...old[gameId],
+            ...Object.fromEntries(
+              positions.map(({ ply, position }) => [ply, position]),
+            ),
+          },
+        }));
+      }
+
       if (gameRecord) {
         const playAgainIndex = pendingPlayAgainIndexRef.current;
         const replacesGameId =
@@ -188,32 +218,47 @@ export const App: React.FC = () => {
         const existingRecord = gameRecordsRef.current.find(
           (g) => g.game_id === gameRecord.game_id,
         );
+        const plyCount = gameRecord.game_state.moveList.length;
         const historyGrowth =
-          gameRecord.game_state.history.length -
-          (existingRecord?.game_state.history.length ?? 0);
+          plyCount - (existingRecord?.game_state.moveList.length ?? 0);
+
+        // Positions from beyond a takeback no longer happened. The position
+        // that was current is now a past one, and is kept for the last move's
+        // highlight.
+        setPastPositions((old) => ({
+          ...old,
+          [gameRecord.game_id]: {
+            ...Object.fromEntries(
+              Object.entries(old[gameRecord.game_id] ?? {}).filter(
+                ([ply]) => Number(ply) < plyCount,
+              ),
+            ),
+            ...(existingRecord && historyGrowth > 0
+              ? {
+                  [existingRecord.game_state.moveList.length]:
+                    existingRecord.game_state.current,
+                }
+              : {}),
+          },
+        }));
 
         // The server sends two messages per engine turn: one after the player
         // moves (engineResult = null) and one after the engine responds
         // (engineResult set). Delay only the second message.
-        const latestEngineResult =
-          gameRecord.game_state.history[
-            gameRecord.game_state.history.length - 1
-          ].engineResult;
+        const latestEngineResult = gameRecord.game_state.current.engineResult;
 
         const shouldDelay =
           isEngineGame &&
           realismPrefRef.current !== "false" &&
           existingRecord !== undefined &&
-          historyGrowth > 0 &&
+          historyGrowth === 1 &&
           latestEngineResult !== null;
 
         if (shouldDelay) {
+          // The game as it was before the engine's move
           const trimmedRecord: GameRecord = {
             ...gameRecord,
-            game_state: {
-              ...gameRecord.game_state,
-              history: gameRecord.game_state.history.slice(0, -1),
-            },
+            game_state: existingRecord.game_state,
           };
 
           const replaceRecord = (record: GameRecord) => {
@@ -273,8 +318,8 @@ export const App: React.FC = () => {
             newGames[index] = gameRecord;
 
             const moveMade =
-              gameRecord.game_state.history.length !==
-              old[index].game_state.history.length;
+              gameRecord.game_state.moveList.length !==
+              old[index].game_state.moveList.length;
 
             if (!moveMade) {
               document.querySelectorAll(".dragging").forEach((el) => {
@@ -468,6 +513,7 @@ export const App: React.FC = () => {
               onRequestAiAnalysis={(gameId) => {
                 pendingAiGameIdRef.current = gameId;
               }}
+              pastPositions={pastPositions[gameRecord.game_id]}
               fenResult={fenResults[gameRecord.game_id] ?? null}
               onRequestFen={(gameId) => {
                 pendingFenGameIdRef.current = gameId;
@@ -12,7 +12,6 @@ import { AiAnalysisModal } from "@src/components/AiAnalysisModal";
 import {
   capitalizeFirstLetter,
   getCapturedPiecesFromBase64,
-  getLast,
 } from "@src/utils";
 
 import { Alert } from "@src/components/Alert";
@@ -28,16 +27,21 @@ import { useNotifications } from "@src/hooks/useNotifications";
 import { useTitleAnimation } from "@src/hooks/useTitleAnimation";
 
 import { getSquaresFromCompactBoard } from "@src/utils";
-import { ExpandedGameStateAtPointInTime } from "@src/types/board";
+import {
+  ExpandedGameStateAtPointInTime,
+  ExpandedHistory,
+} from "@src/types/board";
 import { GameRequest } from "@src/types/api";
 import {
   AiAnalysisResult,
   AnalysisType,
   GameEndingCheckmate,
+  GameEndingForfeit,
   GameEndingOutOfTime,
   GameEndingResignation,
   GameEndingType,
   GameRecord,
+  GameStateAtPointInTime,
   GameStateType,
   PlayerActionName,
 } from "@src/types/game";
@@ -60,6 +64,7 @@ interface GameProps {
   aiAnalysis: AiAnalysisResult | null;
   onRequestAiAnalysis: (gameId: string) => void;
   onClearAiAnalysis: (gameId: string) => void;
+  pastPositions: Record<number, GameStateAtPointInTime> | undefined;
   fenResult: string | null;
   onRequestFen: (gameId: string) => void;
   onClearFen: (gameId: string) => void;
@@ -81,6 +86,7 @@ export const Game: React.FC<GameProps> = ({
   aiAnalysis,
   onRequestAiAnalysis,
   onClearAiAnalysis,
+  pastPositions,
   fenResult,
   onRequestFen,
   onClearFen,
@@ -123,23 +129,86 @@ export const Game: React.FC<GameProps> = ({
 
   const gameState = gameRecord.game_state;
 
-  const history: ExpandedGameStateAtPointInTime[] = useMemo(
-    () =>
-      gameState.history.map((state) => ({
-        ...state,
-        board: { squares: getSquaresFromCompactBoard(state.board) },
-      })),
-    [gameState.history],
+  const numStates = gameState.moveList.length + 1;
+
+  const currentGameState: ExpandedGameStateAtPointInTime = useMemo(
+    () => ({
+      ...gameState.current,
+      board: { squares: getSquaresFromCompactBoard(gameState.current.board) },
+    }),
+    [gameState.current],
   );
 
+  // The snapshots and any past positions that have been requested
+  const history: ExpandedHistory = useMemo(() => {
+    const positions: Record<number, GameStateAtPointInTime> = {
+      ...Object.fromEntries(
+        gameState.snapshots.map(({ ply, position }) => [ply, position]),
+      ),
+      ...pastPositions,
+    };
+
+    return {
+      ...Object.fromEntries(
+        Object.entries(positions)
+          .filter(([ply]) => Number(ply) < numStates - 1)
+          .map(([ply, state]) => [
+            ply,
+            {
+              ...state,
+              board: { squares: getSquaresFromCompactBoard(state.board) },
+            },
+          ]),
+      ),
+      [numStates - 1]: currentGameState,
+    };
+  }, [gameState.snapshots, pastPositions, numStates, currentGameState]);
+
   const gameTime = gameState.gameTime;
 
-  const currentGameState = getLast(history);
   const gameStateType = currentGameState.state;
-  const numStates = history.length;
 
   const [historyIndex, setHistoryIndex] = useState(numStates - 1);
 
+  // Positions that haven't been loaded yet are requested, along with the one
+  // before for the last move's highlight. The last loaded position is shown
+  // until they arrive.
+  const isHistoryIndexLoaded = history[historyIndex] !== undefined;
+  const [lastLoadedHistoryIndex, setLastLoadedHistoryIndex] =
+    useState(historyIndex);
+
+  const viewedHistoryIndex = isHistoryIndexLoaded
+    ? historyIndex
+    : history[lastLoadedHistoryIndex] !== undefined
+      ? lastLoadedHistoryIndex
+      : numStates - 1;
+
+  useEffect(() => {
+    if (isHistoryIndexLoaded) {
+      setLastLoadedHistoryIndex(historyIndex);
+    }
+  }, [historyIndex, isHistoryIndexLoaded]);
+
+  const missingPly = [historyIndex, historyIndex - 1].find(
+    (ply) => ply >= 0 && history[ply] === undefined,
+  );
+
+  useEffect(() => {
+    if (missingPly === undefined) {
+      return;
+    }
+
+    sendWebSocketMessage({
+      route: API_ROUTE,
+      data: {
+        [PlayerActionName.GetPositions]: {
+          gameId,
+          ply: missingPly,
+        },
+      },
+    });
+  }, [gameId, missingPly, sendWebSocketMessage]);
+
   const bothPlayersReady =
     gameRecord.engine_difficulty !== null ||
     ![
@@ -178,7 +247,7 @@ export const Game: React.FC<GameProps> = ({
 
   useTitleAnimation(isActivePlayerTurn && isDocumentHidden, "♟️ Your turn!");
 
-  const viewedGameState = history[historyIndex];
+  const viewedGameState = history[viewedHistoryIndex] ?? currentGameState;
 
   const expandedCapturedPieces = useMemo(
     () => getCapturedPiecesFromBase64(viewedGameState.capturedPieces),
@@ -188,11 +257,21 @@ export const Game: React.FC<GameProps> = ({
   // Player's turn: eval is post-engine-move (+ for human)
   // Engine's turn: eval is post-human-move (+ for engine)
   const latestSearchResult = useMemo(() => {
-    for (let i = historyIndex; i >= 0; i--) {
-      const result = history[i].engineResult;
+    for (let i = viewedHistoryIndex; i >= 0; i--) {
+      const result =
+        i === numStates - 1
+          ? currentGameState.engineResult
+          : gameState.plyDetails[i].engineResult;
 
       if (result) {
-        if (history[i].currentTurn !== playerColor) {
+        // Turns alternate, so whose turn it was is worked out from the current
+        // position
+        const currentTurn =
+          (numStates - 1 - i) % 2 === 0
+            ? currentGameState.currentTurn
+            : getOppositePlayerColor(currentGameState.currentTurn);
+
+        if (currentTurn !== playerColor) {
           return { ...result, evaluation: -result.evaluation };
         }
 
@@ -201,7 +280,13 @@ export const Game: React.FC<GameProps> = ({
     }
 
     return null;
-  }, [history, historyIndex, playerColor]);
+  }, [
+    currentGameState,
+    gameState.plyDetails,
+    numStates,
+    playerColor,
+    viewedHistoryIndex,
+  ]);
 
   const showEvalBar = evalOn && gameRecord.engine_difficulty !== null;
 
@@ -398,6 +483,10 @@ export const Game: React.FC<GameProps> = ({
       return ["Game not started", "red"];
     }
 
+    if (gameStateType === GameStateType.Aborted) {
+      return ["Game aborted", "red"];
+    }
+
     const gameEnding = gameStateType[GameStateType.Finished];
 
     if (typeof gameEnding === "object") {
@@ -414,6 +503,15 @@ export const Game: React.FC<GameProps> = ({
         ];
       }
 
+      if (gameEndingType === GameEndingType.Forfeit) {
+        const losingColor = (gameEnding as GameEndingForfeit)[gameEndingType];
+
+        return [
+          `${capitalizeFirstLetter(losingColor)} never joined and forfeits!`,
+          playerColor === losingColor ? "red" : "green",
+        ];
+      }
+
       if (gameEndingType === GameEndingType.Checkmate) {
         const winningColor = getOppositePlayerColor(
           (gameEnding as GameEndingCheckmate)[gameEndingType],
@@ -919,7 +1017,7 @@ export const Game: React.FC<GameProps> = ({
             playerColor={playerColor}
             gameId={gameId}
             sendWebSocketMessage={sendWebSocketMessage}
-            historyIndex={historyIndex}
+            historyIndex={viewedHistoryIndex}
             isViewingLatestBoard={isViewingLatestBoard}
             gameOverMessage={gameOverMessage}
             isTurn={isTurn}
@@ -4,7 +4,7 @@ import { parseUciMove, rotateMatrix180Degrees } from "@src/utils";
 import { useDrag } from "@src/hooks/useDrag";
 import { GameRequest } from "@src/types/api";
 import { PlayerActionName } from "@src/types/game";
-import { ExpandedGameStateAtPointInTime, Position } from "@src/types/board";
+import { ExpandedHistory, Position } from "@src/types/board";
 import { Color, Piece } from "@src/types/piece";
 import { API_ROUTE, BoardTheme } from "@src/constants";
 import { stateChecks } from "@src/components/chess-board/state-checks";
@@ -12,7 +12,7 @@ import { stateChecks } from "@src/components/chess-board/state-checks";
 import "@src/css/ChessBoard.css";
 
 interface ChessBoardProps {
-  expandedHistory: ExpandedGameStateAtPointInTime[];
+  expandedHistory: ExpandedHistory; // Includes the position at `historyIndex`
   playerColor: Color;
   gameId: string;
   sendWebSocketMessage: (action: GameRequest) => void;
@@ -38,10 +38,11 @@ export const ChessBoard: React.FC<ChessBoardProps> = memo(function ChessBoard({
 }) {
   const shouldRotate = playerColor === Color.Black;
 
-  const viewedBoardState = expandedHistory[historyIndex].board;
-  const viewedBoardStateSquares = viewedBoardState.squares;
+  // eslint-disable-next-line @typescript-eslint/no-non-null-assertion
+  const viewedGameState = expandedHistory[historyIndex]!;
+  const viewedBoardStateSquares = viewedGameState.board.squares;
 
-  const moves = expandedHistory[historyIndex].moves;
+  const moves = viewedGameState.moves;
   const [moveFrom, setMoveFrom] = useState<Position | null>(null);
 
   const [preMoveFrom, setPreMoveFrom] = useState<Position | null>(null);
@@ -99,23 +100,25 @@ export const ChessBoard: React.FC<ChessBoardProps> = memo(function ChessBoard({
       prevHistoryIndex.current !== historyIndex
     ) {
       let one = expandedHistory[prevHistoryIndex.current];
-      let two = expandedHistory[historyIndex];
+      const two = viewedGameState;
 
       if (prevHistoryIndex.current > historyIndex && historyIndex > 0) {
         one = expandedHistory[historyIndex - 1];
-        two = expandedHistory[historyIndex];
       }
 
-      for (const { didStateChange, action } of stateChecks) {
-        if (didStateChange(one, two, playerColor)) {
-          action();
-          break;
+      // A position that hasn't been loaded yet plays no sound
+      if (one) {
+        for (const { didStateChange, action } of stateChecks) {
+          if (didStateChange(one, two, playerColor)) {
+            action();
+            break;
+          }
         }
       }
     }
 
     prevHistoryIndex.current = historyIndex;
-  }, [historyIndex, expandedHistory, playerColor]);
+  }, [historyIndex, expandedHistory, playerColor, viewedGameState]);
 
   const disableMoving = !isViewingLatestBoard || gameOverMessage !== null;
 
@@ -302,8 +305,10 @@ export const ChessBoard: React.FC<ChessBoardProps> = memo(function ChessBoard({
   const lastMoveSquares: Position[] = useMemo(() => {
     const squares: Position[] = [];
 
-    if (historyIndex > 0) {
-      const one = expandedHistory[historyIndex - 1].board.squares;
+    const previousGameState = expandedHistory[historyIndex - 1];
+
+    if (previousGameState) {
+      const one = previousGameState.board.squares;
       const two = viewedBoardStateSquares;
 
       two.forEach((row, rowIndex) => {
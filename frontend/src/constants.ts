@@ -1,5 +1,6 @@
 /* eslint-disable max-len */
-export const WEBSOCKET_ENDPOINT = "wss://3gd4hwwxc0.execute-api.us-east-1.amazonaws.com/Prod";
+export const WEBSOCKET_ENDPOINT = (import.meta.env.VITE_WEBSOCKET_ENDPOINT as string | undefined)
+  ?? "wss://3gd4hwwxc0.execute-api.us-east-1.amazonaws.com/Prod";
 export const API_ROUTE = "game";
 
 export interface BoardTheme {
@@ -29,6 +29,8 @@ export interface ApiResponse<T> {
   messages: ApiMessage[];
   data: T;
   replacesGameId?: string;
+  seatToken?: string; // Only sent to the player who was just seated
+  inviteToken?: string; // Only sent to the creator of a private game
 }
 
 export function isApiRunTimeError(
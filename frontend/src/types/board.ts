@@ -56,3 +56,8 @@ export type ExpandedGameStateAtPointInTime = Omit<
   GameStateAtPointInTime, "board"> & {
     board: ExpandedBoard;
   };
+
+// By ply. Only the positions that have been loaded are present.
+export type ExpandedHistory = Partial<
+  Record<number, ExpandedGameStateAtPointInTime>
+>;
@@ -5,6 +5,7 @@ export enum GameEndingType {
   Checkmate = 'checkmate',
   Resignation = 'resignation',
   OutOfTime = 'out-of-time',
+  Forfeit = 'forfeit',
   Stalemate = 'stalemate',
   DrawByThreefoldRepetition = 'draw-by-threefold-repetition',
   DrawByFiftyMoveRule = 'draw-by-fifty-move-rule',
@@ -15,6 +16,7 @@ export enum GameEndingType {
 export interface GameEndingCheckmate { [GameEndingType.Checkmate]: Color; }
 export interface GameEndingResignation { [GameEndingType.Resignation]: Color; }
 export interface GameEndingOutOfTime { [GameEndingType.OutOfTime]: Color; }
+export interface GameEndingForfeit { [GameEndingType.Forfeit]: Color; } // Never joined
 type GameEndingStalemate = GameEndingType.Stalemate;
 type GameEndingDrawByThreefoldRepetition =
   GameEndingType.DrawByThreefoldRepetition;
@@ -23,10 +25,11 @@ type GameEndingDrawByInsufficientMaterial =
   GameEndingType.DrawByInsufficientMaterial;
 type GameEndingDrawByMutualAgreement = GameEndingType.DrawByMutualAgreement;
 
-type GameEnding =
+export type GameEnding =
   | GameEndingCheckmate
   | GameEndingResignation
   | GameEndingOutOfTime
+  | GameEndingForfeit
   | GameEndingStalemate
   | GameEndingDrawByThreefoldRepetition
   | GameEndingDrawByFiftyMoveRule
@@ -37,10 +40,12 @@ export enum GameStateType {
   NotStarted = 'not-started',
   InProgress = 'in-progress',
   Finished = 'finished',
+  Aborted = 'aborted',
 }
 
 type StateNotStarted = GameStateType.NotStarted;
 type StateInProgress = GameStateType.InProgress;
+type StateAborted = GameStateType.Aborted;
 interface StateFinished {
   [GameStateType.Finished]: GameEnding;
 }
@@ -48,7 +53,8 @@ interface StateFinished {
 type State =
   | StateNotStarted
   | StateInProgress
-  | StateFinished;
+  | StateFinished
+  | StateAborted;
 
 export interface CompactCapturedPieces {
   [Color.White]: string;
@@ -114,14 +120,34 @@ export interface GameStateAtPointInTime {
   capturedPieces: CompactCapturedPieces;
   moves: string[];
   engineResult: SearchStatistics | null;
+  clock?: ClockSnapshot; // Clocks when this state was reached
 }
 
+export interface ClockSnapshot {
+  whiteSecondsLeft: number;
+  blackSecondsLeft: number;
+}
+
+export interface PositionSnapshot {
+  ply: number;
+  position: GameStateAtPointInTime;
+}
+
+export interface PlyDetails {
+  clock?: ClockSnapshot;
+  engineResult?: SearchStatistics;
+}
+
+// Past positions that aren't snapshots are requested with `GetPositions` as they're viewed
 export interface GameState {
   gameId: string;
   gameTime: GameTime | null;
-  history: GameStateAtPointInTime[];
   moveList: string[];
+  sanList: string[];
   opening: OpeningInfo | null;
+  snapshots: PositionSnapshot[]; // Every 20 plies, without their moves
+  plyDetails: PlyDetails[]; // One per past position
+  current: GameStateAtPointInTime;
 }
 
 export enum GamePhase {
@@ -161,6 +187,16 @@ export interface PgnResult {
   pgn: string | null;
 }
 
+export interface PositionsResult {
+  gameId: string;
+  positions: PositionSnapshot[];
+}
+
+export interface AuthResult {
+  username: string;
+  sessionToken: string; // Pass as the `token` query parameter when connecting
+}
+
 export enum PlayerActionName {
   CreateGame = 'create-game',
   JoinGame = 'join-game',
@@ -168,33 +204,69 @@ export enum PlayerActionName {
   GetGameState = 'get-game-state',
   GetFen = 'get-fen',
   GetPgn = 'get-pgn',
+  GetPositions = 'get-positions',
   MovePiece = 'move-piece',
   Heartbeat = 'heartbeat',
   LoseViaOutOfTime = 'lose-via-out-of-time',
   Resign = 'resign',
+  AbortGame = 'abort-game',
   OfferDraw = 'offer-draw',
+  RequestTakeback = 'request-takeback',
+  AcceptTakeback = 'accept-takeback',
+  DeclineTakeback = 'decline-takeback',
   AcceptDraw = 'accept-draw',
   DeclineDraw = 'decline-draw',
   AnalyzePosition = 'analyze-position',
   PlayAgain = 'play-again',
+  Register = 'register',
+  Login = 'login',
+  Logout = 'logout',
+  WatchGame = 'watch-game',
+  StopWatchingGame = 'stop-watching-game',
+  Seek = 'seek',
+  CancelSeek = 'cancel-seek',
+  ListOpenGames = 'list-open-games',
+  LeaveLobby = 'leave-lobby',
+  SendChatMessage = 'send-chat-message',
+  GetChatMessages = 'get-chat-messages',
+  CreateTournament = 'create-tournament',
+  JoinTournament = 'join-tournament',
+  StartTournamentRound = 'start-tournament-round',
+  GetTournament = 'get-tournament',
+  SetPremove = 'set-premove',
+  MuteOpponent = 'mute-opponent',
+  ListMyGames = 'list-my-games',
+  GetUserStats = 'get-user-stats',
+  ChallengeUser = 'challenge-user',
+  AcceptChallenge = 'accept-challenge',
+  DeclineChallenge = 'decline-challenge',
+  AddFriend = 'add-friend',
+  RemoveFriend = 'remove-friend',
+  GetOnlineFriends = 'get-online-friends',
 }
 
 interface PlayerActionCreateGame {
   [PlayerActionName.CreateGame]: {
-    username: string;
+    username: string | null; // Ignored when logged in
     gameId: string | null;
     boardSetup: BoardSetup | null;
     colorPreference: ColorPreference | null;
     secondsPerPlayer: TimeOption | null;
     engineDifficulty: EngineDifficulty | null;
+    allowTakebacks?: boolean; // Defaults to `true`
+    rated?: boolean; // Defaults to `false`
+    private?: boolean; // Defaults to `false`
+    inviteExpiresInMinutes?: number; // Defaults to 60
   };
 }
 
 
 interface PlayerActionJoinGame {
   [PlayerActionName.JoinGame]: {
-    username: string;
+    username: string | null; // Ignored when logged in
     gameId: string;
+    seatToken?: string; // Required to rejoin a seat as a guest
+    inviteToken?: string; // Required to take the free seat in a private game
   };
 }
 
@@ -223,6 +295,13 @@ interface PlayerActionGetPgn {
   };
 }
 
+interface PlayerActionGetPositions {
+  [PlayerActionName.GetPositions]: {
+    gameId: string;
+    ply: number; // Also sends the positions rebuilt on the way to it
+  };
+}
+
 interface PlayerActionMovePiece {
   [PlayerActionName.MovePiece]: {
     gameId: string;
@@ -243,6 +322,30 @@ interface PlayerActionResign {
   };
 }
 
+interface PlayerActionAbortGame {
+  [PlayerActionName.AbortGame]: {
+    gameId: string;
+  };
+}
+
+interface PlayerActionRequestTakeback {
+  [PlayerActionName.RequestTakeback]: {
+    gameId: string;
+  };
+}
+
+interface PlayerActionAcceptTakeback {
+  [PlayerActionName.AcceptTakeback]: {
+    gameId: string;
+  };
+}
+
+interface PlayerActionDeclineTakeback {
+  [PlayerActionName.DeclineTakeback]: {
+    gameId: string;
+  };
+}
+
 interface PlayerActionOfferDraw {
   [PlayerActionName.OfferDraw]: {
     gameId: string;
@@ -271,9 +374,162 @@ interface PlayerActionAnalyzePosition {
 interface PlayerActionPlayAgain {
   [PlayerActionName.PlayAgain]: {
     gameId: string;
+    colorPreference?: ColorPreference; // Defaults to swapping colors
+    bestOf?: number; // Starts a match, unless the game is already part of one
+  };
+}
+
+interface PlayerActionRegister {
+  [PlayerActionName.Register]: {
+    username: string;
+    password: string;
+  };
+}
+
+interface PlayerActionLogin {
+  [PlayerActionName.Login]: {
+    username: string;
+    password: string;
+  };
+}
+
+interface PlayerActionLogout {
+  [PlayerActionName.Logout]: {
+    sessionToken: string;
+  };
+}
+
+interface PlayerActionWatchGame {
+  [PlayerActionName.WatchGame]: {
+    gameId: string;
+  };
+}
+
+interface PlayerActionStopWatchingGame {
+  [PlayerActionName.StopWatchingGame]: {
+    gameId: string;
+  };
+}
+
+interface PlayerActionSeek {
+  [PlayerActionName.Seek]: {
+    username: string | null; // Ignored when logged in
+    boardSetup: BoardSetup | null;
+    secondsPerPlayer: TimeOption | null;
+    minRating: number | null;
+    maxRating: number | null;
+  };
+}
+
+type PlayerActionCancelSeek = PlayerActionName.CancelSeek;
+
+type PlayerActionListOpenGames = PlayerActionName.ListOpenGames;
+
+type PlayerActionLeaveLobby = PlayerActionName.LeaveLobby;
+
+interface PlayerActionSendChatMessage {
+  [PlayerActionName.SendChatMessage]: {
+    gameId: string;
+    message: string;
+  };
+}
+
+interface PlayerActionGetChatMessages {
+  [PlayerActionName.GetChatMessages]: {
+    gameId: string;
+  };
+}
+
+interface PlayerActionCreateTournament {
+  [PlayerActionName.CreateTournament]: {
+    name: string;
+    format: TournamentFormat;
+    boardSetup: BoardSetup | null;
+    secondsPerPlayer: TimeOption | null;
+  };
+}
+
+interface PlayerActionJoinTournament {
+  [PlayerActionName.JoinTournament]: {
+    tournamentId: string;
+  };
+}
+
+interface PlayerActionStartTournamentRound {
+  [PlayerActionName.StartTournamentRound]: {
+    tournamentId: string;
+  };
+}
+
+interface PlayerActionGetTournament {
+  [PlayerActionName.GetTournament]: {
+    tournamentId: string;
+  };
+}
+
+interface PlayerActionSetPremove {
+  [PlayerActionName.SetPremove]: {
+    gameId: string;
+    playerMove: PlayerMove | null; // `null` clears the premove
+  };
+}
+
+interface PlayerActionMuteOpponent {
+  [PlayerActionName.MuteOpponent]: {
+    gameId: string;
+    muted: boolean;
+  };
+}
+
+interface PlayerActionListMyGames {
+  [PlayerActionName.ListMyGames]: {
+    cursor: string | null; // From the previous page's `nextCursor`
+    limit?: number;
+  };
+}
+
+interface PlayerActionGetUserStats {
+  [PlayerActionName.GetUserStats]: {
+    username: string | null; // Defaults to the logged-in user
+  };
+}
+
+interface PlayerActionChallengeUser {
+  [PlayerActionName.ChallengeUser]: {
+    username: string;
+    boardSetup: BoardSetup | null;
+    colorPreference: ColorPreference | null; // The challenger's
+    secondsPerPlayer: TimeOption | null;
+    rated?: boolean; // Defaults to `false`
+  };
+}
+
+interface PlayerActionAcceptChallenge {
+  [PlayerActionName.AcceptChallenge]: {
+    challengeId: string;
+  };
+}
+
+interface PlayerActionDeclineChallenge {
+  [PlayerActionName.DeclineChallenge]: {
+    challengeId: string;
+  };
+}
+
+interface PlayerActionAddFriend {
+  [PlayerActionName.AddFriend]: {
+    username: string;
+  };
+}
+
+interface PlayerActionRemoveFriend {
+  [PlayerActionName.RemoveFriend]: {
+    username: string;
   };
 }
 
+type PlayerActionGetOnlineFriends = PlayerActionName.GetOnlineFriends;
+
 export type PlayerAction =
   | PlayerActionCreateGame
   | PlayerActionJoinGame
@@ -281,18 +537,49 @@ export type PlayerAction =
   | PlayerActionGetGameState
   | PlayerActionGetFen
   | PlayerActionGetPgn
+  | PlayerActionGetPositions
   | PlayerActionMovePiece
   | PlayerActionHeartbeat
   | PlayerActionLoseViaOutOfTime
   | PlayerActionResign
+  | PlayerActionAbortGame
   | PlayerActionOfferDraw
+  | PlayerActionRequestTakeback
+  | PlayerActionAcceptTakeback
+  | PlayerActionDeclineTakeback
   | PlayerActionAcceptDraw
   | PlayerActionDeclineDraw
   | PlayerActionAnalyzePosition
-  | PlayerActionPlayAgain;
+  | PlayerActionPlayAgain
+  | PlayerActionRegister
+  | PlayerActionLogin
+  | PlayerActionLogout
+  | PlayerActionWatchGame
+  | PlayerActionStopWatchingGame
+  | PlayerActionSeek
+  | PlayerActionCancelSeek
+  | PlayerActionListOpenGames
+  | PlayerActionLeaveLobby
+  | PlayerActionSendChatMessage
+  | PlayerActionGetChatMessages
+  | PlayerActionCreateTournament
+  | PlayerActionJoinTournament
+  | PlayerActionStartTournamentRound
+  | PlayerActionGetTournament
+  | PlayerActionSetPremove
+  | PlayerActionMuteOpponent
+  | PlayerActionListMyGames
+  | PlayerActionGetUserStats
+  | PlayerActionChallengeUser
+  | PlayerActionAcceptChallenge
+  | PlayerActionDeclineChallenge
+  | PlayerActionAddFriend
+  | PlayerActionRemoveFriend
+  | PlayerActionGetOnlineFriends;
 
 export interface GameRecord {
   game_id: string;
+  version: number;
   white_connection_id: string | null;
   white_username: string | null;
   black_connection_id: string | null;
@@ -304,4 +591,205 @@ export interface GameRecord {
   game_state: GameState;
   created: string;
   draw_offered_by: Color | null;
+  takeback_requested_by?: Color;
+  takebacks_allowed: boolean;
+  rated: boolean;
+  chat_muted_by?: Color[];
+  spectator_count: number;
+  white_authenticated: boolean;
+  black_authenticated: boolean;
+  match_score?: MatchScore;
+  tournament?: TournamentGame;
+  awaiting_first_move?: Color;
+  awaiting_first_move_since?: string;
+  awaiting_join?: string; // Only set for tournament games that haven't started
+  awaiting_join_since?: string;
+  invite_expires_at?: number; // Epoch seconds. Only set for private games.
+}
+
+export interface PlayerScore {
+  username: string | null; // `null` for the engine
+  points: number;
+}
+
+// Running score of a best-of-N match, as of the start of the game it's attached to
+export interface MatchScore {
+  bestOf: number;
+  gameIds: string[]; // Earlier games in the match, oldest first
+  scores: PlayerScore[];
+}
+
+type TournamentFormat = 'round-robin' | { swiss: { rounds: number } };
+
+type TournamentState = 'registration' | 'in-progress' | 'finished';
+
+export interface PairingResult {
+  whitePoints: number;
+  blackPoints: number;
+}
+
+// A pairing without a black player is a bye
+export interface TournamentPairing {
+  white: string;
+  black: string | null;
+  gameId: string | null; // `null` for a bye
+  result: PairingResult | null; // Filled in once the game is over
+}
+
+export interface TournamentRound {
+  pairings: TournamentPairing[];
+}
+
+// Attached to each game of a tournament
+export interface TournamentGame {
+  tournamentId: string;
+  name: string;
+  round: number;
+  board: number;
+}
+
+export interface TournamentRecord {
+  tournament_id: string;
+  version: number;
+  name: string;
+  organizer: string;
+  format: TournamentFormat;
+  board_setup: BoardSetup;
+  seconds_per_player: TimeOption | null;
+  players: string[]; // In registration order, which is also the seeding
+  rounds: TournamentRound[];
+  state: TournamentState;
+  created: string;
+}
+
+export interface Standing {
+  username: string;
+  points: number;
+  buchholz: number;
+  sonnebornBerger: number;
+}
+
+// Sent in response to tournament actions
+export interface TournamentDetails {
+  tournament: TournamentRecord;
+  standings: Standing[];
+}
+
+export interface ChatMessageRecord {
+  game_id: string;
+  message_id: string;
+  connection_id: string | null; // `null` for the engine
+  username: string | null; // `null` for the engine
+  message: string;
+  created: string;
+}
+
+export interface OpenGameRecord {
+  game_id: string;
+  white_username: string | null;
+  black_username: string | null;
+  board_setup: BoardSetup;
+  color_preference: ColorPreference;
+  seconds_per_player: TimeOption | null;
+  created: string;
+}
+
+// Sent to lobby subscribers when a game is listed or filled
+export interface LobbyUpdate {
+  gameId: string;
+  openGame: OpenGameRecord | null; // `null` if the game is no longer open
+}
+
+export interface SeekRecord {
+  pool: string;
+  seek_id: string;
+  connection_id: string;
+  username: string;
+  rating: number;
+  authenticated: boolean;
+  min_rating: number | null;
+  max_rating: number | null;
+  board_setup: BoardSetup;
+  seconds_per_player: TimeOption | null;
+  created: string;
 }
+
+export enum TimeControlCategory {
+  Bullet = 'bullet',
+  Blitz = 'blitz',
+  Rapid = 'rapid',
+  Classical = 'classical',
+  Correspondence = 'correspondence',
+}
+
+export enum GameOutcome {
+  Win = 'win',
+  Loss = 'loss',
+  Draw = 'draw',
+  Aborted = 'aborted',
+}
+
+export interface GameHistoryEntry {
+  gameId: string;
+  created: string;
+  color: Color;
+  opponent: string | null;
+  outcome: GameOutcome | null; // `null` while the game is in progress
+  endReason: GameEnding | null;
+  secondsPerPlayer: TimeOption | null;
+  timeControl: TimeControlCategory;
+  opening: OpeningInfo | null;
+  rated: boolean;
+}
+
+// Sent in response to `list-my-games`
+export interface GameHistoryPage {
+  games: GameHistoryEntry[];
+  nextCursor: string | null;
+}
+
+export interface ResultTotals {
+  games: number;
+  wins: number;
+  draws: number;
+  losses: number;
+  scorePercentage: number; // Wins count fully and draws count half
+  averageMoves: number;
+}
+
+// Sent in response to `get-user-stats`
+export interface UserStats {
+  username: string;
+  overall: ResultTotals;
+  byColor: { color: Color; results: ResultTotals }[];
+  byTimeControl: { timeControl: TimeControlCategory; results: ResultTotals }[];
+  byBoardSetup: { boardSetup: BoardSetup; results: ResultTotals }[];
+  openings: { eco: string; name: string; results: ResultTotals }[]; // Best-scoring first
+}
+
+// Sent to the challenged player, and back to the challenger, by `challenge-user`
+export interface ChallengeRecord {
+  username: string; // The challenged player
+  sk: string;
+  challenge_id: string;
+  challenger: string;
+  challenger_connection_id: string;
+  board_setup: BoardSetup;
+  color_preference: ColorPreference; // The challenger's
+  seconds_per_player: TimeOption | null;
+  rated: boolean;
+  created: string;
+  expires_at: number; // Epoch seconds
+}
+
+// Sent in response to `get-online-friends`. Friends are users who have added each other.
+export interface FriendPresence {
+  username: string;
+  lastSeen: string | null;
+}
+
+// Pushed to a user's friends as they come online, go offline or start a game
+export type FriendEvent =
+  | { event: 'online'; username: string }
+  | { event: 'offline'; username: string }
+  | { event: 'started-game'; username: string; gameId: string };
@@ -0,0 +1,74 @@
+use aws_config::BehaviorVersion;
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use aws_sdk_dynamodb::Client;
+use lambda_http::aws_lambda_events::apigw::ApiGatewayWebsocketProxyRequest;
+use lambda_http::http::StatusCode;
+use lambda_http::LambdaEvent;
+use lambda_runtime::{run, service_fn, Error};
+
+use chess::helpers::connection::connect_user;
+use chess::notifier::ApiGatewayNotifier;
+use chess::repository::{DynamoDbRepository, UserRepository};
+use chess::utils::api::build_response;
+
+/// Connections without a session token are guests. Connections with an invalid
+/// or expired token are rejected so the client knows to log in again.
+async fn function_handler(
+    event: LambdaEvent<ApiGatewayWebsocketProxyRequest>,
+    notifier: &ApiGatewayNotifier,
+    repository: &impl UserRepository,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let notifier = notifier.for_request(&event.payload.request_context);
+
+    let Some(connection_id) = event.payload.request_context.connection_id.as_ref() else {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            None,
+            Some(vec!["Missing connection ID".into()]),
+            None::<()>,
+        );
+    };
+
+    let Some(token) = event.payload.query_string_parameters.first("token") else {
+        tracing::info!("GUEST CONNECTED (CONNECTION ID: {connection_id})");
+        return build_response(StatusCode::OK, None, None, None::<()>);
+    };
+
+    if connect_user(&notifier, repository, connection_id, token)
+        .await?
+        .is_none()
+    {
+        return build_response(
+            StatusCode::UNAUTHORIZED,
+            None,
+            Some(vec!["Invalid or expired session token".into()]),
+            None::<()>,
+        );
+    }
+
+    build_response(StatusCode::OK, None, None, None::<()>)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let sdk_config = aws_config::load_defaults(BehaviorVersion::latest()).await;
+    let repository = DynamoDbRepository::from_env(Client::new(&sdk_config));
+    let notifier = ApiGatewayNotifier::new(&sdk_config);
+
+    tracing_subscriber::fmt()
+        .json()
+        .with_max_level(tracing::Level::INFO)
+        .with_target(true) // Include the name of the module in every log line
+        .with_current_span(false) // Remove duplicated "span" key in from logs
+        .without_time() // CloudWatch will add the ingestion time
+        .init();
+
+    run(service_fn(
+        |event: LambdaEvent<ApiGatewayWebsocketProxyRequest>| async {
+            function_handler(event, &notifier, &repository).await
+        },
+    ))
+    .await?;
+
+    Ok(())
+}
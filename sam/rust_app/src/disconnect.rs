@@ -1,3 +1,7 @@
+// Disconnecting a player can notify the game, its spectators and the lobby, and the nested futures
+// are deeper than the compiler lays out by default
+#![recursion_limit = "256"]
+
 use aws_config::BehaviorVersion;
 use aws_lambda_events::apigw::ApiGatewayProxyResponse;
 use aws_sdk_dynamodb::Client;
@@ -6,20 +10,18 @@ use lambda_http::http::StatusCode;
 use lambda_http::LambdaEvent;
 use lambda_runtime::{run, service_fn, Error};
 
-use chess::helpers::game::{get_game, mark_user_as_disconnected_and_notify_other_player};
-use chess::helpers::user::{get_user_games_from_connection_id, save_user_record};
+use chess::helpers::connection::handle_connection_closed;
+use chess::notifier::ApiGatewayNotifier;
+use chess::repository::{DynamoDbRepository, Repository};
 use chess::utils::api::build_response;
 
 async fn function_handler(
     event: LambdaEvent<ApiGatewayWebsocketProxyRequest>,
-    sdk_config: &aws_config::SdkConfig,
-    dynamo_db_client: &Client,
+    notifier: &ApiGatewayNotifier,
+    repository: &impl Repository,
 ) -> Result<ApiGatewayProxyResponse, Error> {
     let request_context = event.payload.request_context;
-
-    let user_table = std::env::var("USER_TABLE").unwrap();
-    let user_table_gsi = std::env::var("USER_TABLE_GSI").unwrap();
-    let game_table = std::env::var("GAME_TABLE").unwrap();
+    let notifier = notifier.for_request(&request_context);
 
     let Some(connection_id) = request_context.connection_id.as_ref() else {
         return build_response(
@@ -30,48 +32,7 @@ async fn function_handler(
         );
     };
 
-    let mut user_games = get_user_games_from_connection_id(
-        dynamo_db_client,
-        &user_table,
-        &user_table_gsi,
-        connection_id,
-    )
-    .await?;
-
-    for user_game in user_games.iter_mut() {
-        // Get the game ID from the user-game record sort key (e.g., "GAME-1234")
-        let game_id = user_game.sort_key.trim_start_matches("GAME-");
-
-        tracing::info!(
-            "Found user game record (game ID: {game_id}) for connection ID {connection_id}"
-        );
-
-        let username = &user_game.username;
-
-        // Disassociate this connection from the user-game record
-        user_game.connection_id = Some("<disconnected>".to_string());
-        save_user_record(dynamo_db_client, &user_table, user_game).await?;
-
-        // Fetch the game record with the user-game's game ID in the sort key
-        let Some(mut game) = get_game(dynamo_db_client, &game_table, game_id).await? else {
-            tracing::warn!("Game with ID {game_id} not found for associated user-game record");
-            continue;
-        };
-
-        // Remove the respective connection ID from the game record.
-        // Notify the other player if they are connected.
-        mark_user_as_disconnected_and_notify_other_player(
-            sdk_config,
-            &request_context,
-            dynamo_db_client,
-            &game_table,
-            &mut game,
-            username,
-        )
-        .await?;
-
-        tracing::info!("PLAYER {username} DISCONNECTED FROM GAME (ID: {game_id})");
-    }
+    handle_connection_closed(&notifier, repository, connection_id).await?;
 
     build_response(StatusCode::OK, None, None, None::<()>)
 }
@@ -79,7 +40,8 @@ async fn function_handler(
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     let sdk_config = aws_config::load_defaults(BehaviorVersion::latest()).await;
-    let dynamo_db_client: Client = Client::new(&sdk_config);
+    let repository = DynamoDbRepository::from_env(Client::new(&sdk_config));
+    let notifier = ApiGatewayNotifier::new(&sdk_config);
 
     tracing_subscriber::fmt()
         .json()
@@ -91,7 +53,7 @@ async fn main() -> Result<(), Error> {
 
     run(service_fn(
         |event: LambdaEvent<ApiGatewayWebsocketProxyRequest>| async {
-            function_handler(event, &sdk_config, &dynamo_db_client).await
+            function_handler(event, &notifier, &repository).await
         },
     ))
     .await?;
@@ -5,24 +5,23 @@ use chess::{types::api::GameRequest, utils::api::build_response};
 use lambda_http::{http::StatusCode, LambdaEvent};
 use lambda_runtime::{run, service_fn, Error};
 
-mod player_action_handlers;
-
+use chess::helpers::chat::parse_word_filter;
+use chess::notifier::ApiGatewayNotifier;
+use chess::player_action_handlers::{analyze_position::analyze_position, handle_player_action};
+use chess::repository::{DynamoDbRepository, Repository};
 use chess::types::game::PlayerAction;
-use player_action_handlers::{
-    accept_draw::accept_draw, analyze_position::analyze_position, create_new_game::create_new_game,
-    decline_draw::decline_draw, get_game_state::get_game_state, join_game::join_game,
-    move_piece::move_piece, offer_draw::offer_draw,
-};
 
 async fn function_handler(
     event: LambdaEvent<ApiGatewayWebsocketProxyRequest>,
     sdk_config: &aws_config::SdkConfig,
-    dynamo_db_client: &Client,
+    repository: &impl Repository,
+    notifier: &ApiGatewayNotifier,
 ) -> Result<ApiGatewayProxyResponse, Error> {
-    let game_table = std::env::var("GAME_TABLE").unwrap();
-    let user_table = std::env::var("USER_TABLE").unwrap();
+    let chat_word_filter =
+        parse_word_filter(&std::env::var("CHAT_WORD_FILTER").unwrap_or_default());
 
     let request_context = event.payload.request_context;
+    let notifier = notifier.for_request(&request_context);
 
     let Some(connection_id) = request_context.connection_id.as_ref() else {
         return build_response(
@@ -58,138 +57,6 @@ async fn function_handler(
     };
 
     match request_data.data {
-        PlayerAction::CreateGame {
-            username,
-            game_id,
-            board_setup,
-            color_preference,
-            engine_difficulty,
-            seconds_per_player,
-        } => {
-            create_new_game(
-                sdk_config,
-                &request_context,
-                dynamo_db_client,
-                &game_table,
-                &user_table,
-                connection_id,
-                username.trim(),
-                game_id.as_deref().map(|s| s.trim()),
-                board_setup,
-                color_preference,
-                engine_difficulty,
-                seconds_per_player,
-            )
-            .await
-        }
-        PlayerAction::JoinGame { username, game_id } => {
-            join_game(
-                sdk_config,
-                &request_context,
-                dynamo_db_client,
-                &game_table,
-                &user_table,
-                connection_id,
-                username.trim(),
-                game_id.trim(),
-            )
-            .await
-        }
-        PlayerAction::LeaveGame { game_id } => {
-            player_action_handlers::leave_game::leave_game(
-                sdk_config,
-                &request_context,
-                dynamo_db_client,
-                connection_id,
-                &game_table,
-                &user_table,
-                &game_id,
-            )
-            .await
-        }
-        PlayerAction::GetGameState { game_id } => {
-            get_game_state(dynamo_db_client, connection_id, &game_table, game_id.trim()).await
-        }
-        PlayerAction::MovePiece {
-            game_id,
-            player_move,
-        } => {
-            move_piece(
-                sdk_config,
-                &request_context,
-                dynamo_db_client,
-                &game_table,
-                &user_table,
-                connection_id,
-                &game_id,
-                player_move,
-            )
-            .await
-        }
-        PlayerAction::Heartbeat => build_response(
-            StatusCode::OK,
-            Some(connection_id.clone()),
-            None,
-            None::<()>,
-        ),
-        PlayerAction::LoseViaOutOfTime { game_id } => {
-            player_action_handlers::lose_via_out_of_time::lose_via_out_of_time(
-                sdk_config,
-                &request_context,
-                dynamo_db_client,
-                connection_id,
-                &game_table,
-                &user_table,
-                &game_id,
-            )
-            .await
-        }
-        PlayerAction::Resign { game_id } => {
-            player_action_handlers::resign::resign(
-                sdk_config,
-                &request_context,
-                dynamo_db_client,
-                connection_id,
-                &game_table,
-                &user_table,
-                &game_id,
-            )
-            .await
-        }
-        PlayerAction::OfferDraw { game_id } => {
-            offer_draw(
-                sdk_config,
-                &request_context,
-                dynamo_db_client,
-                connection_id,
-                &game_table,
-                &game_id,
-            )
-            .await
-        }
-        PlayerAction::AcceptDraw { game_id } => {
-            accept_draw(
-                sdk_config,
-                &request_context,
-                dynamo_db_client,
-                connection_id,
-                &game_table,
-                &user_table,
-                &game_id,
-            )
-            .await
-        }
-        PlayerAction::DeclineDraw { game_id } => {
-            decline_draw(
-                sdk_config,
-                &request_context,
-                dynamo_db_client,
-                connection_id,
-                &game_table,
-                &game_id,
-            )
-            .await
-        }
         PlayerAction::AnalyzePosition {
             game_id,
             analysis_type,
@@ -197,45 +64,20 @@ async fn function_handler(
             analyze_position(
                 sdk_config,
                 &request_context,
-                dynamo_db_client,
-                &game_table,
+                repository,
                 connection_id,
                 &game_id,
                 analysis_type,
             )
             .await
         }
-        PlayerAction::GetFen {
-            game_id,
-            history_index,
-        } => {
-            player_action_handlers::get_fen::get_fen(
-                dynamo_db_client,
-                connection_id,
-                &game_table,
-                game_id.trim(),
-                history_index,
-            )
-            .await
-        }
-        PlayerAction::GetPgn { game_id } => {
-            player_action_handlers::get_pgn::get_pgn(
-                dynamo_db_client,
-                connection_id,
-                &game_table,
-                game_id.trim(),
-            )
-            .await
-        }
-        PlayerAction::PlayAgain { game_id } => {
-            player_action_handlers::play_again::play_again(
-                sdk_config,
-                &request_context,
-                dynamo_db_client,
-                &game_table,
-                &user_table,
+        action => {
+            handle_player_action(
+                &notifier,
+                repository,
                 connection_id,
-                game_id.trim(),
+                &chat_word_filter,
+                action,
             )
             .await
         }
@@ -245,7 +87,8 @@ async fn function_handler(
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     let sdk_config = aws_config::load_defaults(BehaviorVersion::latest()).await;
-    let dynamo_db_client: Client = Client::new(&sdk_config);
+    let repository = DynamoDbRepository::from_env(Client::new(&sdk_config));
+    let notifier = ApiGatewayNotifier::new(&sdk_config);
 
     tracing_subscriber::fmt()
         .json()
@@ -257,7 +100,7 @@ async fn main() -> Result<(), Error> {
 
     run(service_fn(
         |event: LambdaEvent<ApiGatewayWebsocketProxyRequest>| async {
-            function_handler(event, &sdk_config, &dynamo_db_client).await
+            function_handler(event, &sdk_config, &repository, &notifier).await
         },
     ))
     .await?;
@@ -0,0 +1,242 @@
+use lambda_runtime::Error;
+
+use crate::{
+    helpers::{
+        game::{
+            is_game_over, notify_everyone_about_game_update, update_game, GameAction, GameUpdate,
+        },
+        generic::{format_timestamp, get_timestamp},
+        lobby::update_lobby_listing,
+    },
+    notifier::Notifier,
+    repository::{GameRepository, SubscriptionRepository},
+    types::{
+        api::{ApiMessage, ApiMessageType},
+        dynamo_db::{AwaitingFirstMoveRecord, GameRecord},
+        game::State,
+        piece::Color,
+    },
+};
+
+/// A game can be aborted until both sides have made their first move
+pub fn can_abort_game(game: &GameRecord) -> bool {
+    !is_game_over(game) && game.game_state.move_list.len() < 2
+}
+
+/// End the game without a result. Aborted games don't count towards records, ratings or matches.
+pub fn abort_game(game: &mut GameRecord) {
+    game.game_state.current_state_mut().state = State::Aborted;
+    game.draw_offered_by = None;
+    game.takeback_requested_by = None;
+    game.white_premove = None;
+    game.black_premove = None;
+
+    update_awaiting_first_move(game);
+    update_lobby_listing(game);
+}
+
+/// The player whose first move the game is waiting on, if the game has started and both
+/// sides haven't moved yet
+pub fn get_color_awaiting_first_move(game: &GameRecord) -> Option<Color> {
+    let current_state = game.game_state.current_state();
+
+    (current_state.state == State::InProgress && game.game_state.move_list.len() < 2)
+        .then_some(current_state.current_turn)
+}
+
+/// Keeps the game's first-move index attributes in sync with its state. The timer restarts
+/// whenever a different player becomes the one who hasn't moved yet.
+pub fn update_awaiting_first_move(game: &mut GameRecord) {
+    let awaiting_first_move = get_color_awaiting_first_move(game);
+
+    if game.awaiting_first_move != awaiting_first_move {
+        game.awaiting_first_move = awaiting_first_move;
+        game.awaiting_first_move_since = awaiting_first_move.map(|_| get_timestamp());
+    }
+}
+
+/// Aborts the games in which a player hasn't made their first move within the timeout, and tells
+/// everyone in them. Run on a schedule by the `sweeper` Lambda and the local server.
+pub async fn abort_games_awaiting_first_move(
+    notifier: &impl Notifier,
+    repository: &(impl GameRepository + SubscriptionRepository),
+    first_move_timeout_seconds: i64,
+) -> Result<(), Error> {
+    let cutoff = format_timestamp(
+        chrono::Utc::now() - chrono::Duration::seconds(first_move_timeout_seconds),
+    );
+
+    for color in [Color::White, Color::Black] {
+        let stale_games = repository
+            .get_games_awaiting_first_move(color, &cutoff)
+            .await?;
+
+        for AwaitingFirstMoveRecord { game_id, .. } in stale_games {
+            let update = update_game(repository, &game_id, async |game| {
+                if get_color_awaiting_first_move(game).is_none() {
+                    // The game ended another way (e.g. resignation), so drop it from the index
+                    update_awaiting_first_move(game);
+                    return Ok(GameAction::Save(None));
+                }
+
+                // The index is eventually consistent, so the first move may have just been made
+                if game
+                    .awaiting_first_move_since
+                    .as_deref()
+                    .is_none_or(|since| since >= cutoff.as_str())
+                {
+                    return Ok(GameAction::Reject(()));
+                }
+
+                let username = match color {
+                    Color::White => game.white_username.clone(),
+                    Color::Black => game.black_username.clone(),
+                }
+                .unwrap_or_else(|| color.to_string());
+
+                abort_game(game);
+
+                Ok(GameAction::Save(Some(username)))
+            })
+            .await?;
+
+            let (game, username) = match update {
+                GameUpdate::Saved {
+                    game,
+                    output: Some(username),
+                } => (*game, username),
+                GameUpdate::Saved { output: None, .. }
+                | GameUpdate::Rejected(())
+                | GameUpdate::NotFound => continue,
+                // The next sweep will try again
+                GameUpdate::Conflict => {
+                    tracing::warn!("Could not abort game (ID: {game_id}) because it kept changing");
+                    continue;
+                }
+            };
+
+            let messages = vec![ApiMessage {
+                message: format!("Game aborted: {username} didn't make their first move in time"),
+                message_type: ApiMessageType::Info,
+            }];
+
+            notify_everyone_about_game_update(notifier, repository, &game, messages).await?;
+
+            tracing::info!("ABORTED GAME (ID: {game_id}): {username} never made a first move");
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::game::{check_if_both_players_just_joined, create_game, make_move};
+    use crate::notifier::RecordingNotifier;
+    use crate::repository::InMemoryRepository;
+    use crate::types::board::{File, Position, Rank};
+    use crate::types::game::{ColorPreference, PlayerMove};
+    use crate::types::presence::ConnectionState;
+
+    fn started_game() -> GameRecord {
+        let mut game = create_game(
+            None,
+            "alice",
+            None,
+            Some(ColorPreference::White),
+            None,
+            None,
+            "conn-alice",
+        );
+        game.black_username = Some("bob".to_string());
+        game.black_connection_id = Some(ConnectionState::connected("conn-bob"));
+        check_if_both_players_just_joined(&mut game);
+        game
+    }
+
+    fn pawn_move(file: usize, from_rank: usize, to_rank: usize) -> PlayerMove {
+        PlayerMove {
+            from: Position {
+                rank: Rank(from_rank),
+                file: File(file),
+            },
+            to: Position {
+                rank: Rank(to_rank),
+                file: File(file),
+            },
+        }
+    }
+
+    #[test]
+    fn test_can_abort_only_before_both_sides_move() {
+        let mut game = started_game();
+        assert!(can_abort_game(&game));
+
+        make_move(&mut game.game_state, &pawn_move(5, 2, 4));
+        assert!(can_abort_game(&game));
+
+        make_move(&mut game.game_state, &pawn_move(5, 7, 5));
+        assert!(!can_abort_game(&game));
+
+        let mut game = started_game();
+        abort_game(&mut game);
+        assert_eq!(game.game_state.current_state().state, State::Aborted);
+        assert!(!can_abort_game(&game));
+        assert!(game.awaiting_first_move.is_none());
+        assert!(game.awaiting_first_move_since.is_none());
+    }
+
+    #[test]
+    fn test_update_awaiting_first_move() {
+        let mut game = started_game();
+        assert_eq!(game.awaiting_first_move, Some(Color::White));
+        assert!(game.awaiting_first_move_since.is_some());
+
+        make_move(&mut game.game_state, &pawn_move(5, 2, 4));
+        update_awaiting_first_move(&mut game);
+        assert_eq!(game.awaiting_first_move, Some(Color::Black));
+
+        make_move(&mut game.game_state, &pawn_move(5, 7, 5));
+        update_awaiting_first_move(&mut game);
+        assert!(game.awaiting_first_move.is_none());
+        assert!(game.awaiting_first_move_since.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_games_are_aborted_once_the_first_move_times_out() {
+        let repository = InMemoryRepository::new();
+        let notifier = RecordingNotifier::new();
+
+        let mut game = started_game();
+        game.game_id = "stale-game".to_string();
+        game.awaiting_first_move_since = Some(format_timestamp(
+            chrono::Utc::now() - chrono::Duration::seconds(120),
+        ));
+        repository.insert_game(&game).await.unwrap();
+
+        let mut game = started_game();
+        game.game_id = "fresh-game".to_string();
+        repository.insert_game(&game).await.unwrap();
+
+        abort_games_awaiting_first_move(&notifier, &repository, 60)
+            .await
+            .unwrap();
+
+        let stale_game = repository.get_game("stale-game").await.unwrap().unwrap();
+        assert_eq!(stale_game.game_state.current_state().state, State::Aborted);
+        assert!(stale_game.awaiting_first_move.is_none());
+
+        let fresh_game = repository.get_game("fresh-game").await.unwrap().unwrap();
+        assert_eq!(
+            fresh_game.game_state.current_state().state,
+            State::InProgress
+        );
+
+        for connection_id in ["conn-alice", "conn-bob"] {
+            let sent = notifier.sent_to(connection_id);
+            assert_eq!(sent.len(), 1);
+            assert_eq!(sent[0]["data"]["game_id"], "stale-game");
+        }
+    }
+}
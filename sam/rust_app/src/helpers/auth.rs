@@ -0,0 +1,233 @@
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use base64::{engine::general_purpose, Engine as _};
+use lambda_runtime::Error;
+use rand::RngCore;
+
+use crate::helpers::generic::{format_timestamp, generate_token, hash_token};
+use crate::helpers::user::{create_user_connection, get_authenticated_username};
+use crate::repository::UserRepository;
+use crate::types::dynamo_db::SessionRecord;
+
+const SESSION_DURATION_DAYS: i64 = 30;
+const MIN_PASSWORD_LENGTH: usize = 8;
+const MAX_USERNAME_LENGTH: usize = 24;
+
+pub fn validate_username(username: &str) -> Result<(), &'static str> {
+    if username.is_empty() {
+        return Err("Must provide a username");
+    }
+
+    if username.chars().count() > MAX_USERNAME_LENGTH {
+        return Err("Username must be at most 24 characters");
+    }
+
+    if !username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err("Username may only contain letters, numbers, underscores and hyphens");
+    }
+
+    Ok(())
+}
+
+pub fn validate_password(password: &str) -> Result<(), &'static str> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err("Password must be at least 8 characters");
+    }
+
+    Ok(())
+}
+
+pub fn hash_password(password: &str) -> Result<String, Error> {
+    let mut salt_bytes = [0u8; 16];
+    rand::rng().fill_bytes(&mut salt_bytes);
+
+    let salt = SaltString::encode_b64(&salt_bytes).map_err(|e| Error::from(e.to_string()))?;
+
+    Ok(Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| Error::from(e.to_string()))?
+        .to_string())
+}
+
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash).is_ok_and(|parsed_hash| {
+        Argon2::default()
+            .verify_password(password.as_bytes(), &parsed_hash)
+            .is_ok()
+    })
+}
+
+/// Session tokens carry the username so the session can be fetched by its primary key.
+/// Only a hash of the secret part is stored.
+pub fn create_session(username: &str) -> (String, SessionRecord) {
+    let secret = generate_token();
+    let now = chrono::Utc::now();
+
+    let token = format!(
+        "{}.{secret}",
+        general_purpose::URL_SAFE_NO_PAD.encode(username)
+    );
+
+    let session = SessionRecord {
+        username: username.to_string(),
+        sort_key: format!("SESSION-{}", hash_token(&secret)),
+        created: format_timestamp(now),
+        expires_at: (now + chrono::Duration::days(SESSION_DURATION_DAYS)).timestamp(),
+    };
+
+    (token, session)
+}
+
+/// Returns the username and the session record's sort key
+pub fn parse_session_token(token: &str) -> Option<(String, String)> {
+    let (encoded_username, secret) = token.split_once('.')?;
+
+    let username = general_purpose::URL_SAFE_NO_PAD
+        .decode(encoded_username)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())?;
+
+    if username.is_empty() || secret.is_empty() {
+        return None;
+    }
+
+    Some((username, format!("SESSION-{}", hash_token(secret))))
+}
+
+/// Returns the username for a valid, unexpired session token
+pub async fn authenticate_session_token(
+    repository: &impl UserRepository,
+    token: &str,
+) -> Result<Option<String>, Error> {
+    let Some((username, sort_key)) = parse_session_token(token) else {
+        return Ok(None);
+    };
+
+    // DynamoDB TTL deletion is not immediate, so check the expiry too
+    Ok(repository
+        .get_session(&username, &sort_key)
+        .await?
+        .filter(|session| session.expires_at > chrono::Utc::now().timestamp())
+        .map(|session| session.username))
+}
+
+/// Creates a new session for the user and associates the connection with them.
+/// Returns the session token for the client to reconnect with.
+pub async fn log_in_connection(
+    repository: &impl UserRepository,
+    username: &str,
+    connection_id: &str,
+) -> Result<String, Error> {
+    let (token, session) = create_session(username);
+    repository.save_session(&session).await?;
+
+    repository
+        .save_user_record(&create_user_connection(username, connection_id))
+        .await?;
+
+    Ok(token)
+}
+
+pub struct ResolvedUsername {
+    pub username: String,
+    /// Whether the connection is logged in as `username`
+    pub authenticated: bool,
+}
+
+/// Players who are logged in always play as their account. Guests may choose
+/// any valid username that doesn't belong to a registered account.
+pub async fn resolve_username(
+    repository: &impl UserRepository,
+    connection_id: &str,
+    requested_username: Option<&str>,
+) -> Result<Result<ResolvedUsername, String>, Error> {
+    if let Some(username) = get_authenticated_username(repository, connection_id).await? {
+        return Ok(Ok(ResolvedUsername {
+            username,
+            authenticated: true,
+        }));
+    }
+
+    let username = requested_username.unwrap_or_default().trim();
+
+    if let Err(e) = validate_username(username) {
+        return Ok(Err(e.to_string()));
+    }
+
+    if repository.get_user_info(username).await?.is_some() {
+        return Ok(Err(format!(
+            "`{username}` belongs to a registered account. Log in to play as `{username}`."
+        )));
+    }
+
+    Ok(Ok(ResolvedUsername {
+        username: username.to_string(),
+        authenticated: false,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::InMemoryRepository;
+
+    #[test]
+    fn test_validate_username() {
+        assert!(validate_username("magnus_c-1").is_ok());
+        assert!(validate_username("").is_err());
+        assert!(validate_username("has space").is_err());
+        assert!(validate_username(&"a".repeat(25)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_guest_usernames_are_validated() {
+        let repository = InMemoryRepository::new();
+
+        let resolved = resolve_username(&repository, "conn", Some(" guest_1 "))
+            .await
+            .unwrap();
+        assert_eq!(resolved.unwrap().username, "guest_1");
+
+        for username in [None, Some(""), Some("has space"), Some("<script>")] {
+            let resolved = resolve_username(&repository, "conn", username)
+                .await
+                .unwrap();
+            assert!(resolved.is_err());
+        }
+
+        let too_long = "a".repeat(MAX_USERNAME_LENGTH + 1);
+        let resolved = resolve_username(&repository, "conn", Some(&too_long))
+            .await
+            .unwrap();
+        assert!(resolved.is_err());
+    }
+
+    #[test]
+    fn test_password_hash_round_trip() {
+        let password_hash = hash_password("correct horse").unwrap();
+        assert!(verify_password("correct horse", &password_hash));
+        assert!(!verify_password("battery staple", &password_hash));
+        assert!(!verify_password("correct horse", "not-a-hash"));
+    }
+
+    #[test]
+    fn test_session_token_round_trip() {
+        let (token, session) = create_session("magnus");
+        let (username, sort_key) = parse_session_token(&token).unwrap();
+        assert_eq!(username, "magnus");
+        assert_eq!(sort_key, session.sort_key);
+        assert!(!session.sort_key.contains(token.split_once('.').unwrap().1));
+    }
+
+    #[test]
+    fn test_parse_invalid_session_token() {
+        assert!(parse_session_token("no-separator").is_none());
+        assert!(parse_session_token("!!!.secret").is_none());
+        assert!(parse_session_token(".secret").is_none());
+    }
+}
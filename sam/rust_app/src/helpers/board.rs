@@ -416,6 +416,7 @@ mod tests {
             },
             moves: Vec::new(),
             engine_result: None,
+            clock: None,
         };
 
         let fen = game_state_to_fen(&game_state);
@@ -0,0 +1,49 @@
+use crate::{
+    helpers::generic::{format_timestamp, generate_token},
+    repository::UserRepository,
+    types::{board::BoardSetup, dynamo_db::ChallengeRecord, game::ColorPreference},
+};
+
+use lambda_runtime::Error;
+
+/// Challenges the challenged user doesn't answer in time are dropped
+pub const CHALLENGE_EXPIRY_SECONDS: i64 = 5 * 60;
+
+pub fn create_challenge(
+    challenger: &str,
+    challenger_connection_id: &str,
+    challenged: &str,
+    board_setup: BoardSetup,
+    color_preference: ColorPreference,
+    seconds_per_player: Option<usize>,
+    rated: bool,
+) -> ChallengeRecord {
+    let challenge_id = generate_token();
+    let now = chrono::Utc::now();
+
+    ChallengeRecord {
+        username: challenged.to_string(),
+        sort_key: format!("CHALLENGE-{challenge_id}"),
+        challenge_id,
+        challenger: challenger.to_string(),
+        challenger_connection_id: challenger_connection_id.to_string(),
+        board_setup,
+        color_preference,
+        seconds_per_player,
+        rated,
+        created: format_timestamp(now),
+        expires_at: now.timestamp() + CHALLENGE_EXPIRY_SECONDS,
+    }
+}
+
+/// Returns the challenge if it was made to the user and hasn't expired. DynamoDB can take a
+/// while to delete items after their TTL passes, so expired challenges are filtered out here.
+pub async fn get_challenge(
+    repository: &impl UserRepository,
+    username: &str,
+    challenge_id: &str,
+) -> Result<Option<ChallengeRecord>, Error> {
+    let challenge = repository.get_challenge(username, challenge_id).await?;
+
+    Ok(challenge.filter(|challenge| challenge.expires_at > chrono::Utc::now().timestamp()))
+}
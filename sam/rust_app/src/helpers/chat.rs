@@ -0,0 +1,216 @@
+use crate::{
+    helpers::{
+        generic::{format_timestamp, generate_id, get_timestamp},
+        subscription::game_topic,
+    },
+    notifier::Notifier,
+    repository::{ChatRepository, SubscriptionRepository},
+    types::{
+        api::ApiResponse,
+        dynamo_db::{ChatMessageRecord, GameRecord},
+        piece::Color,
+        presence::ConnectionState,
+    },
+};
+
+use lambda_runtime::Error;
+
+pub const MAX_CHAT_MESSAGE_LENGTH: usize = 500;
+const RATE_LIMIT_WINDOW_SECONDS: i64 = 10;
+const RATE_LIMIT_MAX_MESSAGES: usize = 5;
+
+const ENGINE_REPLIES: [&str; 5] = [
+    "Good luck, have fun!",
+    "Interesting move...",
+    "I'm thinking about it.",
+    "Let's see what you've got.",
+    "Beep boop. Back to the game!",
+];
+
+pub fn validate_chat_message(message: &str) -> Result<(), String> {
+    if message.trim().is_empty() {
+        return Err("Chat message cannot be empty".to_string());
+    }
+
+    if message.chars().count() > MAX_CHAT_MESSAGE_LENGTH {
+        return Err(format!(
+            "Chat message must be at most {MAX_CHAT_MESSAGE_LENGTH} characters"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Parses a comma-separated list of words to filter out of chat messages
+pub fn parse_word_filter(word_filter: &str) -> Vec<String> {
+    word_filter
+        .split(',')
+        .map(|word| word.trim().to_lowercase())
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Replaces filtered words (case-insensitive, whole words only) with asterisks
+pub fn filter_words(message: &str, filtered_words: &[String]) -> String {
+    let mut filtered_message = String::with_capacity(message.len());
+    let mut word = String::new();
+
+    let flush_word = |word: &mut String, filtered_message: &mut String| {
+        if filtered_words.contains(&word.to_lowercase()) {
+            filtered_message.extend(std::iter::repeat_n('*', word.chars().count()));
+        } else {
+            filtered_message.push_str(word);
+        }
+        word.clear();
+    };
+
+    for c in message.chars() {
+        if c.is_alphanumeric() {
+            word.push(c);
+        } else {
+            flush_word(&mut word, &mut filtered_message);
+            filtered_message.push(c);
+        }
+    }
+
+    flush_word(&mut word, &mut filtered_message);
+
+    filtered_message
+}
+
+/// Whether the connection has sent too many of the given recent messages
+pub fn is_rate_limited(recent_messages: &[ChatMessageRecord], connection_id: &str) -> bool {
+    recent_messages
+        .iter()
+        .filter(|message| message.connection_id.as_deref() == Some(connection_id))
+        .count()
+        >= RATE_LIMIT_MAX_MESSAGES
+}
+
+/// The engine replies to chat messages with a canned message
+pub fn get_engine_reply(message: &str) -> &'static str {
+    ENGINE_REPLIES[message.chars().count() % ENGINE_REPLIES.len()]
+}
+
+pub fn create_chat_message(
+    game_id: &str,
+    connection_id: Option<&str>,
+    username: Option<&str>,
+    message: &str,
+) -> ChatMessageRecord {
+    let created = get_timestamp();
+
+    ChatMessageRecord {
+        game_id: game_id.to_string(),
+        message_id: format!("{created}-{}", generate_id()),
+        connection_id: connection_id.map(|id| id.to_string()),
+        username: username.map(|u| u.to_string()),
+        message: message.to_string(),
+        created,
+    }
+}
+
+/// Returns the game's chat messages within the rate limit window
+pub async fn get_recent_chat_messages(
+    repository: &impl ChatRepository,
+    game_id: &str,
+) -> Result<Vec<ChatMessageRecord>, Error> {
+    let since = chrono::Utc::now() - chrono::Duration::seconds(RATE_LIMIT_WINDOW_SECONDS);
+    repository
+        .get_chat_messages(game_id, Some(&format_timestamp(since)))
+        .await
+}
+
+/// Send a chat message to everyone in the game other than its sender: the opponent (unless they
+/// muted the sender) and any spectators. `sender_color` is `None` for the engine's messages.
+pub async fn relay_chat_message(
+    notifier: &impl Notifier,
+    repository: &impl SubscriptionRepository,
+    game: &GameRecord,
+    chat_message: &ChatMessageRecord,
+    sender_color: Option<Color>,
+) -> Result<(), Error> {
+    let mut connection_ids = Vec::new();
+
+    if let Some(sender_color) = sender_color {
+        let recipient_color = sender_color.opponent_color();
+
+        let recipient_connection_id = match recipient_color {
+            Color::White => &game.white_connection_id,
+            Color::Black => &game.black_connection_id,
+        };
+
+        if let Some(recipient_connection_id) = recipient_connection_id
+            .as_ref()
+            .and_then(ConnectionState::connection_id)
+        {
+            if !game.chat_muted_by.contains(&recipient_color) {
+                connection_ids.push(recipient_connection_id.to_string());
+            }
+        }
+    }
+
+    if game.spectator_count > 0 {
+        let spectators = repository
+            .get_subscribers(&game_topic(&game.game_id))
+            .await?;
+
+        connection_ids.extend(spectators.into_iter().map(|s| s.connection_id));
+    }
+
+    // Gone connections are cleaned up by the disconnect function and game updates
+    for connection_id in connection_ids {
+        notifier
+            .send(
+                &connection_id,
+                &ApiResponse {
+                    status_code: 200,
+                    connection_id: Some(connection_id.clone()),
+                    messages: Vec::new(),
+                    data: Some(chat_message),
+                    replaces_game_id: None,
+                    seat_token: None,
+                    invite_token: None,
+                },
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_chat_message() {
+        assert!(validate_chat_message("gg").is_ok());
+        assert!(validate_chat_message("   ").is_err());
+        assert!(validate_chat_message(&"a".repeat(MAX_CHAT_MESSAGE_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn test_filter_words() {
+        let filtered_words = parse_word_filter(" darn, HECK ,,");
+        assert_eq!(filtered_words, vec!["darn", "heck"]);
+        assert_eq!(
+            filter_words("Darn it, what the heck!", &filtered_words),
+            "**** it, what the ****!"
+        );
+        // Only whole words are filtered
+        assert_eq!(filter_words("darned", &filtered_words), "darned");
+        assert_eq!(filter_words("gg", &[]), "gg");
+    }
+
+    #[test]
+    fn test_is_rate_limited() {
+        let messages: Vec<_> = (0..RATE_LIMIT_MAX_MESSAGES)
+            .map(|_| create_chat_message("game", Some("conn-a"), Some("alice"), "hi"))
+            .collect();
+
+        assert!(is_rate_limited(&messages, "conn-a"));
+        assert!(!is_rate_limited(&messages, "conn-b"));
+        assert!(!is_rate_limited(&messages[1..], "conn-a"));
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{
+    helpers::{
+        auth::authenticate_session_token,
+        game::{
+            mark_user_as_disconnected_and_notify_other_player, remove_spectator_and_notify_game,
+        },
+        lobby::LOBBY_TOPIC,
+        presence::{handle_user_connected, handle_user_disconnected},
+        seek::delete_seeks_from_connection_id,
+        user::create_user_connection,
+    },
+    notifier::Notifier,
+    repository::{Repository, UserRepository},
+    types::presence::ConnectionState,
+};
+
+use lambda_runtime::Error;
+
+/// Logs the connection in as the owner of the session token. Returns `None` if the token is
+/// invalid or expired.
+pub async fn connect_user(
+    notifier: &impl Notifier,
+    repository: &impl UserRepository,
+    connection_id: &str,
+    token: &str,
+) -> Result<Option<String>, Error> {
+    let Some(username) = authenticate_session_token(repository, token).await? else {
+        return Ok(None);
+    };
+
+    repository
+        .save_user_record(&create_user_connection(&username, connection_id))
+        .await?;
+
+    handle_user_connected(notifier, repository, &username).await?;
+
+    tracing::info!("USER {username} CONNECTED (CONNECTION ID: {connection_id})");
+
+    Ok(Some(username))
+}
+
+/// Cleans up after a closed connection: its seeks, login, seats and subscriptions
+pub async fn handle_connection_closed(
+    notifier: &impl Notifier,
+    repository: &impl Repository,
+    connection_id: &str,
+) -> Result<(), Error> {
+    // The connection can no longer be matched with anyone
+    delete_seeks_from_connection_id(repository, connection_id).await?;
+
+    let mut user_games = repository
+        .get_user_games_from_connection_id(connection_id)
+        .await?;
+
+    for user_game in user_games.iter_mut() {
+        // The connection was logged in. Forget that now that it's gone.
+        if user_game.sort_key.starts_with("CONNECTION-") {
+            repository
+                .delete_user_record(&user_game.username, &user_game.sort_key)
+                .await?;
+
+            handle_user_disconnected(notifier, repository, &user_game.username).await?;
+            continue;
+        }
+
+        // Get the game ID from the user-game record sort key (e.g., "GAME-1234")
+        let game_id = user_game.sort_key.trim_start_matches("GAME-");
+
+        tracing::info!(
+            "Found user game record (game ID: {game_id}) for connection ID {connection_id}"
+        );
+
+        let username = &user_game.username;
+
+        // Disassociate this connection from the user-game record
+        user_game.connection_id = Some(ConnectionState::Disconnected);
+        repository.save_user_record(user_game).await?;
+
+        // Remove the respective connection ID from the game record.
+        // Notify the other player if they are connected.
+        mark_user_as_disconnected_and_notify_other_player(notifier, repository, game_id, username)
+            .await?;
+
+        tracing::info!("PLAYER {username} DISCONNECTED FROM GAME (ID: {game_id})");
+    }
+
+    let subscriptions = repository
+        .get_subscriptions_from_connection_id(connection_id)
+        .await?;
+
+    for subscription in subscriptions {
+        if subscription.topic == LOBBY_TOPIC {
+            repository
+                .delete_subscription(LOBBY_TOPIC, connection_id)
+                .await?;
+            continue;
+        }
+
+        let game_id = subscription.topic.trim_start_matches("GAME-");
+
+        remove_spectator_and_notify_game(notifier, repository, game_id, connection_id).await?;
+
+        tracing::info!("SPECTATOR {connection_id} DISCONNECTED FROM GAME (ID: {game_id})");
+    }
+
+    Ok(())
+}
@@ -1,17 +1,23 @@
+use crate::helpers::abort::update_awaiting_first_move;
 use crate::helpers::board::game_state_to_fen;
-use crate::helpers::game::{make_move, notify_player_about_game_update};
+use crate::helpers::game::{
+    is_game_over, make_move, notify_player_about_game_update, prepare_game_result, update_game,
+    GameAction, GameResult, GameUpdate,
+};
 use crate::helpers::opening_book::get_opening_book_path;
+use crate::notifier::Notifier;
+use crate::repository::Repository;
+use crate::types::api::ApiMessage;
 use crate::types::board::{File, Position, Rank};
 use crate::types::dynamo_db::GameRecord;
-use crate::types::game::{GameState, PlayerMove, SearchStatistics};
+use crate::types::game::{ClockSnapshot, GameState, PlayerMove, SearchStatistics};
 use crate::types::piece::Color;
 
-use aws_lambda_events::apigw::ApiGatewayWebsocketProxyRequestContext;
 use chess_engine::engine::Engine;
 use chess_engine::types::{Piece, Square};
 use lambda_runtime::Error;
 
-fn get_engine_color(game: &mut GameRecord) -> Color {
+fn get_engine_color(game: &GameRecord) -> Color {
     match (&game.white_username, &game.black_username) {
         (None, Some(_)) => Color::White,
         (Some(_), None) => Color::Black,
@@ -19,73 +25,156 @@ fn get_engine_color(game: &mut GameRecord) -> Color {
     }
 }
 
-fn set_available_moves_for_next_turn(engine: &mut Engine, game: &mut GameRecord) {
-    game.game_state.current_state_mut().moves = engine.position.get_legal_moves();
+/// The engine's reply to a position, worked out before the game is saved with it
+pub struct EngineReply {
+    /// The engine's evaluation of the position it was given
+    evaluation: SearchStatistics,
+    /// `None` if the engine had no move to make
+    engine_move: Option<PlayerMove>,
+    /// The engine's evaluation of the position after its move
+    evaluation_after_move: Option<SearchStatistics>,
+    think_ms: u64,
+    /// Legal moves for the player once the engine has moved
+    available_moves: Vec<String>,
 }
 
-/// The human's move has already been made
-pub async fn use_engine(
-    game: &mut GameRecord,
-    sdk_config: &aws_config::SdkConfig,
-    request_context: &ApiGatewayWebsocketProxyRequestContext,
-    connection_id: &str,
-) -> Result<(), Error> {
+/// Sets the legal moves for whoever's turn it is. Cheap and has no side effects, so it's safe to
+/// call from an `update_game` action.
+pub fn refresh_available_moves(game: &mut GameRecord) {
     // Engine/AI only supports standard 8x8 boards
-    let current_state = game
-        .game_state
-        .history
-        .last()
-        .expect("Game history should not be empty");
-    if !current_state.board.is_standard_board() {
-        return Ok(());
+    if !game.game_state.current_state().board.is_standard_board() {
+        return;
     }
 
     let mut engine = get_engine(game);
+    game.game_state.current_state_mut().moves = engine.position.get_legal_moves();
+}
 
-    if game.engine_difficulty.is_none() || !is_engine_turn(game) {
-        set_available_moves_for_next_turn(&mut engine, game);
-        return Ok(());
-    }
+pub fn is_engine_turn(game: &GameRecord) -> bool {
+    game.engine_difficulty.is_some()
+        && !is_game_over(game)
+        && game.game_state.current_state().board.is_standard_board()
+        && game.game_state.current_state().current_turn == get_engine_color(game)
+}
 
-    notify_player_about_game_update(sdk_config, request_context, connection_id, game, None, true)
-        .await?;
+/// Searches for the engine's move. This takes seconds, so it's never done inside `update_game`,
+/// where it could be repeated.
+pub fn think_of_reply(game: &GameRecord) -> EngineReply {
+    let mut engine = get_engine(game);
 
-    // This evaluates the position after the human's move. The best move is played by the engine.
     let search_result = engine.think::<fn(u16, i32, &mut chess_engine::position::Position)>(None);
 
-    let engine_move_think_ms = search_result.time_ms;
-    let engine_best_move_from = search_result.best_move_from;
-    let engine_best_move_to = search_result.best_move_to;
-    let engine_best_move_promote = search_result.best_move_promote;
+    let think_ms = search_result.time_ms;
+    let best_move_from = search_result.best_move_from;
+    let best_move_to = search_result.best_move_to;
+    let best_move_promote = search_result.best_move_promote;
+    let evaluation = SearchStatistics::from(search_result);
 
-    // Store evaluation on the human's move state (included in the final Lambda response)
-    game.game_state.current_state_mut().engine_result = Some(SearchStatistics::from(search_result));
+    let engine_move = get_engine_move_from_search_result(best_move_from, best_move_to);
 
-    if let Some(engine_move) =
-        get_engine_move_from_search_result(engine_best_move_from, engine_best_move_to)
-    {
+    let evaluation_after_move = engine_move.as_ref().map(|_| {
         make_engine_move_from_search_result(
             &mut engine,
-            engine_best_move_from,
-            engine_best_move_to,
-            engine_best_move_promote,
+            best_move_from,
+            best_move_to,
+            best_move_promote,
         );
-        make_move(&mut game.game_state, &engine_move);
 
         // Evaluate the position after the engine's move
-        let search_result =
-            engine.think::<fn(u16, i32, &mut chess_engine::position::Position)>(None);
+        SearchStatistics::from(
+            engine.think::<fn(u16, i32, &mut chess_engine::position::Position)>(None),
+        )
+    });
+
+    EngineReply {
+        evaluation,
+        engine_move,
+        evaluation_after_move,
+        think_ms,
+        available_moves: engine.position.get_legal_moves(),
+    }
+}
+
+/// Plays the reply into the game that was searched
+pub fn apply_engine_reply(game: &mut GameRecord, reply: &EngineReply) {
+    // The evaluation of the position is stored on the human's move
+    game.game_state.current_state_mut().engine_result = Some(reply.evaluation.clone());
+
+    if let Some(engine_move) = &reply.engine_move {
+        make_move(&mut game.game_state, engine_move);
+        game.game_state.current_state_mut().engine_result = reply.evaluation_after_move.clone();
+    }
+
+    handle_engine_think_time(&mut game.game_state, reply.think_ms);
+
+    game.game_state.current_state_mut().moves = reply.available_moves.clone();
+}
 
-        let search_statistics = SearchStatistics::from(search_result);
+/// For a game that hasn't been saved yet, so no one else can change it in the meantime
+pub fn use_engine_in_new_game(game: &mut GameRecord) {
+    match is_engine_turn(game) {
+        true => apply_engine_reply(game, &think_of_reply(game)),
+        false => refresh_available_moves(game),
+    }
+}
 
-        game.game_state.current_state_mut().engine_result = Some(search_statistics);
+/// If it's the engine's turn in the saved game, lets the player know the engine is thinking,
+/// then saves the engine's move. A resignation or anything else that changes the position while
+/// the engine thinks wins, and the move is thrown away.
+///
+/// Returns the latest game and any messages about its result.
+pub async fn play_engine_reply(
+    notifier: &impl Notifier,
+    repository: &impl Repository,
+    connection_id: &str,
+    game: GameRecord,
+) -> Result<(GameRecord, Vec<ApiMessage>), Error> {
+    if !is_engine_turn(&game) {
+        return Ok((game, Vec::new()));
     }
 
-    handle_engine_think_time(&mut game.game_state, engine_move_think_ms);
+    notify_player_about_game_update(notifier, repository, connection_id, &game, None, true).await?;
+
+    let reply = think_of_reply(&game);
+    let searched_ply_count = game.game_state.ply_count();
+    let searched_fen = game_state_to_fen(game.game_state.current_state());
+
+    let update = update_game(repository, &game.game_id, async |latest| {
+        if !is_engine_turn(latest)
+            || latest.game_state.ply_count() != searched_ply_count
+            || game_state_to_fen(latest.game_state.current_state()) != searched_fen
+        {
+            return Ok(GameAction::Reject(()));
+        }
+
+        apply_engine_reply(latest, &reply);
+        update_awaiting_first_move(latest);
+
+        let GameResult { writes, messages } = prepare_game_result(repository, latest).await?;
 
-    set_available_moves_for_next_turn(&mut engine, game);
+        Ok(GameAction::SaveWith(messages, writes))
+    })
+    .await?;
+
+    match update {
+        GameUpdate::Saved { game, output } => Ok((*game, output)),
+        GameUpdate::Rejected(()) => {
+            tracing::info!(
+                "Game (ID: {}) changed while the engine was thinking. Its move was discarded.",
+                game.game_id
+            );
 
-    Ok(())
+            let latest = repository.get_game(&game.game_id).await?;
+            Ok((latest.unwrap_or(game), Vec::new()))
+        }
+        GameUpdate::NotFound | GameUpdate::Conflict => {
+            tracing::warn!(
+                "Could not save the engine's move in game (ID: {})",
+                game.game_id
+            );
+            Ok((game, Vec::new()))
+        }
+    }
 }
 
 fn make_engine_move_from_search_result(
@@ -138,12 +227,14 @@ pub fn handle_engine_think_time(game_state: &mut GameState, search_duration: u64
                     .saturating_sub(time_to_decrement);
             }
         }
+
+        game_state.current_state_mut().clock = Some(ClockSnapshot::from(&*game_time));
     }
 }
 
 /// Initializes an engine with the current game position and difficulty settings.
 pub fn get_engine(game_record: &GameRecord) -> Engine {
-    let fen = game_state_to_fen(game_record.game_state.history.last().unwrap());
+    let fen = game_state_to_fen(game_record.game_state.current_state());
     get_engine_from_fen(&fen, 3000, game_record.engine_difficulty.map(|d| d.into()))
 }
 
@@ -171,8 +262,3 @@ pub fn get_engine_from_fen(
 
     engine
 }
-
-fn is_engine_turn(game: &mut GameRecord) -> bool {
-    let current_turn = game.game_state.current_state().current_turn;
-    current_turn == get_engine_color(game)
-}
@@ -1,38 +1,133 @@
+use crate::helpers::abort::update_awaiting_first_move;
 use crate::helpers::board::game_state_to_fen;
-use crate::helpers::opening_detection::detect_opening;
-use crate::helpers::user::{get_user_game, save_user_record};
+use crate::helpers::lobby::{notify_lobby_subscribers, update_lobby_listing};
+use crate::helpers::opening_detection::{detect_opening, OpeningInfo};
+use crate::helpers::rating::{get_rating_change_message, prepare_rating_updates};
+use crate::helpers::subscription::game_topic;
+use crate::notifier::Notifier;
+use crate::repository::{GameRepository, GameResultWrites, SubscriptionRepository, UserRepository};
 use crate::types::api::{ApiMessage, ApiResponse};
 use crate::types::board::{Board, BoardSetup, Position};
 use crate::types::dynamo_db::GameRecord;
 use crate::types::game::{
-    ColorPreference, EngineDifficulty, GameEnding, GameState, GameStateAtPointInTime, GameTime,
-    PlayerMove, State,
+    ClockSnapshot, ColorPreference, EngineDifficulty, GameEnding, GameState,
+    GameStateAtPointInTime, GameTime, MatchScore, PlayerMove, PlayerScore, State,
 };
 use crate::types::piece::{Color, Piece, PieceType};
-use crate::utils::api_gateway::post_to_connection;
-use crate::utils::dynamo_db::{get_item, put_item};
+use crate::types::presence::ConnectionState;
 
-use aws_lambda_events::apigw::ApiGatewayWebsocketProxyRequestContext;
-use aws_sdk_dynamodb::types::AttributeValue;
-use aws_sdk_dynamodb::Client;
 use chrono::{TimeZone, Utc};
 use lambda_runtime::Error;
-use std::collections::HashMap;
-
-use super::generic::generate_id;
+use std::convert::Infallible;
+
+use super::generic::{generate_game_id, generate_token, get_timestamp, hash_token};
+
+/// Generated game IDs to try before giving up on finding one that isn't taken
+const MAX_GAME_ID_ATTEMPTS: usize = 5;
+/// Times to re-apply an action to a game that keeps being changed by other requests
+const MAX_GAME_UPDATE_ATTEMPTS: usize = 5;
+const MAX_GAME_ID_LENGTH: usize = 48;
+
+/// What an action applied with `update_game` decided to do with the game
+pub enum GameAction<T, R> {
+    /// Save the changed game
+    Save(T),
+    /// Save the changed game along with its result, all or nothing
+    SaveWith(T, GameResultWrites),
+    /// Leave the game as it was, e.g. because the action isn't allowed
+    Reject(R),
+}
 
-pub async fn save_game(client: &Client, table: &str, game: &GameRecord) -> Result<(), Error> {
-    put_item(client, table, game).await
+pub enum GameUpdate<T, R> {
+    Saved {
+        game: Box<GameRecord>,
+        output: T,
+    },
+    Rejected(R),
+    NotFound,
+    /// Other requests kept changing the game, so the action was never saved
+    Conflict,
 }
 
-pub async fn get_game(
-    client: &Client,
-    table: &str,
+/// Applies the action to the latest version of the game and saves it. If another request saves
+/// the game first, the game is read again and the action is re-applied to it, so the action
+/// shouldn't have side effects that can't be repeated.
+pub async fn update_game<T, R>(
+    repository: &impl GameRepository,
     game_id: &str,
-) -> Result<Option<GameRecord>, Error> {
-    let mut key = HashMap::new();
-    key.insert("game_id".into(), AttributeValue::S(game_id.into()));
-    get_item(client, table, key).await
+    mut action: impl AsyncFnMut(&mut GameRecord) -> Result<GameAction<T, R>, Error>,
+) -> Result<GameUpdate<T, R>, Error> {
+    for attempt in 1..=MAX_GAME_UPDATE_ATTEMPTS {
+        let Some(mut game) = repository.get_latest_game(game_id).await? else {
+            return Ok(GameUpdate::NotFound);
+        };
+
+        let (output, result_writes) = match action(&mut game).await? {
+            GameAction::Reject(rejection) => return Ok(GameUpdate::Rejected(rejection)),
+            GameAction::Save(output) => (output, GameResultWrites::default()),
+            GameAction::SaveWith(output, result_writes) => (output, result_writes),
+        };
+
+        // The saved game is one version ahead of what the action was applied to
+        let expected_version = game.version;
+        game.version += 1;
+
+        if repository
+            .save_game(&game, expected_version, &result_writes)
+            .await?
+        {
+            return Ok(GameUpdate::Saved {
+                game: Box::new(game),
+                output,
+            });
+        }
+
+        tracing::warn!("Game (ID: {game_id}) was changed by another request (attempt {attempt})");
+    }
+
+    Ok(GameUpdate::Conflict)
+}
+
+/// Saves a new game with a generated ID, generating another if that ID is taken
+pub async fn insert_game_with_generated_id(
+    repository: &impl GameRepository,
+    game: &mut GameRecord,
+) -> Result<(), Error> {
+    for _ in 0..MAX_GAME_ID_ATTEMPTS {
+        if repository.insert_game(game).await? {
+            return Ok(());
+        }
+
+        tracing::warn!("Game ID {} is taken. Generating another.", game.game_id);
+        set_game_id(game, generate_game_id());
+    }
+
+    Err(Error::from(format!(
+        "Could not find a free game ID after {MAX_GAME_ID_ATTEMPTS} attempts"
+    )))
+}
+
+pub fn set_game_id(game: &mut GameRecord, game_id: String) {
+    game.game_state.game_id = game_id.clone();
+    game.game_id = game_id;
+}
+
+/// Custom game IDs are chosen by players, so they're kept short and URL-safe
+pub fn validate_game_id(game_id: &str) -> Result<(), String> {
+    if game_id.is_empty() || game_id.len() > MAX_GAME_ID_LENGTH {
+        return Err(format!(
+            "Game ID must be between 1 and {MAX_GAME_ID_LENGTH} characters"
+        ));
+    }
+
+    if !game_id
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err("Game ID may only contain letters, numbers, underscores and hyphens".into());
+    }
+
+    Ok(())
 }
 
 /// Returns a tuple containing:
@@ -47,20 +142,20 @@ pub fn determine_player_color(
     username: &str,
     connection_id: &str,
 ) -> (
+    Option<ConnectionState>,
     Option<String>,
-    Option<String>,
-    Option<String>,
+    Option<ConnectionState>,
     Option<String>,
 ) {
     match color_preference {
         ColorPreference::Black => (
             None,
             None,
-            Some(connection_id.to_string()),
+            Some(ConnectionState::connected(connection_id)),
             Some(username.to_string()),
         ),
         ColorPreference::White => (
-            Some(connection_id.to_string()),
+            Some(ConnectionState::connected(connection_id)),
             Some(username.to_string()),
             None,
             None,
@@ -74,7 +169,7 @@ pub fn determine_player_color(
 
             if random_value == 0 {
                 (
-                    Some(connection_id.to_string()),
+                    Some(ConnectionState::connected(connection_id)),
                     Some(username.to_string()),
                     None,
                     None,
@@ -83,7 +178,7 @@ pub fn determine_player_color(
                 (
                     None,
                     None,
-                    Some(connection_id.to_string()),
+                    Some(ConnectionState::connected(connection_id)),
                     Some(username.to_string()),
                 )
             }
@@ -91,12 +186,95 @@ pub fn determine_player_color(
     }
 }
 
+pub const DEFAULT_INVITE_EXPIRY_MINUTES: u32 = 60;
+pub const MAX_INVITE_EXPIRY_MINUTES: u32 = 7 * 24 * 60;
+
+/// Make the game private, so that its free seat can only be claimed with the returned invite
+/// token until it expires. Only the token's hash is stored on the game.
+pub fn issue_invite_token(game: &mut GameRecord, expires_in_seconds: i64) -> String {
+    let invite_token = generate_token();
+
+    game.invite_token_hash = Some(hash_token(&invite_token));
+    game.invite_expires_at = Some(chrono::Utc::now().timestamp() + expires_in_seconds);
+
+    invite_token
+}
+
+/// Issue a new seat token for a player. Only its hash is stored on the game.
+pub fn issue_seat_token(game: &mut GameRecord, color: Color) -> String {
+    let seat_token = generate_token();
+    let seat_token_hash = Some(hash_token(&seat_token));
+
+    match color {
+        Color::White => game.white_seat_token_hash = seat_token_hash,
+        Color::Black => game.black_seat_token_hash = seat_token_hash,
+    }
+
+    seat_token
+}
+
+/// Record whether the player in the seat is logged in, which lets them rejoin it without its
+/// seat token
+pub fn set_seat_authenticated(game: &mut GameRecord, color: Color, authenticated: bool) {
+    match color {
+        Color::White => game.white_authenticated = authenticated,
+        Color::Black => game.black_authenticated = authenticated,
+    }
+}
+
+pub fn is_seat_authenticated(game: &GameRecord, color: Color) -> bool {
+    match color {
+        Color::White => game.white_authenticated,
+        Color::Black => game.black_authenticated,
+    }
+}
+
+/// Private games can only be looked up with an unexpired invite token or one of their seat tokens.
+/// Anyone can look up every other game.
+pub fn can_view_game(game: &GameRecord, token: Option<&str>) -> bool {
+    let Some(invite_token_hash) = &game.invite_token_hash else {
+        return true;
+    };
+
+    let Some(token_hash) = token.map(hash_token) else {
+        return false;
+    };
+
+    let is_invite_valid = token_hash == *invite_token_hash
+        && game
+            .invite_expires_at
+            .is_none_or(|expires_at| expires_at > chrono::Utc::now().timestamp());
+
+    is_invite_valid
+        || [&game.white_seat_token_hash, &game.black_seat_token_hash]
+            .into_iter()
+            .any(|seat_token_hash| seat_token_hash.as_ref() == Some(&token_hash))
+}
+
+pub fn get_player_color(game: &GameRecord, username: &str) -> Option<Color> {
+    if game.white_username.as_deref() == Some(username) {
+        Some(Color::White)
+    } else if game.black_username.as_deref() == Some(username) {
+        Some(Color::Black)
+    } else {
+        None
+    }
+}
+
 /// We know that the game has at least one player assigned.
+///
+/// Rejoining a seat requires the seat token issued for it, unless the seat was taken by a
+/// logged-in user and the player is logged in as them. A guest's seat always needs its token,
+/// since anyone could register the guest's username afterwards. Returns a new seat token if one
+/// was issued.
 pub fn assign_player_to_existing_or_remaining_slot(
     game: &mut GameRecord,
     username: &str,
     connection_id: &str,
-) -> Result<(), Error> {
+    seat_token: Option<&str>,
+    invite_token: Option<&str>,
+    authenticated: bool,
+) -> Result<Option<String>, Error> {
     if let Some(white_username) = &game.white_username {
         if let Some(black_username) = &game.black_username {
             if black_username != username && white_username != username {
@@ -112,7 +290,10 @@ pub fn assign_player_to_existing_or_remaining_slot(
         ("white", &game.white_connection_id),
         ("black", &game.black_connection_id),
     ] {
-        if let Some(existing_connection_id) = connection_id_option {
+        if let Some(existing_connection_id) = connection_id_option
+            .as_ref()
+            .and_then(ConnectionState::connection_id)
+        {
             if connection_id == existing_connection_id {
                 return Err(Error::from(format!(
                     "You are already connected to this game as {color}",
@@ -121,54 +302,156 @@ pub fn assign_player_to_existing_or_remaining_slot(
         }
     }
 
-    match &game.white_username {
-        Some(white_username) if white_username == username => {
-            // NOTE: Commented to handle bug where disconnect function doesn't run
+    let Some(color) = get_player_color(game, username) else {
+        if game.engine_difficulty.is_some() {
+            return Err(Error::from(format!(
+                "Game (ID: `{}`) is against the engine",
+                game.game_id
+            )));
+        }
+
+        // Guests have no rating to update
+        if game.rated && !authenticated {
+            return Err(Error::from("You must be logged in to join a rated game"));
+        }
+
+        if let Some(invite_token_hash) = &game.invite_token_hash {
+            if invite_token.is_none_or(|token| hash_token(token) != *invite_token_hash) {
+                return Err(Error::from(format!(
+                    "Game (ID: `{}`) is private. You need an invite to join it.",
+                    game.game_id
+                )));
+            }
+
+            if game
+                .invite_expires_at
+                .is_some_and(|expires_at| expires_at <= chrono::Utc::now().timestamp())
+            {
+                return Err(Error::from("This invite has expired"));
+            }
+        }
+
+        let color = match game.white_username {
+            Some(_) => Color::Black,
+            None => Color::White,
+        };
+
+        match color {
+            Color::White => {
+                game.white_username = Some(username.to_string());
+                game.white_connection_id = Some(ConnectionState::connected(connection_id));
+            }
+            Color::Black => {
+                game.black_username = Some(username.to_string());
+                game.black_connection_id = Some(ConnectionState::connected(connection_id));
+            }
+        }
+
+        set_seat_authenticated(game, color, authenticated);
+
+        return Ok(Some(issue_seat_token(game, color)));
+    };
+
+    let (seat_connection_id, seat_token_hash) = match color {
+        Color::White => (&game.white_connection_id, &game.white_seat_token_hash),
+        Color::Black => (&game.black_connection_id, &game.black_seat_token_hash),
+    };
+
+    let has_valid_seat_token = matches!(
+        (seat_token_hash, seat_token),
+        (Some(seat_token_hash), Some(seat_token)) if hash_token(seat_token) == *seat_token_hash
+    );
 
-            // if let Some(white_connection_id) = &game.white_connection_id {
-            //     if white_connection_id != "<disconnected>" {
-            //         return Err(Error::from(format!(
-            //             "{username} has already joined this game (ID: {}) as white",
-            //             game.game_id
-            //         )));
-            //     }
-            // }
+    let rejoins_as_logged_in_user = authenticated && is_seat_authenticated(game, color);
 
-            game.white_connection_id = Some(connection_id.to_string());
+    if !has_valid_seat_token && !rejoins_as_logged_in_user {
+        if seat_token_hash.is_some() {
+            return Err(Error::from(format!(
+                "A valid seat token is required to rejoin this game (ID: {}) as {username}",
+                game.game_id
+            )));
         }
-        Some(_) => {
-            // NOTE: Commented to handle bug where disconnect function doesn't run
-
-            // if let Some(black_username) = &game.black_username {
-            //     if let Some(black_connection_id) = &game.black_connection_id {
-            //         if black_connection_id != "<disconnected>" {
-            //             return Err(Error::from(format!(
-            //                 "{} has already joined this game (ID: {}) as black",
-            //                 black_username, game.game_id
-            //             )));
-            //         }
-            //     }
-            // }
-
-            game.black_username = Some(username.to_string());
-            game.black_connection_id = Some(connection_id.to_string());
+
+        // Games created before seat tokens can only be rejoined once the seat is free
+        if seat_connection_id
+            .as_ref()
+            .is_some_and(ConnectionState::is_connected)
+        {
+            return Err(Error::from(format!(
+                "{username} has already joined this game (ID: {}) as {color}",
+                game.game_id
+            )));
         }
-        None => {
-            let black_username = game
-                .black_username
-                .as_deref()
-                .expect("Black username should be set if white is not");
-
-            if black_username == username {
-                game.black_connection_id = Some(connection_id.to_string());
+    }
+
+    match color {
+        Color::White => game.white_connection_id = Some(ConnectionState::connected(connection_id)),
+        Color::Black => game.black_connection_id = Some(ConnectionState::connected(connection_id)),
+    }
+
+    if has_valid_seat_token {
+        Ok(None)
+    } else {
+        Ok(Some(issue_seat_token(game, color)))
+    }
+}
+
+/// Points earned by the player of the given color in a finished game: 1 for a win, 0.5 for a draw
+pub fn get_points_earned(game: &GameRecord, color: Color) -> f32 {
+    match game.game_state.current_state().state {
+        State::Finished(GameEnding::Checkmate(losing_color))
+        | State::Finished(GameEnding::OutOfTime(losing_color))
+        | State::Finished(GameEnding::Resignation(losing_color))
+        | State::Finished(GameEnding::Forfeit(losing_color)) => {
+            if losing_color == color {
+                0.0
             } else {
-                game.white_username = Some(username.to_string());
-                game.white_connection_id = Some(connection_id.to_string());
+                1.0
             }
         }
+        State::Finished(_) => 0.5,
+        _ => 0.0,
     }
+}
 
-    Ok(())
+/// Returns the match score for a rematch of the finished game. An undecided match carries on,
+/// otherwise a new match is started if `best_of` is provided.
+pub fn get_next_match_score(old_game: &GameRecord, best_of: Option<u8>) -> Option<MatchScore> {
+    if let Some(match_score) = &old_game.match_score {
+        // Aborted games don't count towards the match
+        if old_game.game_state.current_state().state == State::Aborted {
+            return Some(match_score.clone());
+        }
+
+        let mut next_match_score = match_score.clone();
+        next_match_score.game_ids.push(old_game.game_id.clone());
+
+        for score in next_match_score.scores.iter_mut() {
+            let color = if score.username == old_game.white_username {
+                Color::White
+            } else {
+                Color::Black
+            };
+
+            score.points += get_points_earned(old_game, color);
+        }
+
+        if !next_match_score.is_decided() {
+            return Some(next_match_score);
+        }
+    }
+
+    best_of.map(|best_of| MatchScore {
+        best_of,
+        game_ids: Vec::new(),
+        scores: [&old_game.white_username, &old_game.black_username]
+            .into_iter()
+            .map(|username| PlayerScore {
+                username: username.clone(),
+                points: 0.0,
+            })
+            .collect(),
+    })
 }
 
 pub fn create_game(
@@ -180,7 +463,7 @@ pub fn create_game(
     seconds_per_player: Option<usize>,
     connection_id: &str,
 ) -> GameRecord {
-    let game_id = game_id.map_or_else(generate_id, |id| id.to_string());
+    let game_id = game_id.map_or_else(generate_game_id, |id| id.to_string());
 
     let effective_board_setup = board_setup.unwrap_or(BoardSetup::Standard);
     let effective_color_preference = color_preference.unwrap_or(ColorPreference::Random);
@@ -192,6 +475,7 @@ pub fn create_game(
 
     GameRecord {
         game_id,
+        version: 0,
         white_connection_id,
         white_username,
         black_connection_id,
@@ -201,80 +485,211 @@ pub fn create_game(
         seconds_per_player,
         engine_difficulty,
         game_state,
-        created: chrono::Utc::now().to_rfc3339(),
+        created: get_timestamp(),
         draw_offered_by: None,
+        takeback_requested_by: None,
+        chat_muted_by: Vec::new(),
+        white_premove: None,
+        black_premove: None,
+        takebacks_allowed: true,
+        rated: false,
+        spectator_count: 0,
+        white_seat_token_hash: None,
+        black_seat_token_hash: None,
+        white_authenticated: false,
+        black_authenticated: false,
+        invite_token_hash: None,
+        invite_expires_at: None,
+        lobby: None,
+        match_score: None,
+        tournament: None,
+        awaiting_first_move: None,
+        awaiting_first_move_since: None,
+        awaiting_join: None,
+        awaiting_join_since: None,
     }
 }
 
 pub async fn mark_user_as_disconnected_and_notify_other_player(
-    sdk_config: &aws_config::SdkConfig,
-    request_context: &ApiGatewayWebsocketProxyRequestContext,
-    dynamo_db_client: &Client,
-    game_table: &str,
-    game: &mut GameRecord,
+    notifier: &impl Notifier,
+    repository: &(impl GameRepository + SubscriptionRepository),
+    game_id: &str,
     username: &str,
 ) -> Result<(), Error> {
-    match game.white_username == Some(username.to_string()) {
-        true => {
-            game.white_connection_id = Some("<disconnected>".to_string());
-            save_game(dynamo_db_client, game_table, game).await?;
-
-            if let Some(black_connection_id) = &game.black_connection_id {
-                if black_connection_id != "<disconnected>"
-                    && (post_to_connection(
-                        sdk_config,
-                        request_context,
-                        black_connection_id,
-                        &ApiResponse {
-                            status_code: 200,
-                            connection_id: Some(black_connection_id.clone()),
-                            messages: vec![
-                                format!("{username} has disconnected from the game").into()
-                            ],
-                            data: Some(&game),
-                            replaces_game_id: None,
-                        },
-                    )
-                    .await?)
-                        .is_some()
-                {
-                    tracing::info!(
-                        "Notified black player of disconnection for game (ID: {})",
-                        game.game_id
-                    );
-                }
-            }
+    let update = update_game(repository, game_id, async |game| {
+        let color = match game.white_username.as_deref() == Some(username) {
+            true => Color::White,
+            false => Color::Black,
+        };
+
+        match color {
+            Color::White => game.white_connection_id = Some(ConnectionState::Disconnected),
+            Color::Black => game.black_connection_id = Some(ConnectionState::Disconnected),
         }
-        false => {
-            game.black_connection_id = Some("<disconnected>".to_string());
-            save_game(dynamo_db_client, game_table, game).await?;
-
-            if let Some(white_connection_id) = &game.white_connection_id {
-                if white_connection_id != "<disconnected>"
-                    && (post_to_connection(
-                        sdk_config,
-                        request_context,
-                        white_connection_id,
-                        &ApiResponse {
-                            status_code: 200,
-                            connection_id: Some(white_connection_id.clone()),
-                            messages: vec![
-                                format!("{username} has disconnected from the game").into()
-                            ],
-                            data: Some(&game),
-                            replaces_game_id: None,
-                        },
-                    )
-                    .await?)
-                        .is_some()
-                {
-                    tracing::info!(
-                        "Notified white player of disconnection for game (ID: {})",
-                        game.game_id
-                    );
-                }
-            }
+
+        let lobby_listing_changed = update_lobby_listing(game);
+
+        Ok(GameAction::<_, Infallible>::Save((
+            color,
+            lobby_listing_changed,
+        )))
+    })
+    .await?;
+
+    let (game, (color, lobby_listing_changed)) = match update {
+        GameUpdate::Saved { game, output } => (*game, output),
+        GameUpdate::Rejected(never) => match never {},
+        GameUpdate::NotFound => {
+            tracing::warn!("Game with ID {game_id} not found while disconnecting {username}");
+            return Ok(());
         }
+        GameUpdate::Conflict => {
+            tracing::warn!("Could not mark {username} as disconnected from game (ID: {game_id})");
+            return Ok(());
+        }
+    };
+
+    let opponent_connection_id = match color {
+        Color::White => game.black_connection_id.as_ref(),
+        Color::Black => game.white_connection_id.as_ref(),
+    }
+    .and_then(ConnectionState::connection_id);
+
+    if let Some(opponent_connection_id) = opponent_connection_id {
+        if notifier
+            .send(
+                opponent_connection_id,
+                &ApiResponse {
+                    status_code: 200,
+                    connection_id: Some(opponent_connection_id.to_string()),
+                    messages: vec![format!("{username} has disconnected from the game").into()],
+                    data: Some(&game),
+                    replaces_game_id: None,
+                    seat_token: None,
+                    invite_token: None,
+                },
+            )
+            .await?
+        {
+            tracing::info!(
+                "Notified {} player of disconnection for game (ID: {})",
+                color.opponent_color(),
+                game.game_id
+            );
+        }
+    }
+
+    notify_spectators_about_game_update(
+        notifier,
+        repository,
+        &game,
+        Some(vec![
+            format!("{username} has disconnected from the game").into()
+        ]),
+    )
+    .await?;
+
+    // The game's creator is no longer waiting for an opponent
+    if lobby_listing_changed {
+        notify_lobby_subscribers(notifier, repository, &game).await?;
+    }
+
+    Ok(())
+}
+
+/// Remove a spectator's subscription to a game and let everyone else know the spectator count changed
+pub async fn remove_spectator_and_notify_game(
+    notifier: &impl Notifier,
+    repository: &(impl GameRepository + SubscriptionRepository),
+    game_id: &str,
+    connection_id: &str,
+) -> Result<(), Error> {
+    repository
+        .delete_subscription(&game_topic(game_id), connection_id)
+        .await?;
+
+    let Some(game) = decrement_spectator_count(repository, game_id, 1).await? else {
+        return Ok(());
+    };
+
+    notify_player_about_game_update(notifier, repository, connection_id, &game, None, false).await
+}
+
+/// Lowers the game's spectator count after subscriptions to it were removed. Returns the saved
+/// game, or `None` if it couldn't be updated.
+pub async fn decrement_spectator_count(
+    repository: &impl GameRepository,
+    game_id: &str,
+    removed_subscriptions: usize,
+) -> Result<Option<GameRecord>, Error> {
+    let update = update_game(repository, game_id, async |game| {
+        game.spectator_count = game.spectator_count.saturating_sub(removed_subscriptions);
+        Ok(GameAction::<_, Infallible>::Save(()))
+    })
+    .await?;
+
+    match update {
+        GameUpdate::Saved { game, .. } => Ok(Some(*game)),
+        GameUpdate::Rejected(never) => match never {},
+        GameUpdate::NotFound => {
+            tracing::warn!("Game with ID {game_id} not found for spectator subscription");
+            Ok(None)
+        }
+        GameUpdate::Conflict => {
+            tracing::warn!("Could not update the spectator count of game (ID: {game_id})");
+            Ok(None)
+        }
+    }
+}
+
+/// Notify everyone watching the game. Spectators never receive a direct response for a game
+/// update, so they are sent every update regardless of which player caused it.
+pub async fn notify_spectators_about_game_update(
+    notifier: &impl Notifier,
+    repository: &(impl GameRepository + SubscriptionRepository),
+    game: &GameRecord,
+    messages: Option<Vec<ApiMessage>>,
+) -> Result<(), Error> {
+    let unsubscribed_count = notifier
+        .broadcast_to_game(repository, game, &messages.unwrap_or_default(), |_| false)
+        .await?;
+
+    if unsubscribed_count > 0 {
+        decrement_spectator_count(repository, &game.game_id, unsubscribed_count).await?;
+    }
+
+    Ok(())
+}
+
+/// Notify both players, if they are connected, and everyone watching. For changes that neither
+/// player made, e.g. by the sweeper.
+pub async fn notify_everyone_about_game_update(
+    notifier: &impl Notifier,
+    repository: &(impl GameRepository + SubscriptionRepository),
+    game: &GameRecord,
+    messages: Vec<ApiMessage>,
+) -> Result<(), Error> {
+    notify_spectators_about_game_update(notifier, repository, game, Some(messages.clone())).await?;
+
+    for connection_id in [&game.white_connection_id, &game.black_connection_id]
+        .into_iter()
+        .flatten()
+        .filter_map(ConnectionState::connection_id)
+    {
+        notifier
+            .send(
+                connection_id,
+                &ApiResponse {
+                    status_code: 200,
+                    connection_id: Some(connection_id.to_string()),
+                    messages: messages.clone(),
+                    data: Some(game),
+                    replaces_game_id: None,
+                    seat_token: None,
+                    invite_token: None,
+                },
+            )
+            .await?;
     }
 
     Ok(())
@@ -284,72 +699,44 @@ pub async fn mark_user_as_disconnected_and_notify_other_player(
 ///
 /// Originally used *only* to notify a human opponent (single WebSocket HTTP response would go to current player).
 /// With the engine, we now need to notify the current player before the Lambda returns (i.e. between their move and the engine move).
+/// Spectators are sent every update, including the intermediate one before an engine move.
 pub async fn notify_player_about_game_update(
-    sdk_config: &aws_config::SdkConfig,
-    request_context: &ApiGatewayWebsocketProxyRequestContext,
+    notifier: &impl Notifier,
+    repository: &(impl GameRepository + SubscriptionRepository),
     current_user_connection_id: &str,
     game: &GameRecord,
     messages: Option<Vec<ApiMessage>>,
     current_player: bool, // Notify current player? Otherwise notify opponent.
 ) -> Result<(), Error> {
-    if !current_player && game.engine_difficulty.is_some() {
-        return Ok(());
-    }
+    // The engine has no connection to notify
+    let notify_opponent = game.engine_difficulty.is_none();
 
     let player_check = |s: &str| match current_player {
         true => s == current_user_connection_id,
-        false => s != current_user_connection_id,
+        false => notify_opponent && s != current_user_connection_id,
     };
 
-    if let Some(white_connection_id) = &game.white_connection_id {
-        if player_check(white_connection_id)
-            && white_connection_id != "<disconnected>"
-            && (post_to_connection(
-                sdk_config,
-                request_context,
-                white_connection_id,
-                &ApiResponse {
-                    status_code: 200,
-                    connection_id: Some(white_connection_id.clone()),
-                    messages: messages.clone().unwrap_or_default(),
-                    data: Some(game),
-                    replaces_game_id: None,
-                },
-            )
-            .await?)
-                .is_some()
-        {
-            tracing::info!("Sent game (ID: {}) update to white player", game.game_id);
-        }
-    }
-
-    if let Some(black_connection_id) = &game.black_connection_id {
-        if player_check(black_connection_id)
-            && black_connection_id != "<disconnected>"
-            && (post_to_connection(
-                sdk_config,
-                request_context,
-                black_connection_id,
-                &ApiResponse {
-                    status_code: 200,
-                    connection_id: Some(black_connection_id.clone()),
-                    messages: messages.unwrap_or_default(),
-                    data: Some(game),
-                    replaces_game_id: None,
-                },
-            )
-            .await?)
-                .is_some()
-        {
-            tracing::info!("Sent game (ID: {}) update to black player", game.game_id);
-        }
+    let unsubscribed_count = notifier
+        .broadcast_to_game(
+            repository,
+            game,
+            &messages.unwrap_or_default(),
+            player_check,
+        )
+        .await?;
+
+    if unsubscribed_count > 0 {
+        decrement_spectator_count(repository, &game.game_id, unsubscribed_count).await?;
     }
 
     Ok(())
 }
 
 pub fn is_game_over(game: &GameRecord) -> bool {
-    matches!(game.game_state.current_state().state, State::Finished(_))
+    matches!(
+        game.game_state.current_state().state,
+        State::Finished(_) | State::Aborted
+    )
 }
 
 fn are_both_players_present(game: &GameRecord) -> bool {
@@ -357,10 +744,9 @@ fn are_both_players_present(game: &GameRecord) -> bool {
         return true;
     }
 
-    match (&game.white_connection_id, &game.black_connection_id) {
-        (Some(white), Some(black)) => white != "<disconnected>" && black != "<disconnected>",
-        _ => false,
-    }
+    [&game.white_connection_id, &game.black_connection_id]
+        .into_iter()
+        .all(|seat| seat.as_ref().is_some_and(ConnectionState::is_connected))
 }
 
 /// Confirm it is this player's turn
@@ -395,7 +781,7 @@ pub fn get_player_details_from_connection_id(
     connection_id: &str,
 ) -> Option<PlayerDetails> {
     if let Some(white_connection_id) = &game.white_connection_id {
-        if white_connection_id == connection_id {
+        if white_connection_id.connection_id() == Some(connection_id) {
             return Some(PlayerDetails {
                 color: Color::White,
                 username: game
@@ -408,7 +794,7 @@ pub fn get_player_details_from_connection_id(
     }
 
     if let Some(black_connection_id) = &game.black_connection_id {
-        if black_connection_id == connection_id {
+        if black_connection_id.connection_id() == Some(connection_id) {
             return Some(PlayerDetails {
                 color: Color::Black,
                 username: game
@@ -475,30 +861,52 @@ pub fn validate_move(
     Ok(())
 }
 
-/// Update the game time and ensure the game is started if both players have just joined
-pub fn check_if_both_players_just_joined(game_record: &mut GameRecord) {
-    if game_record.engine_difficulty.is_some()
-        || game_record
-            .white_connection_id
-            .as_deref()
-            .unwrap_or("<disconnected>")
-            != "<disconnected>"
-            && game_record
-                .black_connection_id
-                .as_deref()
-                .unwrap_or("<disconnected>")
-                != "<disconnected>"
-    {
-        if let Some(game_time) = &mut game_record.game_state.game_time {
-            game_time.both_players_last_connected_at = Some(chrono::Utc::now().to_rfc3339());
-        }
+/// Play the player's premove, if they queued one, now that it's their turn. The premove is
+/// discarded if it isn't legal in the new position. Returns `None` if there was no premove.
+pub fn play_premove(game: &mut GameRecord, color: Color) -> Option<Result<(), &'static str>> {
+    let premove = match color {
+        Color::White => game.white_premove.take(),
+        Color::Black => game.black_premove.take(),
+    }?;
 
-        let current_state = game_record.game_state.current_state_mut();
+    if is_game_over(game) {
+        return Some(Err("Game is already over"));
+    }
 
-        if current_state.state == State::NotStarted {
-            current_state.state = State::InProgress;
-        }
+    if let Err(e) = validate_move(&game.game_state.current_state().board, &premove, &color) {
+        return Some(Err(e));
     }
+
+    make_move(&mut game.game_state, &premove);
+
+    Some(Ok(()))
+}
+
+/// Update the game time and ensure the game is started if both players have just joined.
+/// Returns `true` if the game has just started.
+pub fn check_if_both_players_just_joined(game_record: &mut GameRecord) -> bool {
+    if !are_both_players_present(game_record) {
+        return false;
+    }
+
+    if let Some(game_time) = &mut game_record.game_state.game_time {
+        game_time.both_players_last_connected_at = Some(get_timestamp());
+    }
+
+    let current_state = game_record.game_state.current_state_mut();
+    let just_started = current_state.state == State::NotStarted;
+
+    if just_started {
+        current_state.state = State::InProgress;
+
+        // Both players made it to the tournament game in time
+        game_record.awaiting_join = None;
+        game_record.awaiting_join_since = None;
+    }
+
+    update_awaiting_first_move(game_record);
+
+    just_started
 }
 
 /// Update the game time remaining for both players after a move is made
@@ -540,7 +948,7 @@ fn update_game_time(game_time: &mut GameTime, game_state: &mut GameStateAtPointI
         return;
     }
 
-    game_time.last_move_at = Some(chrono::Utc::now().to_rfc3339());
+    game_time.last_move_at = Some(get_timestamp());
 }
 
 /// Called after a move is made. Checks if the opponent's king is in check or checkmate.
@@ -606,6 +1014,24 @@ fn player_move_to_uci(board: &Board, player_move: &PlayerMove) -> String {
     uci
 }
 
+/// Moves the piece, records any capture and checks for check and checkmate. Toggles the turn.
+pub fn apply_move_to_position(position: &mut GameStateAtPointInTime, player_move: &PlayerMove) {
+    if let Some(captured_piece) = position.board.apply_move(player_move, false) {
+        match position.current_turn {
+            Color::White => {
+                position.captured_pieces.white.push(captured_piece);
+                position.captured_pieces.white_points += captured_piece.get_point_value();
+            }
+            Color::Black => {
+                position.captured_pieces.black.push(captured_piece);
+                position.captured_pieces.black_points += captured_piece.get_point_value();
+            }
+        }
+    }
+
+    check_for_mates(position);
+}
+
 /// Make a move and update game state. Assumes that the move has been validated.
 pub fn make_move(game_state: &mut GameState, player_move: &PlayerMove) {
     let mut next_state = game_state.current_state().clone();
@@ -623,24 +1049,11 @@ pub fn make_move(game_state: &mut GameState, player_move: &PlayerMove) {
         update_game_time(game_time, &mut next_state);
     }
 
+    next_state.clock = game_state.game_time.as_ref().map(ClockSnapshot::from);
+
     match next_state.state {
         State::Finished(GameEnding::OutOfTime(_)) => {}
-        _ => {
-            if let Some(captured_piece) = next_state.board.apply_move(player_move, false) {
-                match next_state.current_turn {
-                    Color::White => {
-                        next_state.captured_pieces.white.push(captured_piece);
-                        next_state.captured_pieces.white_points += captured_piece.get_point_value();
-                    }
-                    Color::Black => {
-                        next_state.captured_pieces.black.push(captured_piece);
-                        next_state.captured_pieces.black_points += captured_piece.get_point_value();
-                    }
-                }
-            }
-
-            check_for_mates(&mut next_state); // Toggles turn
-        }
+        _ => apply_move_to_position(&mut next_state, player_move),
     };
 
     // Record SAN move (after check_for_mates so we know about check/checkmate)
@@ -651,85 +1064,108 @@ pub fn make_move(game_state: &mut GameState, player_move: &PlayerMove) {
 
     // Detect opening name and game phase (only for standard 8x8 boards)
     if board_before.is_standard_board() {
-        let total_pieces = next_state.board.get_all_pieces(None).len();
-        let fen = game_state_to_fen(&next_state);
-        // Extracts the Extended Position Description (EPD) from a FEN string.
-        // EPD is a subset of FEN that contains only the first 4 fields:
-        // 1. Piece placement
-        // 2. Active color
-        // 3. Castling availability
-        // 4. En passant target square
-        // Omitting the 5th and 6th fields (halfmove clock and fullmove number).
-        let epd = fen.split_whitespace().take(4).collect::<Vec<_>>().join(" ");
-        game_state.opening = Some(detect_opening(
-            &game_state.move_list,
-            Some(&epd),
-            total_pieces,
-        ));
+        game_state.opening = Some(get_opening(&game_state.move_list, &next_state));
     }
 
-    game_state.history.push(next_state);
+    game_state.push_position(next_state);
 }
 
-/// Update the user-game records for both players if the game has finished
-pub async fn handle_if_game_is_finished(
-    dynamo_db_client: &Client,
-    user_table: &str,
-    username: &str,
-    opponent_username: Option<&str>,
-    game_state: &GameState,
-) -> Result<(), Error> {
-    match game_state.current_state().state {
-        State::Finished(GameEnding::Checkmate(losing_color))
-        | State::Finished(GameEnding::OutOfTime(losing_color))
-        | State::Finished(GameEnding::Resignation(losing_color)) => {
-            let winner = Some(losing_color.opponent_color().to_string());
+fn get_opening(move_list: &[String], state: &GameStateAtPointInTime) -> OpeningInfo {
+    let total_pieces = state.board.get_all_pieces(None).len();
+    let fen = game_state_to_fen(state);
+    // Extracts the Extended Position Description (EPD) from a FEN string.
+    // EPD is a subset of FEN that contains only the first 4 fields:
+    // 1. Piece placement
+    // 2. Active color
+    // 3. Castling availability
+    // 4. En passant target square
+    // Omitting the 5th and 6th fields (halfmove clock and fullmove number).
+    let epd = fen.split_whitespace().take(4).collect::<Vec<_>>().join(" ");
+    detect_opening(move_list, Some(&epd), total_pieces)
+}
 
-            let usernames = match opponent_username {
-                Some(opponent) => vec![username, opponent],
-                None => vec![username],
-            };
+/// Number of half-moves to undo so that the player is back to making their last move.
+/// Returns `None` if the player hasn't made a move yet.
+pub fn get_takeback_ply_count(game_state: &GameState, color: Color) -> Option<usize> {
+    // If the opponent has replied, their reply is undone too
+    let ply_count = if game_state.current_state().current_turn == color {
+        2
+    } else {
+        1
+    };
 
-            for username in usernames {
-                let mut user_game =
-                    get_user_game(dynamo_db_client, user_table, username, &game_state.game_id)
-                        .await?
-                        .unwrap_or_else(|| {
-                            panic!(
-                                "User game should exist for user {username} and game ID {}",
-                                game_state.game_id
-                            )
-                        });
-
-                user_game.winner = winner.clone();
-                save_user_record(dynamo_db_client, user_table, &user_game).await?;
-            }
-        }
-        State::Finished(_) => {
-            let usernames = match opponent_username {
-                Some(opponent) => vec![username, opponent],
-                None => vec![username],
-            };
+    (game_state.ply_count() >= ply_count).then_some(ply_count)
+}
 
-            for username in usernames {
-                let mut user_game =
-                    get_user_game(dynamo_db_client, user_table, username, &game_state.game_id)
-                        .await?
-                        .unwrap_or_else(|| {
-                            panic!(
-                                "User game should exist for user {username} and game ID {}",
-                                game_state.game_id
-                            )
-                        });
-
-                user_game.winner = Some("draw".to_string());
-                save_user_record(dynamo_db_client, user_table, &user_game).await?;
-            }
-        }
-        _ => {}
+/// Undo the last half-moves, restoring the clocks and the detected opening
+pub fn take_back_moves(game_state: &mut GameState, ply_count: usize) {
+    game_state.truncate(game_state.ply_count().saturating_sub(ply_count));
+
+    let clock = game_state.current_state().clock;
+
+    // Games from before clock snapshots keep their current clocks
+    if let (Some(game_time), Some(clock)) = (&mut game_state.game_time, clock) {
+        game_time.white_seconds_left = clock.white_seconds_left;
+        game_time.black_seconds_left = clock.black_seconds_left;
+        game_time.last_move_at = Some(get_timestamp());
     }
 
-    Ok(())
+    let current_state = game_state.current_state();
+
+    game_state.opening =
+        if game_state.move_list.is_empty() || !current_state.board.is_standard_board() {
+            None
+        } else {
+            Some(get_opening(&game_state.move_list, current_state))
+        };
+}
+
+/// The writes that record a finished game's result for its players, and messages about how
+/// their ratings changed
+#[derive(Default)]
+pub struct GameResult {
+    pub writes: GameResultWrites,
+    pub messages: Vec<ApiMessage>,
+}
+
+/// Prepares the players' result records, stats and rating changes for a game that has just
+/// finished, to be saved in the same transaction as the game. Empty if the game isn't finished.
+pub async fn prepare_game_result(
+    repository: &impl UserRepository,
+    game: &GameRecord,
+) -> Result<GameResult, Error> {
+    let winner = match game.game_state.current_state().state {
+        State::Finished(GameEnding::Checkmate(losing_color))
+        | State::Finished(GameEnding::OutOfTime(losing_color))
+        | State::Finished(GameEnding::Resignation(losing_color))
+        | State::Finished(GameEnding::Forfeit(losing_color)) => {
+            losing_color.opponent_color().to_string()
+        }
+        State::Finished(_) => "draw".to_string(),
+        _ => return Ok(GameResult::default()),
+    };
+
+    let players = [
+        (game.white_username.as_deref(), Color::White),
+        (game.black_username.as_deref(), Color::Black),
+    ]
+    .into_iter()
+    .filter_map(|(username, color)| Some((username?.to_string(), color)))
+    .collect();
+
+    let (rating_updates, rating_changes) = prepare_rating_updates(repository, game).await?;
+
+    Ok(GameResult {
+        writes: GameResultWrites {
+            winner,
+            players,
+            rating_updates,
+        },
+        messages: rating_changes
+            .iter()
+            .map(get_rating_change_message)
+            .collect(),
+    })
 }
 
 #[cfg(test)]
@@ -737,6 +1173,28 @@ mod tests {
     use super::*;
     use crate::types::board::{BoardSetup, File, Rank};
 
+    #[test]
+    fn test_generated_game_ids_are_valid_custom_ids() {
+        for _ in 0..100 {
+            let game_id = generate_game_id();
+            assert_eq!(game_id.split('-').count(), 3, "{game_id}");
+            assert!(validate_game_id(&game_id).is_ok(), "{game_id}");
+        }
+
+        assert!(validate_game_id("").is_err());
+        assert!(validate_game_id("my game").is_err());
+        assert!(validate_game_id(&"a".repeat(MAX_GAME_ID_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn test_set_game_id_updates_game_state() {
+        let mut game = create_game(None, "alice", None, None, None, None, "conn-1");
+        set_game_id(&mut game, "brave-rook-0001".to_string());
+
+        assert_eq!(game.game_id, "brave-rook-0001");
+        assert_eq!(game.game_state.game_id, "brave-rook-0001");
+    }
+
     #[test]
     fn test_position_to_algebraic() {
         assert_eq!(
@@ -810,4 +1268,276 @@ mod tests {
         let game_state = GameState::new("test".into(), &BoardSetup::Standard, None);
         assert!(game_state.move_list.is_empty());
     }
+
+    #[test]
+    fn test_rejoining_seat_requires_seat_token() {
+        let mut game = create_game(
+            None,
+            "alice",
+            None,
+            Some(ColorPreference::White),
+            None,
+            None,
+            "conn-alice",
+        );
+        let alice_token = issue_seat_token(&mut game, Color::White);
+
+        let bob_token = assign_player_to_existing_or_remaining_slot(
+            &mut game, "bob", "conn-bob", None, None, true,
+        )
+        .unwrap();
+        assert!(bob_token.is_some());
+        assert!(game.black_seat_token_hash.is_some());
+        assert!(game.black_authenticated && !game.white_authenticated);
+
+        // A guest using the same name without the token is refused
+        assert!(assign_player_to_existing_or_remaining_slot(
+            &mut game, "alice", "conn-2", None, None, false
+        )
+        .is_err());
+        assert!(assign_player_to_existing_or_remaining_slot(
+            &mut game,
+            "alice",
+            "conn-2",
+            Some("wrong"),
+            None,
+            false
+        )
+        .is_err());
+
+        // The original player can reclaim the seat with their token
+        let reissued = assign_player_to_existing_or_remaining_slot(
+            &mut game,
+            "alice",
+            "conn-3",
+            Some(&alice_token),
+            None,
+            false,
+        )
+        .unwrap();
+        assert!(reissued.is_none());
+        assert_eq!(
+            game.white_connection_id,
+            Some(ConnectionState::connected("conn-3"))
+        );
+
+        // Someone who registered a guest's name afterwards still needs the guest's token
+        assert!(assign_player_to_existing_or_remaining_slot(
+            &mut game, "alice", "conn-2", None, None, true
+        )
+        .is_err());
+
+        // Players who were logged in when they took the seat don't need a token, but receive a
+        // new one
+        let reissued = assign_player_to_existing_or_remaining_slot(
+            &mut game, "bob", "conn-4", None, None, true,
+        )
+        .unwrap();
+        assert!(
+            reissued.is_some_and(|token| Some(hash_token(&token)) == game.black_seat_token_hash)
+        );
+    }
+
+    #[test]
+    fn test_joining_private_game_requires_unexpired_invite() {
+        let mut game = create_game(
+            None,
+            "alice",
+            None,
+            Some(ColorPreference::White),
+            None,
+            None,
+            "conn-alice",
+        );
+        let invite_token = issue_invite_token(&mut game, 60);
+
+        for invite in [None, Some("wrong")] {
+            assert!(assign_player_to_existing_or_remaining_slot(
+                &mut game, "bob", "conn-bob", None, invite, true
+            )
+            .is_err());
+        }
+
+        game.invite_expires_at = Some(chrono::Utc::now().timestamp() - 1);
+        assert!(assign_player_to_existing_or_remaining_slot(
+            &mut game,
+            "bob",
+            "conn-bob",
+            None,
+            Some(&invite_token),
+            false
+        )
+        .is_err());
+
+        game.invite_expires_at = Some(chrono::Utc::now().timestamp() + 60);
+        assert!(assign_player_to_existing_or_remaining_slot(
+            &mut game,
+            "bob",
+            "conn-bob",
+            None,
+            Some(&invite_token),
+            false
+        )
+        .is_ok());
+        assert_eq!(game.black_username.as_deref(), Some("bob"));
+    }
+
+    fn finished_game(ending: GameEnding) -> GameRecord {
+        let mut game = create_game(
+            None,
+            "alice",
+            None,
+            Some(ColorPreference::White),
+            None,
+            None,
+            "conn-alice",
+        );
+        game.black_username = Some("bob".to_string());
+        game.game_state.current_state_mut().state = State::Finished(ending);
+        game
+    }
+
+    #[test]
+    fn test_next_match_score_starts_match_only_when_requested() {
+        let game = finished_game(GameEnding::Checkmate(Color::Black));
+        assert!(get_next_match_score(&game, None).is_none());
+
+        let match_score = get_next_match_score(&game, Some(3)).unwrap();
+        assert!(match_score.game_ids.is_empty());
+        assert!(match_score.scores.iter().all(|score| score.points == 0.0));
+    }
+
+    #[test]
+    fn test_next_match_score_carries_on_until_decided() {
+        // Alice plays white and wins the first game of a best-of-3
+        let mut game = finished_game(GameEnding::Checkmate(Color::Black));
+        game.match_score = get_next_match_score(&game, Some(3));
+        let match_score = get_next_match_score(&game, None).unwrap();
+        assert_eq!(match_score.game_ids, vec![game.game_id.clone()]);
+        assert_eq!(match_score.scores[0].points, 1.0);
+        assert_eq!(match_score.scores[1].points, 0.0);
+
+        // Colors swap and the second game is drawn, so the match continues
+        let mut game = finished_game(GameEnding::DrawByMutualAgreement);
+        game.white_username = Some("bob".to_string());
+        game.black_username = Some("alice".to_string());
+        game.match_score = Some(match_score);
+        let match_score = get_next_match_score(&game, None).unwrap();
+        assert_eq!(match_score.scores[0].points, 1.5);
+        assert_eq!(match_score.scores[1].points, 0.5);
+
+        // Alice wins as black, deciding the match. No new match unless requested.
+        let mut game = finished_game(GameEnding::Resignation(Color::White));
+        game.white_username = Some("bob".to_string());
+        game.black_username = Some("alice".to_string());
+        game.match_score = Some(match_score);
+        assert!(get_next_match_score(&game, None).is_none());
+    }
+
+    #[test]
+    fn test_next_match_score_ignores_aborted_games() {
+        let mut game = finished_game(GameEnding::Checkmate(Color::Black));
+        game.match_score = get_next_match_score(&game, Some(3));
+        game.game_state.current_state_mut().state = State::Aborted;
+
+        let match_score = get_next_match_score(&game, None).unwrap();
+        assert!(match_score.game_ids.is_empty());
+        assert!(match_score.scores.iter().all(|score| score.points == 0.0));
+    }
+
+    fn pawn_move(file: usize, from_rank: usize, to_rank: usize) -> PlayerMove {
+        PlayerMove {
+            from: Position {
+                rank: Rank(from_rank),
+                file: File(file),
+            },
+            to: Position {
+                rank: Rank(to_rank),
+                file: File(file),
+            },
+        }
+    }
+
+    #[test]
+    fn test_takeback_ply_count() {
+        let mut game_state = GameState::new("test".into(), &BoardSetup::Standard, None);
+        assert_eq!(get_takeback_ply_count(&game_state, Color::White), None);
+
+        make_move(&mut game_state, &pawn_move(5, 2, 4));
+        assert_eq!(get_takeback_ply_count(&game_state, Color::White), Some(1));
+        assert_eq!(get_takeback_ply_count(&game_state, Color::Black), None);
+
+        make_move(&mut game_state, &pawn_move(5, 7, 5));
+        assert_eq!(get_takeback_ply_count(&game_state, Color::White), Some(2));
+        assert_eq!(get_takeback_ply_count(&game_state, Color::Black), Some(1));
+    }
+
+    #[test]
+    fn test_take_back_moves_restores_position_and_opening() {
+        let mut game_state = GameState::new("test".into(), &BoardSetup::Standard, None);
+        make_move(&mut game_state, &pawn_move(5, 2, 4));
+        let opening_after_first_move = game_state.opening.clone().map(|o| o.name);
+        make_move(&mut game_state, &pawn_move(5, 7, 5));
+        make_move(&mut game_state, &pawn_move(4, 2, 4));
+
+        take_back_moves(&mut game_state, 2);
+        assert_eq!(game_state.ply_count(), 1);
+        assert_eq!(game_state.move_list, vec!["e2e4"]);
+        assert_eq!(game_state.san_list.len(), 1);
+        assert_eq!(game_state.current_state().current_turn, Color::Black);
+        assert_eq!(
+            game_state.opening.clone().map(|o| o.name),
+            opening_after_first_move
+        );
+
+        take_back_moves(&mut game_state, 1);
+        assert_eq!(game_state.ply_count(), 0);
+        assert!(game_state.move_list.is_empty());
+        assert!(game_state.opening.is_none());
+    }
+
+    #[test]
+    fn test_take_back_moves_restores_clocks() {
+        let mut game_state = GameState::new("test".into(), &BoardSetup::Standard, Some(300));
+        make_move(&mut game_state, &pawn_move(5, 2, 4));
+
+        let game_time = game_state.game_time.as_mut().unwrap();
+        game_time.white_seconds_left = 100;
+        game_time.black_seconds_left = 50;
+
+        take_back_moves(&mut game_state, 1);
+        let game_time = game_state.game_time.as_ref().unwrap();
+        assert_eq!(game_time.white_seconds_left, 300);
+        assert_eq!(game_time.black_seconds_left, 300);
+    }
+
+    #[test]
+    fn test_play_premove() {
+        let mut game = create_game(
+            None,
+            "alice",
+            None,
+            Some(ColorPreference::White),
+            None,
+            None,
+            "conn-alice",
+        );
+        assert!(play_premove(&mut game, Color::Black).is_none());
+
+        // e4 e5 is legal once White has moved
+        make_move(&mut game.game_state, &pawn_move(5, 2, 4));
+        game.black_premove = Some(pawn_move(5, 7, 5));
+        assert_eq!(play_premove(&mut game, Color::Black), Some(Ok(())));
+        assert!(game.black_premove.is_none());
+        assert_eq!(game.game_state.move_list, vec!["e2e4", "e7e5"]);
+
+        // The e-pawn is now blocked, so pushing it again is discarded
+        game.white_premove = Some(pawn_move(5, 4, 5));
+        assert!(matches!(
+            play_premove(&mut game, Color::White),
+            Some(Err(_))
+        ));
+        assert!(game.white_premove.is_none());
+        assert_eq!(game.game_state.move_list.len(), 2);
+    }
 }
@@ -1,14 +1,93 @@
 use base64::{engine::general_purpose, Engine as _};
-use rand::{distr::Alphanumeric, Rng};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rand::{distr::Alphanumeric, Rng, RngCore};
+use sha2::{Digest, Sha256};
+
+const ID_LENGTH: usize = 8;
+/// Digits in the number that ends a game ID
+const GAME_ID_SUFFIX_DIGITS: u32 = 6;
+
+const GAME_ID_ADJECTIVES: [&str; 32] = [
+    "agile", "bold", "brave", "bright", "calm", "clever", "cosmic", "crafty", "daring", "eager",
+    "fearless", "fierce", "gentle", "golden", "grand", "happy", "hidden", "humble", "jolly",
+    "keen", "lively", "lucky", "mighty", "noble", "quick", "quiet", "royal", "silent", "sly",
+    "steady", "swift", "wise",
+];
+
+const GAME_ID_NOUNS: [&str; 32] = [
+    "bishop",
+    "castle",
+    "check",
+    "crown",
+    "dragon",
+    "falcon",
+    "fianchetto",
+    "fork",
+    "gambit",
+    "griffin",
+    "king",
+    "knight",
+    "ladder",
+    "lion",
+    "mate",
+    "opening",
+    "outpost",
+    "owl",
+    "pawn",
+    "phoenix",
+    "pin",
+    "queen",
+    "raven",
+    "rook",
+    "sacrifice",
+    "skewer",
+    "square",
+    "tempo",
+    "tiger",
+    "tower",
+    "wolf",
+    "zugzwang",
+];
 
 pub fn generate_id() -> String {
     rand::rng()
         .sample_iter(&Alphanumeric)
-        .take(4)
+        .take(ID_LENGTH)
         .map(char::from)
         .collect()
 }
 
+/// Generates a game ID that's easy to read out and type, e.g. `swift-knight-482193`.
+/// There are about a billion of them, so they're not unique on their own.
+pub fn generate_game_id() -> String {
+    let mut rng = rand::rng();
+
+    format!(
+        "{}-{}-{:0width$}",
+        GAME_ID_ADJECTIVES[rng.random_range(0..GAME_ID_ADJECTIVES.len())],
+        GAME_ID_NOUNS[rng.random_range(0..GAME_ID_NOUNS.len())],
+        rng.random_range(0..10u64.pow(GAME_ID_SUFFIX_DIGITS)),
+        width = GAME_ID_SUFFIX_DIGITS as usize
+    )
+}
+
+/// The number of different IDs `generate_game_id` can return
+pub fn get_game_id_space_size() -> u64 {
+    (GAME_ID_ADJECTIVES.len() * GAME_ID_NOUNS.len()) as u64 * 10u64.pow(GAME_ID_SUFFIX_DIGITS)
+}
+
+/// Generates an unguessable, URL-safe token (256 bits of randomness)
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rng().fill_bytes(&mut bytes);
+    general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Tokens are only ever stored hashed. They are high-entropy, so a fast hash is sufficient.
+pub fn hash_token(token: &str) -> String {
+    general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(token.as_bytes()))
+}
+
 pub fn bytes_to_base64(bytes: &[u8]) -> String {
     general_purpose::STANDARD.encode(bytes)
 }
@@ -16,3 +95,42 @@ pub fn bytes_to_base64(bytes: &[u8]) -> String {
 pub fn base64_to_bytes(s: &str) -> Result<Vec<u8>, base64::DecodeError> {
     general_purpose::STANDARD.decode(s)
 }
+
+/// Timestamps are stored and compared as strings, so they must all be the same width for their
+/// order to match the order in time. Plain `to_rfc3339` drops trailing zeros from the fraction.
+pub fn format_timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+pub fn get_timestamp() -> String {
+    format_timestamp(Utc::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_game_id_space_is_larger_than_random_alphanumeric_ids() {
+        // Game IDs used to be 4 random letters and digits
+        assert!(get_game_id_space_size() > 62u64.pow(4));
+        assert_eq!(get_game_id_space_size(), 1_024_000_000);
+
+        let game_id = generate_game_id();
+        let suffix = game_id.rsplit('-').next().unwrap();
+        assert_eq!(suffix.len(), GAME_ID_SUFFIX_DIGITS as usize);
+        assert!(suffix.chars().all(|c| c.is_ascii_digit()));
+    }
+
+    #[test]
+    fn test_timestamps_sort_in_time_order() {
+        let whole_second = DateTime::parse_from_rfc3339("2024-01-01T00:00:05Z").unwrap();
+        let half_second = DateTime::parse_from_rfc3339("2024-01-01T00:00:05.5Z").unwrap();
+
+        let earlier = format_timestamp(whole_second.to_utc());
+        let later = format_timestamp(half_second.to_utc());
+
+        assert_eq!(earlier, "2024-01-01T00:00:05.000000Z");
+        assert!(earlier < later);
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{
+    helpers::{
+        game::get_player_color,
+        generic::{base64_to_bytes, bytes_to_base64},
+    },
+    types::{
+        dynamo_db::GameRecord,
+        game::{GameEnding, State},
+        history::{GameHistoryEntry, GameOutcome, HistoryCursor},
+        piece::Color,
+        rating::TimeControlCategory,
+    },
+};
+
+pub const DEFAULT_HISTORY_PAGE_SIZE: usize = 20;
+pub const MAX_HISTORY_PAGE_SIZE: usize = 50;
+
+/// Turns where a page ended into an opaque cursor for the client
+pub fn encode_history_cursor(cursor: &HistoryCursor) -> Option<String> {
+    Some(bytes_to_base64(
+        serde_json::to_string(cursor).ok()?.as_bytes(),
+    ))
+}
+
+/// Returns `None` if the cursor is invalid or belongs to another user's history
+pub fn decode_history_cursor(username: &str, cursor: &str) -> Option<HistoryCursor> {
+    let bytes = base64_to_bytes(cursor).ok()?;
+    let cursor: HistoryCursor = serde_json::from_slice(&bytes).ok()?;
+
+    (cursor.username == username).then_some(cursor)
+}
+
+/// The game's result for the player, if it's over
+pub fn get_game_outcome(game: &GameRecord, color: Color) -> Option<GameOutcome> {
+    match game.game_state.current_state().state {
+        State::Finished(GameEnding::Checkmate(losing_color))
+        | State::Finished(GameEnding::OutOfTime(losing_color))
+        | State::Finished(GameEnding::Resignation(losing_color))
+        | State::Finished(GameEnding::Forfeit(losing_color)) => {
+            if losing_color == color {
+                Some(GameOutcome::Loss)
+            } else {
+                Some(GameOutcome::Win)
+            }
+        }
+        State::Finished(_) => Some(GameOutcome::Draw),
+        State::Aborted => Some(GameOutcome::Aborted),
+        State::NotStarted | State::InProgress => None,
+    }
+}
+
+/// Summarizes the game from the player's point of view. Returns `None` if they didn't play in it.
+pub fn to_game_history_entry(game: &GameRecord, username: &str) -> Option<GameHistoryEntry> {
+    let color = get_player_color(game, username)?;
+
+    let opponent = match color {
+        Color::White => game.black_username.clone(),
+        Color::Black => game.white_username.clone(),
+    };
+
+    let end_reason = match game.game_state.current_state().state {
+        State::Finished(game_ending) => Some(game_ending),
+        _ => None,
+    };
+
+    Some(GameHistoryEntry {
+        game_id: game.game_id.clone(),
+        created: game.created.clone(),
+        color,
+        opponent,
+        outcome: get_game_outcome(game, color),
+        end_reason,
+        seconds_per_player: game.seconds_per_player,
+        time_control: TimeControlCategory::from_seconds_per_player(game.seconds_per_player),
+        opening: game.game_state.opening.clone(),
+        rated: game.rated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{abort::abort_game, game::create_game};
+    use crate::types::game::ColorPreference;
+
+    fn game_between_alice_and_bob() -> GameRecord {
+        let mut game = create_game(
+            None,
+            "alice",
+            None,
+            Some(ColorPreference::White),
+            None,
+            Some(300),
+            "conn-alice",
+        );
+        game.black_username = Some("bob".to_string());
+        game
+    }
+
+    #[test]
+    fn test_to_game_history_entry_from_each_side() {
+        let mut game = game_between_alice_and_bob();
+        game.game_state.current_state_mut().state =
+            State::Finished(GameEnding::Resignation(Color::Black));
+
+        let alice_entry = to_game_history_entry(&game, "alice").unwrap();
+        assert_eq!(alice_entry.color, Color::White);
+        assert_eq!(alice_entry.opponent.as_deref(), Some("bob"));
+        assert_eq!(alice_entry.outcome, Some(GameOutcome::Win));
+        assert_eq!(
+            alice_entry.end_reason,
+            Some(GameEnding::Resignation(Color::Black))
+        );
+        assert_eq!(alice_entry.time_control, TimeControlCategory::Blitz);
+
+        let bob_entry = to_game_history_entry(&game, "bob").unwrap();
+        assert_eq!(bob_entry.outcome, Some(GameOutcome::Loss));
+
+        assert!(to_game_history_entry(&game, "carol").is_none());
+    }
+
+    #[test]
+    fn test_get_game_outcome_for_unfinished_and_aborted_games() {
+        let mut game = game_between_alice_and_bob();
+        assert_eq!(get_game_outcome(&game, Color::White), None);
+
+        abort_game(&mut game);
+        assert_eq!(
+            get_game_outcome(&game, Color::White),
+            Some(GameOutcome::Aborted)
+        );
+    }
+
+    #[test]
+    fn test_history_cursor_round_trip() {
+        let history_cursor = HistoryCursor {
+            username: "alice".to_string(),
+            sk: "GAME-abc".to_string(),
+            game_created: "2024-01-01T00:00:00+00:00".to_string(),
+        };
+
+        let cursor = encode_history_cursor(&history_cursor).unwrap();
+        assert_eq!(
+            decode_history_cursor("alice", &cursor),
+            Some(history_cursor)
+        );
+
+        // Cursors can't be used to page through someone else's games
+        assert!(decode_history_cursor("bob", &cursor).is_none());
+        assert!(decode_history_cursor("alice", "not a cursor").is_none());
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{
+    notifier::Notifier,
+    repository::{GameRepository, SubscriptionRepository},
+    types::{
+        api::ApiResponse,
+        dynamo_db::{GameRecord, OpenGameRecord},
+        game::State,
+        presence::ConnectionState,
+    },
+};
+
+use lambda_runtime::Error;
+use serde::Serialize;
+
+/// Connections browsing the lobby are subscribed to this topic
+pub const LOBBY_TOPIC: &str = "LOBBY";
+
+/// Value of `GameRecord.lobby` while a game is listed. Games without it aren't in the index.
+pub const OPEN_GAME_LISTING: &str = "OPEN";
+
+/// Sent to lobby subscribers when a game appears in or disappears from the lobby
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LobbyUpdate {
+    pub game_id: String,
+    pub open_game: Option<OpenGameRecord>, // `None` if the game is no longer open
+}
+
+fn is_connected(connection_id: &Option<ConnectionState>) -> bool {
+    connection_id
+        .as_ref()
+        .is_some_and(ConnectionState::is_connected)
+}
+
+/// A game is open if it hasn't started, isn't against the engine or private, and its only
+/// player is still connected to wait for an opponent
+pub fn is_open_game(game: &GameRecord) -> bool {
+    if game.engine_difficulty.is_some()
+        || game.invite_token_hash.is_some()
+        || game.game_state.current_state().state != State::NotStarted
+    {
+        return false;
+    }
+
+    match (&game.white_username, &game.black_username) {
+        (Some(_), None) => is_connected(&game.white_connection_id),
+        (None, Some(_)) => is_connected(&game.black_connection_id),
+        _ => false,
+    }
+}
+
+/// Keeps the game's lobby index attribute in sync with its state.
+/// Returns `true` if the game was added to or removed from the lobby.
+pub fn update_lobby_listing(game: &mut GameRecord) -> bool {
+    let lobby = is_open_game(game).then(|| OPEN_GAME_LISTING.to_string());
+    let changed = game.lobby != lobby;
+    game.lobby = lobby;
+    changed
+}
+
+pub fn to_open_game(game: &GameRecord) -> OpenGameRecord {
+    OpenGameRecord {
+        game_id: game.game_id.clone(),
+        white_username: game.white_username.clone(),
+        black_username: game.black_username.clone(),
+        board_setup: game.board_setup.clone(),
+        color_preference: game.color_preference,
+        seconds_per_player: game.seconds_per_player,
+        created: game.created.clone(),
+    }
+}
+
+/// Returns the open games, newest first
+pub async fn get_open_games(
+    repository: &impl GameRepository,
+) -> Result<Vec<OpenGameRecord>, Error> {
+    let mut open_games = repository.get_open_games().await?;
+
+    // RFC 3339 timestamps in UTC sort chronologically
+    open_games.sort_by(|a, b| b.created.cmp(&a.created));
+
+    Ok(open_games)
+}
+
+/// Let everyone browsing the lobby know that the game was listed or filled
+pub async fn notify_lobby_subscribers(
+    notifier: &impl Notifier,
+    repository: &impl SubscriptionRepository,
+    game: &GameRecord,
+) -> Result<(), Error> {
+    let subscribers = repository.get_subscribers(LOBBY_TOPIC).await?;
+
+    let update = LobbyUpdate {
+        game_id: game.game_id.clone(),
+        open_game: game.lobby.is_some().then(|| to_open_game(game)),
+    };
+
+    for subscriber in subscribers.iter() {
+        if !notifier
+            .send(
+                &subscriber.connection_id,
+                &ApiResponse {
+                    status_code: 200,
+                    connection_id: Some(subscriber.connection_id.clone()),
+                    messages: Vec::new(),
+                    data: Some(&update),
+                    replaces_game_id: None,
+                    seat_token: None,
+                    invite_token: None,
+                },
+            )
+            .await?
+        {
+            repository
+                .delete_subscription(LOBBY_TOPIC, &subscriber.connection_id)
+                .await?;
+        }
+    }
+
+    tracing::info!(
+        "Sent lobby update for game (ID: {}) to {} subscriber(s)",
+        game.game_id,
+        subscribers.len()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::game::create_game;
+    use crate::types::game::{ColorPreference, EngineDifficulty};
+
+    fn new_game() -> GameRecord {
+        create_game(
+            None,
+            "alice",
+            None,
+            Some(ColorPreference::White),
+            None,
+            None,
+            "conn-alice",
+        )
+    }
+
+    #[test]
+    fn test_new_game_is_open_until_filled() {
+        let mut game = new_game();
+        assert!(update_lobby_listing(&mut game));
+        assert_eq!(game.lobby.as_deref(), Some(OPEN_GAME_LISTING));
+        assert!(!update_lobby_listing(&mut game));
+
+        game.black_username = Some("bob".to_string());
+        game.black_connection_id = Some(ConnectionState::connected("conn-bob"));
+        assert!(update_lobby_listing(&mut game));
+        assert!(game.lobby.is_none());
+    }
+
+    #[test]
+    fn test_game_not_open_when_creator_disconnected_or_engine() {
+        let mut game = new_game();
+        game.white_connection_id = Some(ConnectionState::Disconnected);
+        assert!(!is_open_game(&game));
+
+        let mut game = new_game();
+        game.engine_difficulty = Some(EngineDifficulty::Easy);
+        assert!(!is_open_game(&game));
+    }
+}
@@ -1,8 +1,23 @@
+pub mod abort;
+pub mod auth;
 pub mod board;
+pub mod challenge;
+pub mod chat;
+pub mod connection;
 pub mod engine;
 pub mod game;
 pub mod generic;
+pub mod history;
+pub mod lobby;
 pub mod opening_book;
 pub mod opening_detection;
 pub mod pgn;
+pub mod presence;
+pub mod rating;
+pub mod seek;
+pub mod snapshot;
+pub mod stats;
+pub mod subscription;
+pub mod tournament;
+pub mod uci;
 pub mod user;
@@ -181,7 +181,8 @@ fn game_result_to_pgn(game_state: &GameState) -> &'static str {
     match game_state.current_state().state {
         State::Finished(GameEnding::Checkmate(losing_color))
         | State::Finished(GameEnding::Resignation(losing_color))
-        | State::Finished(GameEnding::OutOfTime(losing_color)) => match losing_color {
+        | State::Finished(GameEnding::OutOfTime(losing_color))
+        | State::Finished(GameEnding::Forfeit(losing_color)) => match losing_color {
             Color::White => "0-1",
             Color::Black => "1-0",
         },
@@ -194,17 +195,19 @@ fn game_result_to_pgn(game_state: &GameState) -> &'static str {
     }
 }
 
-/// Build the SAN move list on-the-fly from UCI moves and board history.
+/// Build the SAN move list on-the-fly from UCI moves and rebuilt positions.
 /// Used as a fallback for games that were created before `san_list` was stored.
 fn compute_san_list_from_history(game_state: &GameState) -> Vec<String> {
     let mut san_moves = Vec::with_capacity(game_state.move_list.len());
+    let mut state_before = game_state.position_at(0);
 
     for (i, uci) in game_state.move_list.iter().enumerate() {
-        let state_before = &game_state.history[i];
-        let state_after = &game_state.history[i + 1];
-        let color = &state_before.current_turn;
+        let (Some(before), Some(after)) = (&state_before, game_state.position_at(i + 1)) else {
+            break;
+        };
 
-        san_moves.push(uci_to_san(&state_before.board, uci, color, state_after));
+        san_moves.push(uci_to_san(&before.board, uci, &before.current_turn, &after));
+        state_before = Some(after);
     }
 
     san_moves
@@ -239,12 +242,7 @@ pub fn build_pgn_movetext(game_state: &GameState) -> String {
 /// Generate a PGN string for a game record.
 /// Returns `None` if the board is not a standard 8×8 board.
 pub fn game_to_pgn(game: &GameRecord) -> Option<String> {
-    if !game
-        .game_state
-        .history
-        .first()
-        .map_or(false, |s| s.board.is_standard_board())
-    {
+    if !game.game_state.current_state().board.is_standard_board() {
         return None;
     }
 
@@ -261,12 +259,21 @@ pub fn game_to_pgn(game: &GameRecord) -> Option<String> {
     let white = game.white_username.as_deref().unwrap_or("?");
     let black = game.black_username.as_deref().unwrap_or("?");
 
+    // Tournament games are numbered `<round>.<board>`
+    let (event, round) = match &game.tournament {
+        Some(tournament) => (
+            tournament.name.replace('"', "'"),
+            format!("{}.{}", tournament.round, tournament.board),
+        ),
+        None => ("Live Chess".to_string(), "-".to_string()),
+    };
+
     // Build PGN headers (Seven Tag Roster)
     let mut pgn = String::new();
-    pgn.push_str(&format!("[Event \"Live Chess\"]\n"));
+    pgn.push_str(&format!("[Event \"{event}\"]\n"));
     pgn.push_str(&format!("[Site \"chess.brendandagys.com\"]\n"));
     pgn.push_str(&format!("[Date \"{pgn_date}\"]\n"));
-    pgn.push_str(&format!("[Round \"-\"]\n"));
+    pgn.push_str(&format!("[Round \"{round}\"]\n"));
     pgn.push_str(&format!("[White \"{white}\"]\n"));
     pgn.push_str(&format!("[Black \"{black}\"]\n"));
     pgn.push_str(&format!("[Result \"{result}\"]\n"));
@@ -348,6 +355,7 @@ mod tests {
             },
             moves: vec![],
             engine_result: None,
+            clock: None,
         }
     }
 
@@ -589,4 +597,32 @@ mod tests {
         let san = uci_to_san(&board, "a1e1", &Color::White, &after);
         assert_eq!(san, "Rae1");
     }
+
+    #[test]
+    fn test_tournament_event_and_round_tags() {
+        let mut game = crate::helpers::game::create_game(
+            None,
+            "alice",
+            None,
+            Some(crate::types::game::ColorPreference::White),
+            None,
+            None,
+            "conn-alice",
+        );
+
+        let pgn = game_to_pgn(&game).unwrap();
+        assert!(pgn.contains("[Event \"Live Chess\"]"));
+        assert!(pgn.contains("[Round \"-\"]"));
+
+        game.tournament = Some(crate::types::tournament::TournamentGame {
+            tournament_id: "abcd".to_string(),
+            name: "Club Championship".to_string(),
+            round: 3,
+            board: 2,
+        });
+
+        let pgn = game_to_pgn(&game).unwrap();
+        assert!(pgn.contains("[Event \"Club Championship\"]"));
+        assert!(pgn.contains("[Round \"3.2\"]"));
+    }
 }
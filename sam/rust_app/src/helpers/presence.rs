@@ -0,0 +1,234 @@
+use crate::{
+    helpers::{generic::get_timestamp, user::get_user_connection_ids},
+    notifier::Notifier,
+    repository::UserRepository,
+    types::{
+        api::{ApiMessage, ApiMessageType, ApiResponse},
+        dynamo_db::{FriendRecord, GameRecord},
+        presence::{FriendEvent, FriendPresence},
+    },
+};
+
+use chrono::{DateTime, Utc};
+use lambda_runtime::Error;
+
+/// Clients send a heartbeat every 50 seconds, so a user who hasn't been seen for longer than
+/// this has lost their connection without it being cleaned up
+const PRESENCE_TIMEOUT_SECONDS: i64 = 120;
+pub const MAX_FRIENDS: usize = 100;
+
+pub fn is_recently_seen(last_seen: Option<&str>, now: DateTime<Utc>) -> bool {
+    last_seen
+        .and_then(|last_seen| DateTime::parse_from_rfc3339(last_seen).ok())
+        .is_some_and(|last_seen| {
+            (now - last_seen.with_timezone(&Utc)).num_seconds() <= PRESENCE_TIMEOUT_SECONDS
+        })
+}
+
+pub fn create_friend(username: &str, friend: &str) -> FriendRecord {
+    FriendRecord {
+        username: username.to_string(),
+        sort_key: format!("FRIEND-{friend}"),
+        friend: friend.to_string(),
+        created: get_timestamp(),
+    }
+}
+
+/// The users who the user has added, and who have added them back
+pub async fn get_friends(
+    repository: &impl UserRepository,
+    username: &str,
+) -> Result<Vec<String>, Error> {
+    let mut friends = Vec::new();
+
+    for friend in repository.get_added_friends(username).await? {
+        if repository.has_added_friend(&friend, username).await? {
+            friends.push(friend);
+        }
+    }
+
+    Ok(friends)
+}
+
+pub async fn get_online_friends(
+    repository: &impl UserRepository,
+    username: &str,
+) -> Result<Vec<FriendPresence>, Error> {
+    let now = Utc::now();
+    let mut online_friends = Vec::new();
+
+    for friend in get_friends(repository, username).await? {
+        let last_seen = repository
+            .get_user_info(&friend)
+            .await?
+            .and_then(|user_info| user_info.last_seen);
+
+        if is_recently_seen(last_seen.as_deref(), now)
+            && !get_user_connection_ids(repository, &friend)
+                .await?
+                .is_empty()
+        {
+            online_friends.push(FriendPresence {
+                username: friend,
+                last_seen,
+            });
+        }
+    }
+
+    Ok(online_friends)
+}
+
+fn get_friend_event_message(event: &FriendEvent) -> ApiMessage {
+    let message = match event {
+        FriendEvent::Online { username } => format!("{username} is online"),
+        FriendEvent::Offline { username } => format!("{username} went offline"),
+        FriendEvent::StartedGame { username, .. } => format!("{username} started a game"),
+    };
+
+    ApiMessage {
+        message,
+        message_type: ApiMessageType::Info,
+    }
+}
+
+/// Pushes the event to every connection of each of the user's friends
+pub async fn notify_friends(
+    notifier: &impl Notifier,
+    repository: &impl UserRepository,
+    event: &FriendEvent,
+) -> Result<(), Error> {
+    let message = get_friend_event_message(event);
+
+    for friend in get_friends(repository, event.username()).await? {
+        for connection_id in get_user_connection_ids(repository, &friend).await? {
+            notifier
+                .send(
+                    &connection_id,
+                    &ApiResponse {
+                        status_code: 200,
+                        connection_id: Some(connection_id.clone()),
+                        messages: vec![message.clone()],
+                        data: Some(event),
+                        replaces_game_id: None,
+                        seat_token: None,
+                        invite_token: None,
+                    },
+                )
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Call once the connection's record has been saved. Friends are only told when the user's
+/// first connection opens.
+pub async fn handle_user_connected(
+    notifier: &impl Notifier,
+    repository: &impl UserRepository,
+    username: &str,
+) -> Result<(), Error> {
+    repository.update_last_seen(username).await?;
+
+    if get_user_connection_ids(repository, username).await?.len() == 1 {
+        notify_friends(
+            notifier,
+            repository,
+            &FriendEvent::Online {
+                username: username.to_string(),
+            },
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Call once the connection's record has been deleted. Friends are only told when the user's
+/// last connection closes.
+pub async fn handle_user_disconnected(
+    notifier: &impl Notifier,
+    repository: &impl UserRepository,
+    username: &str,
+) -> Result<(), Error> {
+    repository.update_last_seen(username).await?;
+
+    if get_user_connection_ids(repository, username)
+        .await?
+        .is_empty()
+    {
+        notify_friends(
+            notifier,
+            repository,
+            &FriendEvent::Offline {
+                username: username.to_string(),
+            },
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Lets the players' friends know they can watch the game. Private games are kept quiet.
+pub async fn notify_friends_of_started_game(
+    notifier: &impl Notifier,
+    repository: &impl UserRepository,
+    game: &GameRecord,
+) -> Result<(), Error> {
+    if game.invite_token_hash.is_some() {
+        return Ok(());
+    }
+
+    // Guests have no friends records, so nobody is notified about them
+    for username in [&game.white_username, &game.black_username]
+        .into_iter()
+        .flatten()
+    {
+        notify_friends(
+            notifier,
+            repository,
+            &FriendEvent::StartedGame {
+                username: username.clone(),
+                game_id: game.game_id.clone(),
+            },
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::generic::format_timestamp;
+
+    #[test]
+    fn test_is_recently_seen() {
+        let now = Utc::now();
+        let seconds_ago = |seconds: i64| format_timestamp(now - chrono::Duration::seconds(seconds));
+
+        assert!(is_recently_seen(Some(&seconds_ago(50)), now));
+        assert!(!is_recently_seen(Some(&seconds_ago(600)), now));
+        assert!(!is_recently_seen(Some("not a timestamp"), now));
+        assert!(!is_recently_seen(None, now));
+    }
+
+    #[test]
+    fn test_friend_events_are_tagged() {
+        let event = FriendEvent::StartedGame {
+            username: "alice".to_string(),
+            game_id: "abc".to_string(),
+        };
+
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({ "event": "started-game", "username": "alice", "gameId": "abc" })
+        );
+        assert_eq!(
+            get_friend_event_message(&event).message,
+            "alice started a game"
+        );
+    }
+}
@@ -0,0 +1,308 @@
+use std::f64::consts::PI;
+
+use crate::{
+    repository::UserRepository,
+    types::{
+        api::{ApiMessage, ApiMessageType},
+        dynamo_db::GameRecord,
+        game::{GameEnding, State},
+        piece::Color,
+        rating::{Rating, RatingChange, RatingUpdate, TimeControlCategory},
+    },
+};
+
+use lambda_runtime::Error;
+
+/// Constrains how much the volatility can change between games
+const TAU: f64 = 0.5;
+/// Converts between the Glicko and Glicko-2 scales
+const GLICKO2_SCALE: f64 = 173.7178;
+const CONVERGENCE_TOLERANCE: f64 = 0.000001;
+const MAX_RATING_DEVIATION: f64 = 350.0;
+
+fn g(deviation: f64) -> f64 {
+    1.0 / (1.0 + 3.0 * deviation.powi(2) / PI.powi(2)).sqrt()
+}
+
+fn expected_score(mu: f64, opponent_mu: f64, opponent_phi: f64) -> f64 {
+    1.0 / (1.0 + (-g(opponent_phi) * (mu - opponent_mu)).exp())
+}
+
+/// Finds the new volatility with the Illinois algorithm (step 5 of Glickman's paper)
+fn get_new_volatility(phi: f64, volatility: f64, variance: f64, delta: f64) -> f64 {
+    let a = volatility.powi(2).ln();
+    let f = |x: f64| {
+        let ex = x.exp();
+        ex * (delta.powi(2) - phi.powi(2) - variance - ex)
+            / (2.0 * (phi.powi(2) + variance + ex).powi(2))
+            - (x - a) / TAU.powi(2)
+    };
+
+    let mut bound_a = a;
+    let mut bound_b = if delta.powi(2) > phi.powi(2) + variance {
+        (delta.powi(2) - phi.powi(2) - variance).ln()
+    } else {
+        let mut k = 1.0;
+        while f(a - k * TAU) < 0.0 {
+            k += 1.0;
+        }
+        a - k * TAU
+    };
+
+    let mut f_a = f(bound_a);
+    let mut f_b = f(bound_b);
+
+    while (bound_b - bound_a).abs() > CONVERGENCE_TOLERANCE {
+        let c = bound_a + (bound_a - bound_b) * f_a / (f_b - f_a);
+        let f_c = f(c);
+
+        if f_c * f_b <= 0.0 {
+            bound_a = bound_b;
+            f_a = f_b;
+        } else {
+            f_a /= 2.0;
+        }
+
+        bound_b = c;
+        f_b = f_c;
+    }
+
+    (bound_a / 2.0).exp()
+}
+
+/// Applies the Glicko-2 algorithm to a rating period made up of the given games, each
+/// against an opponent with a score of 1 (win), 0.5 (draw) or 0 (loss)
+pub fn get_new_rating(rating: Rating, results: &[(Rating, f64)]) -> Rating {
+    let mu = (rating.rating - 1500.0) / GLICKO2_SCALE;
+    let phi = rating.deviation / GLICKO2_SCALE;
+
+    if results.is_empty() {
+        return Rating {
+            deviation: ((phi.powi(2) + rating.volatility.powi(2)).sqrt() * GLICKO2_SCALE)
+                .min(MAX_RATING_DEVIATION),
+            ..rating
+        };
+    }
+
+    let opponents: Vec<(f64, f64, f64)> = results
+        .iter()
+        .map(|(opponent, score)| {
+            (
+                (opponent.rating - 1500.0) / GLICKO2_SCALE,
+                opponent.deviation / GLICKO2_SCALE,
+                *score,
+            )
+        })
+        .collect();
+
+    let variance = 1.0
+        / opponents
+            .iter()
+            .map(|&(opponent_mu, opponent_phi, _)| {
+                let expected = expected_score(mu, opponent_mu, opponent_phi);
+                g(opponent_phi).powi(2) * expected * (1.0 - expected)
+            })
+            .sum::<f64>();
+
+    let improvement: f64 = opponents
+        .iter()
+        .map(|&(opponent_mu, opponent_phi, score)| {
+            g(opponent_phi) * (score - expected_score(mu, opponent_mu, opponent_phi))
+        })
+        .sum();
+
+    let volatility = get_new_volatility(phi, rating.volatility, variance, variance * improvement);
+    let pre_period_phi = (phi.powi(2) + volatility.powi(2)).sqrt();
+    let new_phi = 1.0 / (1.0 / pre_period_phi.powi(2) + 1.0 / variance).sqrt();
+    let new_mu = mu + new_phi.powi(2) * improvement;
+
+    Rating {
+        rating: new_mu * GLICKO2_SCALE + 1500.0,
+        deviation: (new_phi * GLICKO2_SCALE).min(MAX_RATING_DEVIATION),
+        volatility,
+        games: rating.games + results.len() as u32,
+    }
+}
+
+/// The player's rating in the category, or the starting rating if they haven't played a rated
+/// game in it (or don't have an account)
+pub async fn get_rating(
+    repository: &impl UserRepository,
+    username: &str,
+    category: TimeControlCategory,
+) -> Result<Rating, Error> {
+    let user_info = repository.get_user_info(username).await?;
+
+    Ok(user_info
+        .and_then(|user_info| user_info.ratings.get(category))
+        .unwrap_or_default())
+}
+
+/// White's score, if the game ended with a result
+fn get_white_score(game: &GameRecord) -> Option<f64> {
+    match game.game_state.current_state().state {
+        State::Finished(GameEnding::Checkmate(losing_color))
+        | State::Finished(GameEnding::OutOfTime(losing_color))
+        | State::Finished(GameEnding::Resignation(losing_color))
+        | State::Finished(GameEnding::Forfeit(losing_color)) => match losing_color {
+            Color::White => Some(0.0),
+            Color::Black => Some(1.0),
+        },
+        State::Finished(_) => Some(0.5),
+        _ => None,
+    }
+}
+
+/// Works out both players' new ratings once a rated game has ended, along with the updates that
+/// save them. The updates fail if either rating changes before they're written. Returns nothing
+/// if the game isn't rated or either player has no account.
+pub async fn prepare_rating_updates(
+    repository: &impl UserRepository,
+    game: &GameRecord,
+) -> Result<(Vec<RatingUpdate>, Vec<RatingChange>), Error> {
+    let (Some(white_score), Some(white_username), Some(black_username)) = (
+        get_white_score(game).filter(|_| game.rated),
+        game.white_username.as_deref(),
+        game.black_username.as_deref(),
+    ) else {
+        return Ok((Vec::new(), Vec::new()));
+    };
+
+    let category = TimeControlCategory::from_seconds_per_player(game.seconds_per_player);
+
+    let (Some(white_info), Some(black_info)) = (
+        repository.get_user_info(white_username).await?,
+        repository.get_user_info(black_username).await?,
+    ) else {
+        return Ok((Vec::new(), Vec::new()));
+    };
+
+    let previous_white_rating = white_info.ratings.get(category);
+    let previous_black_rating = black_info.ratings.get(category);
+    let white_before = previous_white_rating.unwrap_or_default();
+    let black_before = previous_black_rating.unwrap_or_default();
+
+    let white_after = get_new_rating(white_before, &[(black_before, white_score)]);
+    let black_after = get_new_rating(black_before, &[(white_before, 1.0 - white_score)]);
+
+    let updates = vec![
+        RatingUpdate {
+            username: white_username.to_string(),
+            category,
+            previous: previous_white_rating,
+            new: white_after,
+        },
+        RatingUpdate {
+            username: black_username.to_string(),
+            category,
+            previous: previous_black_rating,
+            new: black_after,
+        },
+    ];
+
+    let rating_changes = vec![
+        RatingChange {
+            username: white_username.to_string(),
+            category,
+            before: white_before,
+            after: white_after,
+        },
+        RatingChange {
+            username: black_username.to_string(),
+            category,
+            before: black_before,
+            after: black_after,
+        },
+    ];
+
+    Ok((updates, rating_changes))
+}
+
+/// e.g. "alice's blitz rating: 1500 → 1662 (+162)"
+pub fn get_rating_change_message(rating_change: &RatingChange) -> ApiMessage {
+    let before = rating_change.before.rating.round() as i64;
+    let after = rating_change.after.rating.round() as i64;
+
+    ApiMessage {
+        message: format!(
+            "{}'s {} rating: {before} → {after} ({:+})",
+            rating_change.username,
+            rating_change.category,
+            after - before
+        ),
+        message_type: ApiMessageType::Info,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{dynamo_db::UserInfoRecord, rating::Ratings};
+    use aws_sdk_dynamodb::types::AttributeValue;
+    use serde_dynamo::aws_sdk_dynamodb_1::{from_item, to_item};
+    use std::collections::HashMap;
+
+    fn rating(rating: f64, deviation: f64) -> Rating {
+        Rating {
+            rating,
+            deviation,
+            ..Rating::default()
+        }
+    }
+
+    #[test]
+    fn test_get_new_rating_matches_glickman_example() {
+        let new_rating = get_new_rating(
+            rating(1500.0, 200.0),
+            &[
+                (rating(1400.0, 30.0), 1.0),
+                (rating(1550.0, 100.0), 0.0),
+                (rating(1700.0, 300.0), 0.0),
+            ],
+        );
+
+        assert!((new_rating.rating - 1464.06).abs() < 0.01);
+        assert!((new_rating.deviation - 151.52).abs() < 0.01);
+        assert!((new_rating.volatility - 0.05999).abs() < 0.00001);
+        assert_eq!(new_rating.games, 3);
+    }
+
+    #[test]
+    fn test_get_new_rating_is_symmetric_for_new_players() {
+        let winner = get_new_rating(Rating::default(), &[(Rating::default(), 1.0)]);
+        let loser = get_new_rating(Rating::default(), &[(Rating::default(), 0.0)]);
+
+        assert!(winner.rating > 1500.0);
+        assert!(((winner.rating - 1500.0) - (1500.0 - loser.rating)).abs() < 0.001);
+        assert!(winner.deviation < 350.0);
+
+        let draw = get_new_rating(Rating::default(), &[(Rating::default(), 0.5)]);
+        assert!((draw.rating - 1500.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_ratings_round_trip_through_user_info_item() {
+        let user_info = UserInfoRecord {
+            username: "alice".to_string(),
+            sort_key: "INFO".to_string(),
+            password_hash: "hash".to_string(),
+            created: "2024-01-01T00:00:00+00:00".to_string(),
+            last_seen: None,
+            ratings: Ratings {
+                blitz: Some(rating(1612.5, 80.0)),
+                ..Ratings::default()
+            },
+        };
+
+        let item: HashMap<String, AttributeValue> = to_item(&user_info).unwrap();
+        assert!(item.contains_key("rating_blitz"));
+        assert!(!item.contains_key("rating_bullet"));
+
+        let user_info: UserInfoRecord = from_item(item).unwrap();
+        assert_eq!(
+            user_info.ratings.get(TimeControlCategory::Blitz),
+            Some(rating(1612.5, 80.0))
+        );
+        assert_eq!(user_info.ratings.get(TimeControlCategory::Rapid), None);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{
+    helpers::generic::get_timestamp,
+    repository::SeekRepository,
+    types::{board::BoardSetup, dynamo_db::SeekRecord},
+};
+
+use lambda_runtime::Error;
+
+/// Only seeks for the same board setup and time control can be paired
+pub fn seek_pool(board_setup: &BoardSetup, seconds_per_player: Option<usize>) -> String {
+    let board_setup = serde_json::to_string(board_setup).unwrap_or_default();
+
+    match seconds_per_player {
+        Some(seconds) => format!("POOL-{board_setup}-{seconds}"),
+        None => format!("POOL-{board_setup}-UNTIMED"),
+    }
+}
+
+pub fn create_seek(
+    connection_id: &str,
+    username: &str,
+    rating: u32,
+    authenticated: bool,
+    min_rating: Option<u32>,
+    max_rating: Option<u32>,
+    board_setup: BoardSetup,
+    seconds_per_player: Option<usize>,
+) -> SeekRecord {
+    let created = get_timestamp();
+
+    SeekRecord {
+        pool: seek_pool(&board_setup, seconds_per_player),
+        seek_id: format!("{created}-{connection_id}"),
+        connection_id: connection_id.to_string(),
+        username: username.to_string(),
+        rating,
+        authenticated,
+        min_rating,
+        max_rating,
+        board_setup,
+        seconds_per_player,
+        created,
+    }
+}
+
+fn accepts_rating(seek: &SeekRecord, rating: u32) -> bool {
+    seek.min_rating.is_none_or(|min| rating >= min)
+        && seek.max_rating.is_none_or(|max| rating <= max)
+}
+
+/// Both seekers must be within each other's rating range
+pub fn are_seeks_compatible(seek: &SeekRecord, other_seek: &SeekRecord) -> bool {
+    seek.pool == other_seek.pool
+        && seek.username != other_seek.username
+        && seek.connection_id != other_seek.connection_id
+        && accepts_rating(seek, other_seek.rating)
+        && accepts_rating(other_seek, seek.rating)
+}
+
+/// Returns the number of seeks that were removed
+pub async fn delete_seeks_from_connection_id(
+    repository: &impl SeekRepository,
+    connection_id: &str,
+) -> Result<usize, Error> {
+    let seeks = repository
+        .get_seeks_from_connection_id(connection_id)
+        .await?;
+
+    for seek in seeks.iter() {
+        repository.delete_seek(seek).await?;
+    }
+
+    Ok(seeks.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seek(connection_id: &str, rating: u32, min: Option<u32>, max: Option<u32>) -> SeekRecord {
+        create_seek(
+            connection_id,
+            connection_id,
+            rating,
+            false,
+            min,
+            max,
+            BoardSetup::Standard,
+            Some(300),
+        )
+    }
+
+    #[test]
+    fn test_seek_pool_separates_time_controls() {
+        assert_eq!(
+            seek_pool(&BoardSetup::Standard, Some(300)),
+            "POOL-\"standard\"-300"
+        );
+        assert_ne!(
+            seek_pool(&BoardSetup::Standard, None),
+            seek_pool(&BoardSetup::Chess960, None)
+        );
+    }
+
+    #[test]
+    fn test_seeks_compatible_within_both_rating_ranges() {
+        let a = seek("a", 1500, Some(1400), Some(1600));
+        let b = seek("b", 1550, None, None);
+        assert!(are_seeks_compatible(&a, &b));
+        assert!(are_seeks_compatible(&b, &a));
+
+        let c = seek("c", 1700, None, None);
+        assert!(!are_seeks_compatible(&a, &c));
+
+        let d = seek("d", 1450, Some(1600), None);
+        assert!(!are_seeks_compatible(&a, &d));
+    }
+
+    #[test]
+    fn test_seeks_incompatible_across_pools_or_same_player() {
+        let a = seek("a", 1500, None, None);
+        let mut untimed = seek("b", 1500, None, None);
+        untimed.pool = seek_pool(&BoardSetup::Standard, None);
+        assert!(!are_seeks_compatible(&a, &untimed));
+
+        let same_player = seek("a", 1500, None, None);
+        assert!(!are_seeks_compatible(&a, &same_player));
+    }
+}
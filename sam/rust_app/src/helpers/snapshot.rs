@@ -0,0 +1,236 @@
+use crate::types::{
+    board::{File, Position, Rank},
+    game::{
+        GameState, GameStateAtPointInTime, PlayerMove, PlyDetails, PositionSnapshot,
+        StoredGameState,
+    },
+};
+
+/// Plies between stored positions, so rebuilding a position never replays more moves than this
+pub const SNAPSHOT_INTERVAL_PLIES: usize = 20;
+
+/// Parse a UCI move like "e2e4" or "e7e8q". Large boards can have ranks with more than one digit.
+pub fn uci_to_player_move(uci: &str) -> Option<PlayerMove> {
+    let mut chars = uci.chars().peekable();
+
+    let mut parse_position = || -> Option<Position> {
+        let file = (chars.next()? as usize).checked_sub('a' as usize)? + 1;
+
+        let mut rank = String::new();
+        while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+            rank.push(digit);
+        }
+
+        Some(Position {
+            rank: Rank(rank.parse().ok()?),
+            file: File(file),
+        })
+    };
+
+    let from = parse_position()?;
+    let to = parse_position()?;
+
+    Some(PlayerMove { from, to })
+}
+
+impl TryFrom<StoredGameState> for GameState {
+    type Error = String;
+
+    /// Older games are converted without replaying any moves, so reading them stays cheap
+    fn try_from(stored: StoredGameState) -> Result<Self, Self::Error> {
+        let (current, snapshots, ply_details) = match stored.current {
+            Some(current) => (current, stored.snapshots, stored.ply_details),
+            None if !stored.history.is_empty() => {
+                from_history(stored.history, stored.move_list.len())
+            }
+            None => from_snapshots(stored.snapshots, stored.ply_details)?,
+        };
+
+        Ok(GameState {
+            game_id: stored.game_id,
+            game_time: stored.game_time,
+            move_list: stored.move_list,
+            san_list: stored.san_list,
+            opening: stored.opening,
+            snapshots,
+            ply_details,
+            current,
+        })
+    }
+}
+
+type CompactHistory = (
+    GameStateAtPointInTime,
+    Vec<PositionSnapshot>,
+    Vec<PlyDetails>,
+);
+
+fn to_snapshot(ply: usize, mut position: GameStateAtPointInTime) -> PositionSnapshot {
+    position.moves = Vec::new();
+    PositionSnapshot { ply, position }
+}
+
+/// Games saved before snapshots were added store every position. Positions can only be rebuilt
+/// if every move that led to them was recorded, so otherwise they're all kept.
+fn from_history(
+    mut history: Vec<GameStateAtPointInTime>,
+    recorded_move_count: usize,
+) -> CompactHistory {
+    let current = history.pop().expect("Stored history should not be empty");
+    let can_rebuild = recorded_move_count == history.len();
+
+    let ply_details = history
+        .iter()
+        .map(|position| PlyDetails {
+            clock: position.clock,
+            engine_result: position.engine_result.clone(),
+        })
+        .collect();
+
+    let snapshots = history
+        .into_iter()
+        .enumerate()
+        .filter(|(ply, _)| !can_rebuild || ply % SNAPSHOT_INTERVAL_PLIES == 0)
+        .map(|(ply, position)| to_snapshot(ply, position))
+        .collect();
+
+    (current, snapshots, ply_details)
+}
+
+/// Games saved before the current position was kept separately store it as the last snapshot,
+/// with details for every position including it
+fn from_snapshots(
+    mut snapshots: Vec<PositionSnapshot>,
+    mut ply_details: Vec<PlyDetails>,
+) -> Result<CompactHistory, String> {
+    let current = snapshots
+        .pop()
+        .filter(|snapshot| snapshot.ply + 1 == ply_details.len())
+        .ok_or("Stored game state has no current position")?;
+
+    ply_details.truncate(current.ply);
+
+    let snapshots = snapshots
+        .into_iter()
+        .map(|snapshot| to_snapshot(snapshot.ply, snapshot.position))
+        .collect();
+
+    Ok((current.position, snapshots, ply_details))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::game::{create_game, make_move};
+    use crate::types::board::BoardSetup;
+    use serde_dynamo::aws_sdk_dynamodb_1::{from_attribute_value, to_attribute_value};
+
+    #[test]
+    fn test_uci_to_player_move() {
+        let player_move = uci_to_player_move("e7e8q").unwrap();
+        assert_eq!((player_move.from.file.0, player_move.from.rank.0), (5, 7));
+        assert_eq!((player_move.to.file.0, player_move.to.rank.0), (5, 8));
+
+        let player_move = uci_to_player_move("b10c12").unwrap();
+        assert_eq!((player_move.from.file.0, player_move.from.rank.0), (2, 10));
+        assert_eq!((player_move.to.file.0, player_move.to.rank.0), (3, 12));
+
+        assert!(uci_to_player_move("e2").is_none());
+        assert!(uci_to_player_move("").is_none());
+    }
+
+    /// A game with a capture, then knights hopping back and forth, and every position it reached
+    fn play_game() -> (GameState, Vec<GameStateAtPointInTime>) {
+        let mut game = create_game(
+            None,
+            "alice",
+            Some(BoardSetup::Standard),
+            None,
+            None,
+            None,
+            "conn-alice",
+        );
+
+        let mut moves = vec!["e2e4", "d7d5", "e4d5"];
+        moves.extend(["d8d5", "g1f3", "g8f6", "f3g1", "f6g8"]);
+        moves.extend(["g1f3", "g8f6", "f3g1", "f6g8"].repeat(5));
+
+        let mut positions = vec![game.game_state.current_state().clone()];
+
+        for uci in &moves {
+            make_move(&mut game.game_state, &uci_to_player_move(uci).unwrap());
+            positions.push(game.game_state.current_state().clone());
+        }
+
+        (game.game_state, positions)
+    }
+
+    #[test]
+    fn test_positions_are_rebuilt_from_snapshots() {
+        let (game_state, positions) = play_game();
+
+        assert_eq!(
+            game_state
+                .snapshots
+                .iter()
+                .map(|snapshot| snapshot.ply)
+                .collect::<Vec<_>>(),
+            vec![0, 20]
+        );
+
+        for (ply, position) in positions.iter().enumerate() {
+            assert_eq!(
+                serde_json::to_value(game_state.position_at(ply).unwrap()).unwrap(),
+                serde_json::to_value(position).unwrap()
+            );
+        }
+
+        assert_eq!(game_state.positions_leading_to(23).unwrap().len(), 4);
+        assert!(game_state.position_at(positions.len()).is_none());
+        assert_eq!(
+            game_state
+                .position_at(3)
+                .unwrap()
+                .captured_pieces
+                .white_points,
+            1
+        );
+    }
+
+    #[test]
+    fn test_game_state_round_trip() {
+        let (game_state, _) = play_game();
+
+        let stored: GameState =
+            from_attribute_value(to_attribute_value(&game_state).unwrap()).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&stored).unwrap(),
+            serde_json::to_value(&game_state).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_games_with_every_position_are_converted() {
+        let (game_state, positions) = play_game();
+
+        let stored = StoredGameState {
+            game_id: game_state.game_id.clone(),
+            game_time: None,
+            move_list: game_state.move_list.clone(),
+            san_list: game_state.san_list.clone(),
+            opening: game_state.opening.clone(),
+            snapshots: Vec::new(),
+            ply_details: Vec::new(),
+            current: None,
+            history: positions,
+        };
+
+        let converted = GameState::try_from(stored).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&converted).unwrap(),
+            serde_json::to_value(&game_state).unwrap()
+        );
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+
+use crate::{
+    helpers::history::get_game_outcome,
+    types::{
+        dynamo_db::{GameRecord, UserStatsRecord},
+        history::GameOutcome,
+        piece::Color,
+        rating::TimeControlCategory,
+        stats::{
+            BoardSetupStats, ColorStats, OpeningStats, ResultTotals, TimeControlStats, UserStats,
+        },
+    },
+};
+
+use aws_sdk_dynamodb::types::AttributeValue;
+use lambda_runtime::Error;
+use serde_dynamo::aws_sdk_dynamodb_1::to_attribute_value;
+
+/// A stats record that a finished game counts towards
+pub struct StatsSlice {
+    pub sort_key: String,
+    pub attributes: HashMap<String, AttributeValue>, // Describe the slice, e.g. `color`
+}
+
+pub fn get_stats_slices(game: &GameRecord, color: Color) -> Result<Vec<StatsSlice>, Error> {
+    let time_control = TimeControlCategory::from_seconds_per_player(game.seconds_per_player);
+    let board_setup = serde_json::to_string(&game.board_setup)?;
+
+    let mut slices = vec![
+        StatsSlice {
+            sort_key: "STATS#ALL".to_string(),
+            attributes: HashMap::new(),
+        },
+        StatsSlice {
+            sort_key: format!("STATS#COLOR#{color}"),
+            attributes: HashMap::from([("color".to_string(), to_attribute_value(color)?)]),
+        },
+        StatsSlice {
+            sort_key: format!("STATS#TIME-CONTROL#{time_control}"),
+            attributes: HashMap::from([(
+                "time_control".to_string(),
+                to_attribute_value(time_control)?,
+            )]),
+        },
+        StatsSlice {
+            sort_key: format!("STATS#BOARD#{board_setup}"),
+            attributes: HashMap::from([(
+                "board_setup".to_string(),
+                to_attribute_value(&game.board_setup)?,
+            )]),
+        },
+    ];
+
+    if let Some(opening) = &game.game_state.opening {
+        slices.push(StatsSlice {
+            sort_key: format!("STATS#OPENING#{}#{}", opening.eco, opening.name),
+            attributes: HashMap::from([
+                ("eco".to_string(), AttributeValue::S(opening.eco.clone())),
+                (
+                    "opening_name".to_string(),
+                    AttributeValue::S(opening.name.clone()),
+                ),
+            ]),
+        });
+    }
+
+    Ok(slices)
+}
+
+/// The wins, draws and losses that a finished game adds to the player's stats. `None` for
+/// aborted and unfinished games, which aren't counted.
+pub fn get_result_counts(game: &GameRecord, color: Color) -> Option<(u32, u32, u32)> {
+    match get_game_outcome(game, color)? {
+        GameOutcome::Win => Some((1, 0, 0)),
+        GameOutcome::Draw => Some((0, 1, 0)),
+        GameOutcome::Loss => Some((0, 0, 1)),
+        GameOutcome::Aborted => None,
+    }
+}
+
+fn to_result_totals(record: &UserStatsRecord) -> ResultTotals {
+    let games = record.wins + record.draws + record.losses;
+
+    if games == 0 {
+        return ResultTotals::default();
+    }
+
+    ResultTotals {
+        games,
+        wins: record.wins,
+        draws: record.draws,
+        losses: record.losses,
+        score_percentage: (record.wins as f32 + record.draws as f32 / 2.0) / games as f32 * 100.0,
+        average_moves: record.total_plies as f32 / games as f32 / 2.0,
+    }
+}
+
+/// Assembles the player's stats records into a report
+pub fn build_user_stats(username: &str, records: &[UserStatsRecord]) -> UserStats {
+    let mut stats = UserStats {
+        username: username.to_string(),
+        overall: ResultTotals::default(),
+        by_color: Vec::new(),
+        by_time_control: Vec::new(),
+        by_board_setup: Vec::new(),
+        openings: Vec::new(),
+    };
+
+    for record in records {
+        let results = to_result_totals(record);
+
+        if record.sort_key == "STATS#ALL" {
+            stats.overall = results;
+        } else if let Some(color) = record.color {
+            stats.by_color.push(ColorStats { color, results });
+        } else if let Some(time_control) = record.time_control {
+            stats.by_time_control.push(TimeControlStats {
+                time_control,
+                results,
+            });
+        } else if let Some(board_setup) = &record.board_setup {
+            stats.by_board_setup.push(BoardSetupStats {
+                board_setup: board_setup.clone(),
+                results,
+            });
+        } else if let (Some(eco), Some(name)) = (&record.eco, &record.opening_name) {
+            stats.openings.push(OpeningStats {
+                eco: eco.clone(),
+                name: name.clone(),
+                results,
+            });
+        }
+    }
+
+    // Openings played more often come first among those with the same score
+    stats.openings.sort_by(|a, b| {
+        b.results
+            .score_percentage
+            .total_cmp(&a.results.score_percentage)
+            .then(b.results.games.cmp(&a.results.games))
+    });
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::game::create_game;
+    use crate::helpers::opening_detection::{GamePhase, OpeningInfo};
+    use crate::types::board::BoardSetup;
+    use crate::types::game::{ColorPreference, GameEnding, State};
+
+    fn stats_record(sort_key: &str, wins: u32, draws: u32, losses: u32) -> UserStatsRecord {
+        UserStatsRecord {
+            username: "alice".to_string(),
+            sort_key: sort_key.to_string(),
+            wins,
+            draws,
+            losses,
+            total_plies: 80 * (wins + draws + losses) as u64,
+            color: None,
+            time_control: None,
+            board_setup: None,
+            eco: None,
+            opening_name: None,
+        }
+    }
+
+    fn opening_record(
+        eco: &str,
+        name: &str,
+        wins: u32,
+        draws: u32,
+        losses: u32,
+    ) -> UserStatsRecord {
+        UserStatsRecord {
+            eco: Some(eco.to_string()),
+            opening_name: Some(name.to_string()),
+            ..stats_record(&format!("STATS#OPENING#{eco}#{name}"), wins, draws, losses)
+        }
+    }
+
+    #[test]
+    fn test_get_stats_slices() {
+        let mut game = create_game(
+            None,
+            "alice",
+            Some(BoardSetup::Standard),
+            Some(ColorPreference::White),
+            None,
+            Some(60),
+            "conn-alice",
+        );
+        game.game_state.current_state_mut().state =
+            State::Finished(GameEnding::Resignation(Color::Black));
+
+        let sort_keys = |game: &GameRecord| -> Vec<String> {
+            get_stats_slices(game, Color::White)
+                .unwrap()
+                .into_iter()
+                .map(|slice| slice.sort_key)
+                .collect()
+        };
+
+        assert_eq!(
+            sort_keys(&game),
+            vec![
+                "STATS#ALL",
+                "STATS#COLOR#white",
+                "STATS#TIME-CONTROL#bullet",
+                "STATS#BOARD#\"standard\"",
+            ]
+        );
+
+        game.game_state.opening = Some(OpeningInfo {
+            eco: "C20".to_string(),
+            name: "King's Pawn Game".to_string(),
+            phase: GamePhase::Opening,
+        });
+        assert_eq!(
+            sort_keys(&game).last().unwrap(),
+            "STATS#OPENING#C20#King's Pawn Game"
+        );
+    }
+
+    #[test]
+    fn test_get_result_counts() {
+        let mut game = create_game(
+            None,
+            "alice",
+            None,
+            Some(ColorPreference::White),
+            None,
+            Some(600),
+            "conn-alice",
+        );
+        assert_eq!(get_result_counts(&game, Color::White), None);
+
+        game.game_state.current_state_mut().state =
+            State::Finished(GameEnding::Resignation(Color::Black));
+        assert_eq!(get_result_counts(&game, Color::White), Some((1, 0, 0)));
+        assert_eq!(get_result_counts(&game, Color::Black), Some((0, 0, 1)));
+    }
+
+    #[test]
+    fn test_build_user_stats() {
+        let records = vec![
+            stats_record("STATS#ALL", 3, 2, 1),
+            UserStatsRecord {
+                color: Some(Color::White),
+                ..stats_record("STATS#COLOR#white", 2, 1, 0)
+            },
+            opening_record("B20", "Sicilian Defense", 0, 1, 2),
+            opening_record("C20", "King's Pawn Game", 3, 0, 0),
+            opening_record("A00", "Polish Opening", 1, 0, 0),
+        ];
+
+        let stats = build_user_stats("alice", &records);
+
+        assert_eq!(stats.overall.games, 6);
+        assert!((stats.overall.score_percentage - 66.666_67).abs() < 0.001);
+        assert_eq!(stats.overall.average_moves, 40.0);
+        assert_eq!(stats.by_color.len(), 1);
+        assert_eq!(stats.by_color[0].results.wins, 2);
+
+        let openings: Vec<&str> = stats.openings.iter().map(|o| o.eco.as_str()).collect();
+        assert_eq!(openings, vec!["C20", "A00", "B20"]);
+    }
+}
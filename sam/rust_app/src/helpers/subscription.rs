@@ -0,0 +1,15 @@
+use crate::helpers::generic::get_timestamp;
+use crate::types::dynamo_db::SubscriptionRecord;
+
+/// Subscriptions to a game's updates (i.e. spectators) are stored under this topic
+pub fn game_topic(game_id: &str) -> String {
+    format!("GAME-{game_id}")
+}
+
+pub fn create_subscription(topic: &str, connection_id: &str) -> SubscriptionRecord {
+    SubscriptionRecord {
+        topic: topic.to_string(),
+        connection_id: connection_id.to_string(),
+        created: get_timestamp(),
+    }
+}
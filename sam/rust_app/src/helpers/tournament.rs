@@ -0,0 +1,1023 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    helpers::{
+        abort::abort_game,
+        game::{
+            create_game, get_points_earned, notify_everyone_about_game_update, prepare_game_result,
+            update_game, GameAction, GameResult, GameUpdate,
+        },
+        generic::{format_timestamp, generate_id, get_timestamp},
+    },
+    notifier::Notifier,
+    repository::{GameRepository, SubscriptionRepository, TournamentRepository, UserRepository},
+    types::{
+        api::{ApiMessage, ApiMessageType},
+        board::BoardSetup,
+        dynamo_db::{AwaitingJoinRecord, GameRecord, TournamentRecord},
+        game::{ColorPreference, GameEnding, State},
+        piece::Color,
+        presence::ConnectionState,
+        tournament::{
+            PairingResult, Standing, TournamentFormat, TournamentGame, TournamentPairing,
+            TournamentRound, TournamentState,
+        },
+    },
+};
+
+use lambda_runtime::Error;
+
+pub const MIN_TOURNAMENT_PLAYERS: usize = 2;
+pub const MAX_TOURNAMENT_PLAYERS: usize = 64;
+pub const MAX_SWISS_ROUNDS: u8 = 15;
+pub const MAX_TOURNAMENT_NAME_LENGTH: usize = 100;
+/// Pairings to try before giving up on avoiding rematches, which keeps large Swiss rounds fast
+const MAX_SWISS_PAIRING_STEPS: usize = 10_000;
+/// Value of `GameRecord.awaiting_join` while a tournament game waits for its players to join.
+/// Games without it aren't in the join index.
+pub const AWAITING_JOIN_LISTING: &str = "TOURNAMENT";
+/// Times to re-apply an action to a tournament that keeps being changed by other requests
+const MAX_TOURNAMENT_UPDATE_ATTEMPTS: usize = 5;
+
+/// A bye is worth a win
+const BYE_RESULT: PairingResult = PairingResult {
+    white_points: 1.0,
+    black_points: 0.0,
+};
+
+/// What an action applied with `update_tournament` decided to do with the tournament
+pub enum TournamentAction<T, R> {
+    /// Save the changed tournament
+    Save(T),
+    /// Leave the tournament as it was, e.g. because the action isn't allowed
+    Reject(R),
+}
+
+pub enum TournamentUpdate<T, R> {
+    Saved {
+        tournament: Box<TournamentRecord>,
+        output: T,
+    },
+    Rejected(R),
+    NotFound,
+    /// Other requests kept changing the tournament, so the action was never saved
+    Conflict,
+}
+
+/// Applies the action to the latest version of the tournament and saves it, the same way
+/// `update_game` does for games. The action may be applied more than once, so it shouldn't have
+/// side effects that can't be repeated.
+pub async fn update_tournament<T, R>(
+    repository: &impl TournamentRepository,
+    tournament_id: &str,
+    mut action: impl AsyncFnMut(&mut TournamentRecord) -> Result<TournamentAction<T, R>, Error>,
+) -> Result<TournamentUpdate<T, R>, Error> {
+    for attempt in 1..=MAX_TOURNAMENT_UPDATE_ATTEMPTS {
+        let Some(mut tournament) = repository.get_tournament(tournament_id).await? else {
+            return Ok(TournamentUpdate::NotFound);
+        };
+
+        let output = match action(&mut tournament).await? {
+            TournamentAction::Reject(rejection) => {
+                return Ok(TournamentUpdate::Rejected(rejection));
+            }
+            TournamentAction::Save(output) => output,
+        };
+
+        let expected_version = tournament.version;
+        tournament.version += 1;
+
+        if repository
+            .save_tournament(&tournament, expected_version)
+            .await?
+        {
+            return Ok(TournamentUpdate::Saved {
+                tournament: Box::new(tournament),
+                output,
+            });
+        }
+
+        tracing::warn!(
+            "Tournament (ID: {tournament_id}) was changed by another request (attempt {attempt})"
+        );
+    }
+
+    Ok(TournamentUpdate::Conflict)
+}
+
+pub fn create_tournament(
+    name: &str,
+    organizer: &str,
+    format: TournamentFormat,
+    board_setup: BoardSetup,
+    seconds_per_player: Option<usize>,
+) -> TournamentRecord {
+    TournamentRecord {
+        tournament_id: generate_id(),
+        version: 0,
+        name: name.to_string(),
+        organizer: organizer.to_string(),
+        format,
+        board_setup,
+        seconds_per_player,
+        players: Vec::new(),
+        rounds: Vec::new(),
+        state: TournamentState::Registration,
+        created: get_timestamp(),
+    }
+}
+
+/// Every player meets every other player once in a round robin. Odd player counts need an
+/// extra round because someone sits out each round.
+pub fn get_round_count(tournament: &TournamentRecord) -> usize {
+    match tournament.format {
+        TournamentFormat::RoundRobin => {
+            let player_count = tournament.players.len();
+            player_count + player_count % 2 - 1
+        }
+        TournamentFormat::Swiss { rounds } => rounds as usize,
+    }
+}
+
+pub fn is_round_complete(round: &TournamentRound) -> bool {
+    round
+        .pairings
+        .iter()
+        .all(|pairing| pairing.result.is_some())
+}
+
+/// Berger table pairings for the 0-based round, as (white, black) indices into the players.
+/// With an odd number of players, the player drawn against the phantom player gets a bye.
+pub fn get_berger_pairings(player_count: usize, round: usize) -> Vec<(usize, Option<usize>)> {
+    let table_size = player_count + player_count % 2;
+    let half = table_size / 2;
+    let last = table_size - 1; // Stays put while the others rotate
+
+    let ring: Vec<usize> = (0..last).map(|i| (round * half + i) % last).collect();
+
+    let mut pairings = vec![if round.is_multiple_of(2) {
+        (ring[0], last)
+    } else {
+        (last, ring[0])
+    }];
+
+    pairings.extend((1..half).map(|i| (ring[i], ring[last - i])));
+
+    // The phantom player only exists to give out byes
+    let mut pairings: Vec<(usize, Option<usize>)> = pairings
+        .into_iter()
+        .map(
+            |(white, black)| match (white < player_count, black < player_count) {
+                (true, true) => (white, Some(black)),
+                (true, false) => (white, None),
+                _ => (black, None),
+            },
+        )
+        .collect();
+
+    // Byes go last
+    pairings.sort_by_key(|(_, black)| black.is_none());
+    pairings
+}
+
+/// A player's record so far, as needed for Swiss pairings
+struct SwissPlayer<'a> {
+    username: &'a str,
+    seed: usize,
+    points: f32,
+    opponents: HashSet<&'a str>,
+    colors: Vec<Color>,
+    had_bye: bool,
+}
+
+impl SwissPlayer<'_> {
+    /// Number of games with white minus number of games with black
+    fn color_difference(&self) -> i32 {
+        self.colors
+            .iter()
+            .map(|color| match color {
+                Color::White => 1,
+                Color::Black => -1,
+            })
+            .sum()
+    }
+}
+
+fn get_swiss_players(tournament: &TournamentRecord) -> Vec<SwissPlayer<'_>> {
+    tournament
+        .players
+        .iter()
+        .enumerate()
+        .map(|(seed, username)| {
+            let mut player = SwissPlayer {
+                username,
+                seed,
+                points: 0.0,
+                opponents: HashSet::new(),
+                colors: Vec::new(),
+                had_bye: false,
+            };
+
+            for pairing in tournament.rounds.iter().flat_map(|round| &round.pairings) {
+                player.points += pairing.points_for(username).unwrap_or_default();
+
+                match pairing.opponent_of(username) {
+                    Some(opponent) => {
+                        player.opponents.insert(opponent);
+                        player.colors.push(if pairing.white == *username {
+                            Color::White
+                        } else {
+                            Color::Black
+                        });
+                    }
+                    None if pairing.white == *username => player.had_bye = true,
+                    None => {}
+                }
+            }
+
+            player
+        })
+        .collect()
+}
+
+/// Pairs the players (indices sorted by score, then seed). Within a score group, the top half
+/// plays the bottom half, as in the Dutch system. Players left over in a score group float down
+/// to the next one. Rematches are only made if `allow_rematches` is set, and then only when
+/// there's no other choice for a player.
+///
+/// Each pairing tried counts against `steps_left`, since avoiding rematches can take an
+/// exponential number of tries. Returns `None` if no pairing was found before they ran out.
+fn pair_score_groups(
+    players: &[SwissPlayer],
+    unpaired: &[usize],
+    allow_rematches: bool,
+    steps_left: &mut usize,
+) -> Option<Vec<(usize, usize)>> {
+    let Some((&first, _)) = unpaired.split_first() else {
+        return Some(Vec::new());
+    };
+
+    let score_group_size = unpaired
+        .iter()
+        .take_while(|&&i| players[i].points == players[first].points)
+        .count();
+
+    // Position of the first player's counterpart in the bottom half of their score group
+    let counterpart = (score_group_size / 2).max(1);
+
+    let is_rematch = |position: usize| {
+        players[first]
+            .opponents
+            .contains(players[unpaired[position]].username)
+    };
+
+    let mut candidates: Vec<usize> = (1..unpaired.len())
+        .filter(|&position| allow_rematches || !is_rematch(position))
+        .collect();
+
+    candidates.sort_by_key(|&position| {
+        (
+            is_rematch(position),
+            position.abs_diff(counterpart),
+            position < counterpart,
+        )
+    });
+
+    for position in candidates {
+        if *steps_left == 0 {
+            return None;
+        }
+        *steps_left -= 1;
+
+        let remaining: Vec<usize> = unpaired
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != 0 && i != position)
+            .map(|(_, &player)| player)
+            .collect();
+
+        if let Some(mut pairs) = pair_score_groups(players, &remaining, allow_rematches, steps_left)
+        {
+            pairs.insert(0, (first, unpaired[position]));
+            return Some(pairs);
+        }
+    }
+
+    None
+}
+
+/// Decides who gets white. Players who've had white less often get it, then players who had
+/// black last time. Otherwise the higher-ranked player alternates colours from board to board.
+fn allocate_colors<'a>(
+    higher_ranked: &'a SwissPlayer,
+    lower_ranked: &'a SwissPlayer,
+    board: usize,
+) -> (&'a str, &'a str) {
+    let higher_ranked_gets_white = match higher_ranked
+        .color_difference()
+        .cmp(&lower_ranked.color_difference())
+    {
+        std::cmp::Ordering::Less => true,
+        std::cmp::Ordering::Greater => false,
+        std::cmp::Ordering::Equal => {
+            match (higher_ranked.colors.last(), lower_ranked.colors.last()) {
+                (Some(Color::Black), Some(Color::White)) => true,
+                (Some(Color::White), Some(Color::Black)) => false,
+                (Some(last_color), _) => *last_color == Color::Black,
+                (None, _) => board.is_multiple_of(2),
+            }
+        }
+    };
+
+    if higher_ranked_gets_white {
+        (higher_ranked.username, lower_ranked.username)
+    } else {
+        (lower_ranked.username, higher_ranked.username)
+    }
+}
+
+/// Pairings for the next Swiss round as (white, black) usernames, where a missing black player
+/// is a bye. The bye goes to the lowest-ranked player who hasn't had one. Rematches are avoided
+/// unless every pairing would have one, or finding one without takes too long.
+pub fn get_swiss_pairings(tournament: &TournamentRecord) -> Vec<(String, Option<String>)> {
+    let players = get_swiss_players(tournament);
+
+    let mut ranking: Vec<usize> = (0..players.len()).collect();
+    ranking.sort_by(|&a, &b| {
+        players[b]
+            .points
+            .total_cmp(&players[a].points)
+            .then(players[a].seed.cmp(&players[b].seed))
+    });
+
+    let bye = (ranking.len() % 2 == 1).then(|| {
+        let position = ranking
+            .iter()
+            .rposition(|&i| !players[i].had_bye)
+            .unwrap_or(ranking.len() - 1);
+
+        ranking.remove(position)
+    });
+
+    let mut steps_left = MAX_SWISS_PAIRING_STEPS;
+    let pairs =
+        pair_score_groups(&players, &ranking, false, &mut steps_left).unwrap_or_else(|| {
+            tracing::warn!("Could not pair the round without rematches. Allowing them.");
+
+            // Every player can be paired with the first candidate, so this never backtracks
+            let mut steps_left = usize::MAX;
+            pair_score_groups(&players, &ranking, true, &mut steps_left)
+                .expect("An even number of players can always be paired")
+        });
+
+    let mut pairings: Vec<(String, Option<String>)> = pairs
+        .into_iter()
+        .enumerate()
+        .map(|(board, (higher_ranked, lower_ranked))| {
+            let (white, black) =
+                allocate_colors(&players[higher_ranked], &players[lower_ranked], board);
+            (white.to_string(), Some(black.to_string()))
+        })
+        .collect();
+
+    if let Some(bye) = bye {
+        pairings.push((players[bye].username.to_string(), None));
+    }
+
+    pairings
+}
+
+/// Pairs the next round. Byes are scored right away.
+pub fn get_next_round(tournament: &TournamentRecord) -> TournamentRound {
+    let pairings = match tournament.format {
+        TournamentFormat::RoundRobin => {
+            get_berger_pairings(tournament.players.len(), tournament.rounds.len())
+                .into_iter()
+                .map(|(white, black)| {
+                    (
+                        tournament.players[white].clone(),
+                        black.map(|black| tournament.players[black].clone()),
+                    )
+                })
+                .collect()
+        }
+        TournamentFormat::Swiss { .. } => get_swiss_pairings(tournament),
+    };
+
+    TournamentRound {
+        pairings: pairings
+            .into_iter()
+            .map(|(white, black)| TournamentPairing {
+                result: black.is_none().then_some(BYE_RESULT),
+                white,
+                black,
+                game_id: None,
+            })
+            .collect(),
+    }
+}
+
+/// Creates a game for each pairing of the round that isn't a bye. Both seats are reserved for
+/// the paired players, who join the games themselves.
+pub fn create_round_games(
+    tournament: &TournamentRecord,
+    round_number: usize,
+    round: &mut TournamentRound,
+) -> Vec<GameRecord> {
+    let mut games = Vec::new();
+
+    for (board, pairing) in round.pairings.iter_mut().enumerate() {
+        let Some(black) = &pairing.black else {
+            continue;
+        };
+
+        let mut game = create_game(
+            None,
+            &pairing.white,
+            Some(tournament.board_setup.clone()),
+            Some(ColorPreference::White),
+            None,
+            tournament.seconds_per_player,
+            "",
+        );
+
+        // Players take their seats by joining the game. Only logged-in users can enter tournaments.
+        game.white_connection_id = Some(ConnectionState::Disconnected);
+        game.white_authenticated = true;
+        game.black_username = Some(black.clone());
+        game.black_connection_id = Some(ConnectionState::Disconnected);
+        game.black_authenticated = true;
+        game.takebacks_allowed = false;
+        game.awaiting_join = Some(AWAITING_JOIN_LISTING.to_string());
+        game.awaiting_join_since = Some(get_timestamp());
+        game.tournament = Some(TournamentGame {
+            tournament_id: tournament.tournament_id.clone(),
+            name: tournament.name.clone(),
+            round: round_number,
+            board: board + 1,
+        });
+
+        pairing.game_id = Some(game.game_id.clone());
+        games.push(game);
+    }
+
+    games
+}
+
+/// Ends the tournament games whose players haven't both joined within the timeout, and tells
+/// everyone in them. A player who never joined forfeits to the one who did. If neither joined,
+/// the game is aborted, which counts as a loss for both. Run on a schedule by the `sweeper`
+/// Lambda and the local server.
+pub async fn forfeit_unjoined_tournament_games(
+    notifier: &impl Notifier,
+    repository: &(impl GameRepository + SubscriptionRepository + UserRepository),
+    join_timeout_seconds: i64,
+) -> Result<(), Error> {
+    let cutoff =
+        format_timestamp(chrono::Utc::now() - chrono::Duration::seconds(join_timeout_seconds));
+
+    for AwaitingJoinRecord { game_id, .. } in repository.get_games_awaiting_join(&cutoff).await? {
+        let update = update_game(repository, &game_id, async |game| {
+            let waiting = game.game_state.current_state().state == State::NotStarted;
+
+            // The index is eventually consistent, so the game may have just been started
+            if waiting
+                && game
+                    .awaiting_join_since
+                    .as_deref()
+                    .is_none_or(|since| since >= cutoff.as_str())
+            {
+                return Ok(GameAction::Reject(()));
+            }
+
+            game.awaiting_join = None;
+            game.awaiting_join_since = None;
+
+            // The game ended another way, so it's only dropped from the index
+            if !waiting {
+                return Ok(GameAction::Save(None));
+            }
+
+            let absent_colors: Vec<Color> = [
+                (Color::White, &game.white_connection_id),
+                (Color::Black, &game.black_connection_id),
+            ]
+            .into_iter()
+            .filter(|(_, connection_id)| {
+                !connection_id
+                    .as_ref()
+                    .is_some_and(ConnectionState::is_connected)
+            })
+            .map(|(color, _)| color)
+            .collect();
+
+            let [absent_color] = absent_colors[..] else {
+                abort_game(game);
+
+                return Ok(GameAction::Save(Some(
+                    "Game aborted: neither player joined in time".to_string(),
+                )));
+            };
+
+            game.game_state.current_state_mut().state =
+                State::Finished(GameEnding::Forfeit(absent_color));
+
+            let username = match absent_color {
+                Color::White => game.white_username.clone(),
+                Color::Black => game.black_username.clone(),
+            }
+            .unwrap_or_else(|| absent_color.to_string());
+
+            // Tournament games aren't rated, so there are no rating messages
+            let GameResult { writes, .. } = prepare_game_result(repository, game).await?;
+
+            Ok(GameAction::SaveWith(
+                Some(format!("{username} forfeits for not joining in time")),
+                writes,
+            ))
+        })
+        .await?;
+
+        let (game, message) = match update {
+            GameUpdate::Saved {
+                game,
+                output: Some(message),
+            } => (*game, message),
+            GameUpdate::Saved { output: None, .. }
+            | GameUpdate::Rejected(())
+            | GameUpdate::NotFound => continue,
+            // The next sweep will try again
+            GameUpdate::Conflict => {
+                tracing::warn!("Could not forfeit game (ID: {game_id}) because it kept changing");
+                continue;
+            }
+        };
+
+        let messages = vec![ApiMessage {
+            message: message.clone(),
+            message_type: ApiMessageType::Info,
+        }];
+
+        notify_everyone_about_game_update(notifier, repository, &game, messages).await?;
+
+        tracing::info!("ENDED TOURNAMENT GAME (ID: {game_id}): {message}");
+    }
+
+    Ok(())
+}
+
+/// The result of a tournament game, once it's over. Aborted games count as a loss for both.
+pub fn get_pairing_result(game: &GameRecord) -> Option<PairingResult> {
+    match game.game_state.current_state().state {
+        State::Finished(_) => Some(PairingResult {
+            white_points: get_points_earned(game, Color::White),
+            black_points: get_points_earned(game, Color::Black),
+        }),
+        State::Aborted => Some(PairingResult {
+            white_points: 0.0,
+            black_points: 0.0,
+        }),
+        _ => None,
+    }
+}
+
+/// Reads the results of the current round's finished games, and finishes the tournament once
+/// the last round is complete. Returns `true` if the tournament changed.
+pub async fn update_tournament_results(
+    repository: &impl GameRepository,
+    tournament: &mut TournamentRecord,
+) -> Result<bool, Error> {
+    let mut changed = false;
+    let is_last_round = tournament.rounds.len() >= get_round_count(tournament);
+
+    let Some(round) = tournament.rounds.last_mut() else {
+        return Ok(false);
+    };
+
+    for pairing in round.pairings.iter_mut() {
+        let (None, Some(game_id)) = (&pairing.result, &pairing.game_id) else {
+            continue;
+        };
+
+        let Some(game) = repository.get_game(game_id).await? else {
+            tracing::warn!("Tournament game with ID {game_id} not found");
+            continue;
+        };
+
+        if let Some(result) = get_pairing_result(&game) {
+            pairing.result = Some(result);
+            changed = true;
+        }
+    }
+
+    if is_last_round && is_round_complete(round) && tournament.state == TournamentState::InProgress
+    {
+        tournament.state = TournamentState::Finished;
+        changed = true;
+    }
+
+    Ok(changed)
+}
+
+/// Standings ranked by points, then the Buchholz and Sonneborn-Berger tiebreaks, then seeding.
+/// Buchholz is the sum of the opponents' scores. Sonneborn-Berger weights each opponent's score
+/// by the points scored against them. Byes don't count towards either tiebreak.
+pub fn get_standings(tournament: &TournamentRecord) -> Vec<Standing> {
+    let pairings: Vec<&TournamentPairing> = tournament
+        .rounds
+        .iter()
+        .flat_map(|round| &round.pairings)
+        .collect();
+
+    let points: HashMap<&str, f32> = tournament
+        .players
+        .iter()
+        .map(|username| {
+            let points = pairings
+                .iter()
+                .filter_map(|pairing| pairing.points_for(username))
+                .sum();
+
+            (username.as_str(), points)
+        })
+        .collect();
+
+    let mut standings: Vec<(usize, Standing)> = tournament
+        .players
+        .iter()
+        .enumerate()
+        .map(|(seed, username)| {
+            let mut standing = Standing {
+                username: username.clone(),
+                points: points[username.as_str()],
+                buchholz: 0.0,
+                sonneborn_berger: 0.0,
+            };
+
+            for pairing in pairings.iter() {
+                let (Some(opponent), Some(points_scored)) =
+                    (pairing.opponent_of(username), pairing.points_for(username))
+                else {
+                    continue;
+                };
+
+                let opponent_points = points.get(opponent).copied().unwrap_or_default();
+                standing.buchholz += opponent_points;
+                standing.sonneborn_berger += points_scored * opponent_points;
+            }
+
+            (seed, standing)
+        })
+        .collect();
+
+    standings.sort_by(|(a_seed, a), (b_seed, b)| {
+        b.points
+            .total_cmp(&a.points)
+            .then(b.buchholz.total_cmp(&a.buchholz))
+            .then(b.sonneborn_berger.total_cmp(&a.sonneborn_berger))
+            .then(a_seed.cmp(b_seed))
+    });
+
+    standings
+        .into_iter()
+        .map(|(_, standing)| standing)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::*;
+    use crate::notifier::RecordingNotifier;
+    use crate::repository::InMemoryRepository;
+
+    fn tournament(format: TournamentFormat, player_count: usize) -> TournamentRecord {
+        let mut tournament = create_tournament(
+            "Club Championship",
+            "organizer",
+            format,
+            BoardSetup::Standard,
+            Some(600),
+        );
+        tournament.players = (1..=player_count).map(|i| format!("player{i}")).collect();
+        tournament.state = TournamentState::InProgress;
+        tournament
+    }
+
+    fn play_round(tournament: &mut TournamentRecord, white_points: f32) {
+        let mut round = get_next_round(tournament);
+
+        for pairing in round.pairings.iter_mut() {
+            if pairing.black.is_some() {
+                pairing.result = Some(PairingResult {
+                    white_points,
+                    black_points: 1.0 - white_points,
+                });
+            }
+        }
+
+        tournament.rounds.push(round);
+    }
+
+    #[test]
+    fn test_berger_pairings_match_published_tables() {
+        // Berger table for 4 players (1-based): 1-4 2-3 | 4-3 1-2 | 2-4 3-1
+        let expected = [
+            vec![(0, Some(3)), (1, Some(2))],
+            vec![(3, Some(2)), (0, Some(1))],
+            vec![(1, Some(3)), (2, Some(0))],
+        ];
+
+        for (round, pairings) in expected.iter().enumerate() {
+            assert_eq!(&get_berger_pairings(4, round), pairings);
+        }
+
+        // Berger table for 6 players, round 2 (1-based): 6-4 5-3 1-2
+        assert_eq!(
+            get_berger_pairings(6, 1),
+            vec![(5, Some(3)), (4, Some(2)), (0, Some(1))]
+        );
+    }
+
+    #[test]
+    fn test_round_robin_has_everyone_meet_once() {
+        for player_count in [4, 5] {
+            let mut tournament = tournament(TournamentFormat::RoundRobin, player_count);
+
+            for _ in 0..get_round_count(&tournament) {
+                play_round(&mut tournament, 0.5);
+            }
+
+            let mut games = HashSet::new();
+            let mut byes = HashMap::new();
+
+            for pairing in tournament.rounds.iter().flat_map(|round| &round.pairings) {
+                match &pairing.black {
+                    Some(black) => {
+                        let mut players = [pairing.white.clone(), black.clone()];
+                        players.sort();
+                        assert!(games.insert(players), "Rematch: {pairing:?}");
+                    }
+                    None => *byes.entry(pairing.white.clone()).or_insert(0) += 1,
+                }
+            }
+
+            assert_eq!(games.len(), player_count * (player_count - 1) / 2);
+            assert!(byes.values().all(|&count| count == 1));
+            assert_eq!(byes.len(), player_count % 2 * player_count);
+        }
+    }
+
+    #[test]
+    fn test_swiss_pairs_score_groups_without_rematches() {
+        let mut tournament = tournament(TournamentFormat::Swiss { rounds: 3 }, 8);
+
+        // Round 1: top half plays bottom half, alternating colours down the boards
+        let round = get_next_round(&tournament);
+        let pairings: Vec<(&str, &str)> = round
+            .pairings
+            .iter()
+            .map(|p| (p.white.as_str(), p.black.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            pairings,
+            vec![
+                ("player1", "player5"),
+                ("player6", "player2"),
+                ("player3", "player7"),
+                ("player8", "player4"),
+            ]
+        );
+
+        // White wins every game, so the winners play each other in round 2
+        play_round(&mut tournament, 1.0);
+        let round = get_next_round(&tournament);
+        let winners = ["player1", "player6", "player3", "player8"];
+
+        for pairing in round.pairings.iter().take(2) {
+            assert!(winners.contains(&pairing.white.as_str()));
+            assert!(winners.contains(&pairing.black.as_deref().unwrap()));
+        }
+
+        play_round(&mut tournament, 1.0);
+        play_round(&mut tournament, 0.5);
+
+        let players = get_swiss_players(&tournament);
+        assert!(players.iter().all(|player| player.opponents.len() == 3));
+    }
+
+    #[test]
+    fn test_swiss_bye_goes_to_lowest_ranked_player_without_one() {
+        let mut tournament = tournament(TournamentFormat::Swiss { rounds: 2 }, 5);
+
+        let round = get_next_round(&tournament);
+        let bye = round.pairings.last().unwrap();
+        assert_eq!(bye.white, "player5");
+        assert!(bye.black.is_none());
+        assert_eq!(bye.result, Some(BYE_RESULT));
+
+        tournament.rounds.push(round);
+        let round = get_next_round(&tournament);
+        let bye = round.pairings.last().unwrap();
+        assert_ne!(bye.white, "player5");
+        assert!(bye.black.is_none());
+    }
+
+    #[test]
+    fn test_swiss_allows_a_rematch_when_no_pairing_avoids_one() {
+        let mut tournament = tournament(TournamentFormat::Swiss { rounds: 9 }, 64);
+
+        // The three lowest-ranked players have lost to everyone but each other. An odd number of
+        // players can't be paired among themselves, so every pairing has a rematch, and proving
+        // that by trying each one would take forever.
+        let (others, last_three) = tournament.players.split_at(61);
+        let pairings = last_three
+            .iter()
+            .flat_map(|loser| {
+                others.iter().map(|winner| TournamentPairing {
+                    white: winner.clone(),
+                    black: Some(loser.clone()),
+                    game_id: None,
+                    result: Some(PairingResult {
+                        white_points: 1.0,
+                        black_points: 0.0,
+                    }),
+                })
+            })
+            .collect();
+        tournament.rounds.push(TournamentRound { pairings });
+
+        let round = get_next_round(&tournament);
+        let players = get_swiss_players(&tournament);
+        let opponents: HashMap<&str, &HashSet<&str>> = players
+            .iter()
+            .map(|player| (player.username, &player.opponents))
+            .collect();
+
+        let mut paired = HashSet::new();
+        let mut rematch_count = 0;
+
+        for pairing in &round.pairings {
+            let black = pairing.black.as_deref().unwrap();
+            assert!(paired.insert(pairing.white.as_str()));
+            assert!(paired.insert(black));
+
+            if opponents[pairing.white.as_str()].contains(black) {
+                rematch_count += 1;
+            }
+        }
+
+        assert_eq!(paired.len(), 64);
+        assert_eq!(rematch_count, 1);
+    }
+
+    #[test]
+    fn test_standings() {
+        let mut tournament = tournament(TournamentFormat::RoundRobin, 3);
+        let pairing = |white: &str, black: Option<&str>, white_points: f32| TournamentPairing {
+            white: white.to_string(),
+            black: black.map(str::to_string),
+            game_id: None,
+            result: Some(PairingResult {
+                white_points,
+                black_points: if black.is_some() {
+                    1.0 - white_points
+                } else {
+                    0.0
+                },
+            }),
+        };
+
+        // player1 and player2 both beat player3 and draw each other. player2 had a bye.
+        tournament.rounds = vec![
+            TournamentRound {
+                pairings: vec![
+                    pairing("player1", Some("player3"), 1.0),
+                    pairing("player2", None, 1.0),
+                ],
+            },
+            TournamentRound {
+                pairings: vec![pairing("player2", Some("player3"), 1.0)],
+            },
+            TournamentRound {
+                pairings: vec![pairing("player1", Some("player2"), 0.5)],
+            },
+        ];
+
+        let standings = get_standings(&tournament);
+        let usernames: Vec<&str> = standings.iter().map(|s| s.username.as_str()).collect();
+        assert_eq!(usernames, vec!["player2", "player1", "player3"]);
+
+        assert_eq!(standings[0].points, 2.5);
+        assert_eq!(standings[0].buchholz, 1.5); // player1 (1.5) + player3 (0)
+        assert_eq!(standings[1].points, 1.5);
+        assert_eq!(standings[1].buchholz, 2.5); // player2 (2.5) + player3 (0)
+        assert_eq!(standings[1].sonneborn_berger, 1.25); // Draw with player2
+        assert_eq!(standings[2].points, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_changes_are_reapplied() {
+        let repository = InMemoryRepository::new();
+        let mut registration = tournament(TournamentFormat::RoundRobin, 0);
+        registration.state = TournamentState::Registration;
+        assert!(repository.insert_tournament(&registration).await.unwrap());
+
+        let tournament_id = registration.tournament_id.clone();
+        let mut attempts = 0;
+
+        let update = update_tournament(&repository, &tournament_id, async |tournament| {
+            attempts += 1;
+
+            // Another request registers a player before this one saves
+            if attempts == 1 {
+                let mut concurrent = tournament.clone();
+                concurrent.players.push("player1".to_string());
+                concurrent.version += 1;
+                assert!(repository
+                    .save_tournament(&concurrent, tournament.version)
+                    .await
+                    .unwrap());
+            }
+
+            tournament.players.push("player2".to_string());
+            Ok(TournamentAction::<_, Infallible>::Save(()))
+        })
+        .await
+        .unwrap();
+
+        assert!(matches!(update, TournamentUpdate::Saved { .. }));
+        assert_eq!(attempts, 2);
+
+        let saved = repository
+            .get_tournament(&tournament_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(saved.players, ["player1", "player2"]);
+        assert_eq!(saved.version, 2);
+        assert!(!repository.save_tournament(&saved, 1).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_unjoined_tournament_games_are_forfeited() {
+        let repository = InMemoryRepository::new();
+        let notifier = RecordingNotifier::new();
+        let tournament = tournament(TournamentFormat::RoundRobin, 6);
+        let mut round = get_next_round(&tournament);
+        let mut games = create_round_games(&tournament, 1, &mut round).into_iter();
+        let stale_timestamp = format_timestamp(chrono::Utc::now() - chrono::Duration::seconds(600));
+
+        // Only white joined
+        let mut forfeited_game = games.next().unwrap();
+        forfeited_game.white_connection_id = Some(ConnectionState::connected("conn-white"));
+        forfeited_game.awaiting_join_since = Some(stale_timestamp.clone());
+
+        // Nobody joined
+        let mut aborted_game = games.next().unwrap();
+        aborted_game.awaiting_join_since = Some(stale_timestamp);
+
+        // Still within the timeout
+        let fresh_game = games.next().unwrap();
+
+        for game in [&forfeited_game, &aborted_game, &fresh_game] {
+            repository.insert_game(game).await.unwrap();
+        }
+
+        forfeit_unjoined_tournament_games(&notifier, &repository, 300)
+            .await
+            .unwrap();
+
+        let get_game = async |game: &GameRecord| {
+            let game_id = &game.game_id;
+            repository.get_game(game_id).await.unwrap().unwrap()
+        };
+
+        let game = get_game(&forfeited_game).await;
+        assert_eq!(
+            game.game_state.current_state().state,
+            State::Finished(GameEnding::Forfeit(Color::Black))
+        );
+        assert!(game.awaiting_join.is_none());
+        assert_eq!(
+            get_pairing_result(&game),
+            Some(PairingResult {
+                white_points: 1.0,
+                black_points: 0.0,
+            })
+        );
+
+        let sent = notifier.sent_to("conn-white");
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0]["data"]["game_id"], forfeited_game.game_id);
+
+        let game = get_game(&aborted_game).await;
+        assert_eq!(game.game_state.current_state().state, State::Aborted);
+        assert!(game.awaiting_join.is_none());
+
+        let game = get_game(&fresh_game).await;
+        assert_eq!(game.game_state.current_state().state, State::NotStarted);
+        assert!(game.awaiting_join.is_some());
+    }
+}
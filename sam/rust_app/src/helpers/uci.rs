@@ -0,0 +1,353 @@
+//! The parts of the UCI protocol that don't touch stdin/stdout, used by the `uci` binary
+
+use chess_engine::engine::Engine;
+use chess_engine::position::Position;
+use chess_engine::types::{Board, MoveData};
+
+use crate::helpers::engine::get_engine_from_fen;
+use crate::types::game::EngineDifficulty;
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// How long to search when `go` doesn't say
+const DEFAULT_MOVETIME_MS: u64 = 3000;
+
+// Match the engine's, which it doesn't export
+const MATE_SCORE: i32 = 10_000;
+const MATE_THRESHOLD: i32 = 9000;
+
+#[derive(Debug, PartialEq)]
+pub enum UciCommand {
+    Uci,
+    IsReady,
+    UciNewGame,
+    SetOption { name: String, value: Option<String> },
+    Position { fen: String, moves: Vec<String> },
+    Go(SearchLimits),
+    Stop,
+    Quit,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct SearchLimits {
+    pub movetime: Option<u64>,
+    pub depth: Option<u16>,
+    pub wtime: Option<u64>,
+    pub btime: Option<u64>,
+    pub winc: Option<u64>,
+    pub binc: Option<u64>,
+    pub infinite: bool,
+}
+
+#[derive(Debug)]
+pub struct EngineOptions {
+    pub difficulty: Option<EngineDifficulty>, // `None` plays at full strength
+    pub own_book: bool,
+}
+
+impl Default for EngineOptions {
+    fn default() -> Self {
+        Self {
+            difficulty: None,
+            own_book: true,
+        }
+    }
+}
+
+/// Lines sent in reply to `uci`, advertising the options `setoption` accepts
+pub fn get_uci_options() -> Vec<String> {
+    vec![
+        "option name Difficulty type combo default Full var Full var Beginner var Easy var Medium var Hard var Expert var Master".to_string(),
+        "option name OwnBook type check default true".to_string(),
+    ]
+}
+
+/// Returns `None` for blank lines and commands this engine doesn't support, which the protocol
+/// says to ignore
+pub fn parse_command(line: &str) -> Option<UciCommand> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let (command, arguments) = tokens.split_first()?;
+
+    match *command {
+        "uci" => Some(UciCommand::Uci),
+        "isready" => Some(UciCommand::IsReady),
+        "ucinewgame" => Some(UciCommand::UciNewGame),
+        "setoption" => parse_setoption(arguments),
+        "position" => parse_position(arguments),
+        "go" => Some(UciCommand::Go(parse_go(arguments))),
+        "stop" => Some(UciCommand::Stop),
+        "quit" => Some(UciCommand::Quit),
+        _ => None,
+    }
+}
+
+/// e.g. `setoption name OwnBook value false`. Option names may contain spaces.
+fn parse_setoption(arguments: &[&str]) -> Option<UciCommand> {
+    let ["name", rest @ ..] = arguments else {
+        return None;
+    };
+
+    let (name, value) = match rest.iter().position(|token| *token == "value") {
+        Some(index) => (&rest[..index], Some(rest[index + 1..].join(" "))),
+        None => (rest, None),
+    };
+
+    Some(UciCommand::SetOption {
+        name: name.join(" "),
+        value,
+    })
+}
+
+/// e.g. `position startpos moves e2e4 e7e5` or `position fen <fen> moves e2e4`
+fn parse_position(arguments: &[&str]) -> Option<UciCommand> {
+    let moves_index = arguments
+        .iter()
+        .position(|token| *token == "moves")
+        .unwrap_or(arguments.len());
+    let (setup, moves) = arguments.split_at(moves_index);
+
+    let fen = match setup {
+        ["startpos"] => STARTING_FEN.to_string(),
+        ["fen", fen @ ..] if !fen.is_empty() => fen.join(" "),
+        _ => return None,
+    };
+
+    Some(UciCommand::Position {
+        fen,
+        moves: moves.iter().skip(1).map(|m| m.to_string()).collect(),
+    })
+}
+
+/// e.g. `go wtime 60000 btime 60000 winc 1000 binc 1000`. Unknown or malformed limits are ignored.
+fn parse_go(arguments: &[&str]) -> SearchLimits {
+    let mut limits = SearchLimits::default();
+    let mut tokens = arguments.iter();
+
+    while let Some(token) = tokens.next() {
+        let mut next_number = || tokens.next().and_then(|value| value.parse().ok());
+
+        match *token {
+            "movetime" => limits.movetime = next_number(),
+            "depth" => limits.depth = next_number().and_then(|depth| u16::try_from(depth).ok()),
+            "wtime" => limits.wtime = next_number(),
+            "btime" => limits.btime = next_number(),
+            "winc" => limits.winc = next_number(),
+            "binc" => limits.binc = next_number(),
+            "infinite" => limits.infinite = true,
+            _ => {}
+        }
+    }
+
+    limits
+}
+
+/// Applies a `setoption` command. Returns an error for unknown options and invalid values.
+pub fn set_option(
+    options: &mut EngineOptions,
+    name: &str,
+    value: Option<&str>,
+) -> Result<(), String> {
+    let value = value.unwrap_or_default();
+
+    match name.to_lowercase().as_str() {
+        "difficulty" => {
+            options.difficulty = match value.to_lowercase().as_str() {
+                "full" => None,
+                "beginner" => Some(EngineDifficulty::Beginner),
+                "easy" => Some(EngineDifficulty::Easy),
+                "medium" => Some(EngineDifficulty::Medium),
+                "hard" => Some(EngineDifficulty::Hard),
+                "expert" => Some(EngineDifficulty::Expert),
+                "master" => Some(EngineDifficulty::Master),
+                _ => return Err(format!("Unknown difficulty: {value}")),
+            };
+        }
+        "ownbook" => {
+            options.own_book = value
+                .parse()
+                .map_err(|_| format!("OwnBook must be true or false, not {value}"))?;
+        }
+        _ => return Err(format!("Unknown option: {name}")),
+    }
+
+    Ok(())
+}
+
+/// Sets up an engine to search the position reached by playing the moves from the FEN
+pub fn get_uci_engine(
+    fen: &str,
+    moves: &[String],
+    limits: &SearchLimits,
+    options: &EngineOptions,
+) -> Result<Engine, String> {
+    // The engine panics on a FEN it can't read
+    Position::from_fen(fen).map_err(|_| format!("Invalid FEN: {fen}"))?;
+
+    let mut engine = get_engine_from_fen(
+        fen,
+        limits.movetime.unwrap_or(DEFAULT_MOVETIME_MS),
+        options.difficulty.map(|d| d.into()),
+    );
+
+    if !options.own_book {
+        engine.book = None;
+    }
+
+    for uci_move in moves {
+        let MoveData { from, to, promote } = Board::move_from_uci_string(uci_move)?;
+
+        let is_legal = engine
+            .position
+            .get_legal_moves()
+            .contains(&Board::move_to_uci_string(from, to, promote, false));
+
+        if !is_legal || !engine.position.make_move(from, to, promote) {
+            return Err(format!("Illegal move: {uci_move}"));
+        }
+    }
+
+    let settings = &mut engine.search_settings;
+
+    if limits.infinite {
+        // Searches until `stop`, or until the deepest depth is finished
+        settings.movetime = Some(u64::MAX);
+    } else if limits.movetime.is_none() && (limits.wtime.is_some() || limits.btime.is_some()) {
+        // Let the engine budget its own time from the clock
+        settings.movetime = None;
+        settings.wtime = limits.wtime.unwrap_or(settings.wtime);
+        settings.btime = limits.btime.unwrap_or(settings.btime);
+        settings.winc = limits.winc.unwrap_or(settings.winc);
+        settings.binc = limits.binc.unwrap_or(settings.binc);
+    }
+
+    if let Some(depth) = limits.depth {
+        settings.max_depth = depth;
+
+        if limits.movetime.is_none() && limits.wtime.is_none() && limits.btime.is_none() {
+            settings.movetime = Some(u64::MAX);
+        }
+    }
+
+    Ok(engine)
+}
+
+/// The `info` line sent after each completed depth
+pub fn format_search_info(depth: u16, score: i32, position: &Position) -> String {
+    let time_ms = position.time_manager.elapsed().as_millis() as u64;
+    let nps = (position.nodes as u64 * 1000)
+        .checked_div(time_ms)
+        .unwrap_or_default();
+
+    let mut info = format!(
+        "info depth {depth} seldepth {} score {} nodes {} nps {nps} time {time_ms}",
+        position.max_depth_reached,
+        format_score(score),
+        position.nodes,
+    );
+
+    let pv: Vec<String> = position.pv_table[0][..position.pv_length[0]]
+        .iter()
+        .flatten()
+        .map(|m| Board::move_to_uci_string(m.from, m.to, m.promote, false))
+        .collect();
+
+    if !pv.is_empty() {
+        info.push_str(&format!(" pv {}", pv.join(" ")));
+    }
+
+    info
+}
+
+/// Mates are scored as `MATE_SCORE` less the number of plies to reach them. UCI wants moves.
+fn format_score(score: i32) -> String {
+    match MATE_SCORE - score.abs() {
+        plies if plies <= MATE_SCORE - MATE_THRESHOLD => {
+            format!("mate {}", score.signum() * (plies + 1) / 2)
+        }
+        _ => format!("cp {score}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_position() {
+        assert_eq!(
+            parse_command("position startpos moves e2e4 e7e5"),
+            Some(UciCommand::Position {
+                fen: STARTING_FEN.to_string(),
+                moves: vec!["e2e4".to_string(), "e7e5".to_string()],
+            })
+        );
+
+        assert_eq!(
+            parse_command("position fen 8/8/8/8/8/8/4K3/4k3 w - - 0 1"),
+            Some(UciCommand::Position {
+                fen: "8/8/8/8/8/8/4K3/4k3 w - - 0 1".to_string(),
+                moves: vec![],
+            })
+        );
+
+        assert_eq!(parse_command("position fen"), None);
+        assert_eq!(parse_command("position"), None);
+        assert_eq!(parse_command(""), None);
+    }
+
+    #[test]
+    fn test_parse_go_and_setoption() {
+        assert_eq!(
+            parse_command("go wtime 60000 btime 55000 winc 1000 binc 1000 depth 4"),
+            Some(UciCommand::Go(SearchLimits {
+                depth: Some(4),
+                wtime: Some(60000),
+                btime: Some(55000),
+                winc: Some(1000),
+                binc: Some(1000),
+                ..SearchLimits::default()
+            }))
+        );
+
+        assert_eq!(
+            parse_command("setoption name Difficulty value Hard"),
+            Some(UciCommand::SetOption {
+                name: "Difficulty".to_string(),
+                value: Some("Hard".to_string()),
+            })
+        );
+
+        let mut options = EngineOptions::default();
+        set_option(&mut options, "Difficulty", Some("Hard")).unwrap();
+        set_option(&mut options, "OwnBook", Some("false")).unwrap();
+        assert!(matches!(options.difficulty, Some(EngineDifficulty::Hard)));
+        assert!(!options.own_book);
+        assert!(set_option(&mut options, "Difficulty", Some("Impossible")).is_err());
+        assert!(set_option(&mut options, "Hash", Some("64")).is_err());
+    }
+
+    #[test]
+    fn test_format_score() {
+        assert_eq!(format_score(35), "cp 35");
+        assert_eq!(format_score(-120), "cp -120");
+        assert_eq!(format_score(MATE_SCORE - 1), "mate 1");
+        assert_eq!(format_score(MATE_SCORE - 3), "mate 2");
+        assert_eq!(format_score(-MATE_SCORE + 2), "mate -1");
+    }
+
+    #[test]
+    fn test_get_uci_engine_plays_the_moves() {
+        let moves = vec!["e2e4".to_string(), "e7e5".to_string(), "g1f3".to_string()];
+        let options = EngineOptions::default();
+
+        let mut engine =
+            get_uci_engine(STARTING_FEN, &moves, &SearchLimits::default(), &options).unwrap();
+        assert!(engine
+            .position
+            .get_legal_moves()
+            .contains(&"b8c6".to_string()));
+
+        let moves = vec!["e2e5".to_string()];
+        assert!(get_uci_engine(STARTING_FEN, &moves, &SearchLimits::default(), &options).is_err());
+    }
+}
@@ -1,115 +1,60 @@
-use std::collections::HashMap;
-
 use crate::{
-    types::dynamo_db::UserRecord,
-    utils::dynamo_db::{get_item, put_item, query_items},
+    helpers::generic::get_timestamp,
+    repository::UserRepository,
+    types::{dynamo_db::UserRecord, presence::ConnectionState},
 };
 
-use aws_sdk_dynamodb::{types::AttributeValue, Client};
 use lambda_runtime::Error;
 
-pub async fn save_user_record(
-    client: &Client,
-    table: &str,
-    user_record: &UserRecord,
-) -> Result<(), Error> {
-    put_item(client, table, user_record).await
-}
-
-async fn get_user_record(
-    client: &Client,
-    table: &str,
-    username: &str,
-    sort_key: &str,
-) -> Result<Option<UserRecord>, Error> {
-    let mut key = HashMap::new();
-    key.insert("username".into(), AttributeValue::S(username.into()));
-    key.insert("sk".into(), AttributeValue::S(sort_key.into()));
-    get_item(client, table, key).await
-}
-
-pub async fn get_user_info(
-    client: &Client,
-    table: &str,
-    username: &str,
-) -> Result<Option<UserRecord>, Error> {
-    get_user_record(client, table, username, "INFO").await
-}
-
-pub async fn get_user_game(
-    client: &Client,
-    table: &str,
+/// Returns the IDs of the connections the user is logged in on
+pub async fn get_user_connection_ids(
+    repository: &impl UserRepository,
     username: &str,
-    game_id: &str,
-) -> Result<Option<UserRecord>, Error> {
-    get_user_record(client, table, username, &format!("GAME-{game_id}")).await
-}
-
-pub async fn get_all_user_games(
-    client: &Client,
-    table: &str,
-    username: &str,
-) -> Result<Vec<UserRecord>, Error> {
-    let key_condition_expression = "username
-        = :username AND begins_with(sk, :game_prefix)"
-        .to_string();
-
-    let mut expression_attribute_values = HashMap::new();
-    expression_attribute_values.insert(
-        ":username".to_string(),
-        AttributeValue::S(username.to_string()),
-    );
-    expression_attribute_values.insert(
-        ":game_prefix".to_string(),
-        AttributeValue::S("GAME-".to_string()),
-    );
-
-    let items = query_items(
-        client,
-        table,
-        Some(key_condition_expression),
-        None,
-        Some(expression_attribute_values),
-        None,
-    )
-    .await?;
+) -> Result<Vec<String>, Error> {
+    let user_connections = repository.get_user_connections(username).await?;
 
-    Ok(items)
-}
-
-pub async fn get_user_games_from_connection_id(
-    client: &Client,
-    table: &str,
-    index: &str,
-    connection_id: &str,
-) -> Result<Vec<UserRecord>, Error> {
-    let key_condition_expression = "connection_id = :connection_id".to_string();
-
-    let mut expression_attribute_values = HashMap::new();
-    expression_attribute_values.insert(
-        ":connection_id".to_string(),
-        AttributeValue::S(connection_id.to_string()),
-    );
-
-    query_items(
-        client,
-        table,
-        Some(key_condition_expression),
-        None,
-        Some(expression_attribute_values),
-        Some(index.to_string()),
-    )
-    .await
+    Ok(user_connections
+        .into_iter()
+        .filter_map(|record| record.connection_id?.connection_id().map(str::to_string))
+        .collect())
 }
 
 pub fn create_user_game(game_id: &str, username: &str, connection_id: &str) -> UserRecord {
     let sort_key = format!("GAME-{game_id}");
+    let created = get_timestamp();
 
     UserRecord {
         username: username.to_string(),
         sort_key,
-        connection_id: Some(connection_id.to_string()),
+        connection_id: Some(ConnectionState::connected(connection_id)),
+        winner: None,
+        created: created.clone(),
+        game_created: Some(created),
+    }
+}
+
+pub fn create_user_connection(username: &str, connection_id: &str) -> UserRecord {
+    UserRecord {
+        username: username.to_string(),
+        sort_key: format!("CONNECTION-{connection_id}"),
+        connection_id: Some(ConnectionState::connected(connection_id)),
         winner: None,
-        created: chrono::Utc::now().to_rfc3339(),
+        created: get_timestamp(),
+        game_created: None,
     }
 }
+
+/// Returns the username that the connection logged in as, if any
+pub async fn get_authenticated_username(
+    repository: &impl UserRepository,
+    connection_id: &str,
+) -> Result<Option<String>, Error> {
+    let user_records = repository
+        .get_user_games_from_connection_id(connection_id)
+        .await?;
+
+    Ok(user_records
+        .into_iter()
+        .find(|record| record.sort_key.starts_with("CONNECTION-"))
+        .map(|record| record.username))
+}
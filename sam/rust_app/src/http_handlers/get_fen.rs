@@ -0,0 +1,61 @@
+use lambda_http::http::{HeaderMap, StatusCode};
+use lambda_runtime::Error;
+
+use crate::{
+    helpers::board::game_state_to_fen,
+    http_handlers::get_viewable_game,
+    repository::Repository,
+    utils::http::{
+        build_game_http_response, build_http_error, get_moves_cache_control, HttpResponse,
+        CONTENT_TYPE_TEXT,
+    },
+};
+
+/// `GET /games/{id}/fen?ply=N&token=T`. Ply 0 is the starting position, and the current position
+/// is returned if no ply is given. Private games need their invite token or a seat token.
+pub async fn get_fen(
+    repository: &impl Repository,
+    request_headers: &HeaderMap,
+    game_id: &str,
+    ply: Option<&str>,
+    token: Option<&str>,
+) -> Result<HttpResponse, Error> {
+    let ply = match ply.map(str::parse::<usize>) {
+        Some(Ok(ply)) => Some(ply),
+        Some(Err(_)) => {
+            return build_http_error(StatusCode::BAD_REQUEST, "Ply must be a whole number");
+        }
+        None => None,
+    };
+
+    let Some(game) = get_viewable_game(repository, game_id, token).await? else {
+        return build_http_error(
+            StatusCode::NOT_FOUND,
+            &format!("Game with ID `{game_id}` not found"),
+        );
+    };
+
+    let ply_count = game.game_state.ply_count();
+
+    let Some(state) = game.game_state.position_at(ply.unwrap_or(ply_count)) else {
+        return build_http_error(
+            StatusCode::NOT_FOUND,
+            &format!("Game with ID `{game_id}` only has plies 0 to {ply_count}"),
+        );
+    };
+
+    if !state.board.is_standard_board() {
+        return build_http_error(
+            StatusCode::BAD_REQUEST,
+            "FEN is only supported for standard 8x8 boards",
+        );
+    }
+
+    build_game_http_response(
+        request_headers,
+        &game,
+        CONTENT_TYPE_TEXT,
+        get_moves_cache_control(&game),
+        || Ok(game_state_to_fen(&state)),
+    )
+}
@@ -0,0 +1,95 @@
+use lambda_http::http::{HeaderMap, StatusCode};
+use lambda_runtime::Error;
+use serde::Serialize;
+
+use crate::{
+    http_handlers::get_viewable_game,
+    repository::Repository,
+    types::{
+        board::BoardSetup,
+        dynamo_db::GameRecord,
+        game::{ColorPreference, EngineDifficulty, GameState, MatchScore},
+        piece::Color,
+        tournament::TournamentGame,
+    },
+    utils::http::{
+        build_game_http_response, build_http_error, HttpResponse, CACHE_REVALIDATE,
+        CONTENT_TYPE_JSON,
+    },
+};
+
+/// The parts of a `GameRecord` anyone may see. Connection IDs, token hashes and the keys of the
+/// game table's indexes are left out.
+#[derive(Serialize)]
+struct PublicGame<'a> {
+    game_id: &'a str,
+    version: u64,
+    white_username: Option<&'a str>,
+    black_username: Option<&'a str>,
+    board_setup: &'a BoardSetup,
+    color_preference: &'a ColorPreference,
+    seconds_per_player: Option<usize>,
+    engine_difficulty: &'a Option<EngineDifficulty>,
+    game_state: &'a GameState,
+    created: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    draw_offered_by: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    takeback_requested_by: Option<Color>,
+    takebacks_allowed: bool,
+    rated: bool,
+    spectator_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    match_score: Option<&'a MatchScore>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tournament: Option<&'a TournamentGame>,
+}
+
+impl<'a> From<&'a GameRecord> for PublicGame<'a> {
+    fn from(game: &'a GameRecord) -> Self {
+        PublicGame {
+            game_id: &game.game_id,
+            version: game.version,
+            white_username: game.white_username.as_deref(),
+            black_username: game.black_username.as_deref(),
+            board_setup: &game.board_setup,
+            color_preference: &game.color_preference,
+            seconds_per_player: game.seconds_per_player,
+            engine_difficulty: &game.engine_difficulty,
+            game_state: &game.game_state,
+            created: &game.created,
+            draw_offered_by: game.draw_offered_by,
+            takeback_requested_by: game.takeback_requested_by,
+            takebacks_allowed: game.takebacks_allowed,
+            rated: game.rated,
+            spectator_count: game.spectator_count,
+            match_score: game.match_score.as_ref(),
+            tournament: game.tournament.as_ref(),
+        }
+    }
+}
+
+/// `GET /games/{id}?token=T` returns the public parts of the game record. Private games need
+/// their invite token or a seat token. It keeps changing after the game ends (e.g. as spectators
+/// come and go), so it's always revalidated.
+pub async fn get_game(
+    repository: &impl Repository,
+    request_headers: &HeaderMap,
+    game_id: &str,
+    token: Option<&str>,
+) -> Result<HttpResponse, Error> {
+    let Some(game) = get_viewable_game(repository, game_id, token).await? else {
+        return build_http_error(
+            StatusCode::NOT_FOUND,
+            &format!("Game with ID `{game_id}` not found"),
+        );
+    };
+
+    build_game_http_response(
+        request_headers,
+        &game,
+        CONTENT_TYPE_JSON,
+        CACHE_REVALIDATE,
+        || Ok(serde_json::to_string(&PublicGame::from(&game))?),
+    )
+}
@@ -0,0 +1,49 @@
+use lambda_http::http::{header, HeaderMap, StatusCode};
+use lambda_runtime::Error;
+
+use crate::{
+    helpers::pgn::game_to_pgn,
+    http_handlers::get_viewable_game,
+    repository::Repository,
+    utils::http::{
+        build_game_http_response, build_http_error, get_moves_cache_control, HttpResponse,
+        CONTENT_TYPE_PGN,
+    },
+};
+
+/// `GET /games/{id}.pgn?token=T`. Private games need their invite token or a seat token.
+pub async fn get_pgn(
+    repository: &impl Repository,
+    request_headers: &HeaderMap,
+    game_id: &str,
+    token: Option<&str>,
+) -> Result<HttpResponse, Error> {
+    let Some(game) = get_viewable_game(repository, game_id, token).await? else {
+        return build_http_error(
+            StatusCode::NOT_FOUND,
+            &format!("Game with ID `{game_id}` not found"),
+        );
+    };
+
+    let Some(pgn) = game_to_pgn(&game) else {
+        return build_http_error(
+            StatusCode::BAD_REQUEST,
+            "PGN is only supported for standard 8x8 boards",
+        );
+    };
+
+    let mut response = build_game_http_response(
+        request_headers,
+        &game,
+        CONTENT_TYPE_PGN,
+        get_moves_cache_control(&game),
+        || Ok(pgn),
+    )?;
+
+    response.headers_mut().insert(
+        header::CONTENT_DISPOSITION,
+        format!("inline; filename=\"{game_id}.pgn\"").parse()?,
+    );
+
+    Ok(response)
+}
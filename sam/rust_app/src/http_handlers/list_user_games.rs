@@ -0,0 +1,66 @@
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::{
+    helpers::history::{
+        decode_history_cursor, encode_history_cursor, to_game_history_entry,
+        DEFAULT_HISTORY_PAGE_SIZE, MAX_HISTORY_PAGE_SIZE,
+    },
+    repository::Repository,
+    types::history::GameHistoryPage,
+    utils::http::{
+        build_http_error, build_short_lived_http_response, HttpResponse, CONTENT_TYPE_JSON,
+    },
+};
+
+/// `GET /users/{name}/games?limit=N&cursor=C` lists a page of the player's games, newest first.
+/// Pass `nextCursor` back as `cursor` to get the following page. Private games are left out,
+/// since their IDs are only meant for the people they were shared with, so a page may have fewer
+/// than `limit` games.
+pub async fn list_user_games(
+    repository: &impl Repository,
+    username: &str,
+    limit: Option<&str>,
+    cursor: Option<&str>,
+) -> Result<HttpResponse, Error> {
+    let limit = match limit.map(str::parse::<usize>) {
+        None => DEFAULT_HISTORY_PAGE_SIZE,
+        Some(Ok(limit)) if (1..=MAX_HISTORY_PAGE_SIZE).contains(&limit) => limit,
+        Some(_) => {
+            return build_http_error(
+                StatusCode::BAD_REQUEST,
+                &format!("Limit must be between 1 and {MAX_HISTORY_PAGE_SIZE}"),
+            );
+        }
+    };
+
+    let cursor = match cursor.map(|cursor| decode_history_cursor(username, cursor)) {
+        None => None,
+        Some(Some(cursor)) => Some(cursor),
+        Some(None) => return build_http_error(StatusCode::BAD_REQUEST, "Invalid cursor"),
+    };
+
+    let page = repository
+        .get_user_game_history_page(username, limit, cursor)
+        .await?;
+
+    let mut games = Vec::with_capacity(page.items.len());
+
+    for user_game in &page.items {
+        let game_id = user_game.sort_key.trim_start_matches("GAME-");
+
+        // The game may have been deleted, or the user may have left it before it started
+        if let Some(game) = repository.get_game(game_id).await? {
+            if game.invite_token_hash.is_none() {
+                games.extend(to_game_history_entry(&game, username));
+            }
+        }
+    }
+
+    let next_cursor = page.next_cursor.as_ref().and_then(encode_history_cursor);
+
+    build_short_lived_http_response(
+        CONTENT_TYPE_JSON,
+        serde_json::to_string(&GameHistoryPage { games, next_cursor })?,
+    )
+}
@@ -0,0 +1,363 @@
+//! Read-only HTTP endpoints, so games can be fetched without opening a WebSocket. Served by the
+//! `rest` Lambda when deployed and by the standalone server when self-hosting.
+
+pub mod get_fen;
+pub mod get_game;
+pub mod get_pgn;
+pub mod list_user_games;
+
+use lambda_http::http::{header, request::Parts, HeaderValue, Method, StatusCode, Uri};
+use lambda_runtime::Error;
+use percent_encoding::percent_decode_str;
+
+use crate::{
+    helpers::game::can_view_game,
+    repository::Repository,
+    types::dynamo_db::GameRecord,
+    utils::http::{build_http_error, HttpResponse},
+};
+
+/// Routes the request to its handler
+pub async fn handle_http_request(
+    repository: &impl Repository,
+    request: &Parts,
+) -> Result<HttpResponse, Error> {
+    if request.method != Method::GET {
+        let mut response = build_http_error(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "Only GET requests are supported",
+        )?;
+        response
+            .headers_mut()
+            .insert(header::ALLOW, HeaderValue::from_static("GET"));
+        return Ok(response);
+    }
+
+    let segments: Vec<&str> = request.uri.path().trim_matches('/').split('/').collect();
+    let token = get_query_parameter(&request.uri, "token");
+    let token = token.as_deref();
+
+    match segments.as_slice() {
+        ["games", game_id] => match game_id.strip_suffix(".pgn") {
+            Some(game_id) => get_pgn::get_pgn(repository, &request.headers, game_id, token).await,
+            None => get_game::get_game(repository, &request.headers, game_id, token).await,
+        },
+        ["games", game_id, "fen"] => {
+            get_fen::get_fen(
+                repository,
+                &request.headers,
+                game_id,
+                get_query_parameter(&request.uri, "ply").as_deref(),
+                token,
+            )
+            .await
+        }
+        ["users", username, "games"] => {
+            list_user_games::list_user_games(
+                repository,
+                &percent_decode(username),
+                get_query_parameter(&request.uri, "limit").as_deref(),
+                get_query_parameter(&request.uri, "cursor").as_deref(),
+            )
+            .await
+        }
+        _ => build_http_error(StatusCode::NOT_FOUND, "Not found"),
+    }
+}
+
+/// Private games are reported as missing unless the request has one of their tokens, so that
+/// their existence isn't given away either
+async fn get_viewable_game(
+    repository: &impl Repository,
+    game_id: &str,
+    token: Option<&str>,
+) -> Result<Option<GameRecord>, Error> {
+    Ok(repository
+        .get_game(game_id)
+        .await?
+        .filter(|game| can_view_game(game, token)))
+}
+
+fn get_query_parameter(uri: &Uri, name: &str) -> Option<String> {
+    uri.query()?.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        (key == name).then(|| percent_decode(value))
+    })
+}
+
+/// Path segments and query parameters arrive percent-encoded, e.g. `%20` for a space
+fn percent_decode(value: &str) -> String {
+    percent_decode_str(value).decode_utf8_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use lambda_http::http::{header, Request, StatusCode};
+    use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+
+    use super::handle_http_request;
+    use crate::{
+        helpers::{
+            game::{create_game, issue_invite_token, issue_seat_token, make_move},
+            generic::get_timestamp,
+            user::create_user_game,
+        },
+        repository::{GameRepository, GameResultWrites, InMemoryRepository, UserRepository},
+        types::{
+            board::{File, Position, Rank},
+            game::{ColorPreference, PlayerMove},
+            piece::Color,
+        },
+        utils::http::HttpResponse,
+    };
+
+    const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    /// Alice (white) has played 1. e4 against Bob
+    async fn repository_with_game() -> InMemoryRepository {
+        let repository = InMemoryRepository::new();
+
+        let mut game = create_game(
+            Some("test-game"),
+            "alice",
+            None,
+            Some(ColorPreference::White),
+            None,
+            None,
+            "conn-alice",
+        );
+        game.black_username = Some("bob".to_string());
+        make_move(
+            &mut game.game_state,
+            &PlayerMove {
+                from: Position {
+                    file: File(5),
+                    rank: Rank(2),
+                },
+                to: Position {
+                    file: File(5),
+                    rank: Rank(4),
+                },
+            },
+        );
+        assert!(repository.insert_game(&game).await.unwrap());
+
+        for username in ["alice", "bob"] {
+            repository
+                .save_user_record(&create_user_game("test-game", username, "conn"))
+                .await
+                .unwrap();
+        }
+
+        repository
+    }
+
+    async fn get(repository: &InMemoryRepository, uri: &str) -> HttpResponse {
+        get_with_etag(repository, uri, None).await
+    }
+
+    async fn get_with_etag(
+        repository: &InMemoryRepository,
+        uri: &str,
+        etag: Option<&str>,
+    ) -> HttpResponse {
+        let mut request = Request::get(uri);
+
+        if let Some(etag) = etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+
+        let (parts, ()) = request.body(()).unwrap().into_parts();
+        handle_http_request(repository, &parts).await.unwrap()
+    }
+
+    fn header_value(response: &HttpResponse, name: header::HeaderName) -> &str {
+        response.headers()[name].to_str().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_get_game_revalidates_with_etag() {
+        let repository = repository_with_game().await;
+
+        let response = get(&repository, "/games/test-game").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            header_value(&response, header::CONTENT_TYPE),
+            "application/json"
+        );
+        assert_eq!(
+            header_value(&response, header::CACHE_CONTROL),
+            "public, no-cache"
+        );
+
+        let game: serde_json::Value = serde_json::from_str(response.body()).unwrap();
+        assert_eq!(game["game_id"], "test-game");
+
+        let etag = header_value(&response, header::ETAG).to_string();
+        let response = get_with_etag(&repository, "/games/test-game", Some(&etag)).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert!(response.body().is_empty());
+
+        let response = get(&repository, "/games/missing-game").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_game_leaves_out_connections_and_tokens() {
+        let repository = repository_with_game().await;
+
+        let mut game = repository.get_game("test-game").await.unwrap().unwrap();
+        issue_seat_token(&mut game, Color::White);
+        let writes = GameResultWrites::default();
+        repository
+            .save_game(&game, game.version, &writes)
+            .await
+            .unwrap();
+
+        let response = get(&repository, "/games/test-game").await;
+        let game: serde_json::Value = serde_json::from_str(response.body()).unwrap();
+        assert_eq!(game["white_username"], "alice");
+
+        for field in ["white_connection_id", "white_seat_token_hash", "lobby"] {
+            assert!(game.get(field).is_none(), "{field} was sent");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_private_games_need_a_token() {
+        let repository = InMemoryRepository::new();
+
+        let mut game = create_game(
+            Some("private-game"),
+            "alice",
+            None,
+            None,
+            None,
+            None,
+            "conn",
+        );
+        let invite_token = issue_invite_token(&mut game, 60);
+        let seat_token = issue_seat_token(&mut game, Color::White);
+        assert!(repository.insert_game(&game).await.unwrap());
+
+        for uri in [
+            "/games/private-game",
+            "/games/private-game.pgn",
+            "/games/private-game/fen",
+            "/games/private-game?token=guess",
+        ] {
+            assert_eq!(get(&repository, uri).await.status(), StatusCode::NOT_FOUND);
+        }
+
+        for token in [invite_token, seat_token] {
+            let response = get(&repository, &format!("/games/private-game?token={token}")).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(
+                header_value(&response, header::CACHE_CONTROL),
+                "private, no-cache"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_pgn() {
+        let repository = repository_with_game().await;
+
+        let response = get(&repository, "/games/test-game.pgn").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            header_value(&response, header::CONTENT_TYPE),
+            "application/x-chess-pgn"
+        );
+        assert!(response.body().contains("1. e4"));
+    }
+
+    #[tokio::test]
+    async fn test_get_fen_at_each_ply() {
+        let repository = repository_with_game().await;
+
+        let response = get(&repository, "/games/test-game/fen?ply=0").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), STARTING_FEN);
+
+        // The current position by default
+        let response = get(&repository, "/games/test-game/fen").await;
+        assert!(response
+            .body()
+            .starts_with("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b"));
+
+        let response = get(&repository, "/games/test-game/fen?ply=2").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = get(&repository, "/games/test-game/fen?ply=first").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_list_user_games() {
+        let repository = repository_with_game().await;
+
+        let response = get(&repository, "/users/bob/games").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let page: serde_json::Value = serde_json::from_str(response.body()).unwrap();
+        assert_eq!(page["games"][0]["gameId"], "test-game");
+        assert_eq!(page["games"][0]["opponent"], "alice");
+        assert!(page["nextCursor"].is_null());
+
+        let response = get(&repository, "/users/nobody/games").await;
+        let page: serde_json::Value = serde_json::from_str(response.body()).unwrap();
+        assert_eq!(page["games"], serde_json::json!([]));
+
+        let response = get(&repository, "/users/bob/games?limit=0").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = get(&repository, "/users/bob/games?cursor=nonsense").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_list_user_games_pages() {
+        let repository = repository_with_game().await;
+
+        let mut game = create_game(Some("later-game"), "bob", None, None, None, None, "conn");
+        game.white_username = Some("bob".to_string());
+        assert!(repository.insert_game(&game).await.unwrap());
+
+        let mut user_game = create_user_game("later-game", "bob", "conn");
+        user_game.game_created = Some(get_timestamp());
+        repository.save_user_record(&user_game).await.unwrap();
+
+        // "b%6Fb" is "bob"
+        let response = get(&repository, "/users/b%6Fb/games?limit=1").await;
+        let page: serde_json::Value = serde_json::from_str(response.body()).unwrap();
+        assert_eq!(page["games"].as_array().unwrap().len(), 1);
+        assert_eq!(page["games"][0]["gameId"], "later-game");
+
+        let cursor = page["nextCursor"].as_str().unwrap();
+        let uri = format!(
+            "/users/bob/games?limit=1&cursor={}",
+            utf8_percent_encode(cursor, NON_ALPHANUMERIC)
+        );
+        let response = get(&repository, &uri).await;
+        let page: serde_json::Value = serde_json::from_str(response.body()).unwrap();
+        assert_eq!(page["games"][0]["gameId"], "test-game");
+        assert!(page["nextCursor"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_routes_and_methods() {
+        let repository = repository_with_game().await;
+
+        let response = get(&repository, "/games").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let (parts, ()) = Request::post("/games/test-game")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let response = handle_http_request(&repository, &parts).await.unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(header_value(&response, header::ALLOW), "GET");
+    }
+}
@@ -1,3 +1,7 @@
 pub mod helpers;
 pub mod types;
 pub mod utils;
+pub mod repository;
+pub mod notifier;
+pub mod player_action_handlers;
+pub mod http_handlers;
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use aws_config::SdkConfig;
+use aws_lambda_events::apigw::ApiGatewayWebsocketProxyRequestContext;
+use aws_sdk_apigatewaymanagement::config;
+use aws_sdk_apigatewaymanagement::error::{DisplayErrorContext, SdkError};
+use aws_sdk_apigatewaymanagement::operation::post_to_connection::PostToConnectionError;
+use aws_sdk_apigatewaymanagement::Client;
+use lambda_runtime::Error;
+
+use super::Notifier;
+
+/// Posts to connections through the API Gateway management API. A client is built the first time
+/// an endpoint is used and reused by every later invocation of the Lambda.
+pub struct ApiGatewayNotifier {
+    sdk_config: SdkConfig,
+    clients: Mutex<HashMap<String, Client>>,
+}
+
+/// Posts to the connections of a single API
+pub struct ApiGatewayEndpointNotifier {
+    client: Client,
+}
+
+pub fn get_endpoint_url(domain_name: &str, stage: &str) -> String {
+    format!("https://{domain_name}/{stage}")
+}
+
+impl ApiGatewayNotifier {
+    pub fn new(sdk_config: &SdkConfig) -> Self {
+        Self {
+            sdk_config: sdk_config.clone(),
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn for_endpoint(&self, endpoint_url: &str) -> ApiGatewayEndpointNotifier {
+        let mut clients = self
+            .clients
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let client = clients.entry(endpoint_url.to_string()).or_insert_with(|| {
+            let api_management_config = config::Builder::from(&self.sdk_config)
+                .endpoint_url(endpoint_url)
+                .build();
+
+            Client::from_conf(api_management_config)
+        });
+
+        ApiGatewayEndpointNotifier {
+            client: client.clone(),
+        }
+    }
+
+    /// Notifies connections of the API that the request came through
+    pub fn for_request(
+        &self,
+        request_context: &ApiGatewayWebsocketProxyRequestContext,
+    ) -> ApiGatewayEndpointNotifier {
+        let domain_name = request_context.domain_name.as_ref().unwrap();
+        let stage = request_context.stage.as_ref().unwrap();
+
+        self.for_endpoint(&get_endpoint_url(domain_name, stage))
+    }
+}
+
+impl Notifier for ApiGatewayEndpointNotifier {
+    async fn send_payload(&self, connection_id: &str, payload: String) -> Result<bool, Error> {
+        match self
+            .client
+            .post_to_connection()
+            .connection_id(connection_id)
+            .data(payload.into_bytes().into())
+            .send()
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(SdkError::ServiceError(service_error)) => match service_error.err() {
+                PostToConnectionError::GoneException(e) => {
+                    tracing::warn!(
+                        "Failed to post to connection: connection ID ({connection_id}) is already disconnected: {}",
+                        e.message().unwrap_or("(No message provided)")
+                    );
+                    Ok(false)
+                }
+                _ => {
+                    tracing::error!(
+                        "Failed to post to connection: Service Error occurred: {service_error:?}"
+                    );
+                    Err(service_error.into_err().into())
+                }
+            },
+            Err(e) => {
+                tracing::error!("Failed to post to connection: {}", DisplayErrorContext(&e));
+                Err(Error::from(e))
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Pushes messages to connected clients outside of the response to their own request.
+//! `ApiGatewayNotifier` is used when deployed, `WebSocketNotifier` when self-hosting and
+//! `RecordingNotifier` in tests.
+
+pub mod api_gateway;
+pub mod recording;
+pub mod websocket;
+
+use std::future::Future;
+
+use lambda_runtime::Error;
+use serde::Serialize;
+
+use crate::{
+    helpers::subscription::game_topic,
+    repository::SubscriptionRepository,
+    types::{
+        api::{ApiMessage, ApiResponse},
+        dynamo_db::GameRecord,
+        presence::ConnectionState,
+    },
+};
+
+pub use api_gateway::ApiGatewayNotifier;
+pub use recording::RecordingNotifier;
+pub use websocket::WebSocketNotifier;
+
+pub trait Notifier: Send + Sync {
+    /// Sends the serialized message to the connection. Returns `false` if the connection is gone.
+    fn send_payload(
+        &self,
+        connection_id: &str,
+        payload: String,
+    ) -> impl Future<Output = Result<bool, Error>> + Send;
+
+    /// Returns `false` if the connection is gone
+    fn send<T: Serialize>(
+        &self,
+        connection_id: &str,
+        response: &ApiResponse<T>,
+    ) -> impl Future<Output = Result<bool, Error>> + Send {
+        let payload = serde_json::to_string(response);
+
+        async move { self.send_payload(connection_id, payload?).await }
+    }
+
+    /// Sends the game to everyone watching it, and to the connected players that `include_player`
+    /// accepts. Spectators whose connection is gone are unsubscribed, and how many were is
+    /// returned so the caller can lower the game's spectator count.
+    fn broadcast_to_game(
+        &self,
+        repository: &impl SubscriptionRepository,
+        game: &GameRecord,
+        messages: &[ApiMessage],
+        include_player: impl Fn(&str) -> bool + Send,
+    ) -> impl Future<Output = Result<usize, Error>> + Send {
+        async move {
+            let mut connection_ids: Vec<String> =
+                [&game.white_connection_id, &game.black_connection_id]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(ConnectionState::connection_id)
+                    .filter(|connection_id| include_player(connection_id))
+                    .map(str::to_string)
+                    .collect();
+            let player_count = connection_ids.len();
+            let mut unsubscribed_count = 0;
+
+            // Spectators never receive a direct response for a game update
+            if game.spectator_count > 0 {
+                let spectators = repository
+                    .get_subscribers(&game_topic(&game.game_id))
+                    .await?;
+
+                connection_ids.extend(spectators.into_iter().map(|s| s.connection_id));
+            }
+
+            for (index, connection_id) in connection_ids.iter().enumerate() {
+                let sent = self
+                    .send(
+                        connection_id,
+                        &ApiResponse {
+                            status_code: 200,
+                            connection_id: Some(connection_id.clone()),
+                            messages: messages.to_vec(),
+                            data: Some(game),
+                            replaces_game_id: None,
+                            seat_token: None,
+                            invite_token: None,
+                        },
+                    )
+                    .await?;
+
+                // The spectator's connection is gone, but the disconnect function didn't clean it up
+                if !sent && index >= player_count {
+                    repository
+                        .delete_subscription(&game_topic(&game.game_id), connection_id)
+                        .await?;
+
+                    unsubscribed_count += 1;
+                }
+            }
+
+            tracing::info!(
+                "Sent game (ID: {}) update to {} player(s) and {} spectator(s)",
+                game.game_id,
+                player_count,
+                connection_ids.len() - player_count
+            );
+
+            Ok(unsubscribed_count)
+        }
+    }
+}
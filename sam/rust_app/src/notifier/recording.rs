@@ -0,0 +1,78 @@
+use std::collections::HashSet;
+use std::sync::{Mutex, MutexGuard};
+
+use lambda_runtime::Error;
+
+use super::Notifier;
+
+/// A message sent to a connection, parsed back from JSON so its fields can be checked
+#[derive(Clone, Debug)]
+pub struct SentMessage {
+    pub connection_id: String,
+    pub payload: serde_json::Value,
+}
+
+#[derive(Default)]
+struct Recording {
+    sent: Vec<SentMessage>,
+    gone: HashSet<String>,
+}
+
+/// Records every message instead of sending it, for tests
+#[derive(Default)]
+pub struct RecordingNotifier {
+    recording: Mutex<Recording>,
+}
+
+impl RecordingNotifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Recording> {
+        self.recording
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Later messages to the connection fail as if it had closed
+    pub fn disconnect(&self, connection_id: &str) {
+        self.lock().gone.insert(connection_id.to_string());
+    }
+
+    /// Everything sent so far, in order
+    pub fn sent(&self) -> Vec<SentMessage> {
+        self.lock().sent.clone()
+    }
+
+    pub fn sent_to(&self, connection_id: &str) -> Vec<serde_json::Value> {
+        self.lock()
+            .sent
+            .iter()
+            .filter(|message| message.connection_id == connection_id)
+            .map(|message| message.payload.clone())
+            .collect()
+    }
+
+    /// Returns everything sent so far and starts a new recording
+    pub fn take(&self) -> Vec<SentMessage> {
+        std::mem::take(&mut self.lock().sent)
+    }
+}
+
+impl Notifier for RecordingNotifier {
+    async fn send_payload(&self, connection_id: &str, payload: String) -> Result<bool, Error> {
+        let mut recording = self.lock();
+
+        if recording.gone.contains(connection_id) {
+            return Ok(false);
+        }
+
+        recording.sent.push(SentMessage {
+            connection_id: connection_id.to_string(),
+            payload: serde_json::from_str(&payload)?,
+        });
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+use lambda_runtime::Error;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use super::Notifier;
+
+/// Sends to WebSocket connections held by this process, for self-hosting. Each open socket is
+/// registered with a channel, and whoever owns the socket writes what comes out of the channel to
+/// it.
+#[derive(Default)]
+pub struct WebSocketNotifier {
+    connections: Mutex<HashMap<String, UnboundedSender<String>>>,
+}
+
+impl WebSocketNotifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, UnboundedSender<String>>> {
+        self.connections
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Returns the channel of messages to write to the socket
+    pub fn register(&self, connection_id: &str) -> UnboundedReceiver<String> {
+        let (sender, receiver) = unbounded_channel();
+        self.lock().insert(connection_id.to_string(), sender);
+        receiver
+    }
+
+    /// Called once the socket has closed
+    pub fn unregister(&self, connection_id: &str) {
+        self.lock().remove(connection_id);
+    }
+}
+
+impl Notifier for WebSocketNotifier {
+    async fn send_payload(&self, connection_id: &str, payload: String) -> Result<bool, Error> {
+        let Some(sender) = self.lock().get(connection_id).cloned() else {
+            tracing::warn!("Failed to send: connection ID ({connection_id}) is not connected");
+            return Ok(false);
+        };
+
+        // The receiver is dropped when the socket's writer stops
+        Ok(sender.send(payload).is_ok())
+    }
+}
@@ -0,0 +1,89 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::notifier::Notifier;
+use crate::{
+    helpers::{
+        abort::{abort_game as abort, can_abort_game},
+        game::{
+            get_player_details_from_connection_id, notify_player_about_game_update, update_game,
+            GameAction, GameUpdate, PlayerDetails,
+        },
+    },
+    repository::Repository,
+    types::api::{ApiMessage, ApiMessageType},
+    utils::api::{build_game_conflict_response, build_response},
+};
+
+pub async fn abort_game(
+    notifier: &impl Notifier,
+    repository: &impl Repository,
+    connection_id: &str,
+    game_id: &str,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let update = update_game(repository, game_id, async |game| {
+        let Some(PlayerDetails { username, .. }) =
+            get_player_details_from_connection_id(game, connection_id)
+        else {
+            return Ok(GameAction::Reject(build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec!["You are not a player in this game".into()]),
+                None::<()>,
+            )?));
+        };
+
+        if !can_abort_game(game) {
+            return Ok(GameAction::Reject(build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec![
+                    "Games can only be aborted before both sides have moved".into(),
+                ]),
+                Some(&*game),
+            )?));
+        }
+
+        abort(game);
+
+        Ok(GameAction::Save(username))
+    })
+    .await?;
+
+    let (game, username) = match update {
+        GameUpdate::Saved { game, output } => (*game, output),
+        GameUpdate::Rejected(response) => return Ok(response),
+        GameUpdate::NotFound => {
+            return build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec![format!("Game with ID `{game_id}` not found").into()]),
+                None::<()>,
+            );
+        }
+        GameUpdate::Conflict => return build_game_conflict_response(connection_id),
+    };
+
+    notify_player_about_game_update(
+        notifier,
+        repository,
+        connection_id,
+        &game,
+        Some(vec![ApiMessage {
+            message: format!("{username} aborted the game"),
+            message_type: ApiMessageType::Info,
+        }]),
+        false,
+    )
+    .await?;
+
+    tracing::info!("Player {username} aborted game {game_id}");
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        None,
+        Some(game),
+    )
+}
@@ -0,0 +1,172 @@
+use std::convert::Infallible;
+
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::notifier::Notifier;
+use crate::{
+    helpers::{
+        abort::abort_game,
+        challenge::get_challenge,
+        engine::refresh_available_moves,
+        game::{
+            assign_player_to_existing_or_remaining_slot, check_if_both_players_just_joined,
+            create_game, get_player_color, insert_game_with_generated_id, issue_seat_token,
+            set_seat_authenticated, update_game, GameAction,
+        },
+        presence::notify_friends_of_started_game,
+        user::{create_user_game, get_authenticated_username, get_user_connection_ids},
+    },
+    repository::Repository,
+    types::api::{ApiMessage, ApiMessageType, ApiResponse},
+    utils::api::{build_response, build_seated_player_response},
+};
+
+pub async fn accept_challenge(
+    notifier: &impl Notifier,
+    repository: &impl Repository,
+    connection_id: &str,
+    challenge_id: &str,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let Some(username) = get_authenticated_username(repository, connection_id).await? else {
+        return build_response(
+            StatusCode::UNAUTHORIZED,
+            Some(connection_id.to_string()),
+            Some(vec!["You must be logged in to accept a challenge".into()]),
+            None::<()>,
+        );
+    };
+
+    let Some(challenge) = get_challenge(repository, &username, challenge_id).await? else {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec!["Challenge not found or expired".into()]),
+            None::<()>,
+        );
+    };
+
+    // The connection the challenge was sent from may have closed since, so the game is started on
+    // one the challenger is still logged in on. The challenge is kept for when they're back.
+    let challenger_connection_ids =
+        get_user_connection_ids(repository, &challenge.challenger).await?;
+
+    let Some(challenger_connection_id) = challenger_connection_ids
+        .iter()
+        .find(|id| **id == challenge.challenger_connection_id)
+        .or(challenger_connection_ids.first())
+    else {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec![format!(
+                "{} is no longer online",
+                challenge.challenger
+            )
+            .into()]),
+            None::<()>,
+        );
+    };
+
+    // Another connection of the same user may have answered it in the meantime
+    if !repository.claim_challenge(&challenge).await? {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec!["Challenge has already been answered".into()]),
+            None::<()>,
+        );
+    }
+
+    let mut new_game = create_game(
+        None,
+        &challenge.challenger,
+        Some(challenge.board_setup.clone()),
+        Some(challenge.color_preference),
+        None,
+        challenge.seconds_per_player,
+        challenger_connection_id,
+    );
+
+    // Only logged-in users can send challenges
+    let challenger_seat_token = get_player_color(&new_game, &challenge.challenger).map(|color| {
+        set_seat_authenticated(&mut new_game, color, true);
+        issue_seat_token(&mut new_game, color)
+    });
+
+    let seat_token = assign_player_to_existing_or_remaining_slot(
+        &mut new_game,
+        &username,
+        connection_id,
+        None,
+        None,
+        true,
+    )?;
+
+    new_game.rated = challenge.rated;
+    let game_started = check_if_both_players_just_joined(&mut new_game);
+
+    // Populate legal moves for the initial board state
+    refresh_available_moves(&mut new_game);
+
+    insert_game_with_generated_id(repository, &mut new_game).await?;
+
+    let challenger_notified = notifier
+        .send(
+            challenger_connection_id,
+            &ApiResponse {
+                status_code: 200,
+                connection_id: Some(challenger_connection_id.clone()),
+                messages: vec![ApiMessage {
+                    message: format!("{username} accepted your challenge!"),
+                    message_type: ApiMessageType::Success,
+                }],
+                data: Some(&new_game),
+                replaces_game_id: None,
+                seat_token: challenger_seat_token,
+                invite_token: None,
+            },
+        )
+        .await?;
+
+    // The challenger disconnected just now, and can't rejoin a game they never heard about
+    if !challenger_notified {
+        update_game(repository, &new_game.game_id, async |game| {
+            abort_game(game);
+            Ok(GameAction::<_, Infallible>::Save(()))
+        })
+        .await?;
+
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec![format!(
+                "{} is no longer online",
+                challenge.challenger
+            )
+            .into()]),
+            None::<()>,
+        );
+    }
+
+    if game_started {
+        notify_friends_of_started_game(notifier, repository, &new_game).await?;
+    }
+
+    for (player_username, player_connection_id) in [
+        (username.as_str(), connection_id),
+        (challenge.challenger.as_str(), challenger_connection_id),
+    ] {
+        let user_game = create_user_game(&new_game.game_id, player_username, player_connection_id);
+        repository.save_user_record(&user_game).await?;
+    }
+
+    tracing::info!(
+        "PLAYER {username} ACCEPTED A CHALLENGE FROM {} (game ID: {})",
+        challenge.challenger,
+        new_game.game_id
+    );
+
+    build_seated_player_response(connection_id, new_game, seat_token, None)
+}
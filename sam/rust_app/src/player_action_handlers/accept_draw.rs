@@ -1,102 +1,102 @@
-use aws_lambda_events::apigw::{ApiGatewayProxyResponse, ApiGatewayWebsocketProxyRequestContext};
-use aws_sdk_dynamodb::Client;
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
 use lambda_http::http::StatusCode;
 use lambda_runtime::Error;
 
-use chess::{
+use crate::notifier::Notifier;
+use crate::{
     helpers::game::{
-        get_game, get_player_details_from_connection_id, handle_if_game_is_finished,
-        is_game_over, notify_player_about_game_update, save_game, PlayerDetails,
+        get_player_details_from_connection_id, is_game_over, notify_player_about_game_update,
+        prepare_game_result, update_game, GameAction, GameResult, GameUpdate,
     },
+    repository::Repository,
     types::game::{GameEnding, State},
-    utils::api::build_response,
+    utils::api::{build_game_conflict_response, build_response},
 };
 
 pub async fn accept_draw(
-    sdk_config: &aws_config::SdkConfig,
-    request_context: &ApiGatewayWebsocketProxyRequestContext,
-    dynamo_db_client: &Client,
+    notifier: &impl Notifier,
+    repository: &impl Repository,
     connection_id: &str,
-    game_table: &str,
-    user_table: &str,
     game_id: &str,
 ) -> Result<ApiGatewayProxyResponse, Error> {
-    match get_game(dynamo_db_client, game_table, game_id).await? {
-        None => build_response(
-            StatusCode::BAD_REQUEST,
-            Some(connection_id.to_string()),
-            Some(vec![format!("Game with ID `{game_id}` not found").into()]),
-            None::<()>,
-        ),
-        Some(mut game) => {
-            let Some(PlayerDetails {
-                color: player_color,
-                username,
-                opponent_username,
-            }) = get_player_details_from_connection_id(&game, connection_id)
-            else {
-                return build_response(
-                    StatusCode::BAD_REQUEST,
-                    Some(connection_id.to_string()),
-                    Some(vec!["You are not a player in this game".into()]),
-                    None::<()>,
-                );
-            };
+    let update = update_game(repository, game_id, async |game| {
+        let Some(player_details) = get_player_details_from_connection_id(game, connection_id)
+        else {
+            return Ok(GameAction::Reject(build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec!["You are not a player in this game".into()]),
+                None::<()>,
+            )?));
+        };
+
+        if is_game_over(game) {
+            return Ok(GameAction::Reject(build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec!["Game is already over".into()]),
+                None::<()>,
+            )?));
+        }
 
-            if is_game_over(&game) {
-                return build_response(
+        match game.draw_offered_by {
+            Some(color) if color != player_details.color => {}
+            _ => {
+                return Ok(GameAction::Reject(build_response(
                     StatusCode::BAD_REQUEST,
                     Some(connection_id.to_string()),
-                    Some(vec!["Game is already over".into()]),
+                    Some(vec!["No draw offer to accept".into()]),
                     None::<()>,
-                );
+                )?));
             }
+        }
 
-            match game.draw_offered_by {
-                Some(color) if color != player_color => {}
-                _ => {
-                    return build_response(
-                        StatusCode::BAD_REQUEST,
-                        Some(connection_id.to_string()),
-                        Some(vec!["No draw offer to accept".into()]),
-                        None::<()>,
-                    );
-                }
-            }
+        game.draw_offered_by = None;
+        game.game_state.current_state_mut().state =
+            State::Finished(GameEnding::DrawByMutualAgreement);
 
-            game.draw_offered_by = None;
-            game.game_state.current_state_mut().state =
-                State::Finished(GameEnding::DrawByMutualAgreement);
+        let GameResult {
+            writes,
+            messages: rating_messages,
+        } = prepare_game_result(repository, game).await?;
 
-            save_game(dynamo_db_client, game_table, &game).await?;
+        Ok(GameAction::SaveWith(
+            (player_details.username, rating_messages),
+            writes,
+        ))
+    })
+    .await?;
 
-            handle_if_game_is_finished(
-                dynamo_db_client,
-                user_table,
-                &username,
-                opponent_username.as_deref(),
-                &game.game_state,
-            )
-            .await?;
+    let (game, (username, rating_messages)) = match update {
+        GameUpdate::Saved { game, output } => (*game, output),
+        GameUpdate::Rejected(response) => return Ok(response),
+        GameUpdate::NotFound => {
+            return build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec![format!("Game with ID `{game_id}` not found").into()]),
+                None::<()>,
+            );
+        }
+        GameUpdate::Conflict => return build_game_conflict_response(connection_id),
+    };
 
-            notify_player_about_game_update(
-                sdk_config,
-                request_context,
-                connection_id,
-                &game,
-                None,
-                false,
-            )
-            .await?;
+    notify_player_about_game_update(
+        notifier,
+        repository,
+        connection_id,
+        &game,
+        Some(rating_messages.clone()),
+        false,
+    )
+    .await?;
 
-            tracing::info!("Player {username} accepted a draw in game {game_id}");
+    tracing::info!("Player {username} accepted a draw in game {game_id}");
 
-            build_response(
-                StatusCode::OK,
-                Some(connection_id.to_string()),
-                None,
-                Some(game),
-            )
-        }
-    }
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        Some(rating_messages),
+        Some(game),
+    )
 }
@@ -0,0 +1,121 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::notifier::Notifier;
+use crate::{
+    helpers::{
+        presence::{create_friend, MAX_FRIENDS},
+        user::{get_authenticated_username, get_user_connection_ids},
+    },
+    repository::Repository,
+    types::api::{ApiMessage, ApiMessageType, ApiResponse},
+    utils::api::build_response,
+};
+
+pub async fn add_friend(
+    notifier: &impl Notifier,
+    repository: &impl Repository,
+    connection_id: &str,
+    friend: &str,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let Some(username) = get_authenticated_username(repository, connection_id).await? else {
+        return build_response(
+            StatusCode::UNAUTHORIZED,
+            Some(connection_id.to_string()),
+            Some(vec!["You must be logged in to add friends".into()]),
+            None::<()>,
+        );
+    };
+
+    if friend == username {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec!["You cannot add yourself as a friend".into()]),
+            None::<()>,
+        );
+    }
+
+    if repository.get_user_info(friend).await?.is_none() {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec![format!("User `{friend}` not found").into()]),
+            None::<()>,
+        );
+    }
+
+    let added_friends = repository.get_added_friends(&username).await?;
+
+    if added_friends.iter().any(|added| added == friend) {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec![format!("You have already added {friend}").into()]),
+            None::<()>,
+        );
+    }
+
+    if added_friends.len() >= MAX_FRIENDS {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec![format!(
+                "You cannot add more than {MAX_FRIENDS} friends"
+            )
+            .into()]),
+            None::<()>,
+        );
+    }
+
+    repository
+        .save_friend(&create_friend(&username, friend))
+        .await?;
+
+    let is_mutual = repository.has_added_friend(friend, &username).await?;
+
+    let friend_message = match is_mutual {
+        true => format!("You and {username} are now friends"),
+        false => {
+            format!("{username} added you as a friend. Add them back to see when they're online.")
+        }
+    };
+
+    for friend_connection_id in get_user_connection_ids(repository, friend).await? {
+        notifier
+            .send(
+                &friend_connection_id,
+                &ApiResponse {
+                    status_code: 200,
+                    connection_id: Some(friend_connection_id.clone()),
+                    messages: vec![ApiMessage {
+                        message: friend_message.clone(),
+                        message_type: ApiMessageType::Info,
+                    }],
+                    data: None::<()>,
+                    replaces_game_id: None,
+                    seat_token: None,
+                    invite_token: None,
+                },
+            )
+            .await?;
+    }
+
+    tracing::info!("USER {username} ADDED {friend} AS A FRIEND (mutual: {is_mutual})");
+
+    let message = match is_mutual {
+        true => format!("You and {friend} are now friends"),
+        false => format!("Added {friend}. You'll be friends once they add you back."),
+    };
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        Some(vec![ApiMessage {
+            message,
+            message_type: ApiMessageType::Success,
+        }]),
+        None::<()>,
+    )
+}
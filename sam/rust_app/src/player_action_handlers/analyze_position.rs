@@ -1,18 +1,16 @@
 use aws_lambda_events::apigw::{ApiGatewayProxyResponse, ApiGatewayWebsocketProxyRequestContext};
-use aws_sdk_dynamodb::Client;
 use aws_sdk_lambda::primitives::Blob;
 use aws_sdk_lambda::types::InvocationType;
 use lambda_http::http::StatusCode;
 use lambda_runtime::Error;
 use tracing::info;
 
-use chess::{
+use crate::{
     helpers::{
-        board::game_state_to_fen,
-        game::{get_game, get_player_details_from_connection_id},
-        opening_detection::GamePhase,
-        pgn::build_pgn_movetext,
+        board::game_state_to_fen, game::get_player_details_from_connection_id,
+        opening_detection::GamePhase, pgn::build_pgn_movetext,
     },
+    repository::Repository,
     types::game::AnalysisType,
     utils::api::build_response,
 };
@@ -20,13 +18,12 @@ use chess::{
 pub async fn analyze_position(
     sdk_config: &aws_config::SdkConfig,
     request_context: &ApiGatewayWebsocketProxyRequestContext,
-    dynamo_db_client: &Client,
-    game_table: &str,
+    repository: &impl Repository,
     connection_id: &str,
     game_id: &str,
     analysis_type: AnalysisType,
 ) -> Result<ApiGatewayProxyResponse, Error> {
-    let Some(game) = get_game(dynamo_db_client, game_table, game_id).await? else {
+    let Some(game) = repository.get_game(game_id).await? else {
         return build_response(
             StatusCode::NOT_FOUND,
             Some(connection_id.to_string()),
@@ -44,11 +41,7 @@ pub async fn analyze_position(
         );
     }
 
-    let current_state = game
-        .game_state
-        .history
-        .last()
-        .expect("Game history should not be empty");
+    let current_state = game.game_state.current_state();
 
     if !current_state.board.is_standard_board() {
         return build_response(
@@ -0,0 +1,31 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::repository::Repository;
+use crate::{helpers::seek::delete_seeks_from_connection_id, utils::api::build_response};
+
+pub async fn cancel_seek(
+    repository: &impl Repository,
+    connection_id: &str,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let deleted_count = delete_seeks_from_connection_id(repository, connection_id).await?;
+
+    if deleted_count == 0 {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec!["You are not seeking a game".into()]),
+            None::<()>,
+        );
+    }
+
+    tracing::info!("CONNECTION {connection_id} CANCELLED ITS SEEK");
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        None,
+        None::<()>,
+    )
+}
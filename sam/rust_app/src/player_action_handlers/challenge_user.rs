@@ -0,0 +1,105 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::notifier::Notifier;
+use crate::{
+    helpers::{
+        challenge::create_challenge,
+        user::{get_authenticated_username, get_user_connection_ids},
+    },
+    repository::Repository,
+    types::{
+        api::{ApiMessage, ApiMessageType, ApiResponse},
+        board::BoardSetup,
+        game::ColorPreference,
+    },
+    utils::api::build_response,
+};
+
+pub async fn challenge_user(
+    notifier: &impl Notifier,
+    repository: &impl Repository,
+    connection_id: &str,
+    username: &str,
+    board_setup: Option<BoardSetup>,
+    color_preference: Option<ColorPreference>,
+    seconds_per_player: Option<usize>,
+    rated: Option<bool>,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let Some(challenger) = get_authenticated_username(repository, connection_id).await? else {
+        return build_response(
+            StatusCode::UNAUTHORIZED,
+            Some(connection_id.to_string()),
+            Some(vec![
+                "You must be logged in to challenge other players".into()
+            ]),
+            None::<()>,
+        );
+    };
+
+    if username == challenger {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec!["You cannot challenge yourself".into()]),
+            None::<()>,
+        );
+    }
+
+    // Only logged-in users can be found by username
+    let challenged_connection_ids = get_user_connection_ids(repository, username).await?;
+
+    if challenged_connection_ids.is_empty() {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec![format!("{username} is not online").into()]),
+            None::<()>,
+        );
+    }
+
+    let challenge = create_challenge(
+        &challenger,
+        connection_id,
+        username,
+        board_setup.unwrap_or(BoardSetup::Standard),
+        color_preference.unwrap_or(ColorPreference::Random),
+        seconds_per_player,
+        rated.unwrap_or(false),
+    );
+
+    repository.save_challenge(&challenge).await?;
+
+    for challenged_connection_id in &challenged_connection_ids {
+        notifier
+            .send(
+                challenged_connection_id,
+                &ApiResponse {
+                    status_code: 200,
+                    connection_id: Some(challenged_connection_id.clone()),
+                    messages: vec![ApiMessage {
+                        message: format!("{challenger} has challenged you to a game"),
+                        message_type: ApiMessageType::Info,
+                    }],
+                    data: Some(&challenge),
+                    replaces_game_id: None,
+                    seat_token: None,
+                    invite_token: None,
+                },
+            )
+            .await?;
+    }
+
+    tracing::info!("PLAYER {challenger} CHALLENGED {username}");
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        Some(vec![ApiMessage {
+            message: format!("Challenge sent to {username}"),
+            message_type: ApiMessageType::Success,
+        }]),
+        Some(challenge),
+    )
+}
@@ -1,64 +1,141 @@
-use aws_lambda_events::apigw::{ApiGatewayProxyResponse, ApiGatewayWebsocketProxyRequestContext};
-use aws_sdk_dynamodb::Client;
-use chess::helpers::engine::use_engine;
-use chess::types::board::BoardSetup;
-use chess::types::game::{ColorPreference, EngineDifficulty};
+use crate::helpers::auth::resolve_username;
+use crate::helpers::engine::use_engine_in_new_game;
+use crate::helpers::lobby::{notify_lobby_subscribers, update_lobby_listing};
+use crate::helpers::presence::notify_friends_of_started_game;
+use crate::notifier::Notifier;
+use crate::repository::Repository;
+use crate::types::board::BoardSetup;
+use crate::types::game::{ColorPreference, EngineDifficulty};
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
 use lambda_http::http::StatusCode;
 use lambda_runtime::Error;
 
-use chess::helpers::game::{check_if_both_players_just_joined, create_game, get_game, save_game};
-use chess::helpers::user::{create_user_game, save_user_record};
-use chess::utils::api::build_response;
+use crate::helpers::game::{
+    check_if_both_players_just_joined, create_game, get_player_color,
+    insert_game_with_generated_id, issue_invite_token, issue_seat_token, set_seat_authenticated,
+    validate_game_id, DEFAULT_INVITE_EXPIRY_MINUTES, MAX_INVITE_EXPIRY_MINUTES,
+};
+use crate::helpers::user::create_user_game;
+use crate::utils::api::{
+    build_private_game_response, build_response, build_seated_player_response,
+};
+
+fn build_game_id_taken_response(
+    connection_id: &str,
+    game_id: &str,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    build_response(
+        StatusCode::BAD_REQUEST,
+        Some(connection_id.to_string()),
+        Some(vec![format!(
+            "Game with ID `{game_id}` already exists. Please join the game instead."
+        )
+        .into()]),
+        None::<()>,
+    )
+}
 
 pub async fn create_new_game(
-    sdk_config: &aws_config::SdkConfig,
-    request_context: &ApiGatewayWebsocketProxyRequestContext,
-    dynamo_db_client: &Client,
-    game_table: &str,
-    user_table: &str,
+    notifier: &impl Notifier,
+    repository: &impl Repository,
     connection_id: &str,
-    username: &str,
+    username: Option<&str>,
     game_id: Option<&str>,
     board_setup: Option<BoardSetup>,
     color_preference: Option<ColorPreference>,
     engine_difficulty: Option<EngineDifficulty>,
     seconds_per_player: Option<usize>,
+    allow_takebacks: Option<bool>,
+    rated: Option<bool>,
+    private: Option<bool>,
+    invite_expires_in_minutes: Option<u32>,
 ) -> Result<ApiGatewayProxyResponse, Error> {
-    if username.trim().is_empty() {
+    let resolved_username = match resolve_username(repository, connection_id, username).await? {
+        Ok(resolved_username) => resolved_username,
+        Err(e) => {
+            return build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec![e.into()]),
+                None::<()>,
+            );
+        }
+    };
+    let username = resolved_username.username.as_str();
+
+    // Validate that engine difficulty is only set for standard 8x8 boards
+    if engine_difficulty.is_some() && !matches!(board_setup, Some(BoardSetup::Standard) | None) {
         return build_response(
             StatusCode::BAD_REQUEST,
             Some(connection_id.to_string()),
-            Some(vec!["Must provide a username".into()]),
+            Some(vec![
+                "Engine difficulty can only be set for standard 8x8 boards".into(),
+            ]),
             None::<()>,
         );
     }
 
-    // Validate that engine difficulty is only set for standard 8x8 boards
-    if engine_difficulty.is_some() && !matches!(board_setup, Some(BoardSetup::Standard) | None) {
+    let rated = rated.unwrap_or(false);
+
+    if rated && engine_difficulty.is_some() {
         return build_response(
             StatusCode::BAD_REQUEST,
             Some(connection_id.to_string()),
-            Some(vec![
-                "Engine difficulty can only be set for standard 8x8 boards".into(),
-            ]),
+            Some(vec!["Games against the engine cannot be rated".into()]),
+            None::<()>,
+        );
+    }
+
+    if rated && !resolved_username.authenticated {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec!["You must be logged in to play rated games".into()]),
+            None::<()>,
+        );
+    }
+
+    let private = private.unwrap_or(false);
+
+    if private && engine_difficulty.is_some() {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec!["Games against the engine cannot be private".into()]),
+            None::<()>,
+        );
+    }
+
+    let invite_expires_in_minutes =
+        invite_expires_in_minutes.unwrap_or(DEFAULT_INVITE_EXPIRY_MINUTES);
+
+    if private && !(1..=MAX_INVITE_EXPIRY_MINUTES).contains(&invite_expires_in_minutes) {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec![format!(
+                "Invites must expire within 1 to {MAX_INVITE_EXPIRY_MINUTES} minutes"
+            )
+            .into()]),
             None::<()>,
         );
     }
 
     let mut new_game = match game_id {
         Some(game_id) => {
-            if (get_game(dynamo_db_client, game_table, game_id).await?).is_some() {
+            if let Err(e) = validate_game_id(game_id) {
                 return build_response(
                     StatusCode::BAD_REQUEST,
                     Some(connection_id.to_string()),
-                    Some(vec![format!(
-                        "Game with ID `{game_id}` already exists. Please join the game instead."
-                    )
-                    .into()]),
+                    Some(vec![e.into()]),
                     None::<()>,
                 );
             }
 
+            if (repository.get_game(game_id).await?).is_some() {
+                return build_game_id_taken_response(connection_id, game_id);
+            }
+
             create_game(
                 Some(game_id),
                 username,
@@ -80,13 +157,42 @@ pub async fn create_new_game(
         ),
     };
 
+    new_game.takebacks_allowed = allow_takebacks.unwrap_or(true);
+    new_game.rated = rated;
+
+    let seat_token = get_player_color(&new_game, username).map(|color| {
+        set_seat_authenticated(&mut new_game, color, resolved_username.authenticated);
+        issue_seat_token(&mut new_game, color)
+    });
+
+    let invite_token = private
+        .then(|| issue_invite_token(&mut new_game, i64::from(invite_expires_in_minutes) * 60));
+
     // Ensure state is set to 'in progress' when playing the engine
-    if new_game.engine_difficulty.is_some() {
-        check_if_both_players_just_joined(&mut new_game);
+    let game_started =
+        new_game.engine_difficulty.is_some() && check_if_both_players_just_joined(&mut new_game);
+
+    use_engine_in_new_game(&mut new_game);
+
+    let lobby_listing_changed = update_lobby_listing(&mut new_game);
+
+    match game_id {
+        // Another player may have taken the ID since it was checked
+        Some(game_id) => {
+            if !repository.insert_game(&new_game).await? {
+                return build_game_id_taken_response(connection_id, game_id);
+            }
+        }
+        None => insert_game_with_generated_id(repository, &mut new_game).await?,
     }
 
-    use_engine(&mut new_game, sdk_config, request_context, connection_id).await?;
-    save_game(dynamo_db_client, game_table, &new_game).await?;
+    if game_started {
+        notify_friends_of_started_game(notifier, repository, &new_game).await?;
+    }
+
+    if lobby_listing_changed {
+        notify_lobby_subscribers(notifier, repository, &new_game).await?;
+    }
 
     tracing::info!(
         "Created new game record (ID: {}) for user ({username})",
@@ -94,7 +200,7 @@ pub async fn create_new_game(
     );
 
     let new_user_game = create_user_game(&new_game.game_id, username, connection_id);
-    save_user_record(dynamo_db_client, user_table, &new_user_game).await?;
+    repository.save_user_record(&new_user_game).await?;
 
     tracing::info!(
         "Created new user-game record for {username} (ID: {})",
@@ -106,10 +212,10 @@ pub async fn create_new_game(
         new_game.game_id
     );
 
-    build_response(
-        StatusCode::OK,
-        Some(connection_id.to_string()),
-        None,
-        Some(new_game),
-    )
+    match invite_token {
+        Some(invite_token) => {
+            build_private_game_response(connection_id, new_game, seat_token, invite_token)
+        }
+        None => build_seated_player_response(connection_id, new_game, seat_token, None),
+    }
 }
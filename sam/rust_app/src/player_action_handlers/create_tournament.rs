@@ -0,0 +1,93 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::{
+    helpers::{
+        tournament::{
+            create_tournament as create, get_standings, MAX_SWISS_ROUNDS,
+            MAX_TOURNAMENT_NAME_LENGTH,
+        },
+        user::get_authenticated_username,
+    },
+    repository::Repository,
+    types::{
+        board::BoardSetup,
+        tournament::{TournamentDetails, TournamentFormat},
+    },
+    utils::api::build_response,
+};
+
+pub async fn create_tournament(
+    repository: &impl Repository,
+    connection_id: &str,
+    name: &str,
+    format: TournamentFormat,
+    board_setup: Option<BoardSetup>,
+    seconds_per_player: Option<usize>,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let Some(organizer) = get_authenticated_username(repository, connection_id).await? else {
+        return build_response(
+            StatusCode::UNAUTHORIZED,
+            Some(connection_id.to_string()),
+            Some(vec!["You must be logged in to organize a tournament".into()]),
+            None::<()>,
+        );
+    };
+
+    if name.is_empty() || name.chars().count() > MAX_TOURNAMENT_NAME_LENGTH {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec![format!(
+                "Tournament names must be between 1 and {MAX_TOURNAMENT_NAME_LENGTH} characters"
+            )
+            .into()]),
+            None::<()>,
+        );
+    }
+
+    if let TournamentFormat::Swiss { rounds } = format {
+        if rounds == 0 || rounds > MAX_SWISS_ROUNDS {
+            return build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec![format!(
+                    "Swiss tournaments must have between 1 and {MAX_SWISS_ROUNDS} rounds"
+                )
+                .into()]),
+                None::<()>,
+            );
+        }
+    }
+
+    let tournament = create(
+        name,
+        &organizer,
+        format,
+        board_setup.unwrap_or(BoardSetup::Standard),
+        seconds_per_player,
+    );
+
+    if !repository.insert_tournament(&tournament).await? {
+        return Err(Error::from(format!(
+            "Tournament ID {} is taken",
+            tournament.tournament_id
+        )));
+    }
+
+    tracing::info!(
+        "PLAYER {organizer} CREATED TOURNAMENT (ID: {}): {tournament:?}",
+        tournament.tournament_id
+    );
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        None,
+        Some(TournamentDetails {
+            standings: get_standings(&tournament),
+            tournament,
+        }),
+    )
+}
@@ -0,0 +1,72 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::notifier::Notifier;
+use crate::{
+    helpers::{challenge::get_challenge, user::get_authenticated_username},
+    repository::Repository,
+    types::api::{ApiMessage, ApiMessageType, ApiResponse},
+    utils::api::build_response,
+};
+
+pub async fn decline_challenge(
+    notifier: &impl Notifier,
+    repository: &impl Repository,
+    connection_id: &str,
+    challenge_id: &str,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let Some(username) = get_authenticated_username(repository, connection_id).await? else {
+        return build_response(
+            StatusCode::UNAUTHORIZED,
+            Some(connection_id.to_string()),
+            Some(vec!["You must be logged in to decline a challenge".into()]),
+            None::<()>,
+        );
+    };
+
+    let challenge = match get_challenge(repository, &username, challenge_id).await? {
+        Some(challenge) if repository.claim_challenge(&challenge).await? => challenge,
+        _ => {
+            return build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec!["Challenge not found or expired".into()]),
+                None::<()>,
+            );
+        }
+    };
+
+    notifier
+        .send(
+            &challenge.challenger_connection_id,
+            &ApiResponse {
+                status_code: 200,
+                connection_id: Some(challenge.challenger_connection_id.clone()),
+                messages: vec![ApiMessage {
+                    message: format!("{username} declined your challenge"),
+                    message_type: ApiMessageType::Info,
+                }],
+                data: None::<()>,
+                replaces_game_id: None,
+                seat_token: None,
+                invite_token: None,
+            },
+        )
+        .await?;
+
+    tracing::info!(
+        "PLAYER {username} DECLINED A CHALLENGE FROM {}",
+        challenge.challenger
+    );
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        Some(vec![ApiMessage {
+            message: "Challenge declined".to_string(),
+            message_type: ApiMessageType::Info,
+        }]),
+        None::<()>,
+    )
+}
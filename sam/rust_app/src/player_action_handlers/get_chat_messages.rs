@@ -0,0 +1,20 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::{repository::Repository, utils::api::build_response};
+
+pub async fn get_chat_messages(
+    repository: &impl Repository,
+    connection_id: &str,
+    game_id: &str,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let chat_messages = repository.get_chat_messages(game_id, None).await?;
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        None,
+        Some(chat_messages),
+    )
+}
@@ -1,12 +1,10 @@
 use aws_lambda_events::apigw::ApiGatewayProxyResponse;
-use aws_sdk_dynamodb::Client;
 use lambda_http::http::StatusCode;
 use lambda_runtime::Error;
 use serde::Serialize;
 
-use chess::{
-    helpers::{board::game_state_to_fen, game::get_game},
-    utils::api::build_response,
+use crate::{
+    helpers::board::game_state_to_fen, repository::Repository, utils::api::build_response,
 };
 
 #[derive(Serialize)]
@@ -15,21 +13,19 @@ pub struct FenResult {
 }
 
 pub async fn get_fen(
-    dynamo_db_client: &Client,
+    repository: &impl Repository,
     connection_id: &str,
-    game_table: &str,
     game_id: &str,
     history_index: usize,
 ) -> Result<ApiGatewayProxyResponse, Error> {
-    match get_game(dynamo_db_client, game_table, game_id).await? {
+    match repository.get_game(game_id).await? {
         Some(game) => {
             let fen = game
                 .game_state
-                .history
-                .get(history_index)
+                .position_at(history_index)
                 .and_then(|state| {
                     if state.board.is_standard_board() {
-                        Some(game_state_to_fen(state))
+                        Some(game_state_to_fen(&state))
                     } else {
                         None
                     }
@@ -1,17 +1,16 @@
 use aws_lambda_events::apigw::ApiGatewayProxyResponse;
-use aws_sdk_dynamodb::Client;
 use lambda_http::http::StatusCode;
 use lambda_runtime::Error;
 
-use chess::{helpers::game::get_game, utils::api::build_response};
+use crate::repository::Repository;
+use crate::utils::api::build_response;
 
 pub async fn get_game_state(
-    dynamo_db_client: &Client,
+    repository: &impl Repository,
     connection_id: &str,
-    game_table: &str,
     game_id: &str,
 ) -> Result<ApiGatewayProxyResponse, Error> {
-    match get_game(dynamo_db_client, game_table, game_id).await? {
+    match repository.get_game(game_id).await? {
         Some(game) => {
             tracing::info!("Retrieved game state (ID: {})", game_id);
             build_response(
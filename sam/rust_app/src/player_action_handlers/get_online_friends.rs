@@ -0,0 +1,40 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::{
+    helpers::{
+        presence::get_online_friends as get_online_friends_of_user,
+        user::get_authenticated_username,
+    },
+    repository::Repository,
+    utils::api::build_response,
+};
+
+pub async fn get_online_friends(
+    repository: &impl Repository,
+    connection_id: &str,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let Some(username) = get_authenticated_username(repository, connection_id).await? else {
+        return build_response(
+            StatusCode::UNAUTHORIZED,
+            Some(connection_id.to_string()),
+            Some(vec!["You must be logged in to see your friends".into()]),
+            None::<()>,
+        );
+    };
+
+    let online_friends = get_online_friends_of_user(repository, &username).await?;
+
+    tracing::info!(
+        "Found {} online friend(s) for user {username}",
+        online_friends.len()
+    );
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        None,
+        Some(online_friends),
+    )
+}
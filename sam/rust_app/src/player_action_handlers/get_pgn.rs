@@ -1,13 +1,9 @@
 use aws_lambda_events::apigw::ApiGatewayProxyResponse;
-use aws_sdk_dynamodb::Client;
 use lambda_http::http::StatusCode;
 use lambda_runtime::Error;
 use serde::Serialize;
 
-use chess::{
-    helpers::{game::get_game, pgn::game_to_pgn},
-    utils::api::build_response,
-};
+use crate::{helpers::pgn::game_to_pgn, repository::Repository, utils::api::build_response};
 
 #[derive(Serialize)]
 pub struct PgnResult {
@@ -15,12 +11,11 @@ pub struct PgnResult {
 }
 
 pub async fn get_pgn(
-    dynamo_db_client: &Client,
+    repository: &impl Repository,
     connection_id: &str,
-    game_table: &str,
     game_id: &str,
 ) -> Result<ApiGatewayProxyResponse, Error> {
-    match get_game(dynamo_db_client, game_table, game_id).await? {
+    match repository.get_game(game_id).await? {
         Some(game) => {
             let pgn = game_to_pgn(&game);
 
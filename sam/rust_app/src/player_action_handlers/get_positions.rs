@@ -0,0 +1,64 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+use serde::Serialize;
+
+use crate::{repository::Repository, types::game::PositionSnapshot, utils::api::build_response};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionsResult {
+    pub game_id: String,
+    pub positions: Vec<PositionSnapshot>,
+}
+
+/// Games only keep some of their past positions, so clients ask for the others as they're
+/// viewed. The positions rebuilt along the way are sent too, since they're likely to be viewed
+/// next.
+pub async fn get_positions(
+    repository: &impl Repository,
+    connection_id: &str,
+    game_id: &str,
+    ply: usize,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let Some(game) = repository.get_game(game_id).await? else {
+        return build_response(
+            StatusCode::NOT_FOUND,
+            Some(connection_id.to_string()),
+            Some(vec![format!("Game with ID `{game_id}` not found").into()]),
+            None::<()>,
+        );
+    };
+
+    let Some(positions) = game.game_state.positions_leading_to(ply) else {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec![format!(
+                "Game with ID `{game_id}` only has plies 0 to {}",
+                game.game_state.ply_count()
+            )
+            .into()]),
+            None::<()>,
+        );
+    };
+
+    let first_ply = ply + 1 - positions.len();
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        None,
+        Some(PositionsResult {
+            game_id: game.game_id,
+            positions: positions
+                .into_iter()
+                .enumerate()
+                .map(|(index, position)| PositionSnapshot {
+                    ply: first_ply + index,
+                    position,
+                })
+                .collect(),
+        }),
+    )
+}
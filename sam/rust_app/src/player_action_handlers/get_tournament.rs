@@ -0,0 +1,55 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::{
+    helpers::tournament::{
+        get_standings, update_tournament, update_tournament_results, TournamentAction,
+        TournamentUpdate,
+    },
+    repository::Repository,
+    types::tournament::TournamentDetails,
+    utils::api::{build_response, build_tournament_conflict_response},
+};
+
+/// Returns the tournament with its pairings and standings, including any newly finished games
+pub async fn get_tournament(
+    repository: &impl Repository,
+    connection_id: &str,
+    tournament_id: &str,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let update = update_tournament(repository, tournament_id, async |tournament| {
+        match update_tournament_results(repository, tournament).await? {
+            true => Ok(TournamentAction::Save(())),
+            false => Ok(TournamentAction::Reject(tournament.clone())),
+        }
+    })
+    .await?;
+
+    let tournament = match update {
+        TournamentUpdate::Saved { tournament, .. } => *tournament,
+        TournamentUpdate::Rejected(tournament) => tournament,
+        TournamentUpdate::NotFound => {
+            return build_response(
+                StatusCode::NOT_FOUND,
+                Some(connection_id.to_string()),
+                Some(vec![format!(
+                    "Tournament with ID `{tournament_id}` not found"
+                )
+                .into()]),
+                None::<()>,
+            );
+        }
+        TournamentUpdate::Conflict => return build_tournament_conflict_response(connection_id),
+    };
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        None,
+        Some(TournamentDetails {
+            standings: get_standings(&tournament),
+            tournament,
+        }),
+    )
+}
@@ -0,0 +1,45 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::{
+    helpers::{stats::build_user_stats, user::get_authenticated_username},
+    repository::Repository,
+    utils::api::build_response,
+};
+
+pub async fn get_user_stats(
+    repository: &impl Repository,
+    connection_id: &str,
+    username: Option<&str>,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    // Anyone's stats can be looked up, but only logged-in players can leave out the username
+    let username = match username {
+        Some(username) => username.to_string(),
+        None => match get_authenticated_username(repository, connection_id).await? {
+            Some(username) => username,
+            None => {
+                return build_response(
+                    StatusCode::BAD_REQUEST,
+                    Some(connection_id.to_string()),
+                    Some(vec!["Log in or provide a username to see stats for".into()]),
+                    None::<()>,
+                );
+            }
+        },
+    };
+
+    let records = repository.get_user_stats_records(&username).await?;
+
+    tracing::info!(
+        "Fetched {} stats records for user {username}",
+        records.len()
+    );
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        None,
+        Some(build_user_stats(&username, &records)),
+    )
+}
@@ -0,0 +1,24 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::{
+    helpers::user::get_authenticated_username, repository::Repository, utils::api::build_response,
+};
+
+/// Keeps the connection alive. Logged-in users are also marked as recently seen.
+pub async fn heartbeat(
+    repository: &impl Repository,
+    connection_id: &str,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    if let Some(username) = get_authenticated_username(repository, connection_id).await? {
+        repository.update_last_seen(&username).await?;
+    }
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        None,
+        None::<()>,
+    )
+}
@@ -1,85 +1,99 @@
-use aws_lambda_events::apigw::{ApiGatewayProxyResponse, ApiGatewayWebsocketProxyRequestContext};
-use aws_sdk_dynamodb::Client;
-use chess::helpers::engine::use_engine;
-use chess::types::api::ApiMessage;
+use crate::helpers::auth::resolve_username;
+use crate::helpers::engine::{play_engine_reply, refresh_available_moves};
+use crate::helpers::lobby::{notify_lobby_subscribers, update_lobby_listing};
+use crate::helpers::presence::notify_friends_of_started_game;
+use crate::notifier::Notifier;
+use crate::repository::Repository;
+use crate::types::api::ApiMessage;
+use crate::types::presence::ConnectionState;
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
 use lambda_http::http::StatusCode;
 use lambda_runtime::Error;
 
-use chess::helpers::game::{
-    assign_player_to_existing_or_remaining_slot, check_if_both_players_just_joined, get_game,
-    notify_player_about_game_update, save_game,
+use crate::helpers::game::{
+    assign_player_to_existing_or_remaining_slot, check_if_both_players_just_joined,
+    notify_player_about_game_update, update_game, GameAction, GameUpdate,
+};
+use crate::helpers::user::create_user_game;
+use crate::utils::api::{
+    build_game_conflict_response, build_response, build_seated_player_response,
 };
-use chess::helpers::user::{create_user_game, get_user_game, save_user_record};
-use chess::utils::api::build_response;
 
 pub async fn join_game(
-    sdk_config: &aws_config::SdkConfig,
-    request_context: &ApiGatewayWebsocketProxyRequestContext,
-    dynamo_db_client: &Client,
-    game_table: &str,
-    user_table: &str,
+    notifier: &impl Notifier,
+    repository: &impl Repository,
     connection_id: &str,
-    username: &str,
+    username: Option<&str>,
     game_id: &str,
+    seat_token: Option<&str>,
+    invite_token: Option<&str>,
 ) -> Result<ApiGatewayProxyResponse, Error> {
-    if username.trim().is_empty() {
-        return build_response(
-            StatusCode::BAD_REQUEST,
-            Some(connection_id.to_string()),
-            Some(vec!["Must provide a username".into()]),
-            None::<()>,
-        );
-    }
-
-    let game = match get_game(dynamo_db_client, game_table, game_id).await? {
-        Some(mut existing_game) => {
-            tracing::info!(
-                "Found existing game (ID: {}) for user ({username}) to try to join",
-                existing_game.game_id
+    let resolved_username = match resolve_username(repository, connection_id, username).await? {
+        Ok(resolved_username) => resolved_username,
+        Err(e) => {
+            return build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec![e.into()]),
+                None::<()>,
             );
+        }
+    };
+    let username = resolved_username.username.as_str();
 
-            if let Err(err) = assign_player_to_existing_or_remaining_slot(
-                &mut existing_game,
-                username,
-                connection_id,
-            ) {
-                return build_response(
+    let update = update_game(repository, game_id, async |game| {
+        tracing::info!(
+            "Found existing game (ID: {}) for user ({username}) to try to join",
+            game.game_id
+        );
+
+        let issued_seat_token = match assign_player_to_existing_or_remaining_slot(
+            game,
+            username,
+            connection_id,
+            seat_token,
+            invite_token,
+            resolved_username.authenticated,
+        ) {
+            Ok(issued_seat_token) => issued_seat_token,
+            Err(err) => {
+                return Ok(GameAction::Reject(build_response(
                     StatusCode::BAD_REQUEST,
                     Some(connection_id.to_string()),
                     Some(vec![err.to_string().into()]),
                     None::<()>,
-                );
+                )?));
             }
+        };
 
-            check_if_both_players_just_joined(&mut existing_game);
+        let game_started = check_if_both_players_just_joined(game);
 
-            tracing::info!(
-                "User ({username}) joined game (ID: {}) as {}",
-                existing_game.game_id,
-                if existing_game
-                    .white_username
-                    .as_ref()
-                    .is_some_and(|u| u == username)
-                {
-                    "white"
-                } else {
-                    "black"
-                }
-            );
+        tracing::info!(
+            "User ({username}) joined game (ID: {}) as {}",
+            game.game_id,
+            if game.white_username.as_ref().is_some_and(|u| u == username) {
+                "white"
+            } else {
+                "black"
+            }
+        );
 
-            use_engine(
-                &mut existing_game,
-                sdk_config,
-                request_context,
-                connection_id,
-            )
-            .await?;
+        refresh_available_moves(game);
 
-            save_game(dynamo_db_client, game_table, &existing_game).await?;
+        let lobby_listing_changed = update_lobby_listing(game);
 
-            existing_game
-        }
-        None => {
+        Ok(GameAction::Save((
+            issued_seat_token,
+            game_started,
+            lobby_listing_changed,
+        )))
+    })
+    .await?;
+
+    let (game, (issued_seat_token, game_started, lobby_listing_changed)) = match update {
+        GameUpdate::Saved { game, output } => (*game, output),
+        GameUpdate::Rejected(response) => return Ok(response),
+        GameUpdate::NotFound => {
             return build_response(
                 StatusCode::BAD_REQUEST,
                 Some(connection_id.to_string()),
@@ -90,22 +104,34 @@ pub async fn join_game(
                 None::<()>,
             );
         }
+        GameUpdate::Conflict => return build_game_conflict_response(connection_id),
     };
 
+    // e.g. when rejoining an engine game whose move was never saved
+    let (game, _) = play_engine_reply(notifier, repository, connection_id, game).await?;
+
+    if game_started {
+        notify_friends_of_started_game(notifier, repository, &game).await?;
+    }
+
+    if lobby_listing_changed {
+        notify_lobby_subscribers(notifier, repository, &game).await?;
+    }
+
     // Retrieve or create a new user-game record and assign user's connection ID to it
-    match get_user_game(dynamo_db_client, user_table, username, &game.game_id).await? {
+    match repository.get_user_game(username, &game.game_id).await? {
         Some(mut found_user_game) => {
-            found_user_game.connection_id = Some(connection_id.to_string());
+            found_user_game.connection_id = Some(ConnectionState::connected(connection_id));
 
             tracing::info!(
                 "Found existing user-game record for user ({username}) and game (ID: {})",
                 found_user_game.sort_key.trim_end_matches("GAME-"),
             );
-            save_user_record(dynamo_db_client, user_table, &found_user_game).await?;
+            repository.save_user_record(&found_user_game).await?;
         }
         None => {
             let new_user_game = create_user_game(&game.game_id, username, connection_id);
-            save_user_record(dynamo_db_client, user_table, &new_user_game).await?;
+            repository.save_user_record(&new_user_game).await?;
             tracing::info!(
                 "Created new user-game record for user ({username}) (ID: {})",
                 new_user_game.sort_key.trim_end_matches("GAME-")
@@ -114,13 +140,13 @@ pub async fn join_game(
     };
 
     notify_player_about_game_update(
-        sdk_config,
-        request_context,
+        notifier,
+        repository,
         connection_id,
         &game,
         Some(vec![ApiMessage {
             message: format!("{username} has joined the game"),
-            message_type: chess::types::api::ApiMessageType::Success,
+            message_type: crate::types::api::ApiMessageType::Success,
         }]),
         false,
     )
@@ -128,10 +154,5 @@ pub async fn join_game(
 
     tracing::info!("PLAYER {username} JOINED GAME (ID: {})", game.game_id);
 
-    build_response(
-        StatusCode::OK,
-        Some(connection_id.to_string()),
-        None,
-        Some(game),
-    )
+    build_seated_player_response(connection_id, game, issued_seat_token, None)
 }
@@ -0,0 +1,98 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::{
+    helpers::{
+        tournament::{
+            get_standings, update_tournament, TournamentAction, TournamentUpdate,
+            MAX_TOURNAMENT_PLAYERS,
+        },
+        user::get_authenticated_username,
+    },
+    repository::Repository,
+    types::tournament::{TournamentDetails, TournamentState},
+    utils::api::{build_response, build_tournament_conflict_response},
+};
+
+/// Registers the player for a tournament that hasn't started yet
+pub async fn join_tournament(
+    repository: &impl Repository,
+    connection_id: &str,
+    tournament_id: &str,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let Some(username) = get_authenticated_username(repository, connection_id).await? else {
+        return build_response(
+            StatusCode::UNAUTHORIZED,
+            Some(connection_id.to_string()),
+            Some(vec!["You must be logged in to join a tournament".into()]),
+            None::<()>,
+        );
+    };
+
+    let update = update_tournament(repository, tournament_id, async |tournament| {
+        if tournament.state != TournamentState::Registration {
+            return Ok(TournamentAction::Reject(build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec!["Registration for this tournament is closed".into()]),
+                None::<()>,
+            )?));
+        }
+
+        if tournament.players.contains(&username) {
+            return Ok(TournamentAction::Reject(build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec!["You are already registered for this tournament".into()]),
+                None::<()>,
+            )?));
+        }
+
+        if tournament.players.len() >= MAX_TOURNAMENT_PLAYERS {
+            return Ok(TournamentAction::Reject(build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec![format!(
+                    "Tournaments are limited to {MAX_TOURNAMENT_PLAYERS} players"
+                )
+                .into()]),
+                None::<()>,
+            )?));
+        }
+
+        tournament.players.push(username.clone());
+
+        Ok(TournamentAction::Save(()))
+    })
+    .await?;
+
+    let tournament = match update {
+        TournamentUpdate::Saved { tournament, .. } => *tournament,
+        TournamentUpdate::Rejected(response) => return Ok(response),
+        TournamentUpdate::NotFound => {
+            return build_response(
+                StatusCode::NOT_FOUND,
+                Some(connection_id.to_string()),
+                Some(vec![format!(
+                    "Tournament with ID `{tournament_id}` not found"
+                )
+                .into()]),
+                None::<()>,
+            );
+        }
+        TournamentUpdate::Conflict => return build_tournament_conflict_response(connection_id),
+    };
+
+    tracing::info!("PLAYER {username} JOINED TOURNAMENT (ID: {tournament_id})");
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        None,
+        Some(TournamentDetails {
+            standings: get_standings(&tournament),
+            tournament,
+        }),
+    )
+}
@@ -1,36 +1,32 @@
-use aws_lambda_events::apigw::{ApiGatewayProxyResponse, ApiGatewayWebsocketProxyRequestContext};
-use aws_sdk_dynamodb::Client;
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
 use lambda_http::http::StatusCode;
 use lambda_runtime::Error;
 
-use chess::{
-    helpers::{
-        game::{
-            get_game, get_player_details_from_connection_id,
-            mark_user_as_disconnected_and_notify_other_player, PlayerDetails,
-        },
-        user::{get_user_game, save_user_record},
+use crate::notifier::Notifier;
+use crate::{
+    helpers::game::{
+        get_player_details_from_connection_id, mark_user_as_disconnected_and_notify_other_player,
+        PlayerDetails,
     },
+    repository::Repository,
+    types::presence::ConnectionState,
     utils::api::build_response,
 };
 
 pub async fn leave_game(
-    sdk_config: &aws_config::SdkConfig,
-    request_context: &ApiGatewayWebsocketProxyRequestContext,
-    dynamo_db_client: &Client,
+    notifier: &impl Notifier,
+    repository: &impl Repository,
     connection_id: &str,
-    game_table: &str,
-    user_table: &str,
     game_id: &str,
 ) -> Result<ApiGatewayProxyResponse, Error> {
-    match get_game(dynamo_db_client, game_table, game_id).await? {
+    match repository.get_game(game_id).await? {
         None => build_response(
             StatusCode::NOT_FOUND,
             Some(connection_id.to_string()),
             Some(vec![format!("Game with ID `{game_id}` not found").into()]),
             None::<()>,
         ),
-        Some(mut game) => {
+        Some(game) => {
             let Some(PlayerDetails { username, .. }) =
                 get_player_details_from_connection_id(&game, connection_id)
             else {
@@ -42,22 +38,18 @@ pub async fn leave_game(
                 );
             };
 
-            let mut user_game = get_user_game(dynamo_db_client, user_table, &username, game_id)
+            let mut user_game = repository
+                .get_user_game(&username, game_id)
                 .await?
                 .unwrap_or_else(|| {
                     panic!("User game should exist for player {username} leaving game {game_id}")
                 });
 
-            user_game.connection_id = Some("<disconnected>".to_string());
-            save_user_record(dynamo_db_client, user_table, &user_game).await?;
+            user_game.connection_id = Some(ConnectionState::Disconnected);
+            repository.save_user_record(&user_game).await?;
 
             mark_user_as_disconnected_and_notify_other_player(
-                sdk_config,
-                request_context,
-                dynamo_db_client,
-                game_table,
-                &mut game,
-                &username,
+                notifier, repository, game_id, &username,
             )
             .await?;
 
@@ -0,0 +1,24 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::{helpers::lobby::LOBBY_TOPIC, repository::Repository, utils::api::build_response};
+
+/// Stops sending lobby updates to the connection
+pub async fn leave_lobby(
+    repository: &impl Repository,
+    connection_id: &str,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    repository
+        .delete_subscription(LOBBY_TOPIC, connection_id)
+        .await?;
+
+    tracing::info!("CONNECTION {connection_id} LEFT THE LOBBY");
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        None,
+        None::<()>,
+    )
+}
@@ -0,0 +1,91 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::{
+    helpers::{
+        history::{
+            decode_history_cursor, encode_history_cursor, to_game_history_entry,
+            DEFAULT_HISTORY_PAGE_SIZE, MAX_HISTORY_PAGE_SIZE,
+        },
+        user::get_authenticated_username,
+    },
+    repository::Repository,
+    types::history::GameHistoryPage,
+    utils::api::build_response,
+};
+
+pub async fn list_my_games(
+    repository: &impl Repository,
+    connection_id: &str,
+    cursor: Option<&str>,
+    limit: Option<usize>,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let Some(username) = get_authenticated_username(repository, connection_id).await? else {
+        return build_response(
+            StatusCode::UNAUTHORIZED,
+            Some(connection_id.to_string()),
+            Some(vec!["You must be logged in to see your games".into()]),
+            None::<()>,
+        );
+    };
+
+    let limit = limit.unwrap_or(DEFAULT_HISTORY_PAGE_SIZE);
+
+    if limit == 0 || limit > MAX_HISTORY_PAGE_SIZE {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec![format!(
+                "Limit must be between 1 and {MAX_HISTORY_PAGE_SIZE}"
+            )
+            .into()]),
+            None::<()>,
+        );
+    }
+
+    let cursor = match cursor {
+        Some(cursor) => match decode_history_cursor(&username, cursor) {
+            Some(cursor) => Some(cursor),
+            None => {
+                return build_response(
+                    StatusCode::BAD_REQUEST,
+                    Some(connection_id.to_string()),
+                    Some(vec!["Invalid cursor".into()]),
+                    None::<()>,
+                );
+            }
+        },
+        None => None,
+    };
+
+    let page = repository
+        .get_user_game_history_page(&username, limit, cursor)
+        .await?;
+
+    let mut games = Vec::with_capacity(page.items.len());
+
+    for user_game in &page.items {
+        let game_id = user_game.sort_key.trim_start_matches("GAME-");
+
+        // The game may have been deleted, or the user may have left it before it started
+        if let Some(game) = repository.get_game(game_id).await? {
+            games.extend(to_game_history_entry(&game, &username));
+        }
+    }
+
+    let next_cursor = page.next_cursor.as_ref().and_then(encode_history_cursor);
+
+    tracing::info!(
+        "Listed {} games for user {username} (more: {})",
+        games.len(),
+        next_cursor.is_some()
+    );
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        None,
+        Some(GameHistoryPage { games, next_cursor }),
+    )
+}
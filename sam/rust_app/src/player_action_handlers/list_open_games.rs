@@ -0,0 +1,36 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::{
+    helpers::{
+        lobby::{get_open_games, LOBBY_TOPIC},
+        subscription::create_subscription,
+    },
+    repository::Repository,
+    utils::api::build_response,
+};
+
+/// Lists the games waiting for an opponent and subscribes the connection to lobby updates
+pub async fn list_open_games(
+    repository: &impl Repository,
+    connection_id: &str,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let open_games = get_open_games(repository).await?;
+
+    repository
+        .save_subscription(&create_subscription(LOBBY_TOPIC, connection_id))
+        .await?;
+
+    tracing::info!(
+        "CONNECTION {connection_id} IS BROWSING THE LOBBY ({} open game(s))",
+        open_games.len()
+    );
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        None,
+        Some(open_games),
+    )
+}
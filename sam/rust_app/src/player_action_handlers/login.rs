@@ -0,0 +1,58 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+use serde::Serialize;
+
+use crate::notifier::Notifier;
+use crate::{
+    helpers::{
+        auth::{log_in_connection, verify_password},
+        presence::handle_user_connected,
+    },
+    repository::Repository,
+    utils::api::build_response,
+};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthResult {
+    pub username: String,
+    pub session_token: String,
+}
+
+pub async fn login(
+    notifier: &impl Notifier,
+    repository: &impl Repository,
+    connection_id: &str,
+    username: &str,
+    password: &str,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let user_info = repository.get_user_info(username).await?;
+
+    // Same message whether the user doesn't exist or the password is wrong
+    let Some(user_info) = user_info.filter(|info| verify_password(password, &info.password_hash))
+    else {
+        return build_response(
+            StatusCode::UNAUTHORIZED,
+            Some(connection_id.to_string()),
+            Some(vec!["Incorrect username or password".into()]),
+            None::<()>,
+        );
+    };
+
+    let session_token = log_in_connection(repository, &user_info.username, connection_id).await?;
+
+    handle_user_connected(notifier, repository, &user_info.username).await?;
+
+    tracing::info!("USER {username} LOGGED IN (CONNECTION ID: {connection_id})");
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        None,
+        Some(AuthResult {
+            username: user_info.username,
+            session_token,
+        }),
+    )
+}
@@ -0,0 +1,53 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::notifier::Notifier;
+use crate::{
+    helpers::{
+        auth::parse_session_token, presence::handle_user_disconnected,
+        user::get_authenticated_username,
+    },
+    repository::Repository,
+    utils::api::build_response,
+};
+
+pub async fn logout(
+    notifier: &impl Notifier,
+    repository: &impl Repository,
+    connection_id: &str,
+    session_token: &str,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let Some(username) = get_authenticated_username(repository, connection_id).await? else {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec!["You are not logged in".into()]),
+            None::<()>,
+        );
+    };
+
+    // Revoke the session so the token can't be used to reconnect
+    if let Some((token_username, session_sort_key)) = parse_session_token(session_token) {
+        if token_username == username {
+            repository
+                .delete_user_record(&username, &session_sort_key)
+                .await?;
+        }
+    }
+
+    repository
+        .delete_user_record(&username, &format!("CONNECTION-{connection_id}"))
+        .await?;
+
+    handle_user_disconnected(notifier, repository, &username).await?;
+
+    tracing::info!("USER {username} LOGGED OUT (CONNECTION ID: {connection_id})");
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        None,
+        None::<()>,
+    )
+}
@@ -1,91 +1,102 @@
-use aws_lambda_events::apigw::{ApiGatewayProxyResponse, ApiGatewayWebsocketProxyRequestContext};
-use aws_sdk_dynamodb::Client;
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
 use lambda_http::http::StatusCode;
 use lambda_runtime::Error;
 
-use chess::{
+use crate::notifier::Notifier;
+use crate::{
     helpers::game::{
-        get_game, get_player_details_from_connection_id, handle_if_game_is_finished,
-        notify_player_about_game_update, save_game, PlayerDetails,
+        get_player_details_from_connection_id, is_game_over, notify_player_about_game_update,
+        prepare_game_result, update_game, GameAction, GameResult, GameUpdate,
     },
+    repository::Repository,
     types::{
         game::{GameEnding, State},
         piece::Color,
     },
-    utils::api::build_response,
+    utils::api::{build_game_conflict_response, build_response},
 };
 
 pub async fn lose_via_out_of_time(
-    sdk_config: &aws_config::SdkConfig,
-    request_context: &ApiGatewayWebsocketProxyRequestContext,
-    dynamo_db_client: &Client,
+    notifier: &impl Notifier,
+    repository: &impl Repository,
     connection_id: &str,
-    game_table: &str,
-    user_table: &str,
     game_id: &str,
 ) -> Result<ApiGatewayProxyResponse, Error> {
-    match get_game(dynamo_db_client, game_table, game_id).await? {
-        None => build_response(
-            StatusCode::NOT_FOUND,
-            Some(connection_id.to_string()),
-            Some(vec![format!("Game with ID `{game_id}` not found").into()]),
-            None::<()>,
-        ),
-        Some(mut game) => {
-            let Some(PlayerDetails {
-                color: loser_color,
-                username,
-                opponent_username,
-            }) = get_player_details_from_connection_id(&game, connection_id)
-            else {
-                return build_response(
-                    StatusCode::BAD_REQUEST,
-                    Some(connection_id.to_string()),
-                    Some(vec!["You are not a player in this game".into()]),
-                    None::<()>,
-                );
-            };
+    let update = update_game(repository, game_id, async |game| {
+        let Some(player_details) = get_player_details_from_connection_id(game, connection_id)
+        else {
+            return Ok(GameAction::Reject(build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec!["You are not a player in this game".into()]),
+                None::<()>,
+            )?));
+        };
 
-            game.game_state.current_state_mut().state =
-                State::Finished(GameEnding::OutOfTime(loser_color));
+        if is_game_over(game) {
+            return Ok(GameAction::Reject(build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec!["Game is already over".into()]),
+                None::<()>,
+            )?));
+        }
 
-            if let Some(game_time) = game.game_state.game_time.as_mut() {
-                if loser_color == Color::White {
-                    game_time.white_seconds_left = 0;
-                } else {
-                    game_time.black_seconds_left = 0;
-                }
-            }
+        let loser_color = player_details.color;
 
-            save_game(dynamo_db_client, game_table, &game).await?;
+        game.game_state.current_state_mut().state =
+            State::Finished(GameEnding::OutOfTime(loser_color));
 
-            handle_if_game_is_finished(
-                dynamo_db_client,
-                user_table,
-                &username,
-                opponent_username.as_deref(),
-                &game.game_state,
-            )
-            .await?;
+        if let Some(game_time) = game.game_state.game_time.as_mut() {
+            if loser_color == Color::White {
+                game_time.white_seconds_left = 0;
+            } else {
+                game_time.black_seconds_left = 0;
+            }
+        }
 
-            notify_player_about_game_update(
-                sdk_config,
-                request_context,
-                connection_id,
-                &game,
-                None,
-                false,
-            )
-            .await?;
+        let GameResult {
+            writes,
+            messages: rating_messages,
+        } = prepare_game_result(repository, game).await?;
 
-            tracing::info!("{username} lost game {game_id} via no time left");
+        Ok(GameAction::SaveWith(
+            (player_details.username, rating_messages),
+            writes,
+        ))
+    })
+    .await?;
 
-            build_response(
-                StatusCode::OK,
+    let (game, (username, rating_messages)) = match update {
+        GameUpdate::Saved { game, output } => (*game, output),
+        GameUpdate::Rejected(response) => return Ok(response),
+        GameUpdate::NotFound => {
+            return build_response(
+                StatusCode::NOT_FOUND,
                 Some(connection_id.to_string()),
-                None,
-                Some(game),
-            )
+                Some(vec![format!("Game with ID `{game_id}` not found").into()]),
+                None::<()>,
+            );
         }
-    }
+        GameUpdate::Conflict => return build_game_conflict_response(connection_id),
+    };
+
+    notify_player_about_game_update(
+        notifier,
+        repository,
+        connection_id,
+        &game,
+        Some(rating_messages.clone()),
+        false,
+    )
+    .await?;
+
+    tracing::info!("{username} lost game {game_id} via no time left");
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        Some(rating_messages),
+        Some(game),
+    )
 }
@@ -1,14 +1,831 @@
+pub mod abort_game;
+pub mod accept_challenge;
 pub mod accept_draw;
+pub mod accept_takeback;
+pub mod add_friend;
 pub mod analyze_position;
+pub mod cancel_seek;
+pub mod challenge_user;
 pub mod create_new_game;
+pub mod create_tournament;
+pub mod decline_challenge;
 pub mod decline_draw;
+pub mod decline_takeback;
+pub mod get_chat_messages;
 pub mod get_fen;
 pub mod get_game_state;
+pub mod get_online_friends;
 pub mod get_pgn;
+pub mod get_positions;
+pub mod get_tournament;
+pub mod get_user_stats;
+pub mod heartbeat;
 pub mod join_game;
+pub mod join_tournament;
 pub mod leave_game;
+pub mod leave_lobby;
+pub mod list_my_games;
+pub mod list_open_games;
+pub mod login;
+pub mod logout;
 pub mod lose_via_out_of_time;
 pub mod move_piece;
+pub mod mute_opponent;
 pub mod offer_draw;
 pub mod play_again;
+pub mod register;
+pub mod remove_friend;
+pub mod request_takeback;
 pub mod resign;
+pub mod seek;
+pub mod send_chat_message;
+pub mod set_premove;
+pub mod start_tournament_round;
+pub mod stop_watching_game;
+pub mod watch_game;
+
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::{
+    notifier::Notifier, repository::Repository, types::game::PlayerAction,
+    utils::api::build_response,
+};
+
+/// Routes the action to its handler. The Lambda handles `AnalyzePosition` itself, since it needs
+/// the API Gateway request to invoke the chess agent.
+pub async fn handle_player_action(
+    notifier: &impl Notifier,
+    repository: &impl Repository,
+    connection_id: &str,
+    chat_word_filter: &[String],
+    action: PlayerAction,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    match action {
+        PlayerAction::CreateGame {
+            username,
+            game_id,
+            board_setup,
+            color_preference,
+            engine_difficulty,
+            seconds_per_player,
+            allow_takebacks,
+            rated,
+            private,
+            invite_expires_in_minutes,
+        } => {
+            create_new_game::create_new_game(
+                notifier,
+                repository,
+                connection_id,
+                username.as_deref(),
+                game_id.as_deref().map(|s| s.trim()),
+                board_setup,
+                color_preference,
+                engine_difficulty,
+                seconds_per_player,
+                allow_takebacks,
+                rated,
+                private,
+                invite_expires_in_minutes,
+            )
+            .await
+        }
+        PlayerAction::JoinGame {
+            username,
+            game_id,
+            seat_token,
+            invite_token,
+        } => {
+            join_game::join_game(
+                notifier,
+                repository,
+                connection_id,
+                username.as_deref(),
+                game_id.trim(),
+                seat_token.as_deref(),
+                invite_token.as_deref(),
+            )
+            .await
+        }
+        PlayerAction::LeaveGame { game_id } => {
+            leave_game::leave_game(notifier, repository, connection_id, &game_id).await
+        }
+        PlayerAction::GetGameState { game_id } => {
+            get_game_state::get_game_state(repository, connection_id, game_id.trim()).await
+        }
+        PlayerAction::MovePiece {
+            game_id,
+            player_move,
+        } => {
+            move_piece::move_piece(notifier, repository, connection_id, &game_id, player_move).await
+        }
+        PlayerAction::Heartbeat => heartbeat::heartbeat(repository, connection_id).await,
+        PlayerAction::LoseViaOutOfTime { game_id } => {
+            lose_via_out_of_time::lose_via_out_of_time(
+                notifier,
+                repository,
+                connection_id,
+                &game_id,
+            )
+            .await
+        }
+        PlayerAction::AbortGame { game_id } => {
+            abort_game::abort_game(notifier, repository, connection_id, &game_id).await
+        }
+        PlayerAction::Resign { game_id } => {
+            resign::resign(notifier, repository, connection_id, &game_id).await
+        }
+        PlayerAction::OfferDraw { game_id } => {
+            offer_draw::offer_draw(notifier, repository, connection_id, &game_id).await
+        }
+        PlayerAction::AcceptDraw { game_id } => {
+            accept_draw::accept_draw(notifier, repository, connection_id, &game_id).await
+        }
+        PlayerAction::RequestTakeback { game_id } => {
+            request_takeback::request_takeback(notifier, repository, connection_id, game_id.trim())
+                .await
+        }
+        PlayerAction::AcceptTakeback { game_id } => {
+            accept_takeback::accept_takeback(notifier, repository, connection_id, game_id.trim())
+                .await
+        }
+        PlayerAction::DeclineTakeback { game_id } => {
+            decline_takeback::decline_takeback(notifier, repository, connection_id, game_id.trim())
+                .await
+        }
+        PlayerAction::DeclineDraw { game_id } => {
+            decline_draw::decline_draw(notifier, repository, connection_id, &game_id).await
+        }
+        // Analysis is handed off to the chess agent Lambda, which only exists when deployed
+        PlayerAction::AnalyzePosition { .. } => build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec![
+                "Position analysis is not available on this server".into()
+            ]),
+            None::<()>,
+        ),
+        PlayerAction::GetFen {
+            game_id,
+            history_index,
+        } => get_fen::get_fen(repository, connection_id, game_id.trim(), history_index).await,
+        PlayerAction::GetPgn { game_id } => {
+            get_pgn::get_pgn(repository, connection_id, game_id.trim()).await
+        }
+        PlayerAction::GetPositions { game_id, ply } => {
+            get_positions::get_positions(repository, connection_id, game_id.trim(), ply).await
+        }
+        PlayerAction::PlayAgain {
+            game_id,
+            color_preference,
+            best_of,
+        } => {
+            play_again::play_again(
+                notifier,
+                repository,
+                connection_id,
+                game_id.trim(),
+                color_preference,
+                best_of,
+            )
+            .await
+        }
+        PlayerAction::Register { username, password } => {
+            register::register(repository, connection_id, username.trim(), &password).await
+        }
+        PlayerAction::Login { username, password } => {
+            login::login(
+                notifier,
+                repository,
+                connection_id,
+                username.trim(),
+                &password,
+            )
+            .await
+        }
+        PlayerAction::Logout { session_token } => {
+            logout::logout(notifier, repository, connection_id, &session_token).await
+        }
+        PlayerAction::WatchGame { game_id } => {
+            watch_game::watch_game(notifier, repository, connection_id, game_id.trim()).await
+        }
+        PlayerAction::StopWatchingGame { game_id } => {
+            stop_watching_game::stop_watching_game(
+                notifier,
+                repository,
+                connection_id,
+                game_id.trim(),
+            )
+            .await
+        }
+        PlayerAction::Seek {
+            username,
+            board_setup,
+            seconds_per_player,
+            min_rating,
+            max_rating,
+        } => {
+            seek::seek(
+                notifier,
+                repository,
+                connection_id,
+                username.as_deref(),
+                board_setup,
+                seconds_per_player,
+                min_rating,
+                max_rating,
+            )
+            .await
+        }
+        PlayerAction::CancelSeek => cancel_seek::cancel_seek(repository, connection_id).await,
+        PlayerAction::ListOpenGames => {
+            list_open_games::list_open_games(repository, connection_id).await
+        }
+        PlayerAction::LeaveLobby => leave_lobby::leave_lobby(repository, connection_id).await,
+        PlayerAction::SendChatMessage { game_id, message } => {
+            send_chat_message::send_chat_message(
+                notifier,
+                repository,
+                connection_id,
+                chat_word_filter,
+                game_id.trim(),
+                &message,
+            )
+            .await
+        }
+        PlayerAction::GetChatMessages { game_id } => {
+            get_chat_messages::get_chat_messages(repository, connection_id, game_id.trim()).await
+        }
+        PlayerAction::CreateTournament {
+            name,
+            format,
+            board_setup,
+            seconds_per_player,
+        } => {
+            create_tournament::create_tournament(
+                repository,
+                connection_id,
+                name.trim(),
+                format,
+                board_setup,
+                seconds_per_player,
+            )
+            .await
+        }
+        PlayerAction::JoinTournament { tournament_id } => {
+            join_tournament::join_tournament(repository, connection_id, tournament_id.trim()).await
+        }
+        PlayerAction::StartTournamentRound { tournament_id } => {
+            start_tournament_round::start_tournament_round(
+                repository,
+                connection_id,
+                tournament_id.trim(),
+            )
+            .await
+        }
+        PlayerAction::GetTournament { tournament_id } => {
+            get_tournament::get_tournament(repository, connection_id, tournament_id.trim()).await
+        }
+        PlayerAction::SetPremove {
+            game_id,
+            player_move,
+        } => set_premove::set_premove(repository, connection_id, game_id.trim(), player_move).await,
+        PlayerAction::MuteOpponent { game_id, muted } => {
+            mute_opponent::mute_opponent(repository, connection_id, game_id.trim(), muted).await
+        }
+        PlayerAction::ListMyGames { cursor, limit } => {
+            list_my_games::list_my_games(repository, connection_id, cursor.as_deref(), limit).await
+        }
+        PlayerAction::GetUserStats { username } => {
+            get_user_stats::get_user_stats(
+                repository,
+                connection_id,
+                username.as_deref().map(|s| s.trim()),
+            )
+            .await
+        }
+        PlayerAction::ChallengeUser {
+            username,
+            board_setup,
+            color_preference,
+            seconds_per_player,
+            rated,
+        } => {
+            challenge_user::challenge_user(
+                notifier,
+                repository,
+                connection_id,
+                username.trim(),
+                board_setup,
+                color_preference,
+                seconds_per_player,
+                rated,
+            )
+            .await
+        }
+        PlayerAction::AcceptChallenge { challenge_id } => {
+            accept_challenge::accept_challenge(
+                notifier,
+                repository,
+                connection_id,
+                challenge_id.trim(),
+            )
+            .await
+        }
+        PlayerAction::DeclineChallenge { challenge_id } => {
+            decline_challenge::decline_challenge(
+                notifier,
+                repository,
+                connection_id,
+                challenge_id.trim(),
+            )
+            .await
+        }
+        PlayerAction::AddFriend { username } => {
+            add_friend::add_friend(notifier, repository, connection_id, username.trim()).await
+        }
+        PlayerAction::RemoveFriend { username } => {
+            remove_friend::remove_friend(repository, connection_id, username.trim()).await
+        }
+        PlayerAction::GetOnlineFriends => {
+            get_online_friends::get_online_friends(repository, connection_id).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        helpers::{
+            challenge::create_challenge, connection::handle_connection_closed,
+            engine::play_engine_reply, game::make_move, user::create_user_connection,
+        },
+        notifier::RecordingNotifier,
+        repository::{GameRepository, InMemoryRepository, SubscriptionRepository, UserRepository},
+        types::{
+            board::{BoardSetup, File, Position, Rank},
+            game::{ColorPreference, EngineDifficulty, GameEnding, PlayerMove, State},
+            piece::Color,
+            presence::ConnectionState,
+        },
+    };
+    use aws_lambda_events::{apigw::ApiGatewayProxyResponse, encodings::Body};
+
+    use super::{
+        accept_challenge::accept_challenge, create_new_game::create_new_game,
+        get_positions::get_positions, join_game::join_game, move_piece::move_piece, resign::resign,
+        watch_game::watch_game,
+    };
+
+    const GAME_ID: &str = "test-game";
+
+    fn player_move(from: (usize, usize), to: (usize, usize)) -> PlayerMove {
+        PlayerMove {
+            from: Position {
+                file: File(from.0),
+                rank: Rank(from.1),
+            },
+            to: Position {
+                file: File(to.0),
+                rank: Rank(to.1),
+            },
+        }
+    }
+
+    fn assert_ok(response: ApiGatewayProxyResponse) {
+        assert_eq!(response.status_code, 200, "{:?}", response.body);
+    }
+
+    /// Alice (white) and Bob (black) are both seated in the game
+    async fn start_game(repository: &InMemoryRepository, notifier: &RecordingNotifier) {
+        assert_ok(
+            create_new_game(
+                notifier,
+                repository,
+                "conn-alice",
+                Some("alice"),
+                Some(GAME_ID),
+                None,
+                Some(ColorPreference::White),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap(),
+        );
+
+        assert_ok(
+            join_game(
+                notifier,
+                repository,
+                "conn-bob",
+                Some("bob"),
+                GAME_ID,
+                None,
+                None,
+            )
+            .await
+            .unwrap(),
+        );
+    }
+
+    /// Alice (white) plays the engine
+    async fn start_engine_game(repository: &InMemoryRepository, notifier: &RecordingNotifier) {
+        assert_ok(
+            create_new_game(
+                notifier,
+                repository,
+                "conn-alice",
+                Some("alice"),
+                Some(GAME_ID),
+                None,
+                Some(ColorPreference::White),
+                Some(EngineDifficulty::Beginner),
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap(),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_joining_notifies_only_the_opponent() {
+        let repository = InMemoryRepository::new();
+        let notifier = RecordingNotifier::new();
+        start_game(&repository, &notifier).await;
+
+        let sent = notifier.take();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].connection_id, "conn-alice");
+        assert_eq!(sent[0].payload["data"]["black_username"], "bob");
+
+        let bob_game = repository.get_user_game("bob", GAME_ID).await.unwrap();
+        assert!(bob_game.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_game_is_played_to_checkmate() {
+        let repository = InMemoryRepository::new();
+        let notifier = RecordingNotifier::new();
+        start_game(&repository, &notifier).await;
+        notifier.take();
+
+        // Fool's mate
+        let moves = [
+            ("conn-alice", (6, 2), (6, 3)),
+            ("conn-bob", (5, 7), (5, 5)),
+            ("conn-alice", (7, 2), (7, 4)),
+            ("conn-bob", (4, 8), (8, 4)),
+        ];
+
+        for (connection_id, from, to) in moves {
+            assert_ok(
+                move_piece(
+                    &notifier,
+                    &repository,
+                    connection_id,
+                    GAME_ID,
+                    player_move(from, to),
+                )
+                .await
+                .unwrap(),
+            );
+
+            // The player who moved gets the game in the response, so only the opponent is sent it
+            let sent = notifier.take();
+            assert_eq!(sent.len(), 1);
+            assert_ne!(sent[0].connection_id, connection_id);
+        }
+
+        // A move after the game is over is rejected without notifying anyone
+        let response = move_piece(
+            &notifier,
+            &repository,
+            "conn-alice",
+            GAME_ID,
+            player_move((5, 2), (5, 4)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status_code, 400);
+        assert!(notifier.sent().is_empty());
+
+        let game = repository.get_game(GAME_ID).await.unwrap().unwrap();
+        assert!(matches!(
+            game.game_state.current_state().state,
+            State::Finished(GameEnding::Checkmate(Color::White))
+        ));
+
+        for username in ["alice", "bob"] {
+            let user_game = repository.get_user_game(username, GAME_ID).await.unwrap();
+            assert_eq!(user_game.unwrap().winner.as_deref(), Some("black"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_past_positions_are_sent_on_request() {
+        let repository = InMemoryRepository::new();
+        let notifier = RecordingNotifier::new();
+        start_game(&repository, &notifier).await;
+
+        let moves = [("conn-alice", (5, 2), (5, 4)), ("conn-bob", (5, 7), (5, 5))];
+
+        for (connection_id, from, to) in moves {
+            assert_ok(
+                move_piece(
+                    &notifier,
+                    &repository,
+                    connection_id,
+                    GAME_ID,
+                    player_move(from, to),
+                )
+                .await
+                .unwrap(),
+            );
+        }
+
+        let response = get_positions(&repository, "conn-bob", GAME_ID, 1)
+            .await
+            .unwrap();
+        let Some(Body::Text(body)) = response.body else {
+            panic!("Expected a text body");
+        };
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+        // Rebuilt from the starting position
+        let positions = body["data"]["positions"].as_array().unwrap();
+        assert_eq!(positions.len(), 2);
+        assert_eq!(positions[1]["ply"], 1);
+        assert_eq!(positions[1]["position"]["currentTurn"], "black");
+
+        let response = get_positions(&repository, "conn-bob", GAME_ID, 3)
+            .await
+            .unwrap();
+        assert_eq!(response.status_code, 400);
+    }
+
+    #[tokio::test]
+    async fn test_spectators_are_sent_every_move() {
+        let repository = InMemoryRepository::new();
+        let notifier = RecordingNotifier::new();
+        start_game(&repository, &notifier).await;
+
+        assert_ok(
+            watch_game(&notifier, &repository, "conn-carol", GAME_ID)
+                .await
+                .unwrap(),
+        );
+        notifier.take();
+
+        assert_ok(
+            move_piece(
+                &notifier,
+                &repository,
+                "conn-alice",
+                GAME_ID,
+                player_move((5, 2), (5, 4)),
+            )
+            .await
+            .unwrap(),
+        );
+
+        assert_eq!(notifier.sent_to("conn-bob").len(), 1);
+        assert_eq!(notifier.sent_to("conn-carol").len(), 1);
+        assert!(notifier.sent_to("conn-alice").is_empty());
+
+        // A spectator whose connection is gone is unsubscribed on the next update
+        notifier.disconnect("conn-carol");
+
+        assert_ok(
+            move_piece(
+                &notifier,
+                &repository,
+                "conn-bob",
+                GAME_ID,
+                player_move((5, 7), (5, 5)),
+            )
+            .await
+            .unwrap(),
+        );
+
+        let subscribers = repository
+            .get_subscribers(&format!("GAME-{GAME_ID}"))
+            .await
+            .unwrap();
+        assert!(subscribers.is_empty());
+
+        let game = repository.get_game(GAME_ID).await.unwrap().unwrap();
+        assert_eq!(game.spectator_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_spectators_are_counted_once() {
+        let repository = InMemoryRepository::new();
+        let notifier = RecordingNotifier::new();
+        start_game(&repository, &notifier).await;
+
+        for _ in 0..2 {
+            assert_ok(
+                watch_game(&notifier, &repository, "conn-carol", GAME_ID)
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        // A player can't watch, and isn't left subscribed
+        let response = watch_game(&notifier, &repository, "conn-bob", GAME_ID)
+            .await
+            .unwrap();
+        assert_eq!(response.status_code, 400);
+
+        let subscribers = repository
+            .get_subscribers(&format!("GAME-{GAME_ID}"))
+            .await
+            .unwrap();
+        assert_eq!(subscribers.len(), 1);
+
+        let game = repository.get_game(GAME_ID).await.unwrap().unwrap();
+        assert_eq!(game.spectator_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_closing_a_connection_unseats_the_player() {
+        let repository = InMemoryRepository::new();
+        let notifier = RecordingNotifier::new();
+        start_game(&repository, &notifier).await;
+        notifier.take();
+
+        handle_connection_closed(&notifier, &repository, "conn-bob")
+            .await
+            .unwrap();
+
+        let sent = notifier.take();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].connection_id, "conn-alice");
+        assert_eq!(
+            sent[0].payload["messages"][0]["message"],
+            "bob has disconnected from the game"
+        );
+
+        let game = repository.get_game(GAME_ID).await.unwrap().unwrap();
+        assert_eq!(
+            game.black_connection_id,
+            Some(ConnectionState::Disconnected)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_engine_reply_is_saved_after_the_players_move() {
+        let repository = InMemoryRepository::new();
+        let notifier = RecordingNotifier::new();
+        start_engine_game(&repository, &notifier).await;
+        notifier.take();
+
+        assert_ok(
+            move_piece(
+                &notifier,
+                &repository,
+                "conn-alice",
+                GAME_ID,
+                player_move((5, 2), (5, 4)),
+            )
+            .await
+            .unwrap(),
+        );
+
+        // Alice is told the engine is thinking once her move is saved, and gets its reply in
+        // the response
+        assert_eq!(notifier.sent_to("conn-alice").len(), 1);
+
+        let game = repository.get_game(GAME_ID).await.unwrap().unwrap();
+        assert_eq!(game.game_state.ply_count(), 2);
+        assert_eq!(game.game_state.current_state().current_turn, Color::White);
+        assert!(!game.game_state.current_state().moves.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_engine_reply_is_discarded_if_the_game_changed() {
+        let repository = InMemoryRepository::new();
+        let notifier = RecordingNotifier::new();
+        start_engine_game(&repository, &notifier).await;
+
+        assert_ok(
+            move_piece(
+                &notifier,
+                &repository,
+                "conn-alice",
+                GAME_ID,
+                player_move((5, 2), (5, 4)),
+            )
+            .await
+            .unwrap(),
+        );
+
+        // The game as the engine saw it after Alice's next move...
+        let mut searched_game = repository.get_game(GAME_ID).await.unwrap().unwrap();
+        make_move(&mut searched_game.game_state, &player_move((4, 2), (4, 4)));
+
+        // ...but Alice resigned before the engine replied
+        assert_ok(
+            resign(&notifier, &repository, "conn-alice", GAME_ID)
+                .await
+                .unwrap(),
+        );
+        let resigned_game = repository.get_game(GAME_ID).await.unwrap().unwrap();
+
+        let (game, messages) =
+            play_engine_reply(&notifier, &repository, "conn-alice", searched_game)
+                .await
+                .unwrap();
+
+        assert!(messages.is_empty());
+        assert_eq!(game.version, resigned_game.version);
+        assert!(matches!(
+            game.game_state.current_state().state,
+            State::Finished(GameEnding::Resignation(_))
+        ));
+
+        let saved_game = repository.get_game(GAME_ID).await.unwrap().unwrap();
+        assert_eq!(saved_game.version, resigned_game.version);
+        assert_eq!(
+            saved_game.game_state.ply_count(),
+            resigned_game.game_state.ply_count()
+        );
+    }
+
+    /// Alice challenged Bob from `conn-alice-old`, and is now only logged in on `conn-alice`
+    async fn challenge_from_closed_connection(repository: &InMemoryRepository) -> String {
+        for (username, connection_id) in [("alice", "conn-alice"), ("bob", "conn-bob")] {
+            repository
+                .save_user_record(&create_user_connection(username, connection_id))
+                .await
+                .unwrap();
+        }
+
+        let challenge = create_challenge(
+            "alice",
+            "conn-alice-old",
+            "bob",
+            BoardSetup::Standard,
+            ColorPreference::White,
+            None,
+            false,
+        );
+        repository.save_challenge(&challenge).await.unwrap();
+
+        challenge.challenge_id
+    }
+
+    #[tokio::test]
+    async fn test_accepted_challenge_reaches_the_challengers_live_connection() {
+        let repository = InMemoryRepository::new();
+        let notifier = RecordingNotifier::new();
+        let challenge_id = challenge_from_closed_connection(&repository).await;
+
+        let response = accept_challenge(&notifier, &repository, "conn-bob", &challenge_id)
+            .await
+            .unwrap();
+        assert_ok(response);
+
+        let sent = notifier.take();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].connection_id, "conn-alice");
+
+        let game_id = sent[0].payload["data"]["game_id"].as_str().unwrap();
+        let game = repository.get_game(game_id).await.unwrap().unwrap();
+        assert_eq!(
+            game.white_connection_id,
+            Some(ConnectionState::connected("conn-alice"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_accepted_challenge_is_aborted_if_the_challenger_is_gone() {
+        let repository = InMemoryRepository::new();
+        let notifier = RecordingNotifier::new();
+        let challenge_id = challenge_from_closed_connection(&repository).await;
+        notifier.disconnect("conn-alice");
+
+        let response = accept_challenge(&notifier, &repository, "conn-bob", &challenge_id)
+            .await
+            .unwrap();
+        assert_eq!(response.status_code, 400);
+
+        // The game was started, so it would be waiting on white's first move if it wasn't aborted
+        let awaiting_first_move = repository
+            .get_games_awaiting_first_move(Color::White, "9999")
+            .await
+            .unwrap();
+        assert!(awaiting_first_move.is_empty());
+    }
+}
@@ -1,107 +1,137 @@
-use aws_lambda_events::apigw::{ApiGatewayProxyResponse, ApiGatewayWebsocketProxyRequestContext};
-use aws_sdk_dynamodb::Client;
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
 use lambda_http::http::StatusCode;
 use lambda_runtime::Error;
 
-use chess::{
+use crate::notifier::Notifier;
+use crate::{
     helpers::{
-        engine::use_engine,
+        abort::update_awaiting_first_move,
+        engine::{play_engine_reply, refresh_available_moves},
         game::{
-            can_player_make_a_move, get_game, get_player_details_from_connection_id,
-            handle_if_game_is_finished, make_move, notify_player_about_game_update, save_game,
-            validate_move, PlayerDetails,
+            can_player_make_a_move, get_player_details_from_connection_id, make_move,
+            notify_player_about_game_update, play_premove, prepare_game_result, update_game,
+            validate_move, GameAction, GameResult, GameUpdate,
         },
     },
-    types::game::PlayerMove,
-    utils::api::build_response,
+    repository::Repository,
+    types::{
+        api::{ApiMessage, ApiMessageType},
+        game::PlayerMove,
+    },
+    utils::api::{build_game_conflict_response, build_response},
 };
 
 pub async fn move_piece(
-    sdk_config: &aws_config::SdkConfig,
-    request_context: &ApiGatewayWebsocketProxyRequestContext,
-    dynamo_db_client: &Client,
-    game_table: &str,
-    user_table: &str,
+    notifier: &impl Notifier,
+    repository: &impl Repository,
     connection_id: &str,
     game_id: &str,
     player_move: PlayerMove,
 ) -> Result<ApiGatewayProxyResponse, Error> {
-    match get_game(dynamo_db_client, game_table, game_id).await? {
-        None => build_response(
-            StatusCode::BAD_REQUEST,
-            Some(connection_id.to_string()),
-            Some(vec![format!("Game (ID: {game_id}) not found").into()]),
-            None::<()>,
-        ),
-        Some(mut game) => {
-            let Some(PlayerDetails {
-                color: player_color,
-                username,
-                opponent_username,
-            }) = get_player_details_from_connection_id(&game, connection_id)
-            else {
-                return build_response(
-                    StatusCode::BAD_REQUEST,
-                    Some(connection_id.to_string()),
-                    Some(vec!["You are not a player in this game".into()]),
-                    None::<()>,
-                );
-            };
+    let update = update_game(repository, game_id, async |game| {
+        let Some(player_details) = get_player_details_from_connection_id(game, connection_id)
+        else {
+            return Ok(GameAction::Reject(build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec!["You are not a player in this game".into()]),
+                None::<()>,
+            )?));
+        };
+        let player_color = player_details.color;
 
-            if let Err(e) = can_player_make_a_move(&game, &player_color) {
-                return build_response(
-                    StatusCode::BAD_REQUEST,
-                    Some(connection_id.to_string()),
-                    Some(vec![e.into()]),
-                    Some(game),
-                );
-            }
+        if let Err(e) = can_player_make_a_move(game, &player_color) {
+            return Ok(GameAction::Reject(build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec![e.into()]),
+                Some(&*game),
+            )?));
+        }
 
-            if let Err(e) = validate_move(
-                &game.game_state.current_state().board,
-                &player_move,
-                &player_color,
-            ) {
-                return build_response(
-                    StatusCode::BAD_REQUEST,
-                    Some(connection_id.to_string()),
-                    Some(vec![e.into()]),
-                    Some(game),
-                );
-            }
+        if let Err(e) = validate_move(
+            &game.game_state.current_state().board,
+            &player_move,
+            &player_color,
+        ) {
+            return Ok(GameAction::Reject(build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec![e.into()]),
+                Some(&*game),
+            )?));
+        }
 
-            make_move(&mut game.game_state, &player_move); // Move is toggled here
-            game.draw_offered_by = None;
-            use_engine(&mut game, sdk_config, request_context, connection_id).await?;
-            save_game(dynamo_db_client, game_table, &game).await?;
+        make_move(&mut game.game_state, &player_move); // Move is toggled here
+        game.draw_offered_by = None;
+        game.takeback_requested_by = None;
 
-            handle_if_game_is_finished(
-                dynamo_db_client,
-                user_table,
-                &username,
-                opponent_username.as_deref(),
-                &game.game_state,
-            )
-            .await?;
+        // The opponent's premove is played right away, costing them almost no clock time
+        let premove_messages: Vec<ApiMessage> =
+            match play_premove(game, player_color.opponent_color()) {
+                Some(Ok(())) => vec![ApiMessage {
+                    message: "Your premove was played".to_string(),
+                    message_type: ApiMessageType::Info,
+                }],
+                Some(Err(e)) => vec![ApiMessage {
+                    message: format!("Your premove was discarded: {e}"),
+                    message_type: ApiMessageType::Warning,
+                }],
+                None => Vec::new(),
+            };
+
+        refresh_available_moves(game);
+        update_awaiting_first_move(game);
 
-            notify_player_about_game_update(
-                sdk_config,
-                request_context,
-                connection_id,
-                &game,
-                None,
-                false,
-            )
-            .await?;
+        let GameResult {
+            writes,
+            messages: rating_messages,
+        } = prepare_game_result(repository, game).await?;
 
-            tracing::info!("PLAYER {username} MADE A MOVE (GAME ID: {game_id}): {player_move:?}. Game state: {game:?}");
+        Ok(GameAction::SaveWith(
+            (player_details.username, premove_messages, rating_messages),
+            writes,
+        ))
+    })
+    .await?;
 
-            build_response(
-                StatusCode::OK,
+    let (game, (username, premove_messages, rating_messages)) = match update {
+        GameUpdate::Saved { game, output } => (*game, output),
+        GameUpdate::Rejected(response) => return Ok(response),
+        GameUpdate::NotFound => {
+            return build_response(
+                StatusCode::BAD_REQUEST,
                 Some(connection_id.to_string()),
-                None,
-                Some(game),
-            )
+                Some(vec![format!("Game (ID: {game_id}) not found").into()]),
+                None::<()>,
+            );
         }
-    }
+        GameUpdate::Conflict => return build_game_conflict_response(connection_id),
+    };
+
+    // The engine's reply is saved separately, so a slow search never holds up the player's move
+    let (game, engine_messages) =
+        play_engine_reply(notifier, repository, connection_id, game).await?;
+    let rating_messages = [rating_messages, engine_messages].concat();
+
+    notify_player_about_game_update(
+        notifier,
+        repository,
+        connection_id,
+        &game,
+        Some([premove_messages, rating_messages.clone()].concat()),
+        false,
+    )
+    .await?;
+
+    tracing::info!(
+        "PLAYER {username} MADE A MOVE (GAME ID: {game_id}): {player_move:?}. Game state: {game:?}"
+    );
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        Some(rating_messages),
+        Some(game),
+    )
 }
@@ -0,0 +1,70 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::{
+    helpers::game::{
+        get_player_details_from_connection_id, update_game, GameAction, GameUpdate, PlayerDetails,
+    },
+    repository::Repository,
+    utils::api::{build_game_conflict_response, build_response},
+};
+
+/// Stops (or resumes) relaying the opponent's chat messages to the player
+pub async fn mute_opponent(
+    repository: &impl Repository,
+    connection_id: &str,
+    game_id: &str,
+    muted: bool,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let update = update_game(repository, game_id, async |game| {
+        let Some(PlayerDetails {
+            color: player_color,
+            username,
+            ..
+        }) = get_player_details_from_connection_id(game, connection_id)
+        else {
+            return Ok(GameAction::Reject(build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec!["You are not a player in this game".into()]),
+                None::<()>,
+            )?));
+        };
+
+        game.chat_muted_by.retain(|color| *color != player_color);
+
+        if muted {
+            game.chat_muted_by.push(player_color);
+        }
+
+        Ok(GameAction::Save(username))
+    })
+    .await?;
+
+    let (game, username) = match update {
+        GameUpdate::Saved { game, output } => (*game, output),
+        GameUpdate::Rejected(response) => return Ok(response),
+        GameUpdate::NotFound => {
+            return build_response(
+                StatusCode::NOT_FOUND,
+                Some(connection_id.to_string()),
+                Some(vec![format!("Game with ID `{game_id}` not found").into()]),
+                None::<()>,
+            );
+        }
+        GameUpdate::Conflict => return build_game_conflict_response(connection_id),
+    };
+
+    tracing::info!(
+        "Player {username} {} their opponent in game {game_id}",
+        if muted { "muted" } else { "unmuted" }
+    );
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        None,
+        Some(game),
+    )
+}
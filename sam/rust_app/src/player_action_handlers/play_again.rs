@@ -1,28 +1,35 @@
-use aws_lambda_events::apigw::{ApiGatewayProxyResponse, ApiGatewayWebsocketProxyRequestContext};
-use aws_sdk_dynamodb::Client;
-use lambda_http::{http::StatusCode, Body};
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
 use lambda_runtime::Error;
 
-use chess::helpers::engine::use_engine;
-use chess::helpers::game::{
+use crate::helpers::engine::use_engine_in_new_game;
+use crate::helpers::game::{
     assign_player_to_existing_or_remaining_slot, check_if_both_players_just_joined, create_game,
-    get_game, is_game_over, save_game,
+    get_next_match_score, get_player_color, get_player_details_from_connection_id,
+    insert_game_with_generated_id, is_game_over, is_seat_authenticated, issue_seat_token,
+    set_seat_authenticated, PlayerDetails,
 };
-use chess::helpers::user::{create_user_game, save_user_record};
-use chess::types::api::{ApiMessage, ApiMessageType, ApiResponse};
-use chess::utils::api::build_response;
-use chess::utils::api_gateway::post_to_connection;
+use crate::helpers::presence::notify_friends_of_started_game;
+use crate::helpers::user::create_user_game;
+use crate::notifier::Notifier;
+use crate::repository::Repository;
+use crate::types::api::{ApiMessage, ApiMessageType, ApiResponse};
+use crate::types::game::ColorPreference;
+use crate::types::piece::Color;
+use crate::types::presence::ConnectionState;
+use crate::utils::api::{build_response, build_seated_player_response};
+
+const MAX_BEST_OF: u8 = 15;
 
 pub async fn play_again(
-    sdk_config: &aws_config::SdkConfig,
-    request_context: &ApiGatewayWebsocketProxyRequestContext,
-    dynamo_db_client: &Client,
-    game_table: &str,
-    user_table: &str,
+    notifier: &impl Notifier,
+    repository: &impl Repository,
     connection_id: &str,
     game_id: &str,
+    color_preference: Option<ColorPreference>,
+    best_of: Option<u8>,
 ) -> Result<ApiGatewayProxyResponse, Error> {
-    let old_game = match get_game(dynamo_db_client, game_table, game_id).await? {
+    let old_game = match repository.get_game(game_id).await? {
         Some(game) => game,
         None => {
             return build_response(
@@ -43,99 +50,123 @@ pub async fn play_again(
         );
     }
 
-    if old_game.engine_difficulty.is_some() {
+    if best_of.is_some_and(|best_of| best_of % 2 == 0 || best_of > MAX_BEST_OF) {
         return build_response(
             StatusCode::BAD_REQUEST,
             Some(connection_id.to_string()),
-            Some(vec!["Use create-game for engine rematches".into()]),
+            Some(vec![format!(
+                "Matches must be best of an odd number of games, up to {MAX_BEST_OF}"
+            )
+            .into()]),
             None::<()>,
         );
     }
 
     // Determine which player is requesting and which is the opponent
-    let (requester_username, opponent_username, opponent_connection_id) =
-        if old_game.white_connection_id.as_deref() == Some(connection_id) {
-            (
-                old_game.white_username.as_deref(),
-                old_game.black_username.as_deref(),
-                old_game.black_connection_id.as_deref(),
-            )
-        } else if old_game.black_connection_id.as_deref() == Some(connection_id) {
-            (
-                old_game.black_username.as_deref(),
-                old_game.white_username.as_deref(),
-                old_game.white_connection_id.as_deref(),
-            )
-        } else {
-            return build_response(
-                StatusCode::BAD_REQUEST,
-                Some(connection_id.to_string()),
-                Some(vec!["You are not a player in this game".into()]),
-                None::<()>,
-            );
-        };
-
-    let requester_username = match requester_username {
-        Some(u) => u.to_string(),
-        None => {
-            return build_response(
-                StatusCode::BAD_REQUEST,
-                Some(connection_id.to_string()),
-                Some(vec!["Missing username".into()]),
-                None::<()>,
-            );
-        }
+    let Some(PlayerDetails {
+        color: requester_color,
+        username: requester_username,
+        opponent_username,
+    }) = get_player_details_from_connection_id(&old_game, connection_id)
+    else {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec!["You are not a player in this game".into()]),
+            None::<()>,
+        );
     };
 
-    let opponent_username = match opponent_username {
-        Some(u) => u.to_string(),
-        None => {
+    // The opponent is only needed for human rematches
+    let opponent = if old_game.engine_difficulty.is_some() {
+        None
+    } else {
+        let Some(opponent_username) = opponent_username else {
             return build_response(
                 StatusCode::BAD_REQUEST,
                 Some(connection_id.to_string()),
                 Some(vec!["Opponent username not found".into()]),
                 None::<()>,
             );
-        }
-    };
+        };
 
-    let opponent_connection_id = match opponent_connection_id {
-        Some(id) if id != "<disconnected>" => id.to_string(),
-        _ => {
-            return build_response(
-                StatusCode::BAD_REQUEST,
-                Some(connection_id.to_string()),
-                Some(vec!["Opponent is not connected".into()]),
-                None::<()>,
-            );
+        let opponent_connection_id = match requester_color {
+            Color::White => old_game.black_connection_id.as_ref(),
+            Color::Black => old_game.white_connection_id.as_ref(),
+        };
+
+        match opponent_connection_id.and_then(ConnectionState::connection_id) {
+            Some(id) => Some((opponent_username, id.to_string())),
+            _ => {
+                return build_response(
+                    StatusCode::BAD_REQUEST,
+                    Some(connection_id.to_string()),
+                    Some(vec!["Opponent is not connected".into()]),
+                    None::<()>,
+                );
+            }
         }
     };
 
+    // Colors are swapped by default
+    let color_preference = color_preference.unwrap_or(match requester_color {
+        Color::White => ColorPreference::Black,
+        Color::Black => ColorPreference::White,
+    });
+
     // Create new game with same settings, requester as first player
     let mut new_game = create_game(
         None,
         &requester_username,
-        Some(old_game.board_setup),
-        Some(old_game.color_preference),
-        None, // No engine for human rematch
+        Some(old_game.board_setup.clone()),
+        Some(color_preference),
+        old_game.engine_difficulty,
         old_game.seconds_per_player,
         connection_id,
     );
 
+    new_game.takebacks_allowed = old_game.takebacks_allowed;
+    new_game.match_score = get_next_match_score(&old_game, best_of);
+
+    let requester_seat_token = get_player_color(&new_game, &requester_username).map(|color| {
+        set_seat_authenticated(
+            &mut new_game,
+            color,
+            is_seat_authenticated(&old_game, requester_color),
+        );
+        issue_seat_token(&mut new_game, color)
+    });
+
     // Auto-join opponent
-    assign_player_to_existing_or_remaining_slot(
-        &mut new_game,
-        &opponent_username,
-        &opponent_connection_id,
-    )?;
+    let opponent_seat_token = match &opponent {
+        Some((opponent_username, opponent_connection_id)) => {
+            assign_player_to_existing_or_remaining_slot(
+                &mut new_game,
+                opponent_username,
+                opponent_connection_id,
+                None,
+                None,
+                is_seat_authenticated(&old_game, requester_color.opponent_color()),
+            )?
+        }
+        None => None,
+    };
+
+    // Set after seating the opponent, who already had to be logged in to play the rated game
+    new_game.rated = old_game.rated;
 
     // Both players present — start the game
-    check_if_both_players_just_joined(&mut new_game);
+    let game_started = check_if_both_players_just_joined(&mut new_game);
 
     // Populate legal moves for the initial board state so the rematch is immediately playable.
-    use_engine(&mut new_game, sdk_config, request_context, connection_id).await?;
+    // The engine moves first if it has white.
+    use_engine_in_new_game(&mut new_game);
+
+    insert_game_with_generated_id(repository, &mut new_game).await?;
 
-    save_game(dynamo_db_client, game_table, &new_game).await?;
+    if game_started {
+        notify_friends_of_started_game(notifier, repository, &new_game).await?;
+    }
 
     tracing::info!(
         "Created rematch game (ID: {}) from old game (ID: {game_id})",
@@ -145,50 +176,42 @@ pub async fn play_again(
     // Create user-game records for both players
     let requester_user_game =
         create_user_game(&new_game.game_id, &requester_username, connection_id);
-    save_user_record(dynamo_db_client, user_table, &requester_user_game).await?;
-
-    let opponent_user_game = create_user_game(
-        &new_game.game_id,
-        &opponent_username,
-        &opponent_connection_id,
-    );
-    save_user_record(dynamo_db_client, user_table, &opponent_user_game).await?;
-
-    // Notify opponent with the new game, including which old game it replaces
-    let _ = post_to_connection(
-        sdk_config,
-        request_context,
-        &opponent_connection_id,
-        &ApiResponse {
-            status_code: 200,
-            connection_id: Some(opponent_connection_id.clone()),
-            messages: vec![ApiMessage {
-                message: format!("{requester_username} wants a rematch!"),
-                message_type: ApiMessageType::Success,
-            }],
-            data: Some(&new_game),
-            replaces_game_id: Some(game_id.to_string()),
-        },
-    )
-    .await?;
+    repository.save_user_record(&requester_user_game).await?;
+
+    if let Some((opponent_username, opponent_connection_id)) = &opponent {
+        let opponent_user_game =
+            create_user_game(&new_game.game_id, opponent_username, opponent_connection_id);
+        repository.save_user_record(&opponent_user_game).await?;
+
+        // Notify opponent with the new game, including which old game it replaces
+        let _ = notifier
+            .send(
+                opponent_connection_id,
+                &ApiResponse {
+                    status_code: 200,
+                    connection_id: Some(opponent_connection_id.clone()),
+                    messages: vec![ApiMessage {
+                        message: format!("{requester_username} wants a rematch!"),
+                        message_type: ApiMessageType::Success,
+                    }],
+                    data: Some(&new_game),
+                    replaces_game_id: Some(game_id.to_string()),
+                    seat_token: opponent_seat_token,
+                    invite_token: None,
+                },
+            )
+            .await?;
+    }
 
     tracing::info!(
         "PLAYER {requester_username} STARTED REMATCH (old: {game_id}, new: {})",
         new_game.game_id
     );
 
-    // Return new game to requester — build response manually to include replaces_game_id
-    let body = serde_json::to_string(&ApiResponse {
-        status_code: 200,
-        connection_id: Some(connection_id.to_string()),
-        messages: Vec::new(),
-        data: Some(new_game),
-        replaces_game_id: Some(game_id.to_string()),
-    })?;
-
-    let mut response = ApiGatewayProxyResponse::default();
-    response.status_code = 200;
-    response.body = Some(Body::from(body));
-
-    Ok(response)
+    build_seated_player_response(
+        connection_id,
+        new_game,
+        requester_seat_token,
+        Some(game_id.to_string()),
+    )
 }
@@ -0,0 +1,65 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::{
+    helpers::{
+        auth::{hash_password, log_in_connection, validate_password, validate_username},
+        generic::get_timestamp,
+    },
+    repository::Repository,
+    types::{dynamo_db::UserInfoRecord, rating::Ratings},
+    utils::api::build_response,
+};
+
+use super::login::AuthResult;
+
+pub async fn register(
+    repository: &impl Repository,
+    connection_id: &str,
+    username: &str,
+    password: &str,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    if let Err(e) = validate_username(username).and(validate_password(password)) {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec![e.into()]),
+            None::<()>,
+        );
+    }
+
+    let user_info = UserInfoRecord {
+        username: username.to_string(),
+        sort_key: "INFO".to_string(),
+        password_hash: hash_password(password)?,
+        created: get_timestamp(),
+        last_seen: None,
+        ratings: Ratings::default(),
+    };
+
+    if !repository.create_user_info(&user_info).await? {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec![
+                format!("Username `{username}` is already taken").into()
+            ]),
+            None::<()>,
+        );
+    }
+
+    let session_token = log_in_connection(repository, username, connection_id).await?;
+
+    tracing::info!("USER {username} REGISTERED (CONNECTION ID: {connection_id})");
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        None,
+        Some(AuthResult {
+            username: username.to_string(),
+            session_token,
+        }),
+    )
+}
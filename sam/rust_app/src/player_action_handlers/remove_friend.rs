@@ -0,0 +1,43 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::{
+    helpers::user::get_authenticated_username, repository::Repository, utils::api::build_response,
+};
+
+pub async fn remove_friend(
+    repository: &impl Repository,
+    connection_id: &str,
+    friend: &str,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let Some(username) = get_authenticated_username(repository, connection_id).await? else {
+        return build_response(
+            StatusCode::UNAUTHORIZED,
+            Some(connection_id.to_string()),
+            Some(vec!["You must be logged in to remove friends".into()]),
+            None::<()>,
+        );
+    };
+
+    if !repository.has_added_friend(&username, friend).await? {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec![format!("{friend} is not in your friends list").into()]),
+            None::<()>,
+        );
+    }
+
+    // The other user's record is kept, so adding them again restores the friendship
+    repository.delete_friend(&username, friend).await?;
+
+    tracing::info!("USER {username} REMOVED {friend} AS A FRIEND");
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        Some(vec![format!("Removed {friend} from your friends").into()]),
+        None::<()>,
+    )
+}
@@ -0,0 +1,125 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::notifier::Notifier;
+use crate::{
+    helpers::{
+        abort::update_awaiting_first_move,
+        engine::{play_engine_reply, refresh_available_moves},
+        game::{
+            get_player_details_from_connection_id, get_takeback_ply_count, is_game_over,
+            notify_player_about_game_update, take_back_moves, update_game, GameAction, GameUpdate,
+            PlayerDetails,
+        },
+    },
+    repository::Repository,
+    utils::api::{build_game_conflict_response, build_response},
+};
+
+pub async fn request_takeback(
+    notifier: &impl Notifier,
+    repository: &impl Repository,
+    connection_id: &str,
+    game_id: &str,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let update = update_game(repository, game_id, async |game| {
+        let Some(PlayerDetails {
+            color: player_color,
+            username,
+            ..
+        }) = get_player_details_from_connection_id(game, connection_id)
+        else {
+            return Ok(GameAction::Reject(build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec!["You are not a player in this game".into()]),
+                None::<()>,
+            )?));
+        };
+
+        if is_game_over(game) {
+            return Ok(GameAction::Reject(build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec!["Game is already over".into()]),
+                None::<()>,
+            )?));
+        }
+
+        if !game.takebacks_allowed {
+            return Ok(GameAction::Reject(build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec!["Takebacks are disabled for this game".into()]),
+                None::<()>,
+            )?));
+        }
+
+        let Some(ply_count) = get_takeback_ply_count(&game.game_state, player_color) else {
+            return Ok(GameAction::Reject(build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec!["You have no move to take back".into()]),
+                None::<()>,
+            )?));
+        };
+
+        // The engine always agrees. Its reply is taken back along with the player's move.
+        if game.engine_difficulty.is_some() {
+            take_back_moves(&mut game.game_state, ply_count);
+
+            refresh_available_moves(game);
+            update_awaiting_first_move(game);
+
+            return Ok(GameAction::Save((username, true)));
+        }
+
+        if game.takeback_requested_by.is_some() {
+            return Ok(GameAction::Reject(build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec!["A takeback has already been requested".into()]),
+                None::<()>,
+            )?));
+        }
+
+        game.takeback_requested_by = Some(player_color);
+
+        Ok(GameAction::Save((username, false)))
+    })
+    .await?;
+
+    let (game, (username, taken_back_against_engine)) = match update {
+        GameUpdate::Saved { game, output } => (*game, output),
+        GameUpdate::Rejected(response) => return Ok(response),
+        GameUpdate::NotFound => {
+            return build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec![format!("Game with ID `{game_id}` not found").into()]),
+                None::<()>,
+            );
+        }
+        GameUpdate::Conflict => return build_game_conflict_response(connection_id),
+    };
+
+    // Taking back an odd number of moves leaves the engine to move
+    let (game, _) = play_engine_reply(notifier, repository, connection_id, game).await?;
+
+    notify_player_about_game_update(notifier, repository, connection_id, &game, None, false)
+        .await?;
+
+    if taken_back_against_engine {
+        tracing::info!("Player {username} took back a move against the engine in game {game_id}");
+    } else {
+        tracing::info!("Player {username} requested a takeback in game {game_id}");
+    }
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        None,
+        Some(game),
+    )
+}
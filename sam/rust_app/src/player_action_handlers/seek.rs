@@ -0,0 +1,187 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::helpers::auth::resolve_username;
+use crate::helpers::engine::refresh_available_moves;
+use crate::helpers::game::{
+    assign_player_to_existing_or_remaining_slot, check_if_both_players_just_joined, create_game,
+    get_player_color, insert_game_with_generated_id, issue_seat_token, set_seat_authenticated,
+};
+use crate::helpers::presence::notify_friends_of_started_game;
+use crate::helpers::rating::get_rating;
+use crate::helpers::seek::{are_seeks_compatible, create_seek, delete_seeks_from_connection_id};
+use crate::helpers::user::create_user_game;
+use crate::notifier::Notifier;
+use crate::repository::Repository;
+use crate::types::api::{ApiMessage, ApiMessageType, ApiResponse};
+use crate::types::board::BoardSetup;
+use crate::types::game::ColorPreference;
+use crate::types::rating::{Rating, TimeControlCategory};
+use crate::utils::api::{build_response, build_seated_player_response};
+
+pub async fn seek(
+    notifier: &impl Notifier,
+    repository: &impl Repository,
+    connection_id: &str,
+    username: Option<&str>,
+    board_setup: Option<BoardSetup>,
+    seconds_per_player: Option<usize>,
+    min_rating: Option<u32>,
+    max_rating: Option<u32>,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let resolved_username = match resolve_username(repository, connection_id, username).await? {
+        Ok(resolved_username) => resolved_username,
+        Err(e) => {
+            return build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec![e.into()]),
+                None::<()>,
+            );
+        }
+    };
+    let username = resolved_username.username.as_str();
+
+    if let (Some(min_rating), Some(max_rating)) = (min_rating, max_rating) {
+        if min_rating > max_rating {
+            return build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec![
+                    "Minimum rating cannot be greater than maximum rating".into()
+                ]),
+                None::<()>,
+            );
+        }
+    }
+
+    // A new seek replaces any earlier seek from this connection
+    delete_seeks_from_connection_id(repository, connection_id).await?;
+
+    // Guests could claim any username, so only logged-in players are matched by their rating
+    let rating = match resolved_username.authenticated {
+        true => {
+            let category = TimeControlCategory::from_seconds_per_player(seconds_per_player);
+            get_rating(repository, username, category).await?
+        }
+        false => Rating::default(),
+    };
+
+    let new_seek = create_seek(
+        connection_id,
+        username,
+        rating.rating.round() as u32,
+        resolved_username.authenticated,
+        min_rating,
+        max_rating,
+        board_setup.unwrap_or(BoardSetup::Standard),
+        seconds_per_player,
+    );
+
+    let mut matched_seek = None;
+
+    for existing_seek in repository.get_seeks_in_pool(&new_seek.pool).await? {
+        if !are_seeks_compatible(&new_seek, &existing_seek) {
+            continue;
+        }
+
+        // Another request may have paired with this seek in the meantime
+        if repository.claim_seek(&existing_seek).await? {
+            matched_seek = Some(existing_seek);
+            break;
+        }
+    }
+
+    let Some(opponent_seek) = matched_seek else {
+        repository.save_seek(&new_seek).await?;
+
+        tracing::info!(
+            "PLAYER {username} IS SEEKING A GAME (pool: {})",
+            new_seek.pool
+        );
+
+        return build_response(
+            StatusCode::OK,
+            Some(connection_id.to_string()),
+            Some(vec![ApiMessage {
+                message: "Waiting for an opponent...".to_string(),
+                message_type: ApiMessageType::Info,
+            }]),
+            Some(new_seek),
+        );
+    };
+
+    // The player who has been waiting longer creates the game
+    let mut new_game = create_game(
+        None,
+        &opponent_seek.username,
+        Some(opponent_seek.board_setup),
+        Some(ColorPreference::Random),
+        None,
+        opponent_seek.seconds_per_player,
+        &opponent_seek.connection_id,
+    );
+
+    let opponent_seat_token = get_player_color(&new_game, &opponent_seek.username).map(|color| {
+        set_seat_authenticated(&mut new_game, color, opponent_seek.authenticated);
+        issue_seat_token(&mut new_game, color)
+    });
+
+    let seat_token = assign_player_to_existing_or_remaining_slot(
+        &mut new_game,
+        username,
+        connection_id,
+        None,
+        None,
+        resolved_username.authenticated,
+    )?;
+
+    let game_started = check_if_both_players_just_joined(&mut new_game);
+
+    // Populate legal moves for the initial board state
+    refresh_available_moves(&mut new_game);
+
+    insert_game_with_generated_id(repository, &mut new_game).await?;
+
+    if game_started {
+        notify_friends_of_started_game(notifier, repository, &new_game).await?;
+    }
+
+    for (player_username, player_connection_id) in [
+        (username, connection_id),
+        (
+            opponent_seek.username.as_str(),
+            opponent_seek.connection_id.as_str(),
+        ),
+    ] {
+        let user_game = create_user_game(&new_game.game_id, player_username, player_connection_id);
+        repository.save_user_record(&user_game).await?;
+    }
+
+    notifier
+        .send(
+            &opponent_seek.connection_id,
+            &ApiResponse {
+                status_code: 200,
+                connection_id: Some(opponent_seek.connection_id.clone()),
+                messages: vec![ApiMessage {
+                    message: format!("Matched with {username}!"),
+                    message_type: ApiMessageType::Success,
+                }],
+                data: Some(&new_game),
+                replaces_game_id: None,
+                seat_token: opponent_seat_token,
+                invite_token: None,
+            },
+        )
+        .await?;
+
+    tracing::info!(
+        "PLAYERS {username} AND {} WERE MATCHED (game ID: {})",
+        opponent_seek.username,
+        new_game.game_id
+    );
+
+    build_seated_player_response(connection_id, new_game, seat_token, None)
+}
@@ -0,0 +1,112 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::notifier::Notifier;
+use crate::{
+    helpers::{
+        chat::{
+            create_chat_message, filter_words, get_engine_reply, get_recent_chat_messages,
+            is_rate_limited, relay_chat_message, validate_chat_message,
+        },
+        game::{get_player_details_from_connection_id, PlayerDetails},
+    },
+    repository::Repository,
+    utils::api::build_response,
+};
+
+pub async fn send_chat_message(
+    notifier: &impl Notifier,
+    repository: &impl Repository,
+    connection_id: &str,
+    chat_word_filter: &[String],
+    game_id: &str,
+    message: &str,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let Some(game) = repository.get_game(game_id).await? else {
+        return build_response(
+            StatusCode::NOT_FOUND,
+            Some(connection_id.to_string()),
+            Some(vec![format!("Game with ID `{game_id}` not found").into()]),
+            None::<()>,
+        );
+    };
+
+    let Some(PlayerDetails {
+        color: player_color,
+        username,
+        ..
+    }) = get_player_details_from_connection_id(&game, connection_id)
+    else {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec!["Only players can chat in this game".into()]),
+            None::<()>,
+        );
+    };
+
+    if let Err(e) = validate_chat_message(message) {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec![e.into()]),
+            None::<()>,
+        );
+    }
+
+    let recent_messages = get_recent_chat_messages(repository, game_id).await?;
+
+    if is_rate_limited(&recent_messages, connection_id) {
+        return build_response(
+            StatusCode::TOO_MANY_REQUESTS,
+            Some(connection_id.to_string()),
+            Some(vec![
+                "You are sending messages too quickly. Please wait a moment.".into(),
+            ]),
+            None::<()>,
+        );
+    }
+
+    let chat_message = create_chat_message(
+        game_id,
+        Some(connection_id),
+        Some(&username),
+        &filter_words(message.trim(), chat_word_filter),
+    );
+    repository.save_chat_message(&chat_message).await?;
+
+    relay_chat_message(
+        notifier,
+        repository,
+        &game,
+        &chat_message,
+        Some(player_color),
+    )
+    .await?;
+
+    let mut new_messages = vec![chat_message];
+
+    if game.engine_difficulty.is_some() {
+        let engine_reply = create_chat_message(
+            game_id,
+            None,
+            None,
+            get_engine_reply(&new_messages[0].message),
+        );
+        repository.save_chat_message(&engine_reply).await?;
+
+        relay_chat_message(notifier, repository, &game, &engine_reply, None).await?;
+
+        new_messages.push(engine_reply);
+    }
+
+    tracing::info!("Player {username} sent a chat message in game {game_id}");
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        None,
+        Some(new_messages),
+    )
+}
@@ -0,0 +1,100 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::{
+    helpers::game::{
+        get_player_details_from_connection_id, is_game_over, update_game, GameAction, GameUpdate,
+        PlayerDetails,
+    },
+    repository::Repository,
+    types::{game::PlayerMove, piece::Color},
+    utils::api::{build_game_conflict_response, build_response},
+};
+
+/// Queues a move to be played as soon as the opponent moves. A `None` move clears the premove.
+pub async fn set_premove(
+    repository: &impl Repository,
+    connection_id: &str,
+    game_id: &str,
+    player_move: Option<PlayerMove>,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let update = update_game(repository, game_id, async |game| {
+        let Some(PlayerDetails {
+            color: player_color,
+            username,
+            ..
+        }) = get_player_details_from_connection_id(game, connection_id)
+        else {
+            return Ok(GameAction::Reject(build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec!["You are not a player in this game".into()]),
+                None::<()>,
+            )?));
+        };
+
+        if game.engine_difficulty.is_some() {
+            return Ok(GameAction::Reject(build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec!["Premoves are not available in engine games".into()]),
+                None::<()>,
+            )?));
+        }
+
+        if is_game_over(game) {
+            return Ok(GameAction::Reject(build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec!["Game is already over".into()]),
+                None::<()>,
+            )?));
+        }
+
+        if player_move.is_some() && game.game_state.current_state().current_turn == player_color {
+            return Ok(GameAction::Reject(build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec!["It's your turn. Make a move instead.".into()]),
+                None::<()>,
+            )?));
+        }
+
+        match player_color {
+            Color::White => game.white_premove = player_move.clone(),
+            Color::Black => game.black_premove = player_move.clone(),
+        }
+
+        Ok(GameAction::Save(username))
+    })
+    .await?;
+
+    let is_premove_set = player_move.is_some();
+
+    let username = match update {
+        GameUpdate::Saved { output, .. } => output,
+        GameUpdate::Rejected(response) => return Ok(response),
+        GameUpdate::NotFound => {
+            return build_response(
+                StatusCode::NOT_FOUND,
+                Some(connection_id.to_string()),
+                Some(vec![format!("Game with ID `{game_id}` not found").into()]),
+                None::<()>,
+            );
+        }
+        GameUpdate::Conflict => return build_game_conflict_response(connection_id),
+    };
+
+    tracing::info!(
+        "Player {username} {} a premove in game {game_id}",
+        if is_premove_set { "set" } else { "cleared" }
+    );
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        None,
+        None::<()>,
+    )
+}
@@ -0,0 +1,187 @@
+use std::convert::Infallible;
+
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::{
+    helpers::{
+        game::insert_game_with_generated_id,
+        tournament::{
+            create_round_games, get_next_round, get_round_count, get_standings, is_round_complete,
+            update_tournament, update_tournament_results, TournamentAction, TournamentUpdate,
+            MIN_TOURNAMENT_PLAYERS,
+        },
+        user::{create_user_game, get_authenticated_username},
+    },
+    repository::Repository,
+    types::{
+        presence::ConnectionState,
+        tournament::{TournamentDetails, TournamentState},
+    },
+    utils::api::{build_response, build_tournament_conflict_response},
+};
+
+/// Pairs the next round and creates its games. Starting the first round closes registration.
+/// Only the organizer can start rounds, and only once every game of the previous round is over.
+pub async fn start_tournament_round(
+    repository: &impl Repository,
+    connection_id: &str,
+    tournament_id: &str,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let username = get_authenticated_username(repository, connection_id).await?;
+
+    // The round is saved before its games are inserted, so a concurrent request can't create
+    // the same round's games again
+    let update = update_tournament(repository, tournament_id, async |tournament| {
+        if username.as_deref() != Some(tournament.organizer.as_str()) {
+            return Ok(TournamentAction::Reject(build_response(
+                StatusCode::FORBIDDEN,
+                Some(connection_id.to_string()),
+                Some(vec!["Only the organizer can start tournament rounds".into()]),
+                None::<()>,
+            )?));
+        }
+
+        update_tournament_results(repository, tournament).await?;
+
+        match tournament.state {
+            TournamentState::Registration if tournament.players.len() < MIN_TOURNAMENT_PLAYERS => {
+                return Ok(TournamentAction::Reject(build_response(
+                    StatusCode::BAD_REQUEST,
+                    Some(connection_id.to_string()),
+                    Some(vec![format!(
+                        "At least {MIN_TOURNAMENT_PLAYERS} players must register before the tournament can start"
+                    )
+                    .into()]),
+                    None::<()>,
+                )?));
+            }
+            TournamentState::Registration => tournament.state = TournamentState::InProgress,
+            TournamentState::InProgress
+                if tournament
+                    .rounds
+                    .last()
+                    .is_some_and(|round| !is_round_complete(round)) =>
+            {
+                return Ok(TournamentAction::Reject(build_response(
+                    StatusCode::BAD_REQUEST,
+                    Some(connection_id.to_string()),
+                    Some(vec![
+                        "Every game of the current round must finish first".into()
+                    ]),
+                    None::<()>,
+                )?));
+            }
+            TournamentState::InProgress => {}
+            TournamentState::Finished => {
+                return Ok(TournamentAction::Reject(build_response(
+                    StatusCode::BAD_REQUEST,
+                    Some(connection_id.to_string()),
+                    Some(vec!["All rounds have been played".into()]),
+                    None::<()>,
+                )?));
+            }
+        }
+
+        let mut round = get_next_round(tournament);
+
+        let round_number = tournament.rounds.len() + 1;
+        let games = create_round_games(tournament, round_number, &mut round);
+
+        tournament.rounds.push(round);
+
+        Ok(TournamentAction::Save(games))
+    })
+    .await?;
+
+    let (mut tournament, games) = match update {
+        TournamentUpdate::Saved { tournament, output } => (*tournament, output),
+        TournamentUpdate::Rejected(response) => return Ok(response),
+        TournamentUpdate::NotFound => {
+            return build_response(
+                StatusCode::NOT_FOUND,
+                Some(connection_id.to_string()),
+                Some(vec![format!(
+                    "Tournament with ID `{tournament_id}` not found"
+                )
+                .into()]),
+                None::<()>,
+            );
+        }
+        TournamentUpdate::Conflict => return build_tournament_conflict_response(connection_id),
+    };
+
+    let round_number = tournament.rounds.len();
+    let game_count = games.len();
+    let mut renamed_game_ids = Vec::new();
+
+    for mut game in games {
+        let paired_game_id = game.game_id.clone();
+        insert_game_with_generated_id(repository, &mut game).await?;
+
+        if game.game_id != paired_game_id {
+            renamed_game_ids.push((paired_game_id, game.game_id.clone()));
+        }
+
+        // Players join their games themselves, so their user-game records start disconnected
+        for username in [&game.white_username, &game.black_username]
+            .into_iter()
+            .flatten()
+        {
+            let mut user_game = create_user_game(&game.game_id, username, "");
+            user_game.connection_id = Some(ConnectionState::Disconnected);
+            repository.save_user_record(&user_game).await?;
+        }
+    }
+
+    // The pairings were saved with the IDs the games were created with, one of which was taken
+    if !renamed_game_ids.is_empty() {
+        let update = update_tournament(repository, tournament_id, async |tournament| {
+            let pairings = tournament
+                .rounds
+                .get_mut(round_number - 1)
+                .map(|round| round.pairings.iter_mut())
+                .into_iter()
+                .flatten();
+
+            for pairing in pairings {
+                if let Some((_, game_id)) = renamed_game_ids
+                    .iter()
+                    .find(|(paired_game_id, _)| pairing.game_id.as_ref() == Some(paired_game_id))
+                {
+                    pairing.game_id = Some(game_id.clone());
+                }
+            }
+
+            Ok(TournamentAction::<_, Infallible>::Save(()))
+        })
+        .await?;
+
+        tournament = match update {
+            TournamentUpdate::Saved { tournament, .. } => *tournament,
+            TournamentUpdate::Rejected(never) => match never {},
+            TournamentUpdate::NotFound | TournamentUpdate::Conflict => {
+                return Err(Error::from(format!(
+                    "Could not save the game IDs of round {round_number} in tournament (ID: {tournament_id})"
+                )));
+            }
+        };
+    }
+
+    tracing::info!(
+        "STARTED ROUND {round_number} OF {} IN TOURNAMENT (ID: {tournament_id}) WITH {} GAME(S)",
+        get_round_count(&tournament),
+        game_count
+    );
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        None,
+        Some(TournamentDetails {
+            standings: get_standings(&tournament),
+            tournament,
+        }),
+    )
+}
@@ -0,0 +1,50 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::notifier::Notifier;
+use crate::{
+    helpers::{game::remove_spectator_and_notify_game, subscription::game_topic},
+    repository::Repository,
+    utils::api::build_response,
+};
+
+pub async fn stop_watching_game(
+    notifier: &impl Notifier,
+    repository: &impl Repository,
+    connection_id: &str,
+    game_id: &str,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let Some(game) = repository.get_game(game_id).await? else {
+        return build_response(
+            StatusCode::NOT_FOUND,
+            Some(connection_id.to_string()),
+            Some(vec![format!("Game with ID `{game_id}` not found").into()]),
+            None::<()>,
+        );
+    };
+
+    if repository
+        .get_subscription(&game_topic(&game.game_id), connection_id)
+        .await?
+        .is_none()
+    {
+        return build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec!["You are not watching this game".into()]),
+            None::<()>,
+        );
+    }
+
+    remove_spectator_and_notify_game(notifier, repository, &game.game_id, connection_id).await?;
+
+    tracing::info!("CONNECTION {connection_id} STOPPED WATCHING GAME (ID: {game_id})");
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        None,
+        None::<()>,
+    )
+}
@@ -0,0 +1,97 @@
+use aws_lambda_events::apigw::ApiGatewayProxyResponse;
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+
+use crate::notifier::Notifier;
+use crate::{
+    helpers::{
+        game::{
+            get_player_details_from_connection_id, notify_player_about_game_update, update_game,
+            GameAction, GameUpdate,
+        },
+        subscription::{create_subscription, game_topic},
+    },
+    repository::Repository,
+    utils::api::{build_game_conflict_response, build_response},
+};
+
+pub async fn watch_game(
+    notifier: &impl Notifier,
+    repository: &impl Repository,
+    connection_id: &str,
+    game_id: &str,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let topic = game_topic(game_id);
+
+    // Already watching, so the spectator count stays as it is
+    let already_watching = repository
+        .get_subscription(&topic, connection_id)
+        .await?
+        .is_some();
+
+    // Subscribe before counting the spectator, so a failure can't leave the count too high
+    if !already_watching {
+        repository
+            .save_subscription(&create_subscription(&topic, connection_id))
+            .await?;
+    }
+
+    let update = update_game(repository, game_id, async |game| {
+        if get_player_details_from_connection_id(game, connection_id).is_some() {
+            return Ok(GameAction::Reject(build_response(
+                StatusCode::BAD_REQUEST,
+                Some(connection_id.to_string()),
+                Some(vec!["You are a player in this game".into()]),
+                None::<()>,
+            )?));
+        }
+
+        if already_watching {
+            return Ok(GameAction::Reject(build_response(
+                StatusCode::OK,
+                Some(connection_id.to_string()),
+                None,
+                Some(&*game),
+            )?));
+        }
+
+        game.spectator_count += 1;
+
+        Ok(GameAction::Save(()))
+    })
+    .await;
+
+    if !already_watching && !matches!(update, Ok(GameUpdate::Saved { .. })) {
+        repository
+            .delete_subscription(&topic, connection_id)
+            .await?;
+    }
+
+    let game = match update? {
+        GameUpdate::Saved { game, .. } => *game,
+        GameUpdate::Rejected(response) => return Ok(response),
+        GameUpdate::NotFound => {
+            return build_response(
+                StatusCode::NOT_FOUND,
+                Some(connection_id.to_string()),
+                Some(vec![format!("Game with ID `{game_id}` not found").into()]),
+                None::<()>,
+            );
+        }
+        GameUpdate::Conflict => return build_game_conflict_response(connection_id),
+    };
+
+    // Tell the players and existing spectators about the new spectator count.
+    // The new spectator receives the game in the response instead.
+    notify_player_about_game_update(notifier, repository, connection_id, &game, None, false)
+        .await?;
+
+    tracing::info!("CONNECTION {connection_id} IS WATCHING GAME (ID: {game_id})");
+
+    build_response(
+        StatusCode::OK,
+        Some(connection_id.to_string()),
+        None,
+        Some(game),
+    )
+}
@@ -0,0 +1,878 @@
+use std::collections::HashMap;
+
+use aws_sdk_dynamodb::{
+    types::{AttributeValue, Put, TransactWriteItem, Update},
+    Client,
+};
+use lambda_runtime::Error;
+use serde_dynamo::aws_sdk_dynamodb_1::{from_item, to_attribute_value, to_item};
+
+use crate::{
+    helpers::{
+        generic::get_timestamp,
+        lobby::OPEN_GAME_LISTING,
+        stats::{get_result_counts, get_stats_slices, StatsSlice},
+        tournament::AWAITING_JOIN_LISTING,
+    },
+    types::{
+        dynamo_db::{
+            AwaitingFirstMoveRecord, AwaitingJoinRecord, ChallengeRecord, ChatMessageRecord, FriendRecord, GameRecord,
+            OpenGameRecord, SeekRecord, SessionRecord, SubscriptionRecord, TournamentRecord,
+            UserInfoRecord, UserRecord, UserStatsRecord,
+        },
+        history::HistoryCursor,
+        piece::Color,
+        rating::{RatingUpdate, Ratings},
+    },
+    utils::dynamo_db::{
+        delete_item, delete_item_if_exists, get_item, put_item, put_item_if,
+        put_item_if_not_exists, query_items, query_page, transact_write_items, update_item,
+    },
+};
+
+use super::{
+    from_game_item, to_game_item, ChatRepository, GameRepository, GameResultWrites, Item,
+    SeekRepository, SubscriptionRepository, TournamentRepository, UserGameHistoryPage,
+    UserRepository,
+};
+
+// Games saved before versioning was added don't have a version yet
+const GAME_VERSION_CONDITION: &str =
+    "version = :expected_version OR (attribute_exists(game_id) AND attribute_not_exists(version))";
+const TOURNAMENT_VERSION_CONDITION: &str = "version = :expected_version OR (attribute_exists(tournament_id) AND attribute_not_exists(version))";
+
+/// The tables and indexes are named by the deployment, so they're read from the environment
+pub struct DynamoDbRepository {
+    client: Client,
+    game_table: String,
+    game_table_lobby_gsi: String,
+    game_table_first_move_gsi: String,
+    game_table_join_gsi: String,
+    user_table: String,
+    user_table_gsi: String,
+    user_table_history_gsi: String,
+    subscription_table: String,
+    subscription_table_gsi: String,
+    seek_table: String,
+    seek_table_gsi: String,
+    chat_table: String,
+    tournament_table: String,
+}
+
+impl DynamoDbRepository {
+    pub fn from_env(client: Client) -> Self {
+        let var = |name: &str| std::env::var(name).unwrap();
+
+        DynamoDbRepository {
+            client,
+            game_table: var("GAME_TABLE"),
+            game_table_lobby_gsi: var("GAME_TABLE_LOBBY_GSI"),
+            game_table_first_move_gsi: var("GAME_TABLE_FIRST_MOVE_GSI"),
+            game_table_join_gsi: var("GAME_TABLE_JOIN_GSI"),
+            user_table: var("USER_TABLE"),
+            user_table_gsi: var("USER_TABLE_GSI"),
+            user_table_history_gsi: var("USER_TABLE_HISTORY_GSI"),
+            subscription_table: var("SUBSCRIPTION_TABLE"),
+            subscription_table_gsi: var("SUBSCRIPTION_TABLE_GSI"),
+            seek_table: var("SEEK_TABLE"),
+            seek_table_gsi: var("SEEK_TABLE_GSI"),
+            chat_table: var("CHAT_TABLE"),
+            tournament_table: var("TOURNAMENT_TABLE"),
+        }
+    }
+
+    /// Queries the table (or one of its indexes) for every item whose attributes have the given
+    /// values, with `begins_with` for the attribute named by `prefix`, if any
+    async fn query<T: serde::de::DeserializeOwned + serde::Serialize>(
+        &self,
+        table: &str,
+        index: Option<&str>,
+        key: &[(&str, &str)],
+        prefix: Option<(&str, &str)>,
+    ) -> Result<Vec<T>, Error> {
+        let mut conditions = Vec::new();
+        let mut expression_attribute_values = HashMap::new();
+
+        for (name, value) in key {
+            conditions.push(format!("{name} = :{name}"));
+            expression_attribute_values
+                .insert(format!(":{name}"), AttributeValue::S(value.to_string()));
+        }
+
+        if let Some((name, value)) = prefix {
+            conditions.push(format!("begins_with({name}, :prefix)"));
+            expression_attribute_values
+                .insert(":prefix".to_string(), AttributeValue::S(value.to_string()));
+        }
+
+        query_items(
+            &self.client,
+            table,
+            Some(conditions.join(" AND ")),
+            None,
+            Some(expression_attribute_values),
+            index.map(str::to_string),
+        )
+        .await
+    }
+
+    /// The game as it's stored, so older games can be converted
+    async fn get_game_item(
+        &self,
+        game_id: &str,
+        consistent_read: bool,
+    ) -> Result<Option<Item>, Error> {
+        let response = self
+            .client
+            .get_item()
+            .table_name(&self.game_table)
+            .set_key(Some(key(&[("game_id", game_id)])))
+            .consistent_read(consistent_read)
+            .send()
+            .await?;
+
+        Ok(response.item)
+    }
+}
+
+fn key(attributes: &[(&str, &str)]) -> Item {
+    attributes
+        .iter()
+        .map(|(name, value)| (name.to_string(), AttributeValue::S(value.to_string())))
+        .collect()
+}
+
+fn user_record_key(username: &str, sort_key: &str) -> Item {
+    key(&[("username", username), ("sk", sort_key)])
+}
+
+/// Builds the update that records the game's winner ("white", "black" or "draw") on the
+/// player's user-game record, creating the record if it's missing
+fn build_user_game_result_update(
+    table: &str,
+    username: &str,
+    game: &GameRecord,
+    winner: &str,
+) -> Result<TransactWriteItem, Error> {
+    let mut expression_attribute_values = HashMap::new();
+    expression_attribute_values.insert(":winner".to_string(), AttributeValue::S(winner.into()));
+    expression_attribute_values.insert(":now".to_string(), AttributeValue::S(get_timestamp()));
+    expression_attribute_values.insert(
+        ":game_created".to_string(),
+        AttributeValue::S(game.created.clone()),
+    );
+
+    let update = Update::builder()
+        .table_name(table)
+        .set_key(Some(user_record_key(
+            username,
+            &format!("GAME-{}", game.game_id),
+        )))
+        .update_expression(
+            "SET winner = :winner, created = if_not_exists(created, :now), \
+             game_created = if_not_exists(game_created, :game_created)",
+        )
+        .set_expression_attribute_values(Some(expression_attribute_values))
+        .build()?;
+
+    Ok(TransactWriteItem::builder().update(update).build())
+}
+
+/// Builds the updates that add a finished game to the player's stats. Aborted and unfinished
+/// games aren't counted.
+fn build_stats_updates(
+    table: &str,
+    game: &GameRecord,
+    username: &str,
+    color: Color,
+) -> Result<Vec<TransactWriteItem>, Error> {
+    let Some((wins, draws, losses)) = get_result_counts(game, color) else {
+        return Ok(Vec::new());
+    };
+
+    let mut updates = Vec::new();
+
+    for StatsSlice {
+        sort_key,
+        attributes: slice_attributes,
+    } in get_stats_slices(game, color)?
+    {
+        let mut expression_attribute_values = HashMap::from([
+            (":wins".to_string(), AttributeValue::N(wins.to_string())),
+            (":draws".to_string(), AttributeValue::N(draws.to_string())),
+            (":losses".to_string(), AttributeValue::N(losses.to_string())),
+            (
+                ":plies".to_string(),
+                AttributeValue::N(game.game_state.move_list.len().to_string()),
+            ),
+        ]);
+
+        // Counters are added to atomically, so concurrent games can't overwrite each other
+        let mut update_expression =
+            "ADD wins :wins, draws :draws, losses :losses, total_plies :plies".to_string();
+
+        // Attribute names are aliased in case any of them is a reserved word
+        let mut expression_attribute_names = HashMap::new();
+
+        if !slice_attributes.is_empty() {
+            let assignments: Vec<String> = slice_attributes
+                .keys()
+                .map(|name| format!("#{name} = :{name}"))
+                .collect();
+            update_expression.push_str(&format!(" SET {}", assignments.join(", ")));
+
+            for (name, value) in slice_attributes {
+                expression_attribute_names.insert(format!("#{name}"), name.clone());
+                expression_attribute_values.insert(format!(":{name}"), value);
+            }
+        }
+
+        let update = Update::builder()
+            .table_name(table)
+            .set_key(Some(user_record_key(username, &sort_key)))
+            .update_expression(update_expression)
+            .set_expression_attribute_names(
+                (!expression_attribute_names.is_empty()).then_some(expression_attribute_names),
+            )
+            .set_expression_attribute_values(Some(expression_attribute_values))
+            .build()?;
+
+        updates.push(TransactWriteItem::builder().update(update).build());
+    }
+
+    Ok(updates)
+}
+
+fn build_rating_update(
+    table: &str,
+    rating_update: &RatingUpdate,
+) -> Result<TransactWriteItem, Error> {
+    // Fails the transaction if another game changed the rating since it was read
+    let condition_expression = match rating_update.previous {
+        Some(_) => "#rating.games = :games",
+        None => "attribute_not_exists(#rating)",
+    };
+
+    let mut expression_attribute_values = HashMap::new();
+    expression_attribute_values.insert(
+        ":rating".to_string(),
+        to_attribute_value(rating_update.new)?,
+    );
+    if let Some(previous) = rating_update.previous {
+        expression_attribute_values.insert(
+            ":games".to_string(),
+            AttributeValue::N(previous.games.to_string()),
+        );
+    }
+
+    let update = Update::builder()
+        .table_name(table)
+        .set_key(Some(user_record_key(&rating_update.username, "INFO")))
+        .update_expression("SET #rating = :rating")
+        .condition_expression(condition_expression)
+        .expression_attribute_names("#rating", Ratings::attribute_name(rating_update.category))
+        .set_expression_attribute_values(Some(expression_attribute_values))
+        .build()?;
+
+    Ok(TransactWriteItem::builder().update(update).build())
+}
+
+fn to_history_cursor(last_evaluated_key: &Item) -> Option<HistoryCursor> {
+    let get = |name: &str| last_evaluated_key.get(name)?.as_s().ok().cloned();
+
+    Some(HistoryCursor {
+        username: get("username")?,
+        sk: get("sk")?,
+        game_created: get("game_created")?,
+    })
+}
+
+impl GameRepository for DynamoDbRepository {
+    async fn get_game(&self, game_id: &str) -> Result<Option<GameRecord>, Error> {
+        match self.get_game_item(game_id, false).await? {
+            Some(item) => Ok(Some(from_game_item(self, item).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_latest_game(&self, game_id: &str) -> Result<Option<GameRecord>, Error> {
+        match self.get_game_item(game_id, true).await? {
+            Some(item) => Ok(Some(from_game_item(self, item).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn insert_game(&self, game: &GameRecord) -> Result<bool, Error> {
+        put_item_if(
+            &self.client,
+            &self.game_table,
+            to_game_item(game)?,
+            "attribute_not_exists(game_id)",
+            None,
+        )
+        .await
+    }
+
+    async fn save_game(
+        &self,
+        game: &GameRecord,
+        expected_version: u64,
+        result_writes: &GameResultWrites,
+    ) -> Result<bool, Error> {
+        let mut expression_attribute_values = HashMap::new();
+        expression_attribute_values.insert(
+            ":expected_version".to_string(),
+            AttributeValue::N(expected_version.to_string()),
+        );
+
+        if result_writes.is_empty() {
+            return put_item_if(
+                &self.client,
+                &self.game_table,
+                to_game_item(game)?,
+                GAME_VERSION_CONDITION,
+                Some(expression_attribute_values),
+            )
+            .await;
+        }
+
+        let put = Put::builder()
+            .table_name(&self.game_table)
+            .set_item(Some(to_game_item(game)?))
+            .condition_expression(GAME_VERSION_CONDITION)
+            .set_expression_attribute_values(Some(expression_attribute_values))
+            .build()?;
+
+        let mut transact_items = vec![TransactWriteItem::builder().put(put).build()];
+
+        for (username, color) in &result_writes.players {
+            transact_items.push(build_user_game_result_update(
+                &self.user_table,
+                username,
+                game,
+                &result_writes.winner,
+            )?);
+            transact_items.extend(build_stats_updates(
+                &self.user_table,
+                game,
+                username,
+                *color,
+            )?);
+        }
+
+        for rating_update in &result_writes.rating_updates {
+            transact_items.push(build_rating_update(&self.user_table, rating_update)?);
+        }
+
+        transact_write_items(&self.client, transact_items).await
+    }
+
+    async fn get_open_games(&self) -> Result<Vec<OpenGameRecord>, Error> {
+        self.query(
+            &self.game_table,
+            Some(&self.game_table_lobby_gsi),
+            &[("lobby", OPEN_GAME_LISTING)],
+            None,
+        )
+        .await
+    }
+
+    async fn get_games_awaiting_first_move(
+        &self,
+        color: Color,
+        cutoff: &str,
+    ) -> Result<Vec<AwaitingFirstMoveRecord>, Error> {
+        let key_condition_expression =
+            "awaiting_first_move = :color AND awaiting_first_move_since < :cutoff".to_string();
+
+        let mut expression_attribute_values = HashMap::new();
+        expression_attribute_values
+            .insert(":color".to_string(), AttributeValue::S(color.to_string()));
+        expression_attribute_values.insert(":cutoff".to_string(), AttributeValue::S(cutoff.into()));
+
+        query_items(
+            &self.client,
+            &self.game_table,
+            Some(key_condition_expression),
+            None,
+            Some(expression_attribute_values),
+            Some(self.game_table_first_move_gsi.clone()),
+        )
+        .await
+    }
+
+    async fn get_games_awaiting_join(&self, cutoff: &str) -> Result<Vec<AwaitingJoinRecord>, Error> {
+        let key_condition_expression =
+            "awaiting_join = :listing AND awaiting_join_since < :cutoff".to_string();
+
+        let mut expression_attribute_values = HashMap::new();
+        expression_attribute_values.insert(
+            ":listing".to_string(),
+            AttributeValue::S(AWAITING_JOIN_LISTING.to_string()),
+        );
+        expression_attribute_values.insert(":cutoff".to_string(), AttributeValue::S(cutoff.into()));
+
+        query_items(
+            &self.client,
+            &self.game_table,
+            Some(key_condition_expression),
+            None,
+            Some(expression_attribute_values),
+            Some(self.game_table_join_gsi.clone()),
+        )
+        .await
+    }
+}
+
+impl UserRepository for DynamoDbRepository {
+    async fn save_user_record(&self, user_record: &UserRecord) -> Result<(), Error> {
+        put_item(&self.client, &self.user_table, user_record).await
+    }
+
+    async fn delete_user_record(&self, username: &str, sort_key: &str) -> Result<(), Error> {
+        delete_item(
+            &self.client,
+            &self.user_table,
+            user_record_key(username, sort_key),
+        )
+        .await
+    }
+
+    async fn get_user_game(
+        &self,
+        username: &str,
+        game_id: &str,
+    ) -> Result<Option<UserRecord>, Error> {
+        get_item(
+            &self.client,
+            &self.user_table,
+            user_record_key(username, &format!("GAME-{game_id}")),
+        )
+        .await
+    }
+
+    async fn get_all_user_games(&self, username: &str) -> Result<Vec<UserRecord>, Error> {
+        self.query(
+            &self.user_table,
+            None,
+            &[("username", username)],
+            Some(("sk", "GAME-")),
+        )
+        .await
+    }
+
+    async fn get_user_connections(&self, username: &str) -> Result<Vec<UserRecord>, Error> {
+        self.query(
+            &self.user_table,
+            None,
+            &[("username", username)],
+            Some(("sk", "CONNECTION-")),
+        )
+        .await
+    }
+
+    async fn get_user_games_from_connection_id(
+        &self,
+        connection_id: &str,
+    ) -> Result<Vec<UserRecord>, Error> {
+        self.query(
+            &self.user_table,
+            Some(&self.user_table_gsi),
+            &[("connection_id", connection_id)],
+            None,
+        )
+        .await
+    }
+
+    async fn get_user_game_history_page(
+        &self,
+        username: &str,
+        limit: usize,
+        cursor: Option<HistoryCursor>,
+    ) -> Result<UserGameHistoryPage, Error> {
+        let key_condition_expression = "username = :username".to_string();
+
+        let mut expression_attribute_values = HashMap::new();
+        expression_attribute_values.insert(
+            ":username".to_string(),
+            AttributeValue::S(username.to_string()),
+        );
+
+        let exclusive_start_key = cursor.map(|cursor| {
+            key(&[
+                ("username", &cursor.username),
+                ("sk", &cursor.sk),
+                ("game_created", &cursor.game_created),
+            ])
+        });
+
+        let page = query_page(
+            &self.client,
+            &self.user_table,
+            Some(key_condition_expression),
+            None,
+            Some(expression_attribute_values),
+            Some(self.user_table_history_gsi.clone()),
+            Some(limit as i32),
+            exclusive_start_key,
+            false,
+        )
+        .await?;
+
+        Ok(UserGameHistoryPage {
+            items: page.items,
+            next_cursor: page.last_evaluated_key.as_ref().and_then(to_history_cursor),
+        })
+    }
+
+    async fn get_user_info(&self, username: &str) -> Result<Option<UserInfoRecord>, Error> {
+        get_item(
+            &self.client,
+            &self.user_table,
+            user_record_key(username, "INFO"),
+        )
+        .await
+    }
+
+    async fn create_user_info(&self, user_info: &UserInfoRecord) -> Result<bool, Error> {
+        put_item_if_not_exists(&self.client, &self.user_table, "username", user_info).await
+    }
+
+    async fn update_last_seen(&self, username: &str) -> Result<(), Error> {
+        let mut expression_attribute_values = HashMap::new();
+        expression_attribute_values
+            .insert(":last_seen".to_string(), AttributeValue::S(get_timestamp()));
+
+        update_item(
+            &self.client,
+            &self.user_table,
+            user_record_key(username, "INFO"),
+            "SET last_seen = :last_seen",
+            None,
+            expression_attribute_values,
+        )
+        .await
+    }
+
+    async fn save_session(&self, session: &SessionRecord) -> Result<(), Error> {
+        put_item(&self.client, &self.user_table, session).await
+    }
+
+    async fn get_session(
+        &self,
+        username: &str,
+        sort_key: &str,
+    ) -> Result<Option<SessionRecord>, Error> {
+        get_item(
+            &self.client,
+            &self.user_table,
+            user_record_key(username, sort_key),
+        )
+        .await
+    }
+
+    async fn get_user_stats_records(&self, username: &str) -> Result<Vec<UserStatsRecord>, Error> {
+        self.query(
+            &self.user_table,
+            None,
+            &[("username", username)],
+            Some(("sk", "STATS#")),
+        )
+        .await
+    }
+
+    async fn save_friend(&self, friend: &FriendRecord) -> Result<(), Error> {
+        put_item(&self.client, &self.user_table, friend).await
+    }
+
+    async fn delete_friend(&self, username: &str, friend: &str) -> Result<(), Error> {
+        self.delete_user_record(username, &format!("FRIEND-{friend}"))
+            .await
+    }
+
+    async fn has_added_friend(&self, username: &str, friend: &str) -> Result<bool, Error> {
+        let friend_record: Option<FriendRecord> = get_item(
+            &self.client,
+            &self.user_table,
+            user_record_key(username, &format!("FRIEND-{friend}")),
+        )
+        .await?;
+
+        Ok(friend_record.is_some())
+    }
+
+    async fn get_added_friends(&self, username: &str) -> Result<Vec<String>, Error> {
+        let friend_records: Vec<FriendRecord> = self
+            .query(
+                &self.user_table,
+                None,
+                &[("username", username)],
+                Some(("sk", "FRIEND-")),
+            )
+            .await?;
+
+        Ok(friend_records
+            .into_iter()
+            .map(|record| record.friend)
+            .collect())
+    }
+
+    async fn save_challenge(&self, challenge: &ChallengeRecord) -> Result<(), Error> {
+        put_item(&self.client, &self.user_table, challenge).await
+    }
+
+    async fn get_challenge(
+        &self,
+        username: &str,
+        challenge_id: &str,
+    ) -> Result<Option<ChallengeRecord>, Error> {
+        get_item(
+            &self.client,
+            &self.user_table,
+            user_record_key(username, &format!("CHALLENGE-{challenge_id}")),
+        )
+        .await
+    }
+
+    async fn claim_challenge(&self, challenge: &ChallengeRecord) -> Result<bool, Error> {
+        delete_item_if_exists(
+            &self.client,
+            &self.user_table,
+            "username",
+            user_record_key(&challenge.username, &challenge.sort_key),
+        )
+        .await
+    }
+}
+
+impl SubscriptionRepository for DynamoDbRepository {
+    async fn save_subscription(&self, subscription: &SubscriptionRecord) -> Result<(), Error> {
+        put_item(&self.client, &self.subscription_table, subscription).await
+    }
+
+    async fn get_subscription(
+        &self,
+        topic: &str,
+        connection_id: &str,
+    ) -> Result<Option<SubscriptionRecord>, Error> {
+        get_item(
+            &self.client,
+            &self.subscription_table,
+            key(&[("topic", topic), ("connection_id", connection_id)]),
+        )
+        .await
+    }
+
+    async fn delete_subscription(&self, topic: &str, connection_id: &str) -> Result<(), Error> {
+        delete_item(
+            &self.client,
+            &self.subscription_table,
+            key(&[("topic", topic), ("connection_id", connection_id)]),
+        )
+        .await
+    }
+
+    async fn get_subscribers(&self, topic: &str) -> Result<Vec<SubscriptionRecord>, Error> {
+        self.query(&self.subscription_table, None, &[("topic", topic)], None)
+            .await
+    }
+
+    async fn get_subscriptions_from_connection_id(
+        &self,
+        connection_id: &str,
+    ) -> Result<Vec<SubscriptionRecord>, Error> {
+        self.query(
+            &self.subscription_table,
+            Some(&self.subscription_table_gsi),
+            &[("connection_id", connection_id)],
+            None,
+        )
+        .await
+    }
+}
+
+impl SeekRepository for DynamoDbRepository {
+    async fn save_seek(&self, seek: &SeekRecord) -> Result<(), Error> {
+        put_item(&self.client, &self.seek_table, seek).await
+    }
+
+    async fn delete_seek(&self, seek: &SeekRecord) -> Result<(), Error> {
+        delete_item(
+            &self.client,
+            &self.seek_table,
+            key(&[("pool", &seek.pool), ("seek_id", &seek.seek_id)]),
+        )
+        .await
+    }
+
+    async fn claim_seek(&self, seek: &SeekRecord) -> Result<bool, Error> {
+        delete_item_if_exists(
+            &self.client,
+            &self.seek_table,
+            "pool",
+            key(&[("pool", &seek.pool), ("seek_id", &seek.seek_id)]),
+        )
+        .await
+    }
+
+    async fn get_seeks_in_pool(&self, pool: &str) -> Result<Vec<SeekRecord>, Error> {
+        self.query(&self.seek_table, None, &[("pool", pool)], None)
+            .await
+    }
+
+    async fn get_seeks_from_connection_id(
+        &self,
+        connection_id: &str,
+    ) -> Result<Vec<SeekRecord>, Error> {
+        self.query(
+            &self.seek_table,
+            Some(&self.seek_table_gsi),
+            &[("connection_id", connection_id)],
+            None,
+        )
+        .await
+    }
+}
+
+impl ChatRepository for DynamoDbRepository {
+    async fn save_chat_message(&self, chat_message: &ChatMessageRecord) -> Result<(), Error> {
+        put_item(&self.client, &self.chat_table, chat_message).await
+    }
+
+    async fn get_chat_messages(
+        &self,
+        game_id: &str,
+        since: Option<&str>,
+    ) -> Result<Vec<ChatMessageRecord>, Error> {
+        let mut expression_attribute_values = HashMap::new();
+        expression_attribute_values.insert(
+            ":game_id".to_string(),
+            AttributeValue::S(game_id.to_string()),
+        );
+
+        let key_condition_expression = match since {
+            Some(since) => {
+                expression_attribute_values
+                    .insert(":since".to_string(), AttributeValue::S(since.to_string()));
+                "game_id = :game_id AND message_id > :since".to_string()
+            }
+            None => "game_id = :game_id".to_string(),
+        };
+
+        query_items(
+            &self.client,
+            &self.chat_table,
+            Some(key_condition_expression),
+            None,
+            Some(expression_attribute_values),
+            None,
+        )
+        .await
+    }
+}
+
+impl TournamentRepository for DynamoDbRepository {
+    /// Tournaments are read before every change to them, so reads are strongly consistent
+    async fn get_tournament(&self, tournament_id: &str) -> Result<Option<TournamentRecord>, Error> {
+        let response = self
+            .client
+            .get_item()
+            .table_name(&self.tournament_table)
+            .set_key(Some(key(&[("tournament_id", tournament_id)])))
+            .consistent_read(true)
+            .send()
+            .await?;
+
+        match response.item {
+            Some(item) => Ok(Some(from_item(item)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn insert_tournament(&self, tournament: &TournamentRecord) -> Result<bool, Error> {
+        put_item_if_not_exists(
+            &self.client,
+            &self.tournament_table,
+            "tournament_id",
+            tournament,
+        )
+        .await
+    }
+
+    async fn save_tournament(
+        &self,
+        tournament: &TournamentRecord,
+        expected_version: u64,
+    ) -> Result<bool, Error> {
+        let mut expression_attribute_values = HashMap::new();
+        expression_attribute_values.insert(
+            ":expected_version".to_string(),
+            AttributeValue::N(expected_version.to_string()),
+        );
+
+        put_item_if(
+            &self.client,
+            &self.tournament_table,
+            to_item(tournament)?,
+            TOURNAMENT_VERSION_CONDITION,
+            Some(expression_attribute_values),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::game::create_game;
+    use crate::types::game::{ColorPreference, GameEnding, State};
+
+    #[test]
+    fn test_build_stats_updates_only_for_finished_games() {
+        let mut game = create_game(
+            None,
+            "alice",
+            None,
+            Some(ColorPreference::White),
+            None,
+            Some(600),
+            "conn-alice",
+        );
+        assert!(build_stats_updates("users", &game, "alice", Color::White)
+            .unwrap()
+            .is_empty());
+
+        game.game_state.current_state_mut().state = State::Finished(GameEnding::Stalemate);
+        let updates = build_stats_updates("users", &game, "alice", Color::White).unwrap();
+        assert_eq!(updates.len(), 4);
+
+        let update = updates[0].update().unwrap();
+        assert_eq!(
+            update.key()["sk"],
+            AttributeValue::S("STATS#ALL".to_string())
+        );
+        assert_eq!(
+            update.expression_attribute_values().unwrap()[":draws"],
+            AttributeValue::N("1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_history_cursor_from_last_evaluated_key() {
+        let last_evaluated_key = key(&[
+            ("username", "alice"),
+            ("sk", "GAME-abc"),
+            ("game_created", "2024-01-01T00:00:00+00:00"),
+        ]);
+
+        assert_eq!(
+            to_history_cursor(&last_evaluated_key),
+            Some(HistoryCursor {
+                username: "alice".to_string(),
+                sk: "GAME-abc".to_string(),
+                game_created: "2024-01-01T00:00:00+00:00".to_string(),
+            })
+        );
+        assert!(to_history_cursor(&key(&[("username", "alice")])).is_none());
+    }
+}
@@ -0,0 +1,978 @@
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard};
+
+use aws_sdk_dynamodb::types::AttributeValue;
+use lambda_runtime::Error;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_dynamo::aws_sdk_dynamodb_1::{
+    from_attribute_value, from_item, to_attribute_value, to_item,
+};
+
+use crate::{
+    helpers::{
+        generic::get_timestamp,
+        lobby::OPEN_GAME_LISTING,
+        stats::{get_result_counts, get_stats_slices, StatsSlice},
+        tournament::AWAITING_JOIN_LISTING,
+    },
+    types::{
+        dynamo_db::{
+            AwaitingFirstMoveRecord, AwaitingJoinRecord, ChallengeRecord, ChatMessageRecord, FriendRecord, GameRecord,
+            OpenGameRecord, SeekRecord, SessionRecord, SubscriptionRecord, TournamentRecord,
+            UserGameHistoryRecord, UserInfoRecord, UserRecord, UserStatsRecord,
+        },
+        history::HistoryCursor,
+        piece::Color,
+        rating::{Rating, Ratings},
+    },
+};
+
+use super::{
+    from_game_item, to_game_item, ChatRepository, GameRepository, GameResultWrites, Item,
+    SeekRepository, SubscriptionRepository, TournamentRepository, UserGameHistoryPage,
+    UserRepository,
+};
+
+/// A table's items by partition key and sort key. Tables without a sort key use an empty one.
+type Table = BTreeMap<(String, String), Item>;
+
+#[derive(Default)]
+struct Tables {
+    games: Table,
+    users: Table,
+    subscriptions: Table,
+    seeks: Table,
+    chat_messages: Table,
+    tournaments: Table,
+}
+
+/// Keeps everything in memory, for local runs and tests. Items are stored the way DynamoDB
+/// stores them, so records round-trip exactly as they would when deployed. Each operation holds
+/// a lock for its duration, so conditional writes and transactions are atomic.
+#[derive(Default)]
+pub struct InMemoryRepository {
+    tables: Mutex<Tables>,
+}
+
+fn get_string<'a>(item: &'a Item, name: &str) -> Option<&'a str> {
+    item.get(name)?.as_s().ok().map(String::as_str)
+}
+
+fn to_key(
+    item: &Item,
+    partition_key: &str,
+    sort_key: Option<&str>,
+) -> Result<(String, String), Error> {
+    let get = |name: &str| {
+        get_string(item, name)
+            .map(str::to_string)
+            .ok_or_else(|| Error::from(format!("Item is missing its `{name}` key")))
+    };
+
+    Ok((
+        get(partition_key)?,
+        sort_key.map(get).transpose()?.unwrap_or_default(),
+    ))
+}
+
+fn from_items<T: DeserializeOwned>(items: impl IntoIterator<Item = Item>) -> Result<Vec<T>, Error> {
+    items.into_iter().map(|item| Ok(from_item(item)?)).collect()
+}
+
+/// The items in the partition, in sort key order
+fn get_partition(table: &Table, partition_key: &str) -> Vec<Item> {
+    table
+        .range((partition_key.to_string(), String::new())..)
+        .take_while(|((pk, _), _)| pk == partition_key)
+        .map(|(_, item)| item.clone())
+        .collect()
+}
+
+/// The items with the given value, as if queried from an index keyed on the attribute
+fn get_by_attribute(table: &Table, name: &str, value: &str) -> Vec<Item> {
+    table
+        .values()
+        .filter(|item| get_string(item, name) == Some(value))
+        .cloned()
+        .collect()
+}
+
+fn add_to_number(item: &mut Item, name: &str, amount: u64) -> Result<(), Error> {
+    let current: u64 = match item.get(name) {
+        Some(value) => from_attribute_value(value.clone())?,
+        None => 0,
+    };
+
+    item.insert(name.to_string(), to_attribute_value(current + amount)?);
+    Ok(())
+}
+
+fn rating_matches(
+    user_info: Option<&Item>,
+    attribute_name: &str,
+    previous: Option<Rating>,
+) -> Result<bool, Error> {
+    let current: Option<Rating> = match user_info.and_then(|item| item.get(attribute_name)) {
+        Some(value) => Some(from_attribute_value(value.clone())?),
+        None => None,
+    };
+
+    Ok(current.map(|rating| rating.games) == previous.map(|rating| rating.games))
+}
+
+impl InMemoryRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Tables> {
+        // A panic while the lock was held can't leave an item half-written
+        self.tables
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn put<T: Serialize>(
+        &self,
+        table: impl FnOnce(&mut Tables) -> &mut Table,
+        record: &T,
+        partition_key: &str,
+        sort_key: Option<&str>,
+    ) -> Result<(), Error> {
+        let item = to_item(record)?;
+        let key = to_key(&item, partition_key, sort_key)?;
+        table(&mut self.lock()).insert(key, item);
+        Ok(())
+    }
+
+    fn get<T: DeserializeOwned>(
+        &self,
+        table: impl FnOnce(&Tables) -> &Table,
+        partition_key: &str,
+        sort_key: &str,
+    ) -> Result<Option<T>, Error> {
+        let tables = self.lock();
+        let item = table(&tables).get(&(partition_key.to_string(), sort_key.to_string()));
+
+        Ok(match item {
+            Some(item) => Some(from_item(item.clone())?),
+            None => None,
+        })
+    }
+
+    /// Returns `false` if there was nothing to delete
+    fn delete(
+        &self,
+        table: impl FnOnce(&mut Tables) -> &mut Table,
+        partition_key: &str,
+        sort_key: &str,
+    ) -> bool {
+        table(&mut self.lock())
+            .remove(&(partition_key.to_string(), sort_key.to_string()))
+            .is_some()
+    }
+
+    fn query<T: DeserializeOwned>(
+        &self,
+        table: impl FnOnce(&Tables) -> &Table,
+        partition_key: &str,
+        sort_key_prefix: &str,
+    ) -> Result<Vec<T>, Error> {
+        let tables = self.lock();
+
+        from_items(
+            get_partition(table(&tables), partition_key)
+                .into_iter()
+                .filter(|item| {
+                    get_string(item, "sk").is_some_and(|sk| sk.starts_with(sort_key_prefix))
+                }),
+        )
+    }
+}
+
+impl GameRepository for InMemoryRepository {
+    async fn get_game(&self, game_id: &str) -> Result<Option<GameRecord>, Error> {
+        let item = self
+            .lock()
+            .games
+            .get(&(game_id.to_string(), String::new()))
+            .cloned();
+
+        match item {
+            Some(item) => Ok(Some(from_game_item(self, item).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_latest_game(&self, game_id: &str) -> Result<Option<GameRecord>, Error> {
+        self.get_game(game_id).await
+    }
+
+    async fn insert_game(&self, game: &GameRecord) -> Result<bool, Error> {
+        let item = to_game_item(game)?;
+        let mut tables = self.lock();
+
+        if tables
+            .games
+            .contains_key(&(game.game_id.clone(), String::new()))
+        {
+            return Ok(false);
+        }
+
+        tables
+            .games
+            .insert((game.game_id.clone(), String::new()), item);
+        Ok(true)
+    }
+
+    async fn save_game(
+        &self,
+        game: &GameRecord,
+        expected_version: u64,
+        result_writes: &GameResultWrites,
+    ) -> Result<bool, Error> {
+        let item = to_game_item(game)?;
+        let game_key = (game.game_id.clone(), String::new());
+        let mut tables = self.lock();
+
+        // Check every condition before making any of the writes
+        let Some(stored_game) = tables.games.get(&game_key) else {
+            return Ok(false);
+        };
+
+        let stored_version: Option<u64> = match stored_game.get("version") {
+            Some(version) => Some(from_attribute_value(version.clone())?),
+            None => None,
+        };
+
+        if stored_version.is_some_and(|version| version != expected_version) {
+            return Ok(false);
+        }
+
+        for rating_update in &result_writes.rating_updates {
+            let user_info = tables
+                .users
+                .get(&(rating_update.username.clone(), "INFO".to_string()));
+
+            if !rating_matches(
+                user_info,
+                &Ratings::attribute_name(rating_update.category),
+                rating_update.previous,
+            )? {
+                return Ok(false);
+            }
+        }
+
+        tables.games.insert(game_key, item);
+
+        let now = get_timestamp();
+
+        for (username, color) in &result_writes.players {
+            let sort_key = format!("GAME-{}", game.game_id);
+            let user_game = tables
+                .users
+                .entry((username.clone(), sort_key.clone()))
+                .or_default();
+
+            user_game.insert("username".to_string(), AttributeValue::S(username.clone()));
+            user_game.insert("sk".to_string(), AttributeValue::S(sort_key));
+            user_game.insert(
+                "winner".to_string(),
+                AttributeValue::S(result_writes.winner.clone()),
+            );
+            user_game
+                .entry("created".to_string())
+                .or_insert_with(|| AttributeValue::S(now.clone()));
+            user_game
+                .entry("game_created".to_string())
+                .or_insert_with(|| AttributeValue::S(game.created.clone()));
+
+            let Some((wins, draws, losses)) = get_result_counts(game, *color) else {
+                continue;
+            };
+
+            for StatsSlice {
+                sort_key,
+                attributes,
+            } in get_stats_slices(game, *color)?
+            {
+                let stats = tables
+                    .users
+                    .entry((username.clone(), sort_key.clone()))
+                    .or_default();
+
+                stats.insert("username".to_string(), AttributeValue::S(username.clone()));
+                stats.insert("sk".to_string(), AttributeValue::S(sort_key));
+                stats.extend(attributes);
+                add_to_number(stats, "wins", wins.into())?;
+                add_to_number(stats, "draws", draws.into())?;
+                add_to_number(stats, "losses", losses.into())?;
+                add_to_number(stats, "total_plies", game.game_state.move_list.len() as u64)?;
+            }
+        }
+
+        for rating_update in &result_writes.rating_updates {
+            let user_info = tables
+                .users
+                .entry((rating_update.username.clone(), "INFO".to_string()))
+                .or_default();
+
+            user_info.insert(
+                Ratings::attribute_name(rating_update.category),
+                to_attribute_value(rating_update.new)?,
+            );
+        }
+
+        Ok(true)
+    }
+
+    async fn get_open_games(&self) -> Result<Vec<OpenGameRecord>, Error> {
+        from_items(get_by_attribute(
+            &self.lock().games,
+            "lobby",
+            OPEN_GAME_LISTING,
+        ))
+    }
+
+    async fn get_games_awaiting_first_move(
+        &self,
+        color: Color,
+        cutoff: &str,
+    ) -> Result<Vec<AwaitingFirstMoveRecord>, Error> {
+        from_items(
+            get_by_attribute(
+                &self.lock().games,
+                "awaiting_first_move",
+                &color.to_string(),
+            )
+            .into_iter()
+            .filter(|item| {
+                get_string(item, "awaiting_first_move_since").is_some_and(|since| since < cutoff)
+            }),
+        )
+    }
+
+    async fn get_games_awaiting_join(&self, cutoff: &str) -> Result<Vec<AwaitingJoinRecord>, Error> {
+        from_items(
+            get_by_attribute(&self.lock().games, "awaiting_join", AWAITING_JOIN_LISTING)
+                .into_iter()
+                .filter(|item| {
+                    get_string(item, "awaiting_join_since").is_some_and(|since| since < cutoff)
+                }),
+        )
+    }
+}
+
+impl UserRepository for InMemoryRepository {
+    async fn save_user_record(&self, user_record: &UserRecord) -> Result<(), Error> {
+        self.put(
+            |tables| &mut tables.users,
+            user_record,
+            "username",
+            Some("sk"),
+        )
+    }
+
+    async fn delete_user_record(&self, username: &str, sort_key: &str) -> Result<(), Error> {
+        self.delete(|tables| &mut tables.users, username, sort_key);
+        Ok(())
+    }
+
+    async fn get_user_game(
+        &self,
+        username: &str,
+        game_id: &str,
+    ) -> Result<Option<UserRecord>, Error> {
+        self.get(|tables| &tables.users, username, &format!("GAME-{game_id}"))
+    }
+
+    async fn get_all_user_games(&self, username: &str) -> Result<Vec<UserRecord>, Error> {
+        self.query(|tables| &tables.users, username, "GAME-")
+    }
+
+    async fn get_user_connections(&self, username: &str) -> Result<Vec<UserRecord>, Error> {
+        self.query(|tables| &tables.users, username, "CONNECTION-")
+    }
+
+    async fn get_user_games_from_connection_id(
+        &self,
+        connection_id: &str,
+    ) -> Result<Vec<UserRecord>, Error> {
+        from_items(get_by_attribute(
+            &self.lock().users,
+            "connection_id",
+            connection_id,
+        ))
+    }
+
+    async fn get_user_game_history_page(
+        &self,
+        username: &str,
+        limit: usize,
+        cursor: Option<HistoryCursor>,
+    ) -> Result<UserGameHistoryPage, Error> {
+        let mut records: Vec<UserGameHistoryRecord> = from_items(
+            get_partition(&self.lock().users, username)
+                .into_iter()
+                .filter(|item| item.contains_key("game_created")),
+        )?;
+
+        // Newest first, like the history index queried backwards
+        let index_key =
+            |record: &UserGameHistoryRecord| (record.game_created.clone(), record.sort_key.clone());
+        records.sort_by_key(|record| std::cmp::Reverse(index_key(record)));
+
+        if let Some(cursor) = cursor {
+            records.retain(|record| {
+                index_key(record) < (cursor.game_created.clone(), cursor.sk.clone())
+            });
+        }
+
+        let has_more = records.len() > limit;
+        records.truncate(limit);
+
+        let next_cursor = records
+            .last()
+            .filter(|_| has_more)
+            .map(|record| HistoryCursor {
+                username: record.username.clone(),
+                sk: record.sort_key.clone(),
+                game_created: record.game_created.clone(),
+            });
+
+        Ok(UserGameHistoryPage {
+            items: records,
+            next_cursor,
+        })
+    }
+
+    async fn get_user_info(&self, username: &str) -> Result<Option<UserInfoRecord>, Error> {
+        self.get(|tables| &tables.users, username, "INFO")
+    }
+
+    async fn create_user_info(&self, user_info: &UserInfoRecord) -> Result<bool, Error> {
+        let item = to_item(user_info)?;
+        let mut tables = self.lock();
+        let key = (user_info.username.clone(), user_info.sort_key.clone());
+
+        if tables.users.contains_key(&key) {
+            return Ok(false);
+        }
+
+        tables.users.insert(key, item);
+        Ok(true)
+    }
+
+    async fn update_last_seen(&self, username: &str) -> Result<(), Error> {
+        let mut tables = self.lock();
+        let user_info = tables
+            .users
+            .entry((username.to_string(), "INFO".to_string()))
+            .or_default();
+
+        user_info.insert(
+            "username".to_string(),
+            AttributeValue::S(username.to_string()),
+        );
+        user_info.insert("sk".to_string(), AttributeValue::S("INFO".to_string()));
+        user_info.insert("last_seen".to_string(), AttributeValue::S(get_timestamp()));
+
+        Ok(())
+    }
+
+    async fn save_session(&self, session: &SessionRecord) -> Result<(), Error> {
+        self.put(|tables| &mut tables.users, session, "username", Some("sk"))
+    }
+
+    async fn get_session(
+        &self,
+        username: &str,
+        sort_key: &str,
+    ) -> Result<Option<SessionRecord>, Error> {
+        self.get(|tables| &tables.users, username, sort_key)
+    }
+
+    async fn get_user_stats_records(&self, username: &str) -> Result<Vec<UserStatsRecord>, Error> {
+        self.query(|tables| &tables.users, username, "STATS#")
+    }
+
+    async fn save_friend(&self, friend: &FriendRecord) -> Result<(), Error> {
+        self.put(|tables| &mut tables.users, friend, "username", Some("sk"))
+    }
+
+    async fn delete_friend(&self, username: &str, friend: &str) -> Result<(), Error> {
+        self.delete_user_record(username, &format!("FRIEND-{friend}"))
+            .await
+    }
+
+    async fn has_added_friend(&self, username: &str, friend: &str) -> Result<bool, Error> {
+        let friend_record: Option<FriendRecord> = self.get(
+            |tables| &tables.users,
+            username,
+            &format!("FRIEND-{friend}"),
+        )?;
+
+        Ok(friend_record.is_some())
+    }
+
+    async fn get_added_friends(&self, username: &str) -> Result<Vec<String>, Error> {
+        let friend_records: Vec<FriendRecord> =
+            self.query(|tables| &tables.users, username, "FRIEND-")?;
+
+        Ok(friend_records
+            .into_iter()
+            .map(|record| record.friend)
+            .collect())
+    }
+
+    async fn save_challenge(&self, challenge: &ChallengeRecord) -> Result<(), Error> {
+        self.put(
+            |tables| &mut tables.users,
+            challenge,
+            "username",
+            Some("sk"),
+        )
+    }
+
+    async fn get_challenge(
+        &self,
+        username: &str,
+        challenge_id: &str,
+    ) -> Result<Option<ChallengeRecord>, Error> {
+        self.get(
+            |tables| &tables.users,
+            username,
+            &format!("CHALLENGE-{challenge_id}"),
+        )
+    }
+
+    async fn claim_challenge(&self, challenge: &ChallengeRecord) -> Result<bool, Error> {
+        Ok(self.delete(
+            |tables| &mut tables.users,
+            &challenge.username,
+            &challenge.sort_key,
+        ))
+    }
+}
+
+impl SubscriptionRepository for InMemoryRepository {
+    async fn save_subscription(&self, subscription: &SubscriptionRecord) -> Result<(), Error> {
+        self.put(
+            |tables| &mut tables.subscriptions,
+            subscription,
+            "topic",
+            Some("connection_id"),
+        )
+    }
+
+    async fn get_subscription(
+        &self,
+        topic: &str,
+        connection_id: &str,
+    ) -> Result<Option<SubscriptionRecord>, Error> {
+        self.get(|tables| &tables.subscriptions, topic, connection_id)
+    }
+
+    async fn delete_subscription(&self, topic: &str, connection_id: &str) -> Result<(), Error> {
+        self.delete(|tables| &mut tables.subscriptions, topic, connection_id);
+        Ok(())
+    }
+
+    async fn get_subscribers(&self, topic: &str) -> Result<Vec<SubscriptionRecord>, Error> {
+        from_items(get_partition(&self.lock().subscriptions, topic))
+    }
+
+    async fn get_subscriptions_from_connection_id(
+        &self,
+        connection_id: &str,
+    ) -> Result<Vec<SubscriptionRecord>, Error> {
+        from_items(get_by_attribute(
+            &self.lock().subscriptions,
+            "connection_id",
+            connection_id,
+        ))
+    }
+}
+
+impl SeekRepository for InMemoryRepository {
+    async fn save_seek(&self, seek: &SeekRecord) -> Result<(), Error> {
+        self.put(|tables| &mut tables.seeks, seek, "pool", Some("seek_id"))
+    }
+
+    async fn delete_seek(&self, seek: &SeekRecord) -> Result<(), Error> {
+        self.delete(|tables| &mut tables.seeks, &seek.pool, &seek.seek_id);
+        Ok(())
+    }
+
+    async fn claim_seek(&self, seek: &SeekRecord) -> Result<bool, Error> {
+        Ok(self.delete(|tables| &mut tables.seeks, &seek.pool, &seek.seek_id))
+    }
+
+    async fn get_seeks_in_pool(&self, pool: &str) -> Result<Vec<SeekRecord>, Error> {
+        from_items(get_partition(&self.lock().seeks, pool))
+    }
+
+    async fn get_seeks_from_connection_id(
+        &self,
+        connection_id: &str,
+    ) -> Result<Vec<SeekRecord>, Error> {
+        from_items(get_by_attribute(
+            &self.lock().seeks,
+            "connection_id",
+            connection_id,
+        ))
+    }
+}
+
+impl ChatRepository for InMemoryRepository {
+    async fn save_chat_message(&self, chat_message: &ChatMessageRecord) -> Result<(), Error> {
+        self.put(
+            |tables| &mut tables.chat_messages,
+            chat_message,
+            "game_id",
+            Some("message_id"),
+        )
+    }
+
+    async fn get_chat_messages(
+        &self,
+        game_id: &str,
+        since: Option<&str>,
+    ) -> Result<Vec<ChatMessageRecord>, Error> {
+        from_items(
+            get_partition(&self.lock().chat_messages, game_id)
+                .into_iter()
+                .filter(|item| {
+                    since.is_none_or(|since| {
+                        get_string(item, "message_id").is_some_and(|message_id| message_id > since)
+                    })
+                }),
+        )
+    }
+}
+
+impl TournamentRepository for InMemoryRepository {
+    async fn get_tournament(&self, tournament_id: &str) -> Result<Option<TournamentRecord>, Error> {
+        self.get(|tables| &tables.tournaments, tournament_id, "")
+    }
+
+    async fn insert_tournament(&self, tournament: &TournamentRecord) -> Result<bool, Error> {
+        let item = to_item(tournament)?;
+        let key = to_key(&item, "tournament_id", None)?;
+        let mut tables = self.lock();
+
+        if tables.tournaments.contains_key(&key) {
+            return Ok(false);
+        }
+
+        tables.tournaments.insert(key, item);
+        Ok(true)
+    }
+
+    async fn save_tournament(
+        &self,
+        tournament: &TournamentRecord,
+        expected_version: u64,
+    ) -> Result<bool, Error> {
+        let item = to_item(tournament)?;
+        let key = to_key(&item, "tournament_id", None)?;
+        let mut tables = self.lock();
+
+        let Some(stored_tournament) = tables.tournaments.get(&key) else {
+            return Ok(false);
+        };
+
+        let stored_version: Option<u64> = match stored_tournament.get("version") {
+            Some(version) => Some(from_attribute_value(version.clone())?),
+            None => None,
+        };
+
+        if stored_version.is_some_and(|version| version != expected_version) {
+            return Ok(false);
+        }
+
+        tables.tournaments.insert(key, item);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        helpers::{
+            game::{
+                assign_player_to_existing_or_remaining_slot, create_game, make_move,
+                prepare_game_result, update_game, GameAction, GameResult, GameUpdate,
+            },
+            user::create_user_game,
+        },
+        repository::is_legacy_game_item,
+        types::{
+            board::{File, Position, Rank},
+            game::{ColorPreference, GameEnding, PlayerMove, State, StoredGameState},
+            rating::{RatingUpdate, TimeControlCategory},
+        },
+    };
+
+    fn player_move(from: (usize, usize), to: (usize, usize)) -> PlayerMove {
+        PlayerMove {
+            from: Position {
+                file: File(from.0),
+                rank: Rank(from.1),
+            },
+            to: Position {
+                file: File(to.0),
+                rank: Rank(to.1),
+            },
+        }
+    }
+
+    fn user_info(username: &str) -> UserInfoRecord {
+        UserInfoRecord {
+            username: username.to_string(),
+            sort_key: "INFO".to_string(),
+            password_hash: String::new(),
+            created: get_timestamp(),
+            last_seen: None,
+            ratings: Ratings::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_save_game_checks_version() {
+        let repository = InMemoryRepository::new();
+        let mut game = create_game(Some("game-1"), "alice", None, None, None, None, "conn-1");
+
+        assert!(repository.insert_game(&game).await.unwrap());
+        assert!(!repository.insert_game(&game).await.unwrap());
+
+        game.version = 1;
+        let no_writes = GameResultWrites::default();
+        assert!(repository.save_game(&game, 0, &no_writes).await.unwrap());
+        assert!(!repository.save_game(&game, 0, &no_writes).await.unwrap());
+
+        let stored_game = repository.get_game("game-1").await.unwrap().unwrap();
+        assert_eq!(stored_game.version, 1);
+    }
+
+    #[tokio::test]
+    async fn test_legacy_games_are_converted_when_read() {
+        let repository = InMemoryRepository::new();
+        let mut game = create_game(Some("game-1"), "alice", None, None, None, None, "conn-1");
+        let mut history = vec![game.game_state.current_state().clone()];
+        make_move(&mut game.game_state, &player_move((5, 2), (5, 4)));
+        history.push(game.game_state.current_state().clone());
+
+        // Saved with every position, before the game state was kept compact
+        let mut item = to_game_item(&game).unwrap();
+        let legacy_game_state = StoredGameState {
+            game_id: game.game_id.clone(),
+            game_time: None,
+            move_list: game.game_state.move_list.clone(),
+            san_list: game.game_state.san_list.clone(),
+            opening: game.game_state.opening.clone(),
+            snapshots: Vec::new(),
+            ply_details: Vec::new(),
+            current: None,
+            history,
+        };
+        item.insert(
+            "game_state".to_string(),
+            to_attribute_value(legacy_game_state).unwrap(),
+        );
+        repository
+            .lock()
+            .games
+            .insert(("game-1".to_string(), String::new()), item);
+
+        let stored_game = repository.get_game("game-1").await.unwrap().unwrap();
+        assert_eq!(stored_game.game_state.ply_count(), 1);
+        assert_eq!(stored_game.game_state.move_list, vec!["e2e4"]);
+
+        let item = repository.lock().games[&("game-1".to_string(), String::new())].clone();
+        assert!(!is_legacy_game_item(&item));
+        assert_eq!(
+            repository
+                .get_game("game-1")
+                .await
+                .unwrap()
+                .unwrap()
+                .version,
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_changed_rating_fails_the_whole_save() {
+        let repository = InMemoryRepository::new();
+        let mut game = create_game(Some("game-1"), "alice", None, None, None, None, "conn-1");
+        repository.insert_game(&game).await.unwrap();
+        repository
+            .create_user_info(&user_info("alice"))
+            .await
+            .unwrap();
+
+        game.version = 1;
+        let result_writes = GameResultWrites {
+            winner: "white".to_string(),
+            players: vec![("alice".to_string(), Color::White)],
+            rating_updates: vec![RatingUpdate {
+                username: "alice".to_string(),
+                category: TimeControlCategory::from_seconds_per_player(None),
+                previous: Some(Rating::default()), // She has no rating yet
+                new: Rating::default(),
+            }],
+        };
+
+        assert!(!repository
+            .save_game(&game, 0, &result_writes)
+            .await
+            .unwrap());
+        assert!(repository
+            .get_user_game("alice", "game-1")
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            repository
+                .get_game("game-1")
+                .await
+                .unwrap()
+                .unwrap()
+                .version,
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_game_from_creation_to_checkmate() {
+        let repository = InMemoryRepository::new();
+
+        for username in ["alice", "bob"] {
+            repository
+                .create_user_info(&user_info(username))
+                .await
+                .unwrap();
+        }
+
+        let mut game = create_game(
+            Some("game-1"),
+            "alice",
+            None,
+            Some(ColorPreference::White),
+            None,
+            None,
+            "conn-1",
+        );
+        game.rated = true;
+        repository.insert_game(&game).await.unwrap();
+        repository
+            .save_user_record(&create_user_game("game-1", "alice", "conn-1"))
+            .await
+            .unwrap();
+
+        let update = update_game(&repository, "game-1", async |game| {
+            assign_player_to_existing_or_remaining_slot(game, "bob", "conn-2", None, None, true)?;
+            Ok(GameAction::<(), ()>::Save(()))
+        })
+        .await
+        .unwrap();
+        assert!(matches!(update, GameUpdate::Saved { .. }));
+        repository
+            .save_user_record(&create_user_game("game-1", "bob", "conn-2"))
+            .await
+            .unwrap();
+
+        // Fool's mate
+        for (from, to) in [
+            ((6, 2), (6, 3)),
+            ((5, 7), (5, 5)),
+            ((7, 2), (7, 4)),
+            ((4, 8), (8, 4)),
+        ] {
+            let update = update_game(&repository, "game-1", async |game| {
+                make_move(&mut game.game_state, &player_move(from, to));
+                let GameResult { writes, .. } = prepare_game_result(&repository, game).await?;
+                Ok(GameAction::<(), ()>::SaveWith((), writes))
+            })
+            .await
+            .unwrap();
+            assert!(matches!(update, GameUpdate::Saved { .. }));
+        }
+
+        let game = repository.get_game("game-1").await.unwrap().unwrap();
+        assert_eq!(game.version, 5);
+        assert_eq!(game.black_username.as_deref(), Some("bob"));
+        assert!(matches!(
+            game.game_state.current_state().state,
+            State::Finished(GameEnding::Checkmate(Color::White))
+        ));
+
+        for username in ["alice", "bob"] {
+            let user_game = repository.get_user_game(username, "game-1").await.unwrap();
+            assert_eq!(user_game.unwrap().winner.as_deref(), Some("black"));
+        }
+
+        let alice_stats = repository.get_user_stats_records("alice").await.unwrap();
+        let alice_overall = alice_stats
+            .iter()
+            .find(|s| s.sort_key == "STATS#ALL")
+            .unwrap();
+        assert_eq!((alice_overall.wins, alice_overall.losses), (0, 1));
+        assert_eq!(alice_overall.total_plies, 4);
+
+        let category = TimeControlCategory::from_seconds_per_player(None);
+        let alice = repository.get_user_info("alice").await.unwrap().unwrap();
+        let bob = repository.get_user_info("bob").await.unwrap().unwrap();
+        assert!(
+            alice.ratings.get(category).unwrap().rating < bob.ratings.get(category).unwrap().rating
+        );
+    }
+
+    #[tokio::test]
+    async fn test_user_game_history_pages() {
+        let repository = InMemoryRepository::new();
+
+        for (game_id, game_created) in [
+            ("a", "2024-01-01"),
+            ("b", "2024-01-03"),
+            ("c", "2024-01-02"),
+        ] {
+            let mut user_game = create_user_game(game_id, "alice", "conn-1");
+            user_game.game_created = Some(game_created.to_string());
+            repository.save_user_record(&user_game).await.unwrap();
+        }
+
+        // Connection records aren't games
+        repository
+            .save_user_record(&crate::helpers::user::create_user_connection(
+                "alice", "conn-1",
+            ))
+            .await
+            .unwrap();
+
+        let first_page = repository
+            .get_user_game_history_page("alice", 2, None)
+            .await
+            .unwrap();
+        let sort_keys: Vec<&str> = first_page
+            .items
+            .iter()
+            .map(|r| r.sort_key.as_str())
+            .collect();
+        assert_eq!(sort_keys, ["GAME-b", "GAME-c"]);
+
+        let second_page = repository
+            .get_user_game_history_page("alice", 2, first_page.next_cursor)
+            .await
+            .unwrap();
+        let sort_keys: Vec<&str> = second_page
+            .items
+            .iter()
+            .map(|r| r.sort_key.as_str())
+            .collect();
+        assert_eq!(sort_keys, ["GAME-a"]);
+        assert!(second_page.next_cursor.is_none());
+    }
+}
@@ -0,0 +1,353 @@
+//! Storage for everything the handlers read and write. `DynamoDbRepository` is used when
+//! deployed, and `InMemoryRepository` for local runs and tests.
+
+pub mod dynamo_db;
+pub mod in_memory;
+
+use std::collections::HashMap;
+use std::future::Future;
+
+use aws_sdk_dynamodb::types::AttributeValue;
+use lambda_runtime::Error;
+use serde_dynamo::aws_sdk_dynamodb_1::{from_item, to_attribute_value, to_item};
+
+use crate::types::{
+    dynamo_db::{
+        AwaitingFirstMoveRecord, AwaitingJoinRecord, ChallengeRecord, ChatMessageRecord, FriendRecord, GameRecord,
+        OpenGameRecord, SeekRecord, SessionRecord, SubscriptionRecord, TournamentRecord,
+        UserGameHistoryRecord, UserInfoRecord, UserRecord, UserStatsRecord,
+    },
+    history::HistoryCursor,
+    piece::Color,
+    rating::RatingUpdate,
+};
+
+pub use dynamo_db::DynamoDbRepository;
+pub use in_memory::InMemoryRepository;
+
+type Item = HashMap<String, AttributeValue>;
+
+/// The writes that record a finished game's result, made in the same transaction as the game
+#[derive(Default)]
+pub struct GameResultWrites {
+    /// "white", "black" or "draw", recorded on each player's user-game record
+    pub winner: String,
+    /// The players whose user-game records and stats are updated
+    pub players: Vec<(String, Color)>,
+    /// Each fails the transaction if the player's rating changed since it was read
+    pub rating_updates: Vec<RatingUpdate>,
+}
+
+impl GameResultWrites {
+    pub fn is_empty(&self) -> bool {
+        self.players.is_empty() && self.rating_updates.is_empty()
+    }
+}
+
+/// A page of a user's games, newest first. `next_cursor` is set if there may be more after it.
+pub struct UserGameHistoryPage {
+    pub items: Vec<UserGameHistoryRecord>,
+    pub next_cursor: Option<HistoryCursor>,
+}
+
+/// Games are stored differently from how they're serialized for clients, since premoves are
+/// kept out of the game that's sent to the opponent
+fn to_game_item(game: &GameRecord) -> Result<Item, Error> {
+    let mut item = to_item(game)?;
+
+    for (attribute_name, premove) in [
+        ("white_premove", &game.white_premove),
+        ("black_premove", &game.black_premove),
+    ] {
+        if let Some(premove) = premove {
+            item.insert(attribute_name.to_string(), to_attribute_value(premove)?);
+        }
+    }
+
+    Ok(item)
+}
+
+/// Games saved before their state was kept compact don't have a separate current position
+fn is_legacy_game_item(item: &Item) -> bool {
+    item.get("game_state")
+        .and_then(|game_state| game_state.as_m().ok())
+        .is_some_and(|game_state| !game_state.contains_key("current"))
+}
+
+/// Older games are converted as they're read, and saved back so that they're only converted
+/// once. The game itself hasn't changed, so it keeps its version. If it's been saved since it
+/// was read, it's already been converted.
+async fn from_game_item(repository: &impl GameRepository, item: Item) -> Result<GameRecord, Error> {
+    let is_legacy = is_legacy_game_item(&item);
+    let game: GameRecord = from_item(item)?;
+
+    if is_legacy {
+        repository
+            .save_game(&game, game.version, &GameResultWrites::default())
+            .await?;
+    }
+
+    Ok(game)
+}
+
+pub trait GameRepository: Send + Sync {
+    fn get_game(
+        &self,
+        game_id: &str,
+    ) -> impl Future<Output = Result<Option<GameRecord>, Error>> + Send;
+
+    /// Like `get_game`, but guaranteed to return the latest version of the game
+    fn get_latest_game(
+        &self,
+        game_id: &str,
+    ) -> impl Future<Output = Result<Option<GameRecord>, Error>> + Send;
+
+    /// Saves a new game without replacing an existing one. Returns `false` if the ID is taken.
+    fn insert_game(&self, game: &GameRecord) -> impl Future<Output = Result<bool, Error>> + Send;
+
+    /// Replaces the game as long as it's still at `expected_version`, along with the result
+    /// writes, all or nothing. Returns `false` if the game has been saved by someone else, or if a
+    /// rating changed.
+    fn save_game(
+        &self,
+        game: &GameRecord,
+        expected_version: u64,
+        result_writes: &GameResultWrites,
+    ) -> impl Future<Output = Result<bool, Error>> + Send;
+
+    /// The games waiting for an opponent, in no particular order
+    fn get_open_games(&self) -> impl Future<Output = Result<Vec<OpenGameRecord>, Error>> + Send;
+
+    /// The games that have been waiting on the given player's first move since before the cutoff
+    /// (an RFC 3339 timestamp)
+    fn get_games_awaiting_first_move(
+        &self,
+        color: Color,
+        cutoff: &str,
+    ) -> impl Future<Output = Result<Vec<AwaitingFirstMoveRecord>, Error>> + Send;
+
+    /// The tournament games that have been waiting for their players to join since before the
+    /// cutoff (an RFC 3339 timestamp)
+    fn get_games_awaiting_join(
+        &self,
+        cutoff: &str,
+    ) -> impl Future<Output = Result<Vec<AwaitingJoinRecord>, Error>> + Send;
+}
+
+pub trait UserRepository: Send + Sync {
+    fn save_user_record(
+        &self,
+        user_record: &UserRecord,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+
+    fn delete_user_record(
+        &self,
+        username: &str,
+        sort_key: &str,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+
+    fn get_user_game(
+        &self,
+        username: &str,
+        game_id: &str,
+    ) -> impl Future<Output = Result<Option<UserRecord>, Error>> + Send;
+
+    fn get_all_user_games(
+        &self,
+        username: &str,
+    ) -> impl Future<Output = Result<Vec<UserRecord>, Error>> + Send;
+
+    /// The records of the connections the user is logged in on
+    fn get_user_connections(
+        &self,
+        username: &str,
+    ) -> impl Future<Output = Result<Vec<UserRecord>, Error>> + Send;
+
+    /// The user-game and connection records of whoever is using the connection
+    fn get_user_games_from_connection_id(
+        &self,
+        connection_id: &str,
+    ) -> impl Future<Output = Result<Vec<UserRecord>, Error>> + Send;
+
+    /// A page of up to `limit` of the user's games, continuing after `cursor`
+    fn get_user_game_history_page(
+        &self,
+        username: &str,
+        limit: usize,
+        cursor: Option<HistoryCursor>,
+    ) -> impl Future<Output = Result<UserGameHistoryPage, Error>> + Send;
+
+    fn get_user_info(
+        &self,
+        username: &str,
+    ) -> impl Future<Output = Result<Option<UserInfoRecord>, Error>> + Send;
+
+    /// Returns `false` if the username is already registered
+    fn create_user_info(
+        &self,
+        user_info: &UserInfoRecord,
+    ) -> impl Future<Output = Result<bool, Error>> + Send;
+
+    fn update_last_seen(&self, username: &str) -> impl Future<Output = Result<(), Error>> + Send;
+
+    fn save_session(
+        &self,
+        session: &SessionRecord,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+
+    fn get_session(
+        &self,
+        username: &str,
+        sort_key: &str,
+    ) -> impl Future<Output = Result<Option<SessionRecord>, Error>> + Send;
+
+    fn get_user_stats_records(
+        &self,
+        username: &str,
+    ) -> impl Future<Output = Result<Vec<UserStatsRecord>, Error>> + Send;
+
+    fn save_friend(&self, friend: &FriendRecord) -> impl Future<Output = Result<(), Error>> + Send;
+
+    fn delete_friend(
+        &self,
+        username: &str,
+        friend: &str,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Whether `username` has added `friend`, whether or not they've been added back
+    fn has_added_friend(
+        &self,
+        username: &str,
+        friend: &str,
+    ) -> impl Future<Output = Result<bool, Error>> + Send;
+
+    /// Everyone the user has added, including those who haven't added them back
+    fn get_added_friends(
+        &self,
+        username: &str,
+    ) -> impl Future<Output = Result<Vec<String>, Error>> + Send;
+
+    fn save_challenge(
+        &self,
+        challenge: &ChallengeRecord,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// May return an expired challenge that hasn't been deleted yet
+    fn get_challenge(
+        &self,
+        username: &str,
+        challenge_id: &str,
+    ) -> impl Future<Output = Result<Option<ChallengeRecord>, Error>> + Send;
+
+    /// Deletes the challenge so that it can only be answered once. Returns `false` if it was
+    /// already answered.
+    fn claim_challenge(
+        &self,
+        challenge: &ChallengeRecord,
+    ) -> impl Future<Output = Result<bool, Error>> + Send;
+}
+
+pub trait SubscriptionRepository: Send + Sync {
+    fn save_subscription(
+        &self,
+        subscription: &SubscriptionRecord,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+
+    fn get_subscription(
+        &self,
+        topic: &str,
+        connection_id: &str,
+    ) -> impl Future<Output = Result<Option<SubscriptionRecord>, Error>> + Send;
+
+    fn delete_subscription(
+        &self,
+        topic: &str,
+        connection_id: &str,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+
+    fn get_subscribers(
+        &self,
+        topic: &str,
+    ) -> impl Future<Output = Result<Vec<SubscriptionRecord>, Error>> + Send;
+
+    fn get_subscriptions_from_connection_id(
+        &self,
+        connection_id: &str,
+    ) -> impl Future<Output = Result<Vec<SubscriptionRecord>, Error>> + Send;
+}
+
+pub trait SeekRepository: Send + Sync {
+    fn save_seek(&self, seek: &SeekRecord) -> impl Future<Output = Result<(), Error>> + Send;
+
+    fn delete_seek(&self, seek: &SeekRecord) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Removes the seek from the queue. Returns `false` if another request claimed it first.
+    fn claim_seek(&self, seek: &SeekRecord) -> impl Future<Output = Result<bool, Error>> + Send;
+
+    /// The seeks in the pool, oldest first
+    fn get_seeks_in_pool(
+        &self,
+        pool: &str,
+    ) -> impl Future<Output = Result<Vec<SeekRecord>, Error>> + Send;
+
+    fn get_seeks_from_connection_id(
+        &self,
+        connection_id: &str,
+    ) -> impl Future<Output = Result<Vec<SeekRecord>, Error>> + Send;
+}
+
+pub trait ChatRepository: Send + Sync {
+    fn save_chat_message(
+        &self,
+        chat_message: &ChatMessageRecord,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// The game's chat messages sent after `since` (an RFC 3339 timestamp), oldest first
+    fn get_chat_messages(
+        &self,
+        game_id: &str,
+        since: Option<&str>,
+    ) -> impl Future<Output = Result<Vec<ChatMessageRecord>, Error>> + Send;
+}
+
+pub trait TournamentRepository: Send + Sync {
+    fn get_tournament(
+        &self,
+        tournament_id: &str,
+    ) -> impl Future<Output = Result<Option<TournamentRecord>, Error>> + Send;
+
+    /// Returns `false` if a tournament with the same ID already exists
+    fn insert_tournament(
+        &self,
+        tournament: &TournamentRecord,
+    ) -> impl Future<Output = Result<bool, Error>> + Send;
+
+    /// Saves the tournament if the stored one is still at `expected_version`. Returns `false`
+    /// if another request changed it in the meantime.
+    fn save_tournament(
+        &self,
+        tournament: &TournamentRecord,
+        expected_version: u64,
+    ) -> impl Future<Output = Result<bool, Error>> + Send;
+}
+
+/// Everything a handler might need to store
+pub trait Repository:
+    GameRepository
+    + UserRepository
+    + SubscriptionRepository
+    + SeekRepository
+    + ChatRepository
+    + TournamentRepository
+{
+}
+
+impl<T> Repository for T where
+    T: GameRepository
+        + UserRepository
+        + SubscriptionRepository
+        + SeekRepository
+        + ChatRepository
+        + TournamentRepository
+{
+}
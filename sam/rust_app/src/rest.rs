@@ -0,0 +1,46 @@
+use aws_config::BehaviorVersion;
+use aws_sdk_dynamodb::Client;
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+
+use chess::http_handlers::handle_http_request;
+use chess::repository::{DynamoDbRepository, Repository};
+
+/// Serves the read-only HTTP endpoints through the HTTP API
+async fn function_handler(
+    request: Request,
+    repository: &impl Repository,
+) -> Result<Response<Body>, Error> {
+    let (parts, _) = request.into_parts();
+
+    let response = handle_http_request(repository, &parts).await?;
+
+    tracing::info!(
+        "{} {} -> {}",
+        parts.method,
+        parts.uri.path(),
+        response.status()
+    );
+
+    Ok(response.map(Body::from))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let sdk_config = aws_config::load_defaults(BehaviorVersion::latest()).await;
+    let repository = DynamoDbRepository::from_env(Client::new(&sdk_config));
+
+    tracing_subscriber::fmt()
+        .json()
+        .with_max_level(tracing::Level::INFO)
+        .with_target(true) // Include the name of the module in every log line
+        .with_current_span(false) // Remove duplicated "span" key in from logs
+        .without_time() // CloudWatch will add the ingestion time
+        .init();
+
+    run(service_fn(|request: Request| async {
+        function_handler(request, &repository).await
+    }))
+    .await?;
+
+    Ok(())
+}
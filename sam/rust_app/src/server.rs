@@ -0,0 +1,323 @@
+//! Runs the whole backend as a single process, for playing locally or self-hosting without AWS.
+//! Clients connect over WebSocket exactly as they do to API Gateway, and games are kept in memory
+//! until the server stops.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use aws_lambda_events::encodings::Body;
+use axum::extract::ws::{Message, WebSocketUpgrade};
+use axum::extract::{Query, Request, State};
+use axum::response::{IntoResponse, Response};
+use axum::routing::any;
+use axum::Router;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use futures_util::{SinkExt, StreamExt};
+use lambda_http::http::StatusCode;
+use lambda_runtime::Error;
+use rand::RngCore;
+use tokio::runtime::Handle;
+
+use chess::helpers::abort::abort_games_awaiting_first_move;
+use chess::helpers::chat::parse_word_filter;
+use chess::helpers::connection::{connect_user, handle_connection_closed};
+use chess::helpers::tournament::forfeit_unjoined_tournament_games;
+use chess::http_handlers::handle_http_request;
+use chess::notifier::{Notifier, WebSocketNotifier};
+use chess::player_action_handlers::handle_player_action;
+use chess::repository::InMemoryRepository;
+use chess::types::api::GameRequest;
+use chess::utils::api::build_response;
+
+/// Setting up the engine needs more stack than Tokio gives its threads by default
+const THREAD_STACK_SIZE: usize = 16 * 1024 * 1024;
+
+/// How often games are checked for a missing first move, as often as the `sweeper` Lambda runs
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+const DEFAULT_FIRST_MOVE_TIMEOUT_SECONDS: i64 = 60;
+
+const DEFAULT_TOURNAMENT_JOIN_TIMEOUT_SECONDS: i64 = 300;
+
+struct Server {
+    repository: InMemoryRepository,
+    notifier: WebSocketNotifier,
+    chat_word_filter: Vec<String>,
+    first_move_timeout_seconds: i64,
+    tournament_join_timeout_seconds: i64,
+}
+
+/// API Gateway connection IDs are 16 characters of base64, and so are these
+fn generate_connection_id() -> String {
+    let mut bytes = [0u8; 12];
+    rand::rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Connections without a session token are guests. Connections with an invalid
+/// or expired token are rejected so the client knows to log in again.
+async fn connect(
+    upgrade: WebSocketUpgrade,
+    Query(query): Query<HashMap<String, String>>,
+    State(server): State<Arc<Server>>,
+) -> Response {
+    let connection_id = generate_connection_id();
+
+    if let Some(token) = query.get("token") {
+        match connect_user(&server.notifier, &server.repository, &connection_id, token).await {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                return (StatusCode::UNAUTHORIZED, "Invalid or expired session token")
+                    .into_response();
+            }
+            Err(e) => {
+                tracing::error!("Failed to connect connection ID ({connection_id}): {e}");
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        }
+    } else {
+        tracing::info!("GUEST CONNECTED (CONNECTION ID: {connection_id})");
+    }
+
+    let outgoing = server.notifier.register(&connection_id);
+    let failed_server = server.clone();
+    let failed_connection_id = connection_id.clone();
+
+    upgrade
+        .on_failed_upgrade(move |e| {
+            tracing::warn!("Failed to upgrade connection ID ({failed_connection_id}): {e}");
+
+            tokio::spawn(async move {
+                disconnect(&failed_server, &failed_connection_id).await;
+            });
+        })
+        .on_upgrade(move |socket| async move {
+            let (mut writer, mut reader) = socket.split();
+
+            let write_connection_id = connection_id.clone();
+            let mut outgoing = outgoing;
+            let mut write = tokio::spawn(async move {
+                while let Some(payload) = outgoing.recv().await {
+                    if let Err(e) = writer.send(Message::Text(payload.into())).await {
+                        tracing::warn!(
+                            "Failed to write to connection ID ({write_connection_id}): {e}"
+                        );
+                        break;
+                    }
+                }
+            });
+
+            let read = async {
+                while let Some(Ok(message)) = reader.next().await {
+                    match message {
+                        Message::Text(text) => {
+                            handle_message(&server, &connection_id, text.to_string()).await
+                        }
+                        Message::Close(_) => break,
+                        _ => {}
+                    }
+                }
+            };
+
+            tokio::select! {
+                _ = read => write.abort(),
+                _ = &mut write => {}
+            }
+
+            disconnect(&server, &connection_id).await;
+        })
+}
+
+/// Serves the read-only HTTP endpoints the way the `rest` Lambda does
+async fn http(State(server): State<Arc<Server>>, request: Request) -> Response {
+    let (parts, _) = request.into_parts();
+
+    match handle_http_request(&server.repository, &parts).await {
+        Ok(response) => response.into_response(),
+        Err(e) => {
+            tracing::error!("Failed to handle {} {}: {e}", parts.method, parts.uri);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handles the request the way the `game` Lambda does, and sends the response back over the
+/// socket the way API Gateway does
+async fn handle_message(server: &Arc<Server>, connection_id: &str, request_body: String) {
+    let server = server.clone();
+    let connection_id = connection_id.to_string();
+
+    run_on_blocking_pool(move |runtime| {
+        runtime.block_on(respond(&server, &connection_id, &request_body))
+    })
+    .await;
+}
+
+async fn respond(server: &Server, connection_id: &str, request_body: &str) {
+    let response = match serde_json::from_str::<GameRequest>(request_body) {
+        Ok(request) => {
+            handle_player_action(
+                &server.notifier,
+                &server.repository,
+                connection_id,
+                &server.chat_word_filter,
+                request.data,
+            )
+            .await
+        }
+        Err(e) => build_response(
+            StatusCode::BAD_REQUEST,
+            Some(connection_id.to_string()),
+            Some(vec![format!(
+                "Failed to parse request body into a valid player action: {e}"
+            )
+            .into()]),
+            None::<()>,
+        ),
+    };
+
+    let response = response.or_else(|e| {
+        tracing::error!("Failed to handle request from connection ID ({connection_id}): {e}");
+
+        build_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Some(connection_id.to_string()),
+            Some(vec!["Internal server error".into()]),
+            None::<()>,
+        )
+    });
+
+    let payload = match response.map(|response| response.body) {
+        Ok(Some(Body::Text(payload))) => payload,
+        Ok(_) => return,
+        Err(e) => {
+            tracing::error!("Failed to build response for connection ID ({connection_id}): {e}");
+            return;
+        }
+    };
+
+    if let Err(e) = server.notifier.send_payload(connection_id, payload).await {
+        tracing::error!("Failed to respond to connection ID ({connection_id}): {e}");
+    }
+}
+
+/// Cleans up the way the `disconnect` Lambda does
+async fn disconnect(server: &Arc<Server>, connection_id: &str) {
+    server.notifier.unregister(connection_id);
+
+    let server = server.clone();
+    let connection_id = connection_id.to_string();
+
+    run_on_blocking_pool(move |runtime| {
+        let closed = handle_connection_closed(&server.notifier, &server.repository, &connection_id);
+
+        if let Err(e) = runtime.block_on(closed) {
+            tracing::error!("Failed to clean up after connection ID ({connection_id}): {e}");
+        }
+
+        tracing::info!("CONNECTION {connection_id} CLOSED");
+    })
+    .await;
+}
+
+/// Aborts games in which a player never made their first move, and forfeits tournament games that
+/// a player never joined, the way the `sweeper` Lambda does
+fn spawn_sweeper(server: Arc<Server>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let server = server.clone();
+
+            run_on_blocking_pool(move |runtime| {
+                let sweep = abort_games_awaiting_first_move(
+                    &server.notifier,
+                    &server.repository,
+                    server.first_move_timeout_seconds,
+                );
+
+                if let Err(e) = runtime.block_on(sweep) {
+                    tracing::error!("Failed to abort games awaiting a first move: {e}");
+                }
+
+                let sweep = forfeit_unjoined_tournament_games(
+                    &server.notifier,
+                    &server.repository,
+                    server.tournament_join_timeout_seconds,
+                );
+
+                if let Err(e) = runtime.block_on(sweep) {
+                    tracing::error!("Failed to forfeit unjoined tournament games: {e}");
+                }
+            })
+            .await;
+        }
+    });
+}
+
+/// Handlers run on the blocking thread pool, since the engine's search is CPU-bound. Their futures
+/// also aren't `Send`, so they couldn't be spawned onto the worker threads anyway.
+async fn run_on_blocking_pool(task: impl FnOnce(&Handle) + Send + 'static) {
+    let runtime = Handle::current();
+
+    if let Err(e) = tokio::task::spawn_blocking(move || task(&runtime)).await {
+        tracing::error!("Handler panicked: {e}");
+    }
+}
+
+fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::INFO)
+        .with_target(true) // Include the name of the module in every log line
+        .init();
+
+    tokio::runtime::Builder::new_multi_thread()
+        .thread_stack_size(THREAD_STACK_SIZE)
+        .enable_all()
+        .build()?
+        .block_on(serve())
+}
+
+async fn serve() -> Result<(), Error> {
+    let address = std::env::var("LISTEN_ADDRESS").unwrap_or_else(|_| "0.0.0.0:8080".to_string());
+
+    let server = Arc::new(Server {
+        repository: InMemoryRepository::new(),
+        notifier: WebSocketNotifier::new(),
+        chat_word_filter: parse_word_filter(&std::env::var("CHAT_WORD_FILTER").unwrap_or_default()),
+        first_move_timeout_seconds: std::env::var("FIRST_MOVE_TIMEOUT_SECONDS").map_or(
+            DEFAULT_FIRST_MOVE_TIMEOUT_SECONDS,
+            |seconds| {
+                seconds
+                    .parse()
+                    .expect("`FIRST_MOVE_TIMEOUT_SECONDS` should be a number")
+            },
+        ),
+        tournament_join_timeout_seconds: std::env::var("TOURNAMENT_JOIN_TIMEOUT_SECONDS").map_or(
+            DEFAULT_TOURNAMENT_JOIN_TIMEOUT_SECONDS,
+            |seconds| {
+                seconds
+                    .parse()
+                    .expect("`TOURNAMENT_JOIN_TIMEOUT_SECONDS` should be a number")
+            },
+        ),
+    });
+
+    spawn_sweeper(server.clone());
+
+    let app = Router::new()
+        .route("/", any(connect))
+        .fallback(http)
+        .with_state(server);
+
+    let listener = tokio::net::TcpListener::bind(&address).await?;
+
+    tracing::info!("Listening for WebSocket connections on ws://{address} and HTTP requests on http://{address}");
+
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
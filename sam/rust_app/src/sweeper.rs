@@ -0,0 +1,59 @@
+use aws_config::BehaviorVersion;
+use aws_sdk_dynamodb::Client;
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+
+use chess::helpers::abort::abort_games_awaiting_first_move;
+use chess::helpers::tournament::forfeit_unjoined_tournament_games;
+use chess::notifier::api_gateway::get_endpoint_url;
+use chess::notifier::ApiGatewayNotifier;
+use chess::repository::{
+    DynamoDbRepository, GameRepository, SubscriptionRepository, UserRepository,
+};
+
+/// Runs on a schedule to abort games in which a player never made their first move, and to
+/// forfeit tournament games that a player never joined
+async fn function_handler(
+    _event: LambdaEvent<serde_json::Value>,
+    notifier: &ApiGatewayNotifier,
+    repository: &(impl GameRepository + SubscriptionRepository + UserRepository),
+) -> Result<(), Error> {
+    let first_move_timeout_seconds: i64 = std::env::var("FIRST_MOVE_TIMEOUT_SECONDS")
+        .unwrap()
+        .parse()
+        .expect("`FIRST_MOVE_TIMEOUT_SECONDS` should be a number");
+    let tournament_join_timeout_seconds: i64 = std::env::var("TOURNAMENT_JOIN_TIMEOUT_SECONDS")
+        .unwrap()
+        .parse()
+        .expect("`TOURNAMENT_JOIN_TIMEOUT_SECONDS` should be a number");
+
+    // There is no WebSocket request to take the API endpoint from
+    let notifier = notifier.for_endpoint(&get_endpoint_url(
+        &std::env::var("WEBSOCKET_API_DOMAIN_NAME").unwrap(),
+        &std::env::var("WEBSOCKET_API_STAGE").unwrap(),
+    ));
+
+    abort_games_awaiting_first_move(&notifier, repository, first_move_timeout_seconds).await?;
+    forfeit_unjoined_tournament_games(&notifier, repository, tournament_join_timeout_seconds).await
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let sdk_config = aws_config::load_defaults(BehaviorVersion::latest()).await;
+    let repository = DynamoDbRepository::from_env(Client::new(&sdk_config));
+    let notifier = ApiGatewayNotifier::new(&sdk_config);
+
+    tracing_subscriber::fmt()
+        .json()
+        .with_max_level(tracing::Level::INFO)
+        .with_target(true) // Include the name of the module in every log line
+        .with_current_span(false) // Remove duplicated "span" key in from logs
+        .without_time() // CloudWatch will add the ingestion time
+        .init();
+
+    run(service_fn(|event: LambdaEvent<serde_json::Value>| async {
+        function_handler(event, &notifier, &repository).await
+    }))
+    .await?;
+
+    Ok(())
+}
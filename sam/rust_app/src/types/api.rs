@@ -51,4 +51,10 @@ pub struct ApiResponse<T> {
     pub data: Option<T>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub replaces_game_id: Option<String>,
+    /// Only sent to the player who was just seated. Needed to rejoin the game later.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seat_token: Option<String>,
+    /// Only sent to the creator of a private game, to share with the opponent they invite
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invite_token: Option<String>,
 }
@@ -1,15 +1,27 @@
 use crate::types::board::BoardSetup;
-use crate::types::game::{ColorPreference, EngineDifficulty, GameState};
+use crate::types::game::{ColorPreference, EngineDifficulty, GameState, MatchScore, PlayerMove};
 use crate::types::piece::Color;
+use crate::types::presence::ConnectionState;
+use crate::types::rating::{Ratings, TimeControlCategory};
+use crate::types::tournament::{
+    TournamentFormat, TournamentGame, TournamentRound, TournamentState,
+};
 
 use serde::{Deserialize, Serialize};
 
+fn default_takebacks_allowed() -> bool {
+    true
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GameRecord {
     pub game_id: String, // PK
-    pub white_connection_id: Option<String>,
+    // Incremented on every save, so concurrent changes to the game can be detected
+    #[serde(default)]
+    pub version: u64,
+    pub white_connection_id: Option<ConnectionState>,
     pub white_username: Option<String>,
-    pub black_connection_id: Option<String>,
+    pub black_connection_id: Option<ConnectionState>,
     pub black_username: Option<String>,
     pub board_setup: BoardSetup,
     pub color_preference: ColorPreference,
@@ -19,14 +31,234 @@ pub struct GameRecord {
     pub created: String,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub draw_offered_by: Option<Color>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub takeback_requested_by: Option<Color>,
+    // Players who don't want to receive their opponent's chat messages
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chat_muted_by: Vec<Color>,
+    #[serde(default = "default_takebacks_allowed")]
+    pub takebacks_allowed: bool,
+    // Rated games update both players' ratings when they end
+    #[serde(default)]
+    pub rated: bool,
+    // Never serialized, so the opponent can't see them. `save_game` stores them separately.
+    #[serde(default, skip_serializing)]
+    pub white_premove: Option<PlayerMove>,
+    #[serde(default, skip_serializing)]
+    pub black_premove: Option<PlayerMove>,
+    #[serde(default)]
+    pub spectator_count: usize,
+    // Hashes of the secrets that let each player reclaim their seat after disconnecting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub white_seat_token_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub black_seat_token_hash: Option<String>,
+    // Seats taken by a logged-in user, who can rejoin them by logging in again. Guest seats can
+    // only be rejoined with their seat token.
+    #[serde(default)]
+    pub white_authenticated: bool,
+    #[serde(default)]
+    pub black_authenticated: bool,
+    // Only set for private games, whose free seat can only be claimed with the invite token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invite_token_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invite_expires_at: Option<i64>, // Epoch seconds
+    // Only set while the game is waiting for an opponent. Partition key of the sparse lobby index.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lobby: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub match_score: Option<MatchScore>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tournament: Option<TournamentGame>,
+    // Only set while a started game is waiting on a player's first move. Keys of the sparse
+    // first-move index, which the sweeper uses to abort games nobody starts playing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub awaiting_first_move: Option<Color>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub awaiting_first_move_since: Option<String>,
+    // Only set while a tournament game is waiting for its players to join. Keys of the sparse
+    // join index, which the sweeper uses to forfeit players who never join.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub awaiting_join: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub awaiting_join_since: Option<String>,
+}
+
+/// The attributes of a `GameRecord` projected into the first-move index
+#[derive(Serialize, Deserialize)]
+pub struct AwaitingFirstMoveRecord {
+    pub game_id: String,
+    pub awaiting_first_move: Color,
+    pub awaiting_first_move_since: String,
+}
+
+/// The attributes of a `GameRecord` projected into the join index
+#[derive(Serialize, Deserialize)]
+pub struct AwaitingJoinRecord {
+    pub game_id: String,
+    pub awaiting_join: String,
+    pub awaiting_join_since: String,
+}
+
+/// The attributes of a `GameRecord` projected into the lobby index
+#[derive(Serialize, Deserialize)]
+pub struct OpenGameRecord {
+    pub game_id: String,
+    pub white_username: Option<String>,
+    pub black_username: Option<String>,
+    pub board_setup: BoardSetup,
+    pub color_preference: ColorPreference,
+    pub seconds_per_player: Option<usize>,
+    pub created: String,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct UserRecord {
     pub username: String, // PK
     #[serde(rename = "sk")]
-    pub sort_key: String, // SK: INFO | GAME-<game-id>
-    pub connection_id: Option<String>,
+    pub sort_key: String, // SK: GAME-<game-id> | CONNECTION-<connection-id>
+    pub connection_id: Option<ConnectionState>,
     pub winner: Option<String>,
     pub created: String,
+    // Only set on user-game records. Sort key of the sparse history index.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_created: Option<String>,
+}
+
+/// The attributes of a `UserRecord` projected into the history index
+#[derive(Serialize, Deserialize)]
+pub struct UserGameHistoryRecord {
+    pub username: String,
+    #[serde(rename = "sk")]
+    pub sort_key: String, // GAME-<game-id>
+    pub game_created: String,
+}
+
+/// Only registered users have an `INFO` record
+#[derive(Serialize, Deserialize)]
+pub struct UserInfoRecord {
+    pub username: String, // PK
+    #[serde(rename = "sk")]
+    pub sort_key: String, // SK: INFO
+    pub password_hash: String,
+    pub created: String,
+    // Refreshed by heartbeats, and when the user connects or disconnects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<String>,
+    #[serde(flatten)]
+    pub ratings: Ratings,
+}
+
+/// One side of a friendship. Users are friends once they've both added each other.
+#[derive(Serialize, Deserialize)]
+pub struct FriendRecord {
+    pub username: String, // PK
+    #[serde(rename = "sk")]
+    pub sort_key: String, // SK: FRIEND-<friend>
+    pub friend: String,
+    pub created: String,
+}
+
+/// Running totals for one slice of a user's finished games (e.g. their games as white), updated
+/// as games end. Only the attribute describing the record's slice is set.
+#[derive(Serialize, Deserialize)]
+pub struct UserStatsRecord {
+    pub username: String, // PK
+    #[serde(rename = "sk")]
+    pub sort_key: String, // SK: STATS#ALL | STATS#COLOR#<color> | STATS#TIME-CONTROL#<category> | STATS#BOARD#<setup> | STATS#OPENING#<eco>#<name>
+    #[serde(default)]
+    pub wins: u32,
+    #[serde(default)]
+    pub draws: u32,
+    #[serde(default)]
+    pub losses: u32,
+    #[serde(default)]
+    pub total_plies: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<Color>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_control: Option<TimeControlCategory>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub board_setup: Option<BoardSetup>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eco: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opening_name: Option<String>,
+}
+
+/// A pending challenge, stored under the challenged user until it's accepted, declined or expires
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChallengeRecord {
+    pub username: String, // PK: The challenged user
+    #[serde(rename = "sk")]
+    pub sort_key: String, // SK: CHALLENGE-<challenge-id>
+    pub challenge_id: String,
+    pub challenger: String,
+    pub challenger_connection_id: String,
+    pub board_setup: BoardSetup,
+    pub color_preference: ColorPreference, // The challenger's
+    pub seconds_per_player: Option<usize>,
+    pub rated: bool,
+    pub created: String,
+    pub expires_at: i64, // Epoch seconds. Also the table's TTL attribute.
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub username: String, // PK
+    #[serde(rename = "sk")]
+    pub sort_key: String, // SK: SESSION-<token-hash>
+    pub created: String,
+    pub expires_at: i64, // Epoch seconds. Also the table's TTL attribute.
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SubscriptionRecord {
+    pub topic: String,         // PK: GAME-<game-id> | LOBBY
+    pub connection_id: String, // SK
+    pub created: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SeekRecord {
+    pub pool: String,    // PK: POOL-<board-setup>-<seconds-per-player | UNTIMED>
+    pub seek_id: String, // SK: <created>-<connection-id>, so the oldest seeks are matched first
+    pub connection_id: String,
+    pub username: String,
+    pub rating: u32,
+    #[serde(default)]
+    pub authenticated: bool, // Whether the seeker was logged in
+    pub min_rating: Option<u32>,
+    pub max_rating: Option<u32>,
+    pub board_setup: BoardSetup,
+    pub seconds_per_player: Option<usize>,
+    pub created: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ChatMessageRecord {
+    pub game_id: String,               // PK
+    pub message_id: String, // SK: <created>-<random-id>, so messages sort chronologically
+    pub connection_id: Option<String>, // `None` for the engine
+    pub username: Option<String>, // `None` for the engine
+    pub message: String,
+    pub created: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TournamentRecord {
+    pub tournament_id: String, // PK
+    // Incremented on every save, so concurrent changes can't overwrite each other
+    #[serde(default)]
+    pub version: u64,
+    pub name: String,
+    pub organizer: String,
+    pub format: TournamentFormat,
+    pub board_setup: BoardSetup,
+    pub seconds_per_player: Option<usize>,
+    pub players: Vec<String>, // In registration order, which is also the seeding
+    pub rounds: Vec<TournamentRound>,
+    pub state: TournamentState,
+    pub created: String,
 }
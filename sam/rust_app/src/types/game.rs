@@ -3,13 +3,16 @@ use serde::{ser::SerializeStruct, Deserialize, Serialize};
 
 use crate::helpers::{
     board::{decode_piece, encode_piece},
+    game::apply_move_to_position,
     generic::{base64_to_bytes, bytes_to_base64},
     opening_detection::OpeningInfo,
+    snapshot::{uci_to_player_move, SNAPSHOT_INTERVAL_PLIES},
 };
 
 use super::{
     board::{Board, BoardSetup, Position},
     piece::{Color, Piece},
+    tournament::TournamentFormat,
 };
 
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -18,6 +21,7 @@ pub enum GameEnding {
     Checkmate(Color),
     Resignation(Color),
     OutOfTime(Color),
+    Forfeit(Color), // Never joined a tournament game
     Stalemate,
     DrawByThreefoldRepetition,
     DrawByFiftyMoveRule,
@@ -31,6 +35,7 @@ pub enum State {
     NotStarted,
     InProgress,
     Finished(GameEnding),
+    Aborted, // Ended before both sides moved, without a result
 }
 
 #[derive(Clone, Debug)]
@@ -187,7 +192,34 @@ impl From<EngineDifficulty> for Difficulty {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerScore {
+    pub username: Option<String>, // `None` for the engine
+    pub points: f32,
+}
+
+/// Running score of a best-of-N match, as of the start of the game it's attached to
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchScore {
+    pub best_of: u8,
+    pub game_ids: Vec<String>, // Earlier games in the match, oldest first
+    pub scores: Vec<PlayerScore>,
+}
+
+impl MatchScore {
+    /// The match is over once a player can no longer be caught or every game has been played
+    pub fn is_decided(&self) -> bool {
+        self.game_ids.len() >= self.best_of as usize
+            || self
+                .scores
+                .iter()
+                .any(|score| score.points * 2.0 > self.best_of as f32)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GameTime {
     pub both_players_last_connected_at: Option<String>,
@@ -230,17 +262,42 @@ pub struct GameStateAtPointInTime {
     pub captured_pieces: CapturedPieces,
     pub moves: Vec<String>, // Available moves
     pub engine_result: Option<SearchStatistics>,
+    // Clocks when this state was reached, so they can be restored by a takeback
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock: Option<ClockSnapshot>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct ClockSnapshot {
+    pub white_seconds_left: usize,
+    pub black_seconds_left: usize,
+}
+
+impl From<&GameTime> for ClockSnapshot {
+    fn from(game_time: &GameTime) -> Self {
+        ClockSnapshot {
+            white_seconds_left: game_time.white_seconds_left,
+            black_seconds_left: game_time.black_seconds_left,
+        }
+    }
+}
+
+// Kept compact, since it's stored and sent to clients with every update. Past positions are
+// rebuilt from the nearest snapshot when they're asked for (see `position_at`).
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", try_from = "StoredGameState")]
 pub struct GameState {
     pub game_id: String,
     pub game_time: Option<GameTime>,
-    pub history: Vec<GameStateAtPointInTime>,
     pub move_list: Vec<String>,
     pub san_list: Vec<String>,
     pub opening: Option<OpeningInfo>,
+    /// Past positions, every `SNAPSHOT_INTERVAL_PLIES` plies. They don't list their moves, since
+    /// past positions can't be played from.
+    pub snapshots: Vec<PositionSnapshot>,
+    pub ply_details: Vec<PlyDetails>, // One per past position
+    pub current: GameStateAtPointInTime,
 }
 
 impl GameState {
@@ -260,7 +317,7 @@ impl GameState {
 
         GameState {
             game_id,
-            history: vec![GameStateAtPointInTime {
+            current: GameStateAtPointInTime {
                 state: State::NotStarted,
                 current_turn: Color::White,
                 in_check: None,
@@ -268,7 +325,11 @@ impl GameState {
                 captured_pieces: captured_pieces.clone(),
                 moves: Vec::new(),
                 engine_result: None,
-            }],
+                clock: seconds_per_player.map(|seconds| ClockSnapshot {
+                    white_seconds_left: seconds,
+                    black_seconds_left: seconds,
+                }),
+            },
             game_time: seconds_per_player.map(|seconds| GameTime {
                 both_players_last_connected_at: None,
                 last_move_at: None,
@@ -278,23 +339,133 @@ impl GameState {
             move_list: Vec::new(),
             san_list: Vec::new(),
             opening: None,
+            snapshots: Vec::new(),
+            ply_details: Vec::new(),
         }
     }
 
     pub fn current_state(&self) -> &GameStateAtPointInTime {
-        self.history
-            .last()
-            .expect("Game history should not be empty")
+        &self.current
     }
 
     pub fn current_state_mut(&mut self) -> &mut GameStateAtPointInTime {
-        self.history
-            .last_mut()
-            .expect("Game history should not be empty")
+        &mut self.current
+    }
+
+    /// The number of half-moves played, which is also the current position's index
+    pub fn ply_count(&self) -> usize {
+        self.ply_details.len()
     }
+
+    /// Makes `position` the current one, recording the one it replaces
+    pub fn push_position(&mut self, position: GameStateAtPointInTime) {
+        let ply = self.ply_count();
+        let mut previous = std::mem::replace(&mut self.current, position);
+
+        self.ply_details.push(PlyDetails {
+            clock: previous.clock,
+            engine_result: previous.engine_result.clone(),
+        });
+
+        if ply.is_multiple_of(SNAPSHOT_INTERVAL_PLIES) {
+            previous.moves = Vec::new();
+            self.snapshots.push(PositionSnapshot {
+                ply,
+                position: previous,
+            });
+        }
+    }
+
+    /// The position after `ply` half-moves, or `None` if fewer have been played
+    pub fn position_at(&self, ply: usize) -> Option<GameStateAtPointInTime> {
+        self.positions_leading_to(ply)?.pop()
+    }
+
+    /// The positions from the nearest snapshot at or before `ply` up to `ply`, since rebuilding
+    /// one passes through the others. Returns `None` if fewer than `ply` half-moves have been
+    /// played, or if the moves leading to the position weren't recorded.
+    pub fn positions_leading_to(&self, ply: usize) -> Option<Vec<GameStateAtPointInTime>> {
+        if ply >= self.ply_count() {
+            return (ply == self.ply_count()).then(|| vec![self.current.clone()]);
+        }
+
+        let snapshot = self
+            .snapshots
+            .iter()
+            .rev()
+            .find(|snapshot| snapshot.ply <= ply)?;
+
+        let mut positions = vec![snapshot.position.clone()];
+
+        for replayed_ply in snapshot.ply + 1..=ply {
+            let player_move = uci_to_player_move(self.move_list.get(replayed_ply - 1)?)?;
+            let mut position = positions.last()?.clone();
+            apply_move_to_position(&mut position, &player_move);
+
+            let details = &self.ply_details[replayed_ply];
+            position.clock = details.clock;
+            position.engine_result = details.engine_result.clone();
+
+            positions.push(position);
+        }
+
+        Some(positions)
+    }
+
+    /// Goes back to the position after `ply` half-moves, forgetting the moves made since
+    pub fn truncate(&mut self, ply: usize) {
+        let Some(mut position) = self.position_at(ply) else {
+            return;
+        };
+
+        position.moves = Vec::new();
+        self.current = position;
+        self.snapshots.retain(|snapshot| snapshot.ply < ply);
+        self.ply_details.truncate(ply);
+        self.move_list.truncate(ply);
+        self.san_list.truncate(ply);
+    }
+}
+
+/// Every way a game's state has been stored. Older games are converted when they're read.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredGameState {
+    pub game_id: String,
+    pub game_time: Option<GameTime>,
+    pub move_list: Vec<String>,
+    #[serde(default)]
+    pub san_list: Vec<String>,
+    pub opening: Option<OpeningInfo>,
+    #[serde(default)]
+    pub snapshots: Vec<PositionSnapshot>,
+    #[serde(default)]
+    pub ply_details: Vec<PlyDetails>,
+    #[serde(default)]
+    pub current: Option<GameStateAtPointInTime>,
+    // Games saved before snapshots were added store every position
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<GameStateAtPointInTime>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionSnapshot {
+    pub ply: usize,
+    pub position: GameStateAtPointInTime,
+}
+
+/// What can't be worked out by replaying the moves
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlyDetails {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock: Option<ClockSnapshot>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub engine_result: Option<SearchStatistics>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PlayerMove {
     pub from: Position,
     pub to: Position,
@@ -312,17 +483,23 @@ pub enum AnalysisType {
 pub enum PlayerAction {
     #[serde(rename_all = "camelCase")]
     CreateGame {
-        username: String,
+        username: Option<String>, // Ignored when logged in
         game_id: Option<String>,
         board_setup: Option<BoardSetup>,
         color_preference: Option<ColorPreference>,
         seconds_per_player: Option<usize>,
         engine_difficulty: Option<EngineDifficulty>,
+        allow_takebacks: Option<bool>,          // Defaults to `true`
+        rated: Option<bool>,                    // Defaults to `false`
+        private: Option<bool>,                  // Defaults to `false`
+        invite_expires_in_minutes: Option<u32>, // For private games
     },
     #[serde(rename_all = "camelCase")]
     JoinGame {
-        username: String,
+        username: Option<String>, // Ignored when logged in
         game_id: String,
+        seat_token: Option<String>, // Required to rejoin a seat as a guest
+        invite_token: Option<String>, // Required to join a private game
     },
     #[serde(rename_all = "camelCase")]
     LeaveGame {
@@ -347,6 +524,10 @@ pub enum PlayerAction {
         game_id: String,
     },
     #[serde(rename_all = "camelCase")]
+    AbortGame {
+        game_id: String,
+    },
+    #[serde(rename_all = "camelCase")]
     OfferDraw {
         game_id: String,
     },
@@ -355,6 +536,18 @@ pub enum PlayerAction {
         game_id: String,
     },
     #[serde(rename_all = "camelCase")]
+    RequestTakeback {
+        game_id: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    AcceptTakeback {
+        game_id: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    DeclineTakeback {
+        game_id: String,
+    },
+    #[serde(rename_all = "camelCase")]
     DeclineDraw {
         game_id: String,
     },
@@ -373,7 +566,116 @@ pub enum PlayerAction {
         game_id: String,
     },
     #[serde(rename_all = "camelCase")]
+    GetPositions {
+        game_id: String,
+        ply: usize,
+    },
+    #[serde(rename_all = "camelCase")]
     PlayAgain {
         game_id: String,
+        color_preference: Option<ColorPreference>, // Defaults to swapping colors
+        best_of: Option<u8>, // Starts a match, unless the game is already part of one
+    },
+    #[serde(rename_all = "camelCase")]
+    Register {
+        username: String,
+        password: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Login {
+        username: String,
+        password: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Logout {
+        session_token: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    WatchGame {
+        game_id: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    StopWatchingGame {
+        game_id: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Seek {
+        username: Option<String>, // Ignored when logged in
+        board_setup: Option<BoardSetup>,
+        seconds_per_player: Option<usize>,
+        min_rating: Option<u32>,
+        max_rating: Option<u32>,
+    },
+    CancelSeek,
+    ListOpenGames,
+    LeaveLobby,
+    #[serde(rename_all = "camelCase")]
+    SendChatMessage {
+        game_id: String,
+        message: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    GetChatMessages {
+        game_id: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    CreateTournament {
+        name: String,
+        format: TournamentFormat,
+        board_setup: Option<BoardSetup>,
+        seconds_per_player: Option<usize>,
+    },
+    #[serde(rename_all = "camelCase")]
+    JoinTournament {
+        tournament_id: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    StartTournamentRound {
+        tournament_id: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    GetTournament {
+        tournament_id: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    SetPremove {
+        game_id: String,
+        player_move: Option<PlayerMove>, // `None` clears the premove
+    },
+    #[serde(rename_all = "camelCase")]
+    MuteOpponent {
+        game_id: String,
+        muted: bool,
+    },
+    #[serde(rename_all = "camelCase")]
+    ListMyGames {
+        cursor: Option<String>, // From the previous page's `nextCursor`
+        limit: Option<usize>,
+    },
+    GetUserStats {
+        username: Option<String>, // Defaults to the logged-in user
+    },
+    #[serde(rename_all = "camelCase")]
+    ChallengeUser {
+        username: String,
+        board_setup: Option<BoardSetup>,
+        color_preference: Option<ColorPreference>, // The challenger's
+        seconds_per_player: Option<usize>,
+        rated: Option<bool>,
+    },
+    #[serde(rename_all = "camelCase")]
+    AcceptChallenge {
+        challenge_id: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    DeclineChallenge {
+        challenge_id: String,
+    },
+    AddFriend {
+        username: String,
+    },
+    RemoveFriend {
+        username: String,
     },
+    GetOnlineFriends,
 }
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::opening_detection::OpeningInfo;
+
+use super::{game::GameEnding, piece::Color, rating::TimeControlCategory};
+
+/// The result of a game from one player's point of view
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GameOutcome {
+    Win,
+    Loss,
+    Draw,
+    Aborted,
+}
+
+/// A summary of one of a player's games, for their game history
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameHistoryEntry {
+    pub game_id: String,
+    pub created: String,
+    pub color: Color,
+    pub opponent: Option<String>,     // `None` if nobody has joined yet
+    pub outcome: Option<GameOutcome>, // `None` while the game is in progress
+    pub end_reason: Option<GameEnding>,
+    pub seconds_per_player: Option<usize>,
+    pub time_control: TimeControlCategory,
+    pub opening: Option<OpeningInfo>,
+    pub rated: bool,
+}
+
+/// Sent in response to `ListMyGames`. Pass `next_cursor` back to get the following page.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameHistoryPage {
+    pub games: Vec<GameHistoryEntry>,
+    pub next_cursor: Option<String>,
+}
+
+/// Where a page of a user's game history ended: the key of its last entry in the history index
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HistoryCursor {
+    pub username: String,
+    pub sk: String,
+    pub game_created: String,
+}
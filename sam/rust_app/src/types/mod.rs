@@ -2,4 +2,9 @@ pub mod api;
 pub mod board;
 pub mod dynamo_db;
 pub mod game;
+pub mod history;
 pub mod piece;
+pub mod presence;
+pub mod rating;
+pub mod stats;
+pub mod tournament;
@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+
+/// Stored in place of a connection ID while a player is away, so they keep their seat
+const DISCONNECTED: &str = "<disconnected>";
+
+/// How a player in a game can be reached. Stored as their connection ID, or as
+/// `"<disconnected>"` while they're away.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum ConnectionState {
+    Connected(String),
+    Disconnected,
+}
+
+impl ConnectionState {
+    pub fn connected(connection_id: &str) -> Self {
+        ConnectionState::Connected(connection_id.to_string())
+    }
+
+    /// `None` while the player is disconnected
+    pub fn connection_id(&self) -> Option<&str> {
+        match self {
+            ConnectionState::Connected(connection_id) => Some(connection_id),
+            ConnectionState::Disconnected => None,
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        matches!(self, ConnectionState::Connected(_))
+    }
+}
+
+impl From<String> for ConnectionState {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            DISCONNECTED => ConnectionState::Disconnected,
+            _ => ConnectionState::Connected(value),
+        }
+    }
+}
+
+impl From<ConnectionState> for String {
+    fn from(value: ConnectionState) -> Self {
+        match value {
+            ConnectionState::Connected(connection_id) => connection_id,
+            ConnectionState::Disconnected => DISCONNECTED.to_string(),
+        }
+    }
+}
+
+/// Sent in response to `GetOnlineFriends`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FriendPresence {
+    pub username: String,
+    pub last_seen: Option<String>,
+}
+
+/// Pushed to a player's friends as their presence changes
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum FriendEvent {
+    Online {
+        username: String,
+    },
+    Offline {
+        username: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    StartedGame {
+        username: String,
+        game_id: String,
+    },
+}
+
+impl FriendEvent {
+    pub fn username(&self) -> &str {
+        match self {
+            FriendEvent::Online { username }
+            | FriendEvent::Offline { username }
+            | FriendEvent::StartedGame { username, .. } => username,
+        }
+    }
+}
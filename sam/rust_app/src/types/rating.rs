@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+
+/// Ratings are kept separately for each time control category
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TimeControlCategory {
+    Bullet,
+    Blitz,
+    Rapid,
+    Classical,
+    Correspondence, // Untimed games
+}
+
+impl TimeControlCategory {
+    pub fn from_seconds_per_player(seconds_per_player: Option<usize>) -> Self {
+        match seconds_per_player {
+            None => TimeControlCategory::Correspondence,
+            Some(seconds) if seconds < 3 * 60 => TimeControlCategory::Bullet,
+            Some(seconds) if seconds < 10 * 60 => TimeControlCategory::Blitz,
+            Some(seconds) if seconds < 30 * 60 => TimeControlCategory::Rapid,
+            Some(_) => TimeControlCategory::Classical,
+        }
+    }
+}
+
+impl std::fmt::Display for TimeControlCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimeControlCategory::Bullet => write!(f, "bullet"),
+            TimeControlCategory::Blitz => write!(f, "blitz"),
+            TimeControlCategory::Rapid => write!(f, "rapid"),
+            TimeControlCategory::Classical => write!(f, "classical"),
+            TimeControlCategory::Correspondence => write!(f, "correspondence"),
+        }
+    }
+}
+
+/// A Glicko-2 rating, on the familiar Glicko scale
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rating {
+    pub rating: f64,
+    pub deviation: f64,
+    pub volatility: f64,
+    pub games: u32, // Rated games played in the category
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Rating {
+            rating: 1500.0,
+            deviation: 350.0,
+            volatility: 0.06,
+            games: 0,
+        }
+    }
+}
+
+/// Each category is stored as its own `rating_<category>` attribute of the user's `INFO` record,
+/// so that a rating update doesn't overwrite the rest of the record
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Ratings {
+    #[serde(
+        default,
+        rename = "rating_bullet",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub bullet: Option<Rating>,
+    #[serde(
+        default,
+        rename = "rating_blitz",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub blitz: Option<Rating>,
+    #[serde(
+        default,
+        rename = "rating_rapid",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub rapid: Option<Rating>,
+    #[serde(
+        default,
+        rename = "rating_classical",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub classical: Option<Rating>,
+    #[serde(
+        default,
+        rename = "rating_correspondence",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub correspondence: Option<Rating>,
+}
+
+impl Ratings {
+    /// `None` if the player hasn't played a rated game in the category
+    pub fn get(&self, category: TimeControlCategory) -> Option<Rating> {
+        match category {
+            TimeControlCategory::Bullet => self.bullet,
+            TimeControlCategory::Blitz => self.blitz,
+            TimeControlCategory::Rapid => self.rapid,
+            TimeControlCategory::Classical => self.classical,
+            TimeControlCategory::Correspondence => self.correspondence,
+        }
+    }
+
+    pub fn attribute_name(category: TimeControlCategory) -> String {
+        format!("rating_{category}")
+    }
+}
+
+/// Sent to the players when a rated game ends
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RatingChange {
+    pub username: String,
+    pub category: TimeControlCategory,
+    pub before: Rating,
+    pub after: Rating,
+}
+
+/// A player's new rating, saved only if their rating hasn't changed since `previous` was read
+#[derive(Clone, Debug)]
+pub struct RatingUpdate {
+    pub username: String,
+    pub category: TimeControlCategory,
+    pub previous: Option<Rating>, // `None` if they hadn't played a rated game in the category
+    pub new: Rating,
+}
@@ -0,0 +1,56 @@
+use serde::Serialize;
+
+use super::{board::BoardSetup, piece::Color, rating::TimeControlCategory};
+
+/// Results for a slice of a player's games
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultTotals {
+    pub games: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    pub score_percentage: f32, // Wins count fully and draws count half
+    pub average_moves: f32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColorStats {
+    pub color: Color,
+    pub results: ResultTotals,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeControlStats {
+    pub time_control: TimeControlCategory,
+    pub results: ResultTotals,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BoardSetupStats {
+    pub board_setup: BoardSetup,
+    pub results: ResultTotals,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpeningStats {
+    pub eco: String,
+    pub name: String,
+    pub results: ResultTotals,
+}
+
+/// Sent in response to `GetUserStats`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserStats {
+    pub username: String,
+    pub overall: ResultTotals,
+    pub by_color: Vec<ColorStats>,
+    pub by_time_control: Vec<TimeControlStats>,
+    pub by_board_setup: Vec<BoardSetupStats>,
+    pub openings: Vec<OpeningStats>, // Best-scoring first
+}
@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+
+use super::dynamo_db::TournamentRecord;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TournamentFormat {
+    RoundRobin,
+    Swiss { rounds: u8 },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TournamentState {
+    Registration,
+    InProgress,
+    Finished,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairingResult {
+    pub white_points: f32,
+    pub black_points: f32,
+}
+
+/// A game in a tournament round. A pairing without a black player is a bye.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TournamentPairing {
+    pub white: String,
+    pub black: Option<String>,
+    pub game_id: Option<String>,       // `None` for a bye
+    pub result: Option<PairingResult>, // Filled in once the game is over
+}
+
+impl TournamentPairing {
+    /// The points the player scored in this pairing, if they played in it and it's over
+    pub fn points_for(&self, username: &str) -> Option<f32> {
+        let result = self.result?;
+
+        if self.white == username {
+            Some(result.white_points)
+        } else if self.black.as_deref() == Some(username) {
+            Some(result.black_points)
+        } else {
+            None
+        }
+    }
+
+    /// The player's opponent, if they played in this pairing and it wasn't a bye
+    pub fn opponent_of(&self, username: &str) -> Option<&str> {
+        let black = self.black.as_deref()?;
+
+        if self.white == username {
+            Some(black)
+        } else if black == username {
+            Some(&self.white)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TournamentRound {
+    pub pairings: Vec<TournamentPairing>,
+}
+
+/// Attached to each game of a tournament, for the PGN `Event` and `Round` tags
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TournamentGame {
+    pub tournament_id: String,
+    pub name: String,
+    pub round: usize, // 1-based
+    pub board: usize, // 1-based
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Standing {
+    pub username: String,
+    pub points: f32,
+    pub buchholz: f32,
+    pub sonneborn_berger: f32,
+}
+
+/// Sent in response to tournament actions
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TournamentDetails {
+    pub tournament: TournamentRecord,
+    pub standings: Vec<Standing>,
+}
@@ -0,0 +1,164 @@
+//! Speaks the UCI protocol on stdin/stdout, so the engine can be loaded into chess GUIs such as
+//! Cute Chess and played against other engines.
+
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use chess_engine::position::Position;
+use chess_engine::types::Board;
+
+use chess::helpers::uci::{
+    format_search_info, get_uci_engine, get_uci_options, parse_command, set_option, EngineOptions,
+    SearchLimits, UciCommand, STARTING_FEN,
+};
+
+/// Setting up the engine needs more stack than a thread gets by default
+const THREAD_STACK_SIZE: usize = 16 * 1024 * 1024;
+
+struct Search {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl Search {
+    /// The engine can't be interrupted mid-depth, so this waits for the current depth to finish
+    fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+
+        if self.handle.join().is_err() {
+            send("info string Search failed");
+        }
+    }
+}
+
+/// Both the main thread and the search thread write to stdout, so each line is written whole
+fn send(line: &str) {
+    let mut stdout = io::stdout().lock();
+    let _ = writeln!(stdout, "{line}");
+    let _ = stdout.flush();
+}
+
+/// Runs the search on its own thread, so `stop` and `isready` can still be read while it thinks
+fn start_search(
+    fen: &str,
+    moves: &[String],
+    limits: &SearchLimits,
+    options: &EngineOptions,
+) -> Option<Search> {
+    let mut engine = match get_uci_engine(fen, moves, limits, options) {
+        Ok(engine) => engine,
+        Err(e) => {
+            send(&format!("info string {e}"));
+            send("bestmove 0000");
+            return None;
+        }
+    };
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_requested = stop.clone();
+
+    let handle = thread::Builder::new()
+        .name("search".to_string())
+        .stack_size(THREAD_STACK_SIZE)
+        .spawn(move || {
+            let result = engine.think(Some(|depth, score, position: &mut Position| {
+                send(&format_search_info(depth, score, position));
+
+                // Out of time as far as the engine is concerned, so it won't start another depth
+                if stop_requested.load(Ordering::Relaxed) {
+                    position.time_manager.fixed_time = false;
+                    position.time_manager.soft_limit = Duration::ZERO;
+                    position.time_manager.hard_limit = Duration::ZERO;
+                }
+            }));
+
+            match (result.best_move_from, result.best_move_to) {
+                (Some(from), Some(to)) => send(&format!(
+                    "bestmove {}",
+                    Board::move_to_uci_string(from, to, result.best_move_promote, false)
+                )),
+                // Checkmate or stalemate
+                _ => send("bestmove 0000"),
+            }
+        });
+
+    match handle {
+        Ok(handle) => Some(Search { stop, handle }),
+        Err(e) => {
+            send(&format!("info string Failed to start search: {e}"));
+            send("bestmove 0000");
+            None
+        }
+    }
+}
+
+fn main() {
+    // Stdout is reserved for the protocol
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::WARN)
+        .with_writer(io::stderr)
+        .init();
+
+    let mut options = EngineOptions::default();
+    let mut fen = STARTING_FEN.to_string();
+    let mut moves = Vec::new();
+    let mut search: Option<Search> = None;
+
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        let Some(command) = parse_command(&line) else {
+            continue;
+        };
+
+        match command {
+            UciCommand::Uci => {
+                send(&format!("id name Chess {}", env!("CARGO_PKG_VERSION")));
+                send("id author Chess contributors");
+
+                for option in get_uci_options() {
+                    send(&option);
+                }
+
+                send("uciok");
+            }
+            UciCommand::IsReady => send("readyok"),
+            // Every search starts from a fresh engine, so there's nothing to reset
+            UciCommand::UciNewGame => {}
+            UciCommand::SetOption { name, value } => {
+                if let Err(e) = set_option(&mut options, &name, value.as_deref()) {
+                    send(&format!("info string {e}"));
+                }
+            }
+            UciCommand::Position {
+                fen: new_fen,
+                moves: new_moves,
+            } => {
+                fen = new_fen;
+                moves = new_moves;
+            }
+            UciCommand::Go(limits) => {
+                if let Some(search) = search.take() {
+                    search.stop();
+                }
+
+                search = start_search(&fen, &moves, &limits, &options);
+            }
+            UciCommand::Stop => {
+                if let Some(search) = search.take() {
+                    search.stop();
+                }
+            }
+            UciCommand::Quit => break,
+        }
+    }
+
+    if let Some(search) = search.take() {
+        search.stop();
+    }
+}
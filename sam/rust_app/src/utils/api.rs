@@ -4,6 +4,18 @@ use serde::Serialize;
 
 use crate::types::api::{ApiMessage, ApiResponse};
 
+fn to_proxy_response<T: Serialize>(
+    api_response: &ApiResponse<T>,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let body = serde_json::to_string(api_response)?;
+
+    let mut response = ApiGatewayProxyResponse::default();
+    response.status_code = api_response.status_code.into();
+    response.body = Some(Body::from(body));
+
+    Ok(response)
+}
+
 /// Sends a response back to the client
 pub fn build_response<T: Serialize>(
     status_code: StatusCode,
@@ -11,19 +23,77 @@ pub fn build_response<T: Serialize>(
     messages: Option<Vec<ApiMessage>>,
     data: Option<T>,
 ) -> Result<ApiGatewayProxyResponse, Error> {
-    let status_code = status_code.as_u16();
-
-    let body = serde_json::to_string(&ApiResponse {
-        status_code,
+    to_proxy_response(&ApiResponse {
+        status_code: status_code.as_u16(),
         connection_id,
         messages: messages.unwrap_or_default(),
         data,
         replaces_game_id: None,
-    })?;
+        seat_token: None,
+        invite_token: None,
+    })
+}
 
-    let mut response = ApiGatewayProxyResponse::default();
-    response.status_code = status_code.into();
-    response.body = Some(Body::from(body));
+/// Sends a game back to a player who was just seated in it, along with the
+/// seat token they need to rejoin it later (if one was issued)
+pub fn build_seated_player_response<T: Serialize>(
+    connection_id: &str,
+    game: T,
+    seat_token: Option<String>,
+    replaces_game_id: Option<String>,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    to_proxy_response(&ApiResponse {
+        status_code: StatusCode::OK.as_u16(),
+        connection_id: Some(connection_id.to_string()),
+        messages: Vec::new(),
+        data: Some(game),
+        replaces_game_id,
+        seat_token,
+        invite_token: None,
+    })
+}
 
-    Ok(response)
+/// Like `build_seated_player_response`, for the creator of a private game, who also needs
+/// the invite token to share with the opponent they want to invite
+pub fn build_private_game_response<T: Serialize>(
+    connection_id: &str,
+    game: T,
+    seat_token: Option<String>,
+    invite_token: String,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    to_proxy_response(&ApiResponse {
+        status_code: StatusCode::OK.as_u16(),
+        connection_id: Some(connection_id.to_string()),
+        messages: Vec::new(),
+        data: Some(game),
+        replaces_game_id: None,
+        seat_token,
+        invite_token: Some(invite_token),
+    })
+}
+
+/// For actions that couldn't be saved because other requests kept changing the tournament
+pub fn build_tournament_conflict_response(
+    connection_id: &str,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    build_response(
+        StatusCode::CONFLICT,
+        Some(connection_id.to_string()),
+        Some(vec![
+            "The tournament was changed by another request. Please try again.".into(),
+        ]),
+        None::<()>,
+    )
+}
+
+/// For actions that couldn't be saved because other requests kept changing the game
+pub fn build_game_conflict_response(connection_id: &str) -> Result<ApiGatewayProxyResponse, Error> {
+    build_response(
+        StatusCode::CONFLICT,
+        Some(connection_id.to_string()),
+        Some(vec![
+            "The game was changed by another request. Please try again.".into(),
+        ]),
+        None::<()>,
+    )
 }
@@ -1,4 +1,8 @@
-use aws_sdk_dynamodb::{types::AttributeValue, Client};
+use aws_sdk_dynamodb::{
+    error::SdkError,
+    types::{AttributeValue, TransactWriteItem},
+    Client,
+};
 use lambda_runtime::Error;
 use serde::{Deserialize, Serialize};
 use serde_dynamo::aws_sdk_dynamodb_1::{from_item, from_items, to_item};
@@ -25,14 +29,26 @@ pub async fn get_item<'a, T: Deserialize<'a> + Serialize>(
     }
 }
 
-pub async fn query_items<'a, T: Deserialize<'a> + Serialize>(
+/// A page of query results. `last_evaluated_key` is set if there may be more results after it.
+pub struct QueryPage<T> {
+    pub items: Vec<T>,
+    pub last_evaluated_key: Option<HashMap<String, AttributeValue>>,
+}
+
+/// Queries for a single page of up to `limit` items, continuing after `exclusive_start_key`
+/// (a previous page's `last_evaluated_key`). `scan_index_forward: false` returns items in
+/// descending sort key order.
+pub async fn query_page<'a, T: Deserialize<'a> + Serialize>(
     client: &Client,
     table_name: &str,
     key_condition_expression: Option<String>,
     expression_attribute_names: Option<HashMap<String, String>>,
     expression_attribute_values: Option<HashMap<String, AttributeValue>>,
     index_name: Option<String>,
-) -> Result<Vec<T>, Error> {
+    limit: Option<i32>,
+    exclusive_start_key: Option<HashMap<String, AttributeValue>>,
+    scan_index_forward: bool,
+) -> Result<QueryPage<T>, Error> {
     let response = client
         .query()
         .table_name(table_name)
@@ -40,6 +56,9 @@ pub async fn query_items<'a, T: Deserialize<'a> + Serialize>(
         .set_key_condition_expression(key_condition_expression)
         .set_expression_attribute_names(expression_attribute_names)
         .set_expression_attribute_values(expression_attribute_values)
+        .set_limit(limit)
+        .set_exclusive_start_key(exclusive_start_key)
+        .scan_index_forward(scan_index_forward)
         .send()
         .await?;
 
@@ -47,9 +66,45 @@ pub async fn query_items<'a, T: Deserialize<'a> + Serialize>(
         .items
         .ok_or_else(|| Error::from("Error obtaining DynamoDB items from `QueryOutput`"))?;
 
-    let typed_entities: Vec<T> = from_items(items)?;
+    Ok(QueryPage {
+        items: from_items(items)?,
+        last_evaluated_key: response.last_evaluated_key,
+    })
+}
+
+/// Queries for every matching item, following `LastEvaluatedKey` across DynamoDB's 1 MB pages
+pub async fn query_items<'a, T: Deserialize<'a> + Serialize>(
+    client: &Client,
+    table_name: &str,
+    key_condition_expression: Option<String>,
+    expression_attribute_names: Option<HashMap<String, String>>,
+    expression_attribute_values: Option<HashMap<String, AttributeValue>>,
+    index_name: Option<String>,
+) -> Result<Vec<T>, Error> {
+    let mut typed_entities = Vec::new();
+    let mut exclusive_start_key = None;
+
+    loop {
+        let page = query_page(
+            client,
+            table_name,
+            key_condition_expression.clone(),
+            expression_attribute_names.clone(),
+            expression_attribute_values.clone(),
+            index_name.clone(),
+            None,
+            exclusive_start_key,
+            true,
+        )
+        .await?;
+
+        typed_entities.extend(page.items);
 
-    Ok(typed_entities)
+        match page.last_evaluated_key {
+            Some(last_evaluated_key) => exclusive_start_key = Some(last_evaluated_key),
+            None => return Ok(typed_entities),
+        }
+    }
 }
 
 pub async fn put_item<'a, T: Deserialize<'a> + Serialize>(
@@ -69,11 +124,69 @@ pub async fn put_item<'a, T: Deserialize<'a> + Serialize>(
     Ok(())
 }
 
-pub async fn update_item<'a, T: Deserialize<'a> + Serialize>(
+/// Puts an item that's already been converted to attributes, as long as the condition holds.
+/// Returns `false` if it doesn't.
+pub async fn put_item_if(
+    client: &Client,
+    table_name: &str,
+    item: HashMap<String, AttributeValue>,
+    condition_expression: &str,
+    expression_attribute_values: Option<HashMap<String, AttributeValue>>,
+) -> Result<bool, Error> {
+    match client
+        .put_item()
+        .table_name(table_name)
+        .set_item(Some(item))
+        .condition_expression(condition_expression)
+        .set_expression_attribute_values(expression_attribute_values)
+        .send()
+        .await
+    {
+        Ok(_) => Ok(true),
+        Err(SdkError::ServiceError(service_error))
+            if service_error.err().is_conditional_check_failed_exception() =>
+        {
+            Ok(false)
+        }
+        Err(e) => Err(Error::from(e)),
+    }
+}
+
+/// Puts an item only if no item exists with the same primary key.
+/// Returns `false` if an item already exists.
+pub async fn put_item_if_not_exists<'a, T: Deserialize<'a> + Serialize>(
+    client: &Client,
+    table_name: &str,
+    partition_key_name: &str,
+    typed_entity: &T,
+) -> Result<bool, Error> {
+    let item = to_item(typed_entity)?;
+
+    match client
+        .put_item()
+        .table_name(table_name)
+        .set_item(Some(item))
+        .condition_expression("attribute_not_exists(#pk)")
+        .expression_attribute_names("#pk", partition_key_name)
+        .send()
+        .await
+    {
+        Ok(_) => Ok(true),
+        Err(SdkError::ServiceError(service_error))
+            if service_error.err().is_conditional_check_failed_exception() =>
+        {
+            Ok(false)
+        }
+        Err(e) => Err(Error::from(e)),
+    }
+}
+
+pub async fn update_item(
     client: &Client,
     table_name: &str,
     key: HashMap<String, AttributeValue>,
     update_expression: &str,
+    expression_attribute_names: Option<HashMap<String, String>>,
     expression_attribute_values: HashMap<String, AttributeValue>,
 ) -> Result<(), Error> {
     client
@@ -81,6 +194,7 @@ pub async fn update_item<'a, T: Deserialize<'a> + Serialize>(
         .table_name(table_name)
         .set_key(Some(key))
         .update_expression(update_expression)
+        .set_expression_attribute_names(expression_attribute_names)
         .set_expression_attribute_values(Some(expression_attribute_values))
         .send()
         .await?;
@@ -102,3 +216,52 @@ pub async fn delete_item(
 
     Ok(())
 }
+
+/// Deletes an item only if it exists. Returns `false` if there was nothing to delete,
+/// which lets concurrent callers race to claim an item.
+pub async fn delete_item_if_exists(
+    client: &Client,
+    table_name: &str,
+    partition_key_name: &str,
+    key: HashMap<String, AttributeValue>,
+) -> Result<bool, Error> {
+    match client
+        .delete_item()
+        .table_name(table_name)
+        .set_key(Some(key))
+        .condition_expression("attribute_exists(#pk)")
+        .expression_attribute_names("#pk", partition_key_name)
+        .send()
+        .await
+    {
+        Ok(_) => Ok(true),
+        Err(SdkError::ServiceError(service_error))
+            if service_error.err().is_conditional_check_failed_exception() =>
+        {
+            Ok(false)
+        }
+        Err(e) => Err(Error::from(e)),
+    }
+}
+
+/// Applies all of the writes or none of them. Returns `false` if the transaction was
+/// cancelled, e.g. because one of its condition expressions failed.
+pub async fn transact_write_items(
+    client: &Client,
+    transact_items: Vec<TransactWriteItem>,
+) -> Result<bool, Error> {
+    match client
+        .transact_write_items()
+        .set_transact_items(Some(transact_items))
+        .send()
+        .await
+    {
+        Ok(_) => Ok(true),
+        Err(SdkError::ServiceError(service_error))
+            if service_error.err().is_transaction_canceled_exception() =>
+        {
+            Ok(false)
+        }
+        Err(e) => Err(Error::from(e)),
+    }
+}
@@ -0,0 +1,92 @@
+use lambda_http::http::{header, HeaderMap, Response, StatusCode};
+use lambda_runtime::Error;
+
+use crate::{helpers::game::is_game_over, types::dynamo_db::GameRecord};
+
+pub const CONTENT_TYPE_JSON: &str = "application/json";
+pub const CONTENT_TYPE_PGN: &str = "application/x-chess-pgn";
+pub const CONTENT_TYPE_TEXT: &str = "text/plain; charset=utf-8";
+
+const CACHE_FOREVER: &str = "public, max-age=31536000, immutable";
+/// Caches have to check the ETag before reusing a response
+pub const CACHE_REVALIDATE: &str = "public, no-cache";
+const CACHE_NEVER: &str = "no-store";
+/// Only the browser of someone holding the game's token may reuse the response
+const CACHE_PRIVATE_REVALIDATE: &str = "private, no-cache";
+
+pub type HttpResponse = Response<String>;
+
+/// Sends a response back to an HTTP client. The data is public, so any site may read it.
+pub fn build_http_response(
+    status_code: StatusCode,
+    content_type: &str,
+    cache_control: &str,
+    body: String,
+) -> Result<HttpResponse, Error> {
+    Ok(Response::builder()
+        .status(status_code)
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CACHE_CONTROL, cache_control)
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .body(body)?)
+}
+
+pub fn build_http_error(status_code: StatusCode, message: &str) -> Result<HttpResponse, Error> {
+    build_http_response(
+        status_code,
+        CONTENT_TYPE_TEXT,
+        CACHE_NEVER,
+        message.to_string(),
+    )
+}
+
+/// The moves of a finished game never change, so anything built from them can be cached for good
+pub fn get_moves_cache_control(game: &GameRecord) -> &'static str {
+    match is_game_over(game) {
+        true => CACHE_FOREVER,
+        false => CACHE_REVALIDATE,
+    }
+}
+
+/// Sends a representation of the game. Every save bumps the game's version, which makes it the
+/// ETag, so a client with the current version is told it's unchanged instead. Private games are
+/// never stored by shared caches, since only token holders may see them.
+pub fn build_game_http_response(
+    request_headers: &HeaderMap,
+    game: &GameRecord,
+    content_type: &str,
+    cache_control: &str,
+    body: impl FnOnce() -> Result<String, Error>,
+) -> Result<HttpResponse, Error> {
+    let etag = format!("\"{}\"", game.version);
+
+    let cache_control = match game.invite_token_hash {
+        Some(_) => CACHE_PRIVATE_REVALIDATE,
+        None => cache_control,
+    };
+
+    let is_unchanged = request_headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|tag| matches!(tag.trim(), "*") || tag.trim().trim_start_matches("W/") == etag);
+
+    let (status_code, body) = match is_unchanged {
+        true => (StatusCode::NOT_MODIFIED, String::new()),
+        false => (StatusCode::OK, body()?),
+    };
+
+    let mut response = build_http_response(status_code, content_type, cache_control, body)?;
+    response.headers_mut().insert(header::ETAG, etag.parse()?);
+
+    Ok(response)
+}
+
+/// For lists that change whenever a game does, but are fine to be a minute stale
+pub fn build_short_lived_http_response(
+    content_type: &str,
+    body: String,
+) -> Result<HttpResponse, Error> {
+    build_http_response(StatusCode::OK, content_type, "public, max-age=60", body)
+}
@@ -1,3 +1,3 @@
 pub mod api;
-pub mod api_gateway;
 pub mod dynamo_db;
+pub mod http;